@@ -0,0 +1,131 @@
+// The hot paths of lines and buffers on generated text, shared by the timings in
+// `benches/hot_paths.rs` and the smoke tests in `tests/hot_paths.rs`.
+use hecto::{Buffer, Line, Location, RenderOptions};
+use std::{
+    env, fs,
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+// Text mixing ASCII words, CJK and emoji, the same for the same seed. `line_bytes` long
+// lines if given, a single line of `bytes` otherwise.
+pub fn corpus(seed: u64, bytes: usize, line_bytes: Option<usize>) -> String {
+    const PIECES: [&str; 8] = [
+        "lorem ",
+        "ipsum ",
+        "dolor42 ",
+        "漢字",
+        "テスト",
+        "한글 ",
+        "👍",
+        "👨\u{200d}👩 ",
+    ];
+    let mut state = seed.max(1);
+    let mut text = String::with_capacity(bytes.saturating_add(32));
+    let mut line_start = 0;
+    while text.len() < bytes {
+        // xorshift, good enough to mix the pieces
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        let idx = usize::try_from(state % 8).unwrap_or_default();
+        text.push_str(PIECES[idx]);
+        if line_bytes.is_some_and(|line_bytes| text.len().saturating_sub(line_start) >= line_bytes)
+        {
+            text.push('\n');
+            line_start = text.len();
+        }
+    }
+    text
+}
+
+// the fastest of a few runs, the others are disturbed by whatever else is going on
+fn fastest(mut run: impl FnMut()) -> Duration {
+    (0..3)
+        .map(|_| {
+            let start = Instant::now();
+            run();
+            start.elapsed()
+        })
+        .min()
+        .unwrap_or_default()
+}
+
+// run on inputs of the given times their smallest size, the sizes named are ten times it
+type Case = fn(usize) -> Duration;
+
+pub const CASES: [(&str, Case); 8] = [
+    ("Line::try_from of a 1 MB line", line_from),
+    ("insert_char at the start of a long line", |scale| {
+        insert_chars(scale, |_| 0)
+    }),
+    ("insert_char in the middle of a long line", |scale| {
+        insert_chars(scale, |count| count >> 1)
+    }),
+    ("insert_char at the end of a long line", |scale| {
+        insert_chars(scale, |count| count)
+    }),
+    ("visible part of a long line", |scale| {
+        visible_substr(scale, None)
+    }),
+    ("visible part of a long line with a query", |scale| {
+        visible_substr(scale, Some("字テ"))
+    }),
+    ("search_forward through 100k lines", search_forward),
+    ("Buffer::load of a 10 MB file", load),
+];
+
+fn line_from(scale: usize) -> Duration {
+    let text = corpus(1, scale.saturating_mul(100_000), None);
+    fastest(|| {
+        let line = Line::try_from(text.as_str()).unwrap();
+        black_box(line.grapheme_count());
+    })
+}
+
+fn insert_chars(scale: usize, at: fn(usize) -> usize) -> Duration {
+    let text = corpus(2, scale.saturating_mul(10_000), None);
+    fastest(|| {
+        let mut line = Line::try_from(text.as_str()).unwrap();
+        for _ in 0..1_000 {
+            let at = at(line.grapheme_count());
+            black_box(line.insert_char('x', at).ok());
+        }
+    })
+}
+
+fn visible_substr(scale: usize, query: Option<&str>) -> Duration {
+    let line = Line::try_from(corpus(3, scale.saturating_mul(100_000), None).as_str()).unwrap();
+    let middle = line.width() >> 1;
+    fastest(|| {
+        for offset in 0..100 {
+            let start = middle.saturating_add(offset);
+            let range = start..start.saturating_add(80);
+            black_box(line.get_annotated_visible_substr(
+                range,
+                query,
+                None,
+                RenderOptions::default(),
+            ));
+        }
+    })
+}
+
+fn search_forward(scale: usize) -> Duration {
+    let buffer = Buffer::from_text(&corpus(4, scale.saturating_mul(400_000), Some(40)));
+    fastest(|| {
+        // not there, so every line is looked at
+        black_box(buffer.search_forward("nowhere", &Location::default(), None));
+    })
+}
+
+fn load(scale: usize) -> Duration {
+    let path = env::temp_dir().join(format!("hecto-bench-load-{scale}.txt"));
+    fs::write(&path, corpus(5, scale.saturating_mul(1_000_000), Some(60))).unwrap();
+    let filename = path.to_string_lossy().to_string();
+    let duration = fastest(|| {
+        black_box(Buffer::load(&filename).unwrap().get_height());
+    });
+    let _ = fs::remove_file(&path);
+    duration
+}
@@ -0,0 +1,27 @@
+//! Timings of the hot paths of lines and buffers on generated text mixing ASCII, CJK and
+//! emoji, to compare before and after a change: `cargo bench`.
+#![warn(
+    clippy::all,
+    clippy::pedantic,
+    clippy::arithmetic_side_effects,
+    clippy::integer_division
+)]
+
+mod cases;
+
+use cases::CASES;
+use std::env;
+
+fn main() {
+    // `cargo bench` passes `--bench`, any other argument picks the cases whose name has it
+    let filter = env::args().skip(1).find(|arg| !arg.starts_with("--"));
+    for (name, case) in CASES {
+        if filter
+            .as_ref()
+            .is_none_or(|filter| name.contains(filter.as_str()))
+        {
+            let duration = case(10);
+            println!("{name:<45} {duration:>12.3?}");
+        }
+    }
+}
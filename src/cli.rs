@@ -0,0 +1,213 @@
+use hecto::DEFAULT_AUTOSAVE_DELAY;
+use std::{path::PathBuf, time::Duration};
+
+pub const USAGE: &str = "\
+Usage: hecto [options] [+LINE] [file ...]
+
+Options:
+  -h, --help           show this help and exit
+  -V, --version        show the version and exit
+  -R, --readonly       refuse to save the files over themselves
+  +LINE                put the caret on LINE of the first file
+  --config PATH        read the config from PATH instead of ~/.config/hecto/config.toml
+  --log                write a debug log
+  --autosave[=SECS]    keep a copy of unsaved changes after SECS idle seconds (30)
+  --session[=NAME]     reopen the buffers open when quitting the last time
+  --script FILE        type the keys in FILE before handing over
+  --                   take everything after it as a file, even if it starts with -
+  -                    as a file, read the buffer from stdin
+
+Keys:
+  Ctrl-S save          Ctrl-O open           Ctrl-T quit
+  Ctrl-F search        Ctrl-N next match     Ctrl-R reload
+  Alt-X command palette, which lists every command and its keys
+  Alt-Left/Right switch buffers, Alt-S split, Ctrl-K S save all
+";
+
+// What the command line asks for, see `USAGE`
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Args {
+    pub help: bool,
+    pub version: bool,
+    pub read_only: bool,
+    // of the first file, counting from 1
+    pub line: Option<usize>,
+    pub config: Option<PathBuf>,
+    pub log: bool,
+    pub autosave: Option<Duration>,
+    pub session: Option<Session>,
+    pub script: Option<String>,
+    // in the order given, each opened as a buffer of its own
+    pub files: Vec<String>,
+}
+
+// which session `--session` picks up
+#[derive(Debug, PartialEq, Eq)]
+pub enum Session {
+    // the one in the working directory
+    Local,
+    Named(String),
+}
+
+impl Session {
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            Self::Local => None,
+            Self::Named(name) => Some(name),
+        }
+    }
+}
+
+impl Args {
+    pub fn reads_stdin(&self) -> bool {
+        self.files.iter().any(|file| file == "-")
+    }
+}
+
+// the error is a one-line message, without the "hecto: " in front
+pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Args, String> {
+    let mut args = args.into_iter();
+    let mut parsed = Args::default();
+    let mut files = Vec::new();
+    let mut only_files = false;
+    while let Some(arg) = args.next() {
+        if arg.is_empty() {
+            continue;
+        }
+        if only_files {
+            files.push(arg);
+            continue;
+        }
+        match arg.as_str() {
+            "-h" | "--help" => parsed.help = true,
+            "-V" | "--version" => parsed.version = true,
+            "-R" | "--readonly" => parsed.read_only = true,
+            "--config" => parsed.config = Some(PathBuf::from(value_of(&mut args, &arg, "a path")?)),
+            "--log" => parsed.log = true,
+            "--autosave" => parsed.autosave = Some(DEFAULT_AUTOSAVE_DELAY),
+            "--session" => parsed.session = Some(Session::Local),
+            "--script" => parsed.script = Some(value_of(&mut args, &arg, "a file")?),
+            "--" => only_files = true,
+            "-" => files.push(arg),
+            _ => {
+                if let Some(secs) = arg.strip_prefix("--autosave=") {
+                    let secs = secs.parse().map_err(|_| {
+                        format!("--autosave expects a number of seconds, got '{secs}'")
+                    })?;
+                    parsed.autosave = Some(Duration::from_secs(secs));
+                } else if let Some(name) = arg.strip_prefix("--session=") {
+                    if name.is_empty() || name.contains('/') {
+                        return Err(format!("--session expects a name, got '{name}'"));
+                    }
+                    parsed.session = Some(Session::Named(name.to_string()));
+                } else if let Some(line) = arg.strip_prefix('+') {
+                    let line = line.parse().ok().filter(|line| *line > 0);
+                    parsed.line =
+                        Some(line.ok_or_else(|| format!("+ expects a line number, got '{arg}'"))?);
+                } else if arg.starts_with('-') {
+                    return Err(format!("unknown option '{arg}', see --help"));
+                } else {
+                    files.push(arg);
+                }
+            }
+        }
+    }
+    if files.iter().filter(|file| *file == "-").count() > 1 {
+        return Err(String::from(
+            "stdin can only be read once, '-' was given twice",
+        ));
+    }
+    parsed.files = files;
+    Ok(parsed)
+}
+
+// the argument after `option`
+fn value_of(
+    args: &mut impl Iterator<Item = String>,
+    option: &str,
+    what: &str,
+) -> Result<String, String> {
+    args.next()
+        .filter(|value| !value.is_empty())
+        .ok_or_else(|| format!("{option} expects {what}"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse_str(args: &str) -> Result<Args, String> {
+        parse(args.split_whitespace().map(ToString::to_string))
+    }
+
+    #[test]
+    fn takes_options_and_every_file() {
+        assert_eq!(
+            parse_str("a.txt --log b.txt --autosave=5 - c.txt"),
+            Ok(Args {
+                log: true,
+                autosave: Some(Duration::from_secs(5)),
+                files: ["a.txt", "b.txt", "-", "c.txt"].map(String::from).to_vec(),
+                ..Args::default()
+            })
+        );
+        assert_eq!(
+            parse_str("--session=notes --script keys.txt --autosave"),
+            Ok(Args {
+                autosave: Some(DEFAULT_AUTOSAVE_DELAY),
+                session: Some(Session::Named(String::from("notes"))),
+                script: Some(String::from("keys.txt")),
+                ..Args::default()
+            })
+        );
+        assert_eq!(
+            parse_str("--session").unwrap().session,
+            Some(Session::Local)
+        );
+        assert!(parse_str("-").unwrap().reads_stdin());
+    }
+
+    #[test]
+    fn explains_what_is_wrong() {
+        assert_eq!(
+            parse_str("--autosave=soon"),
+            Err(String::from(
+                "--autosave expects a number of seconds, got 'soon'"
+            ))
+        );
+        assert_eq!(
+            parse_str("--session=a/b"),
+            Err(String::from("--session expects a name, got 'a/b'"))
+        );
+        assert!(parse_str("- -").is_err());
+        assert_eq!(
+            parse_str("--bogus a.txt"),
+            Err(String::from("unknown option '--bogus', see --help"))
+        );
+        assert_eq!(
+            parse_str("+x"),
+            Err(String::from("+ expects a line number, got '+x'"))
+        );
+        assert_eq!(
+            parse_str("--config"),
+            Err(String::from("--config expects a path"))
+        );
+    }
+
+    #[test]
+    fn flags_stop_at_a_double_dash() {
+        assert_eq!(
+            parse_str("-R +12 --config hecto.toml -h -V -- --weird -R"),
+            Ok(Args {
+                help: true,
+                version: true,
+                read_only: true,
+                line: Some(12),
+                config: Some(PathBuf::from("hecto.toml")),
+                files: ["--weird", "-R"].map(String::from).to_vec(),
+                ..Args::default()
+            })
+        );
+    }
+}
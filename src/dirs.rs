@@ -0,0 +1,27 @@
+//! Where hecto keeps its files, following the XDG base directories.
+use std::{env, path::PathBuf};
+
+/// `$XDG_CACHE_HOME/hecto`, falling back to `~/.cache/hecto`
+#[must_use]
+pub fn cache_dir() -> Option<PathBuf> {
+    xdg_dir("XDG_CACHE_HOME", ".cache")
+}
+
+/// `$XDG_CONFIG_HOME/hecto`, falling back to `~/.config/hecto`
+#[must_use]
+pub fn config_dir() -> Option<PathBuf> {
+    xdg_dir("XDG_CONFIG_HOME", ".config")
+}
+
+/// `$XDG_STATE_HOME/hecto`, falling back to `~/.local/state/hecto`
+#[must_use]
+pub fn state_dir() -> Option<PathBuf> {
+    xdg_dir("XDG_STATE_HOME", ".local/state")
+}
+
+fn xdg_dir(variable: &str, fallback: &str) -> Option<PathBuf> {
+    let base = env::var_os(variable)
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(fallback)))?;
+    Some(base.join("hecto"))
+}
@@ -1,151 +1,674 @@
 use crossterm::event::{
     Event::{self, Key},
-    KeyEvent, KeyEventKind, read,
+    KeyCode, KeyEvent, KeyEventKind, MouseButton, MouseEvent, MouseEventKind, poll, read,
 };
+use log::{debug, error, warn};
 use std::{
-    env,
+    backtrace::Backtrace,
+    cell::RefCell,
+    cmp::Ordering,
+    env, fs,
+    ops::Range,
     panic::{set_hook, take_hook},
+    path::{Path, PathBuf},
+    rc::Rc,
+    thread,
+    time::{Duration, Instant},
 };
 
-mod annotated_string;
-mod command;
+pub(crate) mod annotated_string;
+mod autosave;
+pub(crate) mod command;
+mod completion;
+mod config;
+mod diff;
 mod documentstatus;
-mod line;
+mod file_search;
+mod git;
+mod keyboard_macro;
+mod keymap;
+pub(crate) mod line;
+mod link;
+mod modal;
+mod overlay;
+mod palette;
 mod position;
-mod size;
+mod prompt;
+mod recovery;
+mod script;
+mod search_worker;
+mod session;
+mod shell;
+pub(crate) mod size;
+mod spell;
+mod split;
+mod syntax;
 mod terminal;
-mod ui;
+mod termination;
+pub(crate) mod ui;
+mod unicode;
 
+use autosave::Autosave;
+pub use autosave::DEFAULT_DELAY as DEFAULT_AUTOSAVE_DELAY;
 use command::{
     Command::{self, Edit, Move, System},
-    System::{Dismiss, Quit, Resize, Save, Search, SearchNext, SearchPrevious},
+    System::{CommandPalette, CompleteWord, Count, Dismiss, PlayMacro, Quit, RecordMacro, Resize},
 };
+use completion::{Completion, WordCompletion};
+pub use config::Config;
+use file_search::{FileSearch, Found};
+use keyboard_macro::KeyboardMacro;
+use keymap::{Key as ChordKey, Keymap, Lookup};
+use line::Line;
+use link::Target;
+use modal::{Action, Modal, Mode, Translation};
+use overlay::Overlay;
+use palette::{NamedCommand, Palette};
 use position::Position;
+use prompt::Prompt;
+pub use script::parse_script;
+use search_worker::{Progress, SearchWorker};
+pub use session::session_path;
+use session::{BufferState, Session};
 use size::Size;
-use terminal::Terminal;
-use ui::{CommandBar, MessageBar, StatusBar, UIComponent, View};
+use spell::Dictionary;
+use split::Split;
+use terminal::{Terminal, TerminalIo, Theme};
+use termination::Termination;
+use ui::{
+    BufferEntry, BufferList, ByteInspector, CommandBar, DiffList, Encoding, LineEnding, Location,
+    MatchList, MessageBar, Segment, Severity, StatusBar, StatusFormat, UIComponent, View,
+    known_file_types,
+};
+use unicode_segmentation::UnicodeSegmentation;
 
+/// The name of the editor, `hecto`.
 pub const NAME: &str = env!("CARGO_PKG_NAME");
+/// The version of the editor.
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
-
-const QUIT_TIMES: u8 = 3;
+// counts are capped so inserting with a mistyped count can't hang the editor
+const MAX_COUNT: usize = 9_999;
+// how long to wait for the rest of a chord
+const CHORD_TIMEOUT: Duration = Duration::from_secs(2);
+// how often to look for results of a search or a load in the background
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+// how often to look whether hecto was told to terminate while waiting for input
+const SIGNAL_INTERVAL: Duration = Duration::from_millis(250);
+// files at least this big are read in the background, the buffer fills while they are
+const BACKGROUND_LOAD_BYTES: u64 = 16 << 20;
+// below this, a row of text, the status bar and the message bar don't fit, and only a note
+// asking for more room is drawn
+const MIN_WIDTH: usize = 10;
+const MIN_HEIGHT: usize = 3;
 
 #[derive(Debug, Default, PartialEq)]
 enum PromptType {
     Search,
+    // a query to look for in the files below the working directory
+    SearchFiles,
     Save,
+    Open,
+    Palette,
+    GoToLine,
+    // a shell command whose output is inserted
+    Shell {
+        keep_newline: bool,
+    },
+    // a shell command the buffer is piped through
+    Filter,
+    // a code point or name of a character to insert
+    Unicode,
+    // the name of a file type, instead of the one the extension tells
+    FileType,
+    // picking one of the matches listed below the panes
+    Matches,
+    // looking through the changes since the file was saved
+    Diff,
+    // looking through the bytes of the caret's line
+    Bytes,
+    // picking one of the open buffers, narrowed down by what was typed
+    Buffers,
+    Confirm,
+    ConfirmQuit,
     #[default]
     None,
 }
 
+// an action waiting for the user to answer a yes/no question
+enum Confirmation {
+    Reload,
+    // closing the focused buffer, which has unsaved changes
+    Close,
+    Overwrite(String),
+    Restore(PathBuf),
+    // the directory to create before saving, and the name to save as if it is a new one
+    CreateDirectory {
+        dir: PathBuf,
+        filename: Option<String>,
+    },
+    // the file which isn't UTF-8, to read as Windows-1252
+    Windows1252(String),
+}
+
+impl Confirmation {
+    fn question(&self) -> String {
+        match self {
+            Self::Reload => String::from("File has unsaved changes. Reload anyway?"),
+            Self::Close => String::from("File has unsaved changes. Close anyway?"),
+            Self::Overwrite(filename) => format!("File {filename} exists. Overwrite?"),
+            Self::Restore(path) => format!(
+                "Found unsaved changes from a crash in {}. Restore them?",
+                path.display()
+            ),
+            Self::CreateDirectory { dir, .. } => {
+                format!("Directory {} does not exist. Create it?", dir.display())
+            }
+            Self::Windows1252(filename) => {
+                let name = Path::new(filename).file_name().map_or_else(
+                    || filename.clone(),
+                    |name| name.to_string_lossy().to_string(),
+                );
+                format!("{name} is not UTF-8. Open it as Windows-1252?")
+            }
+        }
+    }
+}
+
+// what continues once the prompts it needs have been answered
+#[derive(Clone, Copy)]
+enum PendingAction {
+    // saving every buffer, after saving an unnamed one under the name asked for. `saved`
+    // counts the buffers saved so far, and it quits at the end if `quit` is set.
+    SaveAll { saved: usize, quit: bool },
+}
+
+// what the key after `set-mark`, `jump-to-mark` or `delete-mark` names a mark for
+#[derive(Clone, Copy)]
+enum MarkAction {
+    Set,
+    Jump,
+    Delete,
+}
+
 impl PromptType {
     fn is_none(&self) -> bool {
         *self == Self::None
     }
 }
 
-#[derive(Default)]
+/// The editor on the terminal, with its buffers, panes, prompts and bars.
 pub struct Editor {
+    terminal: Box<dyn TerminalIo>,
     should_quit: bool,
-    view: View,
+    views: Vec<View>,
+    // the buffers in the order they were last shown in the focused pane, the latest last
+    recent_buffers: Vec<usize>,
+    active_view: usize,
+    split: Option<Split>,
     status_bar: StatusBar,
     message_bar: MessageBar,
     command_bar: CommandBar,
     prompt_type: PromptType,
+    confirmation: Option<Confirmation>,
+    completion: Option<Completion>,
+    word_completion: Option<WordCompletion>,
+    // open while the matches of a search or a diff are listed
+    overlay: Option<Overlay>,
     terminal_size: Size,
     title: String,
-    quit_times: u8,
+    // continued once the prompt or confirmation asking for it completes
+    pending_action: Option<PendingAction>,
+    // None unless autosave was turned on
+    autosave: Option<Autosave>,
+    config: Config,
+    // shared by the views, made again once the config names other word lists
+    dictionary: Rc<RefCell<Dictionary>>,
+    // what keys and the palette can run by name
+    commands: Vec<NamedCommand>,
+    palette: Palette,
+    keyboard_macro: KeyboardMacro,
+    // set by commands which found nothing, so a macro stops instead of running on
+    command_failed: bool,
+    // typed with Alt and digits, repeats the next move or edit
+    pending_count: Option<usize>,
+    modal: Modal,
+    // lines deleted or yanked in normal mode
+    yanked: Vec<String>,
+    keymap: Keymap,
+    // the keys of a chord typed so far
+    pending_chord: Vec<ChordKey>,
+    chord_started: Instant,
+    pending_mark: Option<MarkAction>,
+    // where the open buffers are written on quit, None without `--session`
+    session: Option<PathBuf>,
+    search_worker: SearchWorker,
+    // the search through files the match list is waiting for
+    file_search: Option<FileSearch>,
+    // where that search looks, the working directory but in tests
+    files_root: PathBuf,
+    // smaller files are loaded at once
+    load_in_background_from: u64,
+    termination: Termination,
+    // where the config is reloaded from, None for the config directory
+    config_path: Option<PathBuf>,
+    // the signal which ended the editor, its unsaved changes are written to recovery files
+    terminated_by: Option<i32>,
+    // where recovery files go, None for next to their files, in tests a temporary directory
+    recovery_dir: Option<PathBuf>,
 }
 
 impl Editor {
-    pub fn new() -> Result<Self, std::io::Error> {
+    /// Sets up the terminal and opens every file as a buffer, "-" being the text read from
+    /// `stdin`. The config is read from `config_path`, or the config directory without one;
+    /// one which could not be loaded is reported, and the defaults are used instead.
+    ///
+    /// # Errors
+    ///
+    /// If the terminal can't be set up.
+    pub fn new(
+        filenames: &[String],
+        stdin: Option<&str>,
+        config_path: Option<PathBuf>,
+        session: Option<PathBuf>,
+    ) -> Result<Self, std::io::Error> {
         // custom Panic Hook to execute terminate before the program ends
         let current_hook = take_hook();
         set_hook(Box::new(move |panic_info| {
-            let _ = Terminal::terminate();
+            error!("{panic_info}\n{}", Backtrace::force_capture());
+            log::logger().flush();
+            let _ = Terminal::default().terminate();
             current_hook(panic_info);
         }));
 
-        Terminal::initialize()?;
+        let mut editor = Self::with_terminal(Box::new(Terminal::default()))?;
+        if let Err(err) = editor.termination.listen() {
+            warn!("Could not handle termination signals: {err}");
+        }
+        editor.message_bar.update_message(
+            "HELP: <C-f> = find | <C-n> = search next | <C-s> = Save | <C-o> = Open | <C-t> = Quit",
+            Severity::Info,
+        );
+        // a broken config is more important than the help
+        editor.apply_config(Config::load(config_path.as_deref()));
+        editor.config_path = config_path;
+
+        editor.open_startup_files(filenames, stdin, session);
+        Ok(editor)
+    }
 
-        let mut editor = Self::default();
-        let size = Terminal::size().unwrap_or_default();
-        editor.handle_resize_command(size);
+    // the files from the command line, then the session, which may bring others to the front
+    fn open_startup_files(
+        &mut self,
+        filenames: &[String],
+        stdin: Option<&str>,
+        session: Option<PathBuf>,
+    ) {
+        let opened = self.open_files(filenames, stdin);
+        if let Some(session) = session {
+            self.restore_session(session);
+        }
 
-        let args: Vec<String> = env::args().collect();
-        if let Some(filename) = args.get(1) {
-            debug_assert!(!filename.is_empty());
-            editor.view.load(filename);
+        self.refresh_status();
+        match (opened, filenames.first()) {
+            // reported last, so the session's message doesn't hide it
+            (Err(err), Some(first)) => self.report_read_error(first, &err),
+            // recovered changes are offered once the file has been loaded
+            _ if !self.view().is_loading() => {
+                self.report_loaded(false);
+                self.offer_recovery();
+            }
+            _ => {}
         }
+    }
 
-        editor.refresh_status();
-        editor.message_bar.update_message(
-            "HELP: <C-f> = find | <C-n> = search next | <C-s> = Save | <C-t> = Quit",
-        );
+    // Open every file as a buffer. Only the first is read right away, the others once they
+    // are switched to, the error is the first's.
+    fn open_files(
+        &mut self,
+        filenames: &[String],
+        stdin: Option<&str>,
+    ) -> Result<(), std::io::Error> {
+        let Some((first, rest)) = filenames.split_first() else {
+            return Ok(());
+        };
+        debug_assert!(!first.is_empty());
+        for filename in rest {
+            let mut view = self.new_view();
+            if filename == "-" {
+                view.show_text(stdin.unwrap_or_default());
+            } else {
+                view.defer_load(filename);
+            }
+            self.views.push(view);
+        }
+        if !rest.is_empty() {
+            self.update_message(&format!("{} files to edit", filenames.len()));
+        }
+        let background_from = self.load_in_background_from;
+        if first == "-" {
+            self.view_mut().show_text(stdin.unwrap_or_default());
+            Ok(())
+        } else {
+            Self::load_view(self.view_mut(), first, background_from, Encoding::Utf8)
+        }
+    }
+
+    // an empty view, set up like the others
+    fn new_view(&self) -> View {
+        let mut view = View::default();
+        view.set_config(&self.config);
+        view.set_dictionary(&self.dictionary);
+        view
+    }
 
+    // an editor with an empty buffer, drawing to `terminal`
+    fn with_terminal(mut terminal: Box<dyn TerminalIo>) -> Result<Self, std::io::Error> {
+        terminal.initialize()?;
+        let size = terminal.size().unwrap_or_default();
+
+        let mut editor = Self {
+            terminal,
+            should_quit: false,
+            views: vec![View::default()],
+            recent_buffers: vec![0],
+            active_view: 0,
+            split: None,
+            status_bar: StatusBar::default(),
+            message_bar: MessageBar::default(),
+            command_bar: CommandBar::default(),
+            prompt_type: PromptType::default(),
+            confirmation: None,
+            completion: None,
+            word_completion: None,
+            overlay: None,
+            terminal_size: Size::default(),
+            title: String::new(),
+            pending_action: None,
+            autosave: None,
+            config: Config::default(),
+            dictionary: Rc::new(RefCell::new(dictionary_for(&Config::default()))),
+            commands: palette::registry(),
+            palette: Palette::default(),
+            keyboard_macro: KeyboardMacro::default(),
+            command_failed: false,
+            pending_count: None,
+            modal: Modal::default(),
+            yanked: Vec::new(),
+            keymap: Keymap::default(),
+            pending_chord: Vec::new(),
+            chord_started: Instant::now(),
+            pending_mark: None,
+            session: None,
+            search_worker: SearchWorker::default(),
+            file_search: None,
+            files_root: PathBuf::from("."),
+            load_in_background_from: BACKGROUND_LOAD_BYTES,
+            termination: Termination::default(),
+            config_path: None,
+            terminated_by: None,
+            recovery_dir: None,
+        };
+        editor.views[0].set_dictionary(&editor.dictionary);
+        editor.handle_resize_command(size);
         Ok(editor)
     }
 
+    /// Refuses to save the open buffers over their files.
+    pub fn set_read_only(&mut self) {
+        for view in &mut self.views {
+            view.set_read_only();
+        }
+        self.refresh_status();
+    }
+
+    /// Puts the caret on `line`, counting from 1.
+    pub fn start_at_line(&mut self, line: usize) {
+        self.view_mut().go_to_line(line.saturating_sub(1));
+    }
+
+    /// Saves modified buffers to a sidecar file once no key was pressed for `delay`.
+    pub fn enable_autosave(&mut self, delay: Duration) {
+        self.autosave = Some(Autosave::new(delay));
+    }
+
+    // the view of the focused pane
+    fn view(&self) -> &View {
+        match &self.split {
+            Some(split) if split.focused => &split.view,
+            _ => {
+                debug_assert!(self.active_view < self.views.len());
+                &self.views[self.active_view]
+            }
+        }
+    }
+
+    fn view_mut(&mut self) -> &mut View {
+        match &mut self.split {
+            Some(split) if split.focused => &mut split.view,
+            _ => {
+                debug_assert!(self.active_view < self.views.len());
+                &mut self.views[self.active_view]
+            }
+        }
+    }
+
+    // index of the buffer shown in the focused pane
+    fn focused_buffer_idx(&self) -> usize {
+        match &self.split {
+            Some(split) if split.focused => split.buffer_idx,
+            _ => self.active_view,
+        }
+    }
+
+    // sizes of the upper and lower pane, the lower one is empty if there is no split
+    fn pane_sizes(&self) -> (Size, Size) {
+        let width = self.terminal_size.width;
+        let height = self
+            .terminal_size
+            .height
+            .saturating_sub(2)
+            .saturating_sub(self.overlay_height());
+        if self.split.is_none() {
+            return (Size { height, width }, Size::default());
+        }
+
+        // one row is taken by the divider
+        let upper = height.saturating_sub(1).div_ceil(2);
+        let lower = height.saturating_sub(1).saturating_sub(upper);
+        (
+            Size {
+                height: upper,
+                width,
+            },
+            Size {
+                height: lower,
+                width,
+            },
+        )
+    }
+
+    // an overlay takes the bottom third of the rows above the status bar
+    fn overlay_height(&self) -> usize {
+        if self.overlay.is_some() {
+            self.terminal_size.height.saturating_sub(2).div_ceil(3)
+        } else {
+            0
+        }
+    }
+
     fn refresh_status(&mut self) {
-        let status = self.view.get_status();
+        let mut status = self.view().get_status();
+        status.buffer_idx = self.focused_buffer_idx();
+        status.buffer_count = self.views.len();
+        status.is_recording = self.keyboard_macro.is_recording();
+        status.pending_count = self.pending_count;
+        status.mode = self.config.modal.then(|| self.modal.mode().name());
+        if let Some(Overlay::Bytes(inspector)) = &self.overlay {
+            status.byte_offset = Some(inspector.byte_offset());
+        }
+
+        if let Some(split) = &mut self.split {
+            let mut upper_status = self.views[self.active_view].get_status();
+            upper_status.buffer_idx = self.active_view;
+            upper_status.buffer_count = self.views.len();
+            split.divider.update_status(upper_status);
+        }
 
-        let title = format!("{} - {NAME}", &status.filename);
-        if title != self.title && matches!(Terminal::set_title(&title), Ok(())) {
+        let title = if status.is_modified {
+            format!("{} [+] - {NAME}", &status.path)
+        } else {
+            format!("{} - {NAME}", &status.path)
+        };
+        if title != self.title && matches!(self.terminal.set_title(&title), Ok(())) {
             self.title = title;
         }
 
         self.status_bar.update_status(status);
     }
 
+    /// Reads keys from the terminal and evaluates them until hecto quits.
     pub fn run(&mut self) {
         loop {
             self.refresh_screen();
-            if self.should_quit {
+            if self.should_quit || self.check_termination() {
                 break;
             }
 
-            match read() {
-                Ok(event) => {
-                    self.evaluate_event(event);
-                }
-                Err(err) => {
-                    // panic if something goes wrong in a Release build
-                    // in case user can not leave hecto with `CTRL-T`
-                    #[cfg(debug_assertions)]
-                    {
-                        panic!("Could not read event: {err:?}");
-                    }
-                    #[cfg(not(debug_assertions))]
-                    {
-                        let _ = err;
-                    }
-                }
+            // with pending edits, a pending chord, a search or a load running only wait for
+            // input until they are due, and never longer than to look for a signal
+            let timeout = [
+                self.autosave.as_ref().and_then(Autosave::timeout),
+                self.chord_timeout(),
+                (self.search_worker.is_searching()
+                    || self.file_search.is_some()
+                    || self.loading_idx().is_some()
+                    || self.views.iter().any(View::is_comparing_with_head))
+                .then_some(POLL_INTERVAL),
+                Some(SIGNAL_INTERVAL),
+            ]
+            .into_iter()
+            .flatten()
+            .min();
+            if timeout.is_some_and(|timeout| !matches!(poll(timeout), Ok(true))) {
+                self.autosave();
+                self.expire_chord();
+            } else {
+                // handle all queued events behind a resize before redrawing, so dragging the
+                // window only redraws for the final size
+                while self.read_event() && matches!(poll(Duration::ZERO), Ok(true)) {}
             }
+            self.receive_search_results();
+            self.receive_file_matches(false);
+            self.receive_loaded(false);
+            self.receive_git_changes(false);
 
             self.refresh_status();
         }
     }
 
+    // whether a signal told hecto to terminate, which stops it like a crash would
+    fn check_termination(&mut self) -> bool {
+        if let Some(signal) = self.termination.signal() {
+            warn!("Terminated by signal {signal}");
+            self.terminated_by = Some(signal);
+        }
+        self.terminated_by.is_some()
+    }
+
+    /// The signal which stopped [`Editor::run`], if one did.
+    #[must_use]
+    pub fn terminated_by(&self) -> Option<i32> {
+        self.terminated_by
+    }
+
+    /// Replays `events` as if they had been typed, without reading from the terminal.
+    pub fn run_with_events(&mut self, events: impl IntoIterator<Item = Event>) {
+        for event in events {
+            self.refresh_screen();
+            if self.should_quit {
+                return;
+            }
+            self.process_event(event);
+            // scripts don't type ahead of a search or a load
+            self.wait_for_search();
+            self.receive_file_matches(true);
+            self.receive_loaded(true);
+            self.receive_git_changes(true);
+        }
+        self.refresh_screen();
+    }
+
+    /// Evaluates a single event, like a key or a resize of the terminal.
+    pub fn process_event(&mut self, event: Event) {
+        self.evaluate_event(event);
+        self.refresh_status();
+    }
+
+    // read and evaluate the next event, returning whether it was a resize
+    fn read_event(&mut self) -> bool {
+        match read() {
+            Ok(event) => {
+                let is_resize = matches!(event, Event::Resize(_, _));
+                self.evaluate_event(event);
+                is_resize
+            }
+            Err(err) => {
+                // panic if something goes wrong in a Release build
+                // in case user can not leave hecto with `CTRL-T`
+                #[cfg(debug_assertions)]
+                {
+                    panic!("Could not read event: {err:?}");
+                }
+                #[cfg(not(debug_assertions))]
+                {
+                    error!("Could not read event: {err:?}");
+                    false
+                }
+            }
+        }
+    }
+
     fn refresh_screen(&mut self) {
         if self.terminal_size.height == 0 || self.terminal_size.width == 0 {
             return;
         }
 
-        let _ = Terminal::hide_caret();
+        let _ = self.terminal.begin_synchronized_update();
+        let _ = self.terminal.hide_caret();
+        if self.is_too_small() {
+            self.draw_too_small();
+            let _ = self.terminal.end_synchronized_update();
+            let _ = self.terminal.execute();
+            return;
+        }
 
         let bottom_bar_row = self.terminal_size.height.saturating_sub(1);
         if self.no_prompt() {
-            self.message_bar.render(bottom_bar_row);
+            self.message_bar.render(&mut *self.terminal, bottom_bar_row);
         } else {
-            self.command_bar.render(bottom_bar_row);
+            self.command_bar.render(&mut *self.terminal, bottom_bar_row);
         }
 
         let height = self.terminal_size.height;
         if height > 1 {
-            self.status_bar.render(height.saturating_sub(2));
+            self.status_bar
+                .render(&mut *self.terminal, height.saturating_sub(2));
         }
+        let (upper, _) = self.pane_sizes();
         if height > 2 {
-            self.view.render(0);
+            self.views[self.active_view].render(&mut *self.terminal, 0);
+            if let Some(split) = &mut self.split {
+                split.divider.render(&mut *self.terminal, upper.height);
+                split
+                    .view
+                    .render(&mut *self.terminal, upper.height.saturating_add(1));
+            }
+            let overlay_row = height
+                .saturating_sub(2)
+                .saturating_sub(self.overlay_height());
+            if let Some(overlay) = &mut self.overlay {
+                overlay.component().render(&mut *self.terminal, overlay_row);
+            }
         }
 
         let new_caret_pos = if self.in_prompt() {
@@ -153,32 +676,171 @@ impl Editor {
                 row: bottom_bar_row,
                 col: self.command_bar.caret_position_col(),
             }
+        } else if self.split.as_ref().is_some_and(|split| split.focused) {
+            let Position { row, col } = self.view().caret_position();
+            Position {
+                row: row.saturating_add(upper.height).saturating_add(1),
+                col,
+            }
         } else {
-            self.view.caret_position()
+            self.view().caret_position()
         };
         debug_assert!(new_caret_pos.col <= self.terminal_size.width);
         debug_assert!(new_caret_pos.row <= self.terminal_size.height);
 
-        let _ = Terminal::move_caret_to(&new_caret_pos);
-        let _ = Terminal::show_caret();
-        let _ = Terminal::execute();
+        let _ = self.terminal.move_caret_to(&new_caret_pos);
+        let _ = self.terminal.show_caret();
+        let _ = self.terminal.end_synchronized_update();
+        let _ = self.terminal.execute();
+    }
+
+    fn is_too_small(&self) -> bool {
+        self.terminal_size.width < MIN_WIDTH || self.terminal_size.height < MIN_HEIGHT
+    }
+
+    // A note centered on screen instead of the panes and bars, which don't fit. The question
+    // whether to save before quitting is shown below it, as it is answered meanwhile.
+    fn draw_too_small(&mut self) {
+        let Size { height, width } = self.terminal_size;
+        let asks = self.prompt_type == PromptType::ConfirmQuit && height > 1;
+        let note_row = height
+            .saturating_sub(usize::from(asks))
+            .saturating_sub(1)
+            .div_euclid(2);
+        let note = format!("terminal too small (need ≥{MIN_WIDTH}x{MIN_HEIGHT})");
+        let note = Line::from(&note).get_visible_graphemes(0..width);
+        let padding = width
+            .saturating_sub(Line::from(&note).width())
+            .div_euclid(2);
+        for row in 0..height {
+            let text = if row == note_row {
+                format!("{:padding$}{note}", "")
+            } else {
+                String::new()
+            };
+            let _ = self.terminal.print_row(row, &text);
+        }
+        if asks {
+            // drawn over whatever the bar drew before
+            self.command_bar.set_needs_redraw(true);
+            self.command_bar
+                .render(&mut *self.terminal, height.saturating_sub(1));
+        }
     }
 
     fn evaluate_event(&mut self, event: Event) {
         let should_process = match &event {
-            Key(KeyEvent { kind, .. }) => kind == &KeyEventKind::Press,
+            // held keys repeat, releases reported by the kitty protocol don't type anything
+            Key(KeyEvent { kind, .. }) => kind != &KeyEventKind::Release,
             Event::Resize(_, _) => true,
+            // whatever covered hecto meanwhile may still be on screen
+            Event::FocusGained => {
+                self.redraw(false);
+                false
+            }
+            Event::Mouse(mouse) => {
+                self.process_mouse(*mouse);
+                false
+            }
             _ => false,
         };
 
-        #[allow(clippy::collapsible_if)]
         if should_process {
-            if let Ok(command) = Command::try_from(event) {
-                self.process_command(command);
+            // only quitting goes on while nothing can be seen, and answering whether to save
+            if let Key(key) = event
+                && self.is_too_small()
+                && self.prompt_type != PromptType::ConfirmQuit
+            {
+                if let Ok(command @ System(Quit)) = Command::try_from(Key(key)) {
+                    self.process_command(command);
+                }
+                return;
+            }
+            // the prompt to confirm quitting is the only one which can be open meanwhile
+            if let Key(key) = event
+                && self.prompt_type == PromptType::None
+                && self.loading_idx().is_some()
+            {
+                self.process_key_while_loading(key);
+                return;
+            }
+            if let Key(key) = event {
+                if let Some(autosave) = &mut self.autosave {
+                    autosave.key_pressed();
+                }
+                self.message_bar.key_pressed();
+                self.keyboard_macro.record(key);
+                if let Some(action) = self.pending_mark.take() {
+                    self.process_mark_key(action, key);
+                    return;
+                }
+                // chords don't apply to prompts, they take keys as they are
+                if self.prompt_type == PromptType::None && self.process_chord_key(key) {
+                    return;
+                }
+                if self.config.modal && self.prompt_type == PromptType::None {
+                    match self.modal.translate(key, self.pending_count.is_some()) {
+                        Translation::Unchanged => {}
+                        Translation::Ignored => return,
+                        Translation::Action(action) => {
+                            self.process_modal_action(action);
+                            return;
+                        }
+                    }
+                }
+            }
+            match Command::try_from(event) {
+                Ok(command) => self.process_command(command),
+                Err(err) => debug!("{err}"),
             }
         }
     }
 
+    // Clicks on the status bar run what its segments stand for, everything else ignores the
+    // mouse. Each of them has a name in the palette as well.
+    fn process_mouse(&mut self, mouse: MouseEvent) {
+        if !self.prompt_type.is_none() || self.loading_idx().is_some() || self.is_too_small() {
+            return;
+        }
+        let (row, col) = (usize::from(mouse.row), usize::from(mouse.column));
+        if matches!(
+            mouse.kind,
+            MouseEventKind::Down(MouseButton::Left) | MouseEventKind::Drag(MouseButton::Left)
+        ) && col == self.terminal_size.width.saturating_sub(1)
+            && self.scroll_by_scrollbar(row)
+        {
+            return;
+        }
+        let status_row = self.terminal_size.height.checked_sub(2);
+        if mouse.kind != MouseEventKind::Down(MouseButton::Left) || status_row != Some(row) {
+            return;
+        }
+        let name = match self.status_bar.segment_at(usize::from(mouse.column)) {
+            Some(Segment::Position) => "go-to-line",
+            Some(Segment::FileType) => "set-file-type",
+            Some(Segment::Modified) => "save",
+            None => return,
+        };
+        self.run_command(name);
+    }
+
+    // jump through the pane whose scrollbar `row` is on, false if it's on none
+    fn scroll_by_scrollbar(&mut self, row: usize) -> bool {
+        let (upper, lower) = self.pane_sizes();
+        let lower_top = upper.height.saturating_add(1);
+        if row < upper.height {
+            self.views[self.active_view].scroll_to_scrollbar_row(row)
+        } else if let Some(split) = &mut self.split
+            && (lower_top..lower_top.saturating_add(lower.height)).contains(&row)
+        {
+            split
+                .view
+                .scroll_to_scrollbar_row(row.saturating_sub(lower_top))
+        } else {
+            false
+        }
+    }
+
     fn process_command(&mut self, command: Command) {
         if let System(Resize(size)) = command {
             self.handle_resize_command(size);
@@ -186,8 +848,49 @@ impl Editor {
 
         match self.prompt_type {
             PromptType::None => self.process_command_no_prompt(command),
-            PromptType::Save => self.process_command_during_save(command),
-            PromptType::Search => self.process_command_during_search(command),
+            PromptType::Matches => self.process_command_during_matches(command),
+            PromptType::Diff => self.process_command_during_diff(command),
+            PromptType::Bytes => self.process_command_during_bytes(command),
+            PromptType::Buffers => self.process_command_during_buffers(command),
+            PromptType::Confirm => self.process_command_during_confirm(&command),
+            PromptType::ConfirmQuit => self.process_command_during_confirm_quit(&command),
+            _ => {
+                if let Some(prompt) = prompt::text_prompt(&self.prompt_type) {
+                    self.process_command_during_prompt(&prompt, command);
+                }
+            }
+        }
+    }
+
+    // the keys every prompt asking for text shares, what they lead to is up to the prompt
+    fn process_command_during_prompt(&mut self, prompt: &Prompt, command: Command) {
+        match command {
+            System(Dismiss) => {
+                self.dismiss_prompt();
+                (prompt.on_cancel)(self);
+            }
+            Edit(command::Edit::InsertNewline) => {
+                let value = self.command_bar.value();
+                // dismissed first, the prompt may lead to another one
+                self.dismiss_prompt();
+                (prompt.on_submit)(self, &value);
+            }
+            Edit(command::Edit::InsertTab) if prompt.on_tab.is_some() => {
+                if let Some(on_tab) = prompt.on_tab {
+                    on_tab(self);
+                }
+            }
+            Edit(command::Edit::Insert(ch)) if !(prompt.accepts)(ch) => {}
+            Edit(command) => {
+                self.command_bar.handle_edit_command(command);
+                (prompt.on_change)(self);
+            }
+            Move(command @ (command::Move::Up | command::Move::Down)) => {
+                if let Some(on_select) = prompt.on_select {
+                    on_select(self, command);
+                }
+            }
+            System(_) | Move(_) => {}
         }
     }
 
@@ -198,179 +901,4350 @@ impl Editor {
             width: size.width,
         };
 
-        self.view.resize(Size {
-            height: size.height.saturating_sub(2),
+        let (upper, lower) = self.pane_sizes();
+        for view in &mut self.views {
+            view.resize(upper);
+        }
+        if let Some(split) = &mut self.split {
+            split.view.resize(lower);
+            split.divider.resize(bar_size);
+        }
+        let overlay_size = Size {
+            height: self.overlay_height(),
             width: size.width,
-        });
+        };
+        if let Some(overlay) = &mut self.overlay {
+            overlay.component().resize(overlay_size);
+        }
         self.status_bar.resize(bar_size);
         self.message_bar.resize(bar_size);
         self.command_bar.resize(bar_size);
     }
 
     fn process_command_no_prompt(&mut self, command: Command) {
-        if matches!(command, System(Quit)) {
-            self.handle_quit();
-            return;
+        // any other command uses up the count, Esc just clears it
+        let count = match command {
+            System(Resize(_) | Count(_)) => 1,
+            _ => self.take_count(),
+        };
+        // only another completion keeps cycling through the candidates
+        if !matches!(
+            command,
+            System(CompleteWord) | Edit(command::Edit::InsertTab)
+        ) {
+            self.word_completion = None;
         }
-        self.reset_quit_times();
 
         match command {
-            System(Quit | Resize(_) | Dismiss) => {}
-            System(Save) => self.handle_save(),
-            System(Search) => self.handle_search(),
-            System(SearchNext) => self.handle_search_next(),
-            System(SearchPrevious) => self.handle_search_previous(),
-            Move(command) => self.view.handle_move_command(&command),
-            Edit(command) => self.view.handle_edit_command(&command),
+            System(Resize(_) | Dismiss) => {}
+            System(Count(digit)) => self.push_count_digit(digit),
+            System(CommandPalette) => self.set_prompt(PromptType::Palette),
+            System(RecordMacro) => self.toggle_macro_recording(),
+            System(PlayMacro) => self.play_macro(),
+            System(command) => {
+                if let Some(name) = command.name() {
+                    self.run_command(name);
+                }
+            }
+            Move(command) => self.view_mut().handle_repeated_move_command(command, count),
+            // Tab completes after a word
+            Edit(command::Edit::InsertTab) if self.view().word_before_caret().is_some() => {
+                self.complete_word(true);
+            }
+            Edit(command) => {
+                self.view_mut().handle_repeated_edit_command(command, count);
+                self.edited();
+            }
         }
     }
 
-    fn reset_quit_times(&mut self) {
-        if self.quit_times > 0 {
-            self.quit_times = 0;
-            self.update_message("");
+    // after the focused buffer was changed
+    fn edited(&mut self) {
+        self.redraw_other_pane();
+        if let Some(autosave) = &mut self.autosave {
+            autosave.edited();
         }
     }
 
-    // clippy::arithmetic_side_effects: quit_times is guaranteed to be between 0 and QUIT_TIMES
-    #[allow(clippy::arithmetic_side_effects)]
-    fn handle_quit(&mut self) {
-        #[cfg(debug_assertions)]
-        {
-            self.should_quit = true;
-        }
-        #[cfg(not(debug_assertions))]
-        {
-            let is_modified = self.view.get_status().is_modified;
-            if !is_modified || self.quit_times.saturating_add(1) == QUIT_TIMES {
-                self.should_quit = true;
-            } else if is_modified {
-                self.update_message(&format!(
-                    "WARNING!!! File has unsaved changes. Press Ctrl-T {} more times to quit.",
-                    QUIT_TIMES - self.quit_times - 1
-                ));
-                self.quit_times += 1;
-            }
-        }
+    fn take_count(&mut self) -> usize {
+        self.pending_count.take().unwrap_or(1).max(1)
     }
 
-    fn handle_save(&mut self) {
-        if self.view.is_file_loaded() {
-            self.save(None);
+    fn push_count_digit(&mut self, digit: u8) {
+        let count = self
+            .pending_count
+            .unwrap_or(0)
+            .saturating_mul(10)
+            .saturating_add(usize::from(digit));
+        self.pending_count = Some(count.min(MAX_COUNT));
+    }
+
+    // region: quit
+    fn handle_quit(&mut self) {
+        if !self.config.quit_confirmation || self.modified_buffer_names().is_empty() {
+            self.should_quit = true;
         } else {
-            self.set_prompt(PromptType::Save);
+            self.set_prompt(PromptType::ConfirmQuit);
         }
     }
 
-    fn save(&mut self, filename: Option<&str>) {
-        let result = if let Some(filename) = filename {
-            self.view.save_as(filename)
-        } else {
-            self.view.save()
-        };
+    fn save_all_and_quit(&mut self) {
+        self.continue_saving_all(0, true);
+    }
 
-        let msg = match result {
-            Ok(()) => "File saved successfully",
-            Err(_) => "Error writing file!",
+    fn process_command_during_confirm_quit(&mut self, command: &Command) {
+        let answer = match command {
+            System(Dismiss) => 'c',
+            Edit(edit) => match self.command_bar.answer(*edit) {
+                Some(answer) => answer,
+                None => return,
+            },
+            System(_) | Move(_) => return,
         };
-        self.update_message(msg);
+        match answer {
+            'y' => {
+                self.dismiss_prompt();
+                self.save_all_and_quit();
+            }
+            'n' => self.should_quit = true,
+            _ => {
+                self.dismiss_prompt();
+                self.update_message("Quit aborted");
+            }
+        }
     }
 
-    fn handle_search(&mut self) {
-        self.set_prompt(PromptType::Search);
-        self.view.enter_search();
-        self.update_message("");
+    // Esc on a prompt some action waits for goes back to the question which started it, so
+    // the whole chain only ends with an Esc there
+    fn abort(&mut self, aborted: &str) {
+        match self.pending_action.take() {
+            Some(PendingAction::SaveAll { quit: true, .. }) => {
+                self.set_prompt(PromptType::ConfirmQuit);
+            }
+            Some(PendingAction::SaveAll { quit: false, .. }) | None => {
+                self.update_message(&format!("{aborted} aborted"));
+            }
+        }
     }
 
-    fn handle_search_next(&mut self) {
-        self.view.search_next();
-        // let success = self.view.search_next();
-        // if !success {
-        //     self.update_message("Have no search query, please search for something first");
-        // }
+    // the name of every buffer and whether it has unsaved changes, in order
+    fn buffers(&self) -> impl Iterator<Item = (String, bool)> + '_ {
+        self.views
+            .iter()
+            .map(View::get_status)
+            .map(|status| (status.filename, status.is_modified))
     }
 
-    fn handle_search_previous(&mut self) {
-        self.view.search_backward();
-        // let success = self.view.search_previous();
-        // if !success {
-        //     self.update_message("Have no search query, please search for something first");
-        // }
+    fn modified_buffer_names(&self) -> Vec<String> {
+        self.buffers()
+            .filter(|(_, is_modified)| *is_modified)
+            .map(|(name, _)| name)
+            .collect()
     }
+    // endregion
 
-    fn process_command_during_save(&mut self, command: Command) {
-        match command {
-            System(Quit | Resize(_) | Save | Search | SearchNext | SearchPrevious) => {}
-            System(Dismiss) => {
-                self.dismiss_prompt();
-                self.update_message("Save aborted");
-            }
-            Move(command) => self.command_bar.handle_move_command(&command),
-            Edit(command) => {
-                if matches!(command, command::Edit::InsertNewline) {
-                    let pat = self.command_bar.value();
-                    self.save(Some(&pat));
-                    self.dismiss_prompt();
-                } else {
-                    self.command_bar.handle_edit_command(&command);
-                }
+    // region: session
+    // reopen the buffers of the session at `path`, which is written back on quit. Files which
+    // are gone are skipped, and a file given on the command line stays in front.
+    fn restore_session(&mut self, path: PathBuf) {
+        let session = Session::load(&path);
+        self.session = Some(path);
+        let session = match session {
+            Ok(Some(session)) => session,
+            Ok(None) => return,
+            Err(err) => {
+                self.show_error(&err);
+                return;
             }
-        }
-    }
+        };
 
-    fn process_command_during_search(&mut self, command: Command) {
-        match command {
-            System(Quit | Resize(_) | Save | Search | SearchNext | SearchPrevious) => {}
-            Move(command) => self.command_bar.handle_move_command(&command),
-            System(Dismiss) => {
-                self.dismiss_prompt();
-                self.view.dismiss_search();
-                self.update_message("Search aborted");
+        let from_command_line = self.view().file_path();
+        let mut active = None;
+        let mut missing = Vec::new();
+        for (idx, buffer) in session.buffers.iter().enumerate() {
+            let filename = buffer.path.to_string_lossy();
+            if !buffer.path.is_file() {
+                missing.push(filename.to_string());
+                continue;
             }
-            Edit(command::Edit::InsertNewline) => {
-                self.dismiss_prompt();
+            if let Some(open_idx) = self
+                .views
+                .iter()
+                .position(|view| view.is_same_file(&filename))
+            {
+                self.activate_buffer(open_idx);
+            } else if !self.load_buffer(&filename, false, Encoding::Utf8) {
+                continue;
             }
-            Edit(command) => {
-                self.command_bar.handle_edit_command(&command);
-                let query = self.command_bar.value();
-                self.view.search(&query);
+            self.view_mut().restore_position(
+                Location {
+                    line_idx: buffer.line_idx,
+                    grapheme_idx: buffer.grapheme_idx,
+                },
+                Position {
+                    row: buffer.scroll_row,
+                    col: buffer.scroll_col,
+                },
+            );
+            if idx == session.active {
+                active = Some(self.focused_buffer_idx());
             }
         }
+
+        let front = from_command_line
+            .and_then(|path| {
+                self.views
+                    .iter()
+                    .position(|view| view.file_path().as_ref() == Some(&path))
+            })
+            .or(active);
+        if let Some(idx) = front {
+            self.activate_buffer(idx);
+        }
+        if missing.is_empty() {
+            self.update_message("Session restored");
+        } else {
+            self.show_warning(&format!(
+                "Skipped files which no longer exist: {}",
+                missing.join(", ")
+            ));
+        }
     }
 
-    fn update_message(&mut self, new_message: &str) {
-        self.message_bar.update_message(new_message);
+    // the named buffers with where their carets are, unnamed ones can't be reopened
+    fn session(&self) -> Session {
+        let focused = self.focused_buffer_idx();
+        let mut session = Session {
+            version: session::VERSION,
+            ..Session::default()
+        };
+        for (idx, view) in self.views.iter().enumerate() {
+            // the focused pane may show the buffer somewhere else than the upper one
+            let view = if idx == focused { self.view() } else { view };
+            let Some(path) = view.file_path() else {
+                continue;
+            };
+            if idx == focused {
+                session.active = session.buffers.len();
+            }
+            let Location {
+                line_idx,
+                grapheme_idx,
+            } = view.caret_location();
+            let Position { row, col } = view.scroll_offset();
+            session.buffers.push(BufferState {
+                path: std::path::absolute(&path).unwrap_or(path),
+                line_idx,
+                grapheme_idx,
+                scroll_row: row,
+                scroll_col: col,
+            });
+        }
+        session
     }
+    // endregion
 
-    fn no_prompt(&self) -> bool {
-        self.prompt_type.is_none()
+    // wraps around at both ends of the buffer list
+    fn switch_buffer(&mut self, idx: usize) {
+        let count = self.views.len();
+        if count <= 1 {
+            self.show_warning("No other buffers open");
+            return;
+        }
+        self.activate_buffer(idx.checked_rem(count).unwrap_or(0));
     }
 
-    fn in_prompt(&self) -> bool {
-        !self.no_prompt()
+    // show the buffer at `idx` in the focused pane
+    fn activate_buffer(&mut self, idx: usize) {
+        debug_assert!(idx < self.views.len());
+        let is_unread = self.views[idx].is_unread();
+        if is_unread && !self.read_unread(idx) {
+            return;
+        }
+        let (upper, lower) = self.pane_sizes();
+        match &mut self.split {
+            Some(split) if split.focused => {
+                split.view = self.views[idx].share_buffer();
+                split.view.resize(lower);
+                split.buffer_idx = idx;
+            }
+            _ => {
+                self.active_view = idx;
+                let view = self.view_mut();
+                view.resize(upper);
+                view.set_needs_redraw(true);
+            }
+        }
+        self.recent_buffers.retain(|other| *other != idx);
+        self.recent_buffers.push(idx);
+        if is_unread && !self.view().is_loading() {
+            self.report_loaded(false);
+            self.offer_recovery();
+        }
     }
 
-    fn set_prompt(&mut self, prompt_type: PromptType) {
-        match prompt_type {
-            PromptType::None => self.message_bar.set_needs_redraw(true),
-            PromptType::Save => self.command_bar.set_prompt("Save as: "),
-            PromptType::Search => self.command_bar.set_prompt("Search: "),
+    // Close the focused buffer, asking first if it has unsaved changes. A prompt may still
+    // need the buffer, so it has to be finished first.
+    fn close_buffer(&mut self) {
+        if !self.prompt_type.is_none() {
+            self.show_warning("Finish the prompt before closing the buffer");
+        } else if self.view().get_status().is_modified {
+            self.confirm(Confirmation::Close);
+        } else {
+            self.discard_buffer();
         }
-        self.command_bar.clear_value();
-        self.prompt_type = prompt_type;
     }
 
-    fn dismiss_prompt(&mut self) {
-        self.prompt_type = PromptType::None;
-        self.message_bar.set_needs_redraw(true);
+    // close the focused buffer whatever its changes
+    fn discard_buffer(&mut self) {
+        let filename = self.view().get_status().filename;
+        self.remove_buffer(self.focused_buffer_idx());
+        self.update_message(&format!("Closed {filename}"));
     }
-}
 
-impl Drop for Editor {
-    fn drop(&mut self) {
-        let _ = Terminal::terminate();
-        if self.should_quit {
-            let _ = Terminal::print("Goodbye.\r\n");
+    // Panes showing the removed buffer go to the one shown most recently. The last buffer
+    // is replaced by an empty one, so there is always one to edit.
+    fn remove_buffer(&mut self, idx: usize) {
+        if self.views.len() <= 1 {
+            self.views[idx] = self.new_view();
+            if let Some(split) = &mut self.split {
+                split.view = self.views[idx].share_buffer();
+            }
+            self.handle_resize_command(self.terminal_size);
+            return;
         }
+
+        self.views.remove(idx);
+        let last = self.views.len().saturating_sub(1);
+        // indices behind the closed buffer move down by one
+        self.recent_buffers.retain(|other| *other != idx);
+        for other in &mut self.recent_buffers {
+            if *other > idx {
+                *other = other.saturating_sub(1);
+            }
+        }
+        let recent = self.recent_buffers.last().copied().unwrap_or(idx.min(last));
+        let shift = |other: usize| match other.cmp(&idx) {
+            Ordering::Greater => other.saturating_sub(1),
+            Ordering::Equal => recent,
+            Ordering::Less => other,
+        };
+        self.active_view = shift(self.active_view);
+        if let Some(split) = &mut self.split {
+            let buffer_idx = shift(split.buffer_idx);
+            if split.buffer_idx == idx {
+                split.view = self.views[buffer_idx].share_buffer();
+            }
+            split.buffer_idx = buffer_idx;
+        }
+        self.handle_resize_command(self.terminal_size);
+        // buffers named on the command line are only read once shown
+        let focused = self.focused_buffer_idx();
+        if self.views[focused].is_unread() {
+            self.activate_buffer(focused);
+        }
+    }
+
+    // draw everything again, after clearing the screen if it is garbled
+    fn redraw(&mut self, clear: bool) {
+        if clear {
+            let _ = self.terminal.clear_screen();
+        }
+        // resizing to the same size forgets what was drawn
+        self.handle_resize_command(self.terminal_size);
+    }
+
+    // region: split
+    fn split_view(&mut self) {
+        if self.split.is_some() {
+            self.show_warning("View is already split");
+            return;
+        }
+
+        let view = self.views[self.active_view].share_buffer();
+        self.split = Some(Split::new(view, self.active_view));
+        self.handle_resize_command(self.terminal_size);
+    }
+
+    fn close_split(&mut self) {
+        if self.split.take().is_none() {
+            self.show_warning("View is not split");
+            return;
+        }
+        self.handle_resize_command(self.terminal_size);
+    }
+
+    fn switch_pane(&mut self) {
+        if let Some(split) = &mut self.split {
+            split.focused = !split.focused;
+        } else {
+            self.show_warning("View is not split");
+        }
+    }
+
+    // The unfocused pane has to redraw if both panes show the same buffer, and its caret
+    // may have been left behind the last line.
+    fn redraw_other_pane(&mut self) {
+        if let Some(split) = &mut self.split {
+            let upper = &mut self.views[self.active_view];
+            if split.view.shares_buffer_with(upper) {
+                upper.snap_caret();
+                split.view.snap_caret();
+                upper.set_needs_redraw(true);
+                split.view.set_needs_redraw(true);
+            }
+        }
+    }
+    // endregion
+
+    fn open(&mut self, filename: &str) {
+        self.open_as(filename, Encoding::Utf8);
+    }
+
+    fn open_as(&mut self, filename: &str, encoding: Encoding) {
+        if filename.is_empty() {
+            self.update_message("Open aborted");
+            return;
+        }
+
+        if let Some(idx) = self
+            .views
+            .iter()
+            .position(|view| view.is_same_file(filename))
+        {
+            self.activate_buffer(idx);
+            self.update_message("Switched to already open buffer");
+            return;
+        }
+
+        if self.load_buffer(filename, true, encoding) {
+            self.update_message("");
+            if !self.view().is_loading() {
+                self.report_loaded(false);
+                self.offer_recovery();
+            }
+        }
+    }
+
+    // a file under the caret goes into a buffer of its own, anything else like a URL to the
+    // system's opener
+    fn open_under_caret(&mut self) {
+        let dir = self
+            .view()
+            .file_path()
+            .and_then(|path| path.parent().map(Path::to_path_buf));
+        let target = self
+            .view()
+            .token_under_caret()
+            .and_then(|token| link::target(&token, dir.as_deref()));
+        let external = match target {
+            Some(Target::Path(path)) if path.is_file() => {
+                self.open(&path.to_string_lossy());
+                return;
+            }
+            Some(Target::Path(path)) if !path.exists() => {
+                self.command_failed = true;
+                self.show_warning(&format!("No file '{}'", path.display()));
+                return;
+            }
+            Some(Target::Path(path)) => path.to_string_lossy().to_string(),
+            Some(Target::Url(url)) => url,
+            None => {
+                self.command_failed = true;
+                self.show_warning("Nothing to open under the caret");
+                return;
+            }
+        };
+        match link::open_externally(&external) {
+            Ok(()) => self.update_message(&format!("Opened {external}")),
+            Err(err) => {
+                self.command_failed = true;
+                self.show_error(&err);
+            }
+        }
+    }
+
+    // load `filename` into a buffer of its own and show it, false if it couldn't be read
+    // Big files are read in the background if `in_background` is set, they are loaded
+    // entirely otherwise.
+    fn load_buffer(&mut self, filename: &str, in_background: bool, encoding: Encoding) -> bool {
+        let path = completion::expand_tilde(filename);
+        let filename = &path.to_string_lossy();
+        let mut view = self.new_view();
+        let background_from = if in_background {
+            self.load_in_background_from
+        } else {
+            u64::MAX
+        };
+        if let Err(err) = Self::load_view(&mut view, filename, background_from, encoding) {
+            self.report_read_error(filename, &err);
+            return false;
+        }
+        view.resize(self.pane_sizes().0);
+
+        // replace the initial scratch buffer instead of keeping it around
+        let is_scratch = self.split.is_none()
+            && !self.view().is_file_loaded()
+            && !self.view().get_status().is_modified;
+        if is_scratch {
+            *self.view_mut() = view;
+        } else {
+            self.views.push(view);
+            self.activate_buffer(self.views.len().saturating_sub(1));
+        }
+        true
+    }
+
+    // region: loading
+    // read the file of a buffer named on the command line, which is dropped if it can't be
+    fn read_unread(&mut self, idx: usize) -> bool {
+        let Some(path) = self.views[idx].file_path() else {
+            return true;
+        };
+        let filename = path.to_string_lossy().to_string();
+        // one file is read in the background at most
+        let background_from = if self.loading_idx().is_some() {
+            u64::MAX
+        } else {
+            self.load_in_background_from
+        };
+        if let Err(err) = Self::load_view(
+            &mut self.views[idx],
+            &filename,
+            background_from,
+            Encoding::Utf8,
+        ) {
+            self.remove_buffer(idx);
+            self.report_read_error(&filename, &err);
+            return false;
+        }
+        let size = self.pane_sizes().0;
+        self.views[idx].resize(size);
+        true
+    }
+
+    // files of at least `background_from` bytes are read in the background
+    fn load_view(
+        view: &mut View,
+        filename: &str,
+        background_from: u64,
+        encoding: Encoding,
+    ) -> Result<(), std::io::Error> {
+        let len = fs::metadata(filename).map_or(0, |metadata| metadata.len());
+        if len >= background_from {
+            view.start_loading(filename, encoding)
+        } else {
+            view.load(filename, encoding)
+        }
+    }
+
+    // Tell why `filename` couldn't be read. A file which isn't UTF-8 is offered to be read as
+    // Windows-1252 instead, unless another question is waiting for an answer.
+    fn report_read_error(&mut self, filename: &str, err: &std::io::Error) {
+        if err.kind() == std::io::ErrorKind::InvalidData && self.prompt_type == PromptType::None {
+            warn!("{filename} is not valid UTF-8: {err}");
+            self.confirm(Confirmation::Windows1252(filename.to_string()));
+        } else {
+            self.show_error(&io_error_message("reading", filename, err));
+        }
+    }
+
+    // the buffer whose file is still being read, there is one at most
+    fn loading_idx(&self) -> Option<usize> {
+        self.views.iter().position(View::is_loading)
+    }
+
+    // show what arrived of the file being loaded, or wait for the rest if `wait` is set
+    fn receive_loaded(&mut self, wait: bool) {
+        let Some(idx) = self.loading_idx() else {
+            return;
+        };
+        let result = self.views[idx].receive_loaded(wait);
+        let filename = self.views[idx].get_status().filename;
+        self.redraw_other_pane();
+        match result {
+            Ok(()) => {
+                if let Some(percent) = self.views[idx].load_percent() {
+                    self.update_message(&format!("Loading {filename}… {percent}%"));
+                } else {
+                    self.report_loaded(true);
+                    self.offer_recovery();
+                }
+            }
+            Err(err) => {
+                let path = self.views[idx].file_path();
+                self.abandon_loading(idx);
+                let filename = path.map_or(filename, |path| path.to_string_lossy().to_string());
+                self.report_read_error(&filename, &err);
+            }
+        }
+    }
+
+    // Warn about a line too long to be highlighted in the file just loaded. Files read in the
+    // background are big enough for their size to be worth telling.
+    fn report_loaded(&mut self, in_background: bool) {
+        let size = self.view().text_size();
+        let long_line = size
+            .longest_line
+            .filter(|(_, bytes)| *bytes > self.config.long_line_bytes);
+        if let Some((line_idx, bytes)) = long_line {
+            self.show_warning(&format!(
+                "Line {} is {bytes} bytes long, it isn't highlighted",
+                line_idx.saturating_add(1)
+            ));
+        } else if in_background {
+            self.update_message(&format!(
+                "Loaded {} characters in {} bytes",
+                size.chars, size.bytes
+            ));
+        }
+    }
+
+    // mark how lines differ from their files as checked in once git has read them, or wait
+    // for git if `wait` is set
+    fn receive_git_changes(&mut self, wait: bool) {
+        let mut received = false;
+        for view in &mut self.views {
+            received |= view.receive_head(wait);
+        }
+        if received && let Some(split) = &mut self.split {
+            split.view.set_needs_redraw(true);
+        }
+    }
+
+    // Only moving around and quitting work until the file has been loaded, so nothing
+    // can change the buffer meanwhile. Esc stops loading.
+    fn process_key_while_loading(&mut self, key: KeyEvent) {
+        let Some(idx) = self.loading_idx() else {
+            return;
+        };
+        let filename = self.views[idx].get_status().filename;
+        match Command::try_from(Key(key)) {
+            Ok(command @ (Move(_) | System(Quit))) => self.process_command(command),
+            Ok(System(Dismiss)) => {
+                self.abandon_loading(idx);
+                self.update_message(&format!("Stopped loading {filename}"));
+            }
+            _ => self.show_warning(&format!("{filename} is still loading, <Esc> stops it")),
+        }
+    }
+
+    // close a buffer whose file couldn't be loaded entirely, as if it had never been opened
+    fn abandon_loading(&mut self, idx: usize) {
+        self.views[idx].cancel_loading();
+        self.remove_buffer(idx);
+    }
+    // endregion
+
+    fn handle_save(&mut self) {
+        // a read-only buffer can only be saved under another name
+        if self.view().is_file_loaded() && !self.view().is_read_only() {
+            self.save(None);
+        } else {
+            self.set_prompt(PromptType::Save);
+        }
+    }
+
+    fn save(&mut self, filename: Option<&str>) {
+        let target = filename
+            .map(completion::expand_tilde)
+            .or_else(|| self.view().file_path());
+        match target.as_deref().map(missing_directory) {
+            Some(Err(message)) => {
+                self.show_error(&message);
+                return;
+            }
+            Some(Ok(Some(dir))) if !self.config.create_directories => {
+                let filename = filename.map(ToString::to_string);
+                self.confirm(Confirmation::CreateDirectory { dir, filename });
+                return;
+            }
+            Some(Ok(Some(dir))) => {
+                if let Err(err) = fs::create_dir_all(&dir) {
+                    let dir = dir.to_string_lossy();
+                    self.show_error(&io_error_message("creating", &dir, &err));
+                    return;
+                }
+            }
+            Some(Ok(None)) | None => {}
+        }
+        let format_error = self.format_on_save(filename).err();
+        let (result, path) = if let Some(filename) = filename {
+            let path = completion::expand_tilde(filename);
+            let path = path.to_string_lossy();
+            (self.view_mut().save_as(&path), path.to_string())
+        } else {
+            (self.view_mut().save(), self.view().get_status().filename)
+        };
+
+        match result {
+            Ok(()) => {
+                self.redraw_other_pane();
+                if let Some(path) = self.view().file_path() {
+                    self.remove_autosave_sidecar(&path);
+                }
+                if let Some(err) = format_error {
+                    self.show_error(&format!("Saved unformatted: {err}"));
+                } else {
+                    self.update_message("File saved successfully");
+                }
+            }
+            Err(err) => self.show_error(&io_error_message("writing", &path, &err)),
+        }
+    }
+
+    // format the focused buffer if a formatter is configured for the file it is saved to
+    fn format_on_save(&mut self, filename: Option<&str>) -> Result<(), String> {
+        let path = filename
+            .map(completion::expand_tilde)
+            .or_else(|| self.view().file_path());
+        let Some(formatter) = path
+            .and_then(|path| self.config.formatter(&path))
+            .map(ToString::to_string)
+        else {
+            return Ok(());
+        };
+        if Self::format(self.view_mut(), &formatter)? {
+            self.edited();
+        }
+        Ok(())
+    }
+
+    fn format_buffer(&mut self) {
+        let Some(formatter) = self
+            .view()
+            .file_path()
+            .and_then(|path| self.config.formatter(&path))
+            .map(ToString::to_string)
+        else {
+            self.show_warning("No formatter configured for this file type");
+            return;
+        };
+        match Self::format(self.view_mut(), &formatter) {
+            Ok(true) => {
+                self.edited();
+                self.update_message("Buffer formatted");
+            }
+            Ok(false) => self.update_message("Buffer already formatted"),
+            Err(err) => {
+                self.command_failed = true;
+                self.show_error(&err);
+            }
+        }
+    }
+
+    // pipe the buffer through `formatter`, returning whether that changed it
+    fn format(view: &mut View, formatter: &str) -> Result<bool, String> {
+        let output = shell::filter(formatter, &view.text())?;
+        Ok(view.reformat_text(&output.text))
+    }
+
+    // rearrange the lines of the buffer, there being no selection to limit it to
+    fn transform_lines(&mut self, done: &str, transform: impl FnOnce(Vec<Line>) -> Vec<Line>) {
+        if self.view_mut().transform_lines(transform) {
+            self.edited();
+            self.update_message(done);
+        } else {
+            self.update_message("Nothing changed");
+        }
+    }
+
+    // save every modified buffer with a file, unnamed ones need a name from `Ctrl-S`
+    fn save_all(&mut self) {
+        self.continue_saving_all(0, false);
+    }
+
+    // Saves the modified buffers, asking for a filename for unnamed ones one at a time, and
+    // quits afterwards if `quit` is set. `saved` buffers were saved before. The first error
+    // stops it.
+    fn continue_saving_all(&mut self, mut saved: usize, quit: bool) {
+        let mut format_error = None;
+        for idx in 0..self.views.len() {
+            let status = self.views[idx].get_status();
+            if !status.is_modified {
+                continue;
+            }
+            if !self.views[idx].is_file_loaded() || self.views[idx].is_read_only() {
+                self.activate_buffer(idx);
+                self.pending_action = Some(PendingAction::SaveAll { saved, quit });
+                self.set_prompt(PromptType::Save);
+                return;
+            }
+            let formatter = self.views[idx]
+                .file_path()
+                .and_then(|path| self.config.formatter(&path))
+                .map(ToString::to_string);
+            if let Some(formatter) = formatter
+                && let Err(err) = Self::format(&mut self.views[idx], &formatter)
+            {
+                format_error = Some(format!("Saved {} unformatted: {err}", status.filename));
+            }
+            if let Err(err) = self.views[idx].save() {
+                let msg = io_error_message("writing", &status.filename, &err);
+                self.show_error(&if quit {
+                    format!("{msg}. Quit aborted")
+                } else {
+                    msg
+                });
+                return;
+            }
+            saved = saved.saturating_add(1);
+            if let Some(path) = self.views[idx].file_path() {
+                self.remove_autosave_sidecar(&path);
+            }
+            // quitting would hide that the formatter failed on it
+            if quit && let Some(err) = &format_error {
+                self.show_error(&format!("{err}. Quit aborted"));
+                return;
+            }
+        }
+        if quit {
+            self.should_quit = true;
+            return;
+        }
+
+        self.redraw_other_pane();
+        match format_error {
+            Some(err) => self.show_error(&err),
+            None if saved == 1 => self.update_message("Saved 1 file"),
+            None => self.update_message(&format!("Saved {saved} files")),
+        }
+    }
+
+    // the buffer is written with the new line endings on the next save
+    // an empty name goes by the extension again
+    fn set_file_type(&mut self, value: &str) {
+        let value = value.trim();
+        let file_type = (!value.is_empty()).then(|| value.to_lowercase());
+        self.view_mut().set_file_type(file_type);
+        let file_type = self.view().get_status().file_type;
+        self.update_message(&format!("File type set to {file_type}"));
+    }
+
+    fn set_line_ending(&mut self, line_ending: LineEnding) {
+        self.view_mut().set_line_ending(line_ending);
+        self.edited();
+        self.update_message(&format!("Line endings set to {}", line_ending.name()));
+    }
+
+    fn set_encoding(&mut self, encoding: Encoding) {
+        self.view_mut().set_encoding(encoding);
+        self.edited();
+        self.update_message(&format!("Encoding set to {}", encoding.name()));
+    }
+
+    // asks before overwriting a file other than the buffer's own
+    fn handle_save_as(&mut self, filename: &str) {
+        let path = completion::expand_tilde(filename);
+        if path.exists() && !self.view().is_same_file(&path.to_string_lossy()) {
+            self.confirm(Confirmation::Overwrite(filename.to_string()));
+        } else {
+            self.save_as(filename);
+        }
+    }
+
+    fn save_as(&mut self, filename: &str) {
+        self.save(Some(filename));
+        if self.confirmation.is_some() {
+            // it goes on once asked whether to create the directory
+            return;
+        }
+        // a failed save keeps the buffer modified, which stops the rest
+        let is_saved = !self.view().get_status().is_modified;
+        match self.pending_action.take() {
+            Some(PendingAction::SaveAll { saved, quit }) if is_saved => {
+                self.continue_saving_all(saved.saturating_add(1), quit);
+            }
+            Some(_) | None => {}
+        }
+    }
+
+    fn handle_reload(&mut self) {
+        if !self.view().is_file_loaded() {
+            self.show_warning("No file to reload");
+        } else if self.view().get_status().is_modified {
+            self.confirm(Confirmation::Reload);
+        } else {
+            self.reload();
+        }
+    }
+
+    fn reload(&mut self) {
+        match self.view_mut().reload() {
+            Ok(()) => self.update_message("File reloaded"),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                self.show_warning("File no longer exists on disk, keeping buffer contents");
+            }
+            Err(err) => {
+                let filename = self.view().get_status().filename;
+                self.show_error(&io_error_message("reading", &filename, &err));
+            }
+        }
+    }
+
+    fn handle_search(&mut self) {
+        self.set_prompt(PromptType::Search);
+        self.view_mut().enter_search();
+        self.update_message("");
+    }
+
+    fn handle_search_next(&mut self) {
+        if !self.view_mut().search_next() {
+            self.command_failed = true;
+        }
+        // let success = self.view.search_next();
+        // if !success {
+        //     self.update_message("Have no search query, please search for something first");
+        // }
+    }
+
+    fn handle_search_previous(&mut self) {
+        if !self.view_mut().search_backward() {
+            self.command_failed = true;
+        }
+        // let success = self.view.search_previous();
+        // if !success {
+        //     self.update_message("Have no search query, please search for something first");
+        // }
+    }
+
+    fn search_while_typing(&mut self) {
+        let value = self.command_bar.value();
+        let (lines, query) = split_line_range(&value);
+        self.set_search_hint(None);
+        if self.view().line_count() < self.search_worker.min_lines {
+            if !self.view_mut().search(query, lines) {
+                self.command_failed = true;
+            }
+            return;
+        }
+        self.view_mut().set_search(query, lines);
+        if let Some(request) = self.view().search_request() {
+            self.search_worker.start(request);
+            self.set_search_hint(Some("searching…"));
+        } else {
+            self.search_worker.cancel();
+        }
+    }
+
+    fn submit_search(&mut self, value: &str) {
+        self.finish_search();
+        self.view_mut().accept_search();
+        if let (Some(lines), _) = split_line_range(value) {
+            self.update_message(&format!(
+                "Searching in lines {}-{}",
+                lines.start.saturating_add(1),
+                lines.end
+            ));
+        }
+    }
+
+    fn abort_search(&mut self) {
+        self.search_worker.cancel();
+        self.view_mut().dismiss_search();
+        self.update_message("Search aborted");
+    }
+
+    // the lines searched in and, if given, how the search is going
+    fn set_search_hint(&mut self, status: Option<&str>) {
+        let (lines, _) = split_line_range(&self.command_bar.value());
+        let hint = lines
+            .map(|lines| format!("[in lines {}-{}]", lines.start.saturating_add(1), lines.end))
+            .into_iter()
+            .chain(status.map(|status| format!("[{status}]")))
+            .collect::<Vec<_>>()
+            .join(" ");
+        self.command_bar.set_hint(&hint);
+    }
+
+    // region: background search
+    // show what the search in the background found since last time
+    fn receive_search_results(&mut self) {
+        while let Some(progress) = self.search_worker.next(false) {
+            self.apply_search_progress(&progress);
+        }
+    }
+
+    // let the search in the background finish, for when what it finds is needed right away
+    fn wait_for_search(&mut self) {
+        while let Some(progress) = self.search_worker.next(true) {
+            self.apply_search_progress(&progress);
+        }
+    }
+
+    // the match is all accepting a search needs, counting the rest isn't waited for
+    fn finish_search(&mut self) {
+        while let Some(progress) = self.search_worker.next(true) {
+            self.apply_search_progress(&progress);
+            if progress.hit.is_some() {
+                break;
+            }
+        }
+        self.search_worker.cancel();
+    }
+
+    fn apply_search_progress(&mut self, progress: &Progress) {
+        if let Some(hit) = progress.hit {
+            self.view_mut().show_search_hit(hit);
+        }
+        if progress.done {
+            if progress.hit.is_none() {
+                self.command_failed = true;
+            }
+            let status = match progress.match_count {
+                0 => String::from("no match"),
+                1 => String::from("1 match"),
+                count => format!("{count} matches"),
+            };
+            self.set_search_hint(Some(&status));
+        }
+    }
+    // endregion
+
+    // region: match list
+    fn list_matches(&mut self) {
+        let Some(query) = self.view().search_query() else {
+            self.command_failed = true;
+            self.show_warning("Search for something first");
+            return;
+        };
+        let Some(match_list) = self.find_matches(&query) else {
+            self.command_failed = true;
+            self.show_warning(&format!("No matches for '{query}'"));
+            return;
+        };
+        self.open_overlay(Overlay::Matches(match_list), PromptType::Matches);
+    }
+
+    fn find_matches(&self, query: &str) -> Option<MatchList> {
+        let view = self.view();
+        MatchList::new(
+            query,
+            view.find_all_matches(query),
+            view.revision(),
+            view.caret_location(),
+            |line_idx| view.line_text(line_idx),
+        )
+    }
+
+    // look for `query` in every text file below the working directory, listing the matches
+    // as they are found
+    fn search_files(&mut self, query: &str) {
+        if query.is_empty() {
+            self.update_message("Search aborted");
+            return;
+        }
+        let root = self.files_root.clone();
+        self.file_search = Some(FileSearch::start(root.clone(), query.to_string()));
+        let match_list = MatchList::in_files(query, root);
+        self.open_overlay(Overlay::Matches(match_list), PromptType::Matches);
+    }
+
+    // what the search through files found meanwhile, waiting for all of it if `wait` is set
+    fn receive_file_matches(&mut self, wait: bool) {
+        let Some(file_search) = &self.file_search else {
+            return;
+        };
+        let Some(Overlay::Matches(match_list)) = &mut self.overlay else {
+            return;
+        };
+        let mut received = false;
+        while let Some(found) = file_search.next(wait) {
+            received = true;
+            match found {
+                Found::Match(found) => {
+                    match_list.push_file_match(
+                        &found.path,
+                        found.line_idx,
+                        found.byte_idx,
+                        &found.text,
+                    );
+                }
+                Found::Done { capped } => {
+                    match_list.finish_files(capped);
+                    self.file_search = None;
+                    break;
+                }
+            }
+        }
+        if received {
+            self.update_overlay_hint();
+        }
+    }
+
+    fn process_command_during_matches(&mut self, command: Command) {
+        // the buffer may have been reloaded meanwhile
+        if let Some(Overlay::Matches(match_list)) = &self.overlay
+            && !match_list.is_in_files()
+            && match_list.revision() != self.view().revision()
+        {
+            let query = match_list.query().to_string();
+            let Some(match_list) = self.find_matches(&query) else {
+                self.close_overlay();
+                self.show_warning(&format!("No matches for '{query}' anymore"));
+                return;
+            };
+            self.overlay = Some(Overlay::Matches(match_list));
+            self.handle_resize_command(self.terminal_size);
+        }
+        let Some(Overlay::Matches(match_list)) = &mut self.overlay else {
+            return;
+        };
+        match command {
+            System(Dismiss) => self.close_overlay(),
+            Move(command::Move::Up) => {
+                match_list.select_previous();
+                self.update_overlay_hint();
+            }
+            Move(command::Move::Down) => {
+                match_list.select_next();
+                self.update_overlay_hint();
+            }
+            Edit(command::Edit::InsertNewline) => {
+                let selected = match_list.selected();
+                let file = match_list.selected_file().map(Path::to_path_buf);
+                self.close_overlay();
+                if let Some(file) = file {
+                    // in the buffer of the file, whether it was open already or not
+                    let filename = file.to_string_lossy();
+                    self.open(&filename);
+                    if !self.view().is_same_file(&filename) {
+                        return;
+                    }
+                }
+                if let Some(location) = selected {
+                    self.view_mut().jump_to(location);
+                }
+            }
+            System(_) | Move(_) | Edit(_) => {}
+        }
+    }
+    // endregion
+
+    // region: diff
+    // list what changed since the file was saved, compared with what is on disk now
+    fn diff_against_saved(&mut self) {
+        let filename = self.view().get_status().filename;
+        let saved = match self.view().saved_lines() {
+            None => {
+                self.command_failed = true;
+                self.show_warning("No file to compare with");
+                return;
+            }
+            Some(Err(err)) if err.kind() == std::io::ErrorKind::NotFound => {
+                self.command_failed = true;
+                self.show_warning(&format!("{filename} is not on disk to compare with"));
+                return;
+            }
+            Some(Err(err)) => {
+                self.command_failed = true;
+                self.show_error(&io_error_message("reading", &filename, &err));
+                return;
+            }
+            Some(Ok(saved)) => saved,
+        };
+        let lines = self.view().lines();
+        let hunks = diff::diff(&saved, &lines);
+        let Some(diff_list) = DiffList::new(&hunks, &saved, &lines) else {
+            self.update_message("No changes since the file was saved");
+            return;
+        };
+        self.open_overlay(Overlay::Diff(diff_list), PromptType::Diff);
+    }
+
+    fn process_command_during_diff(&mut self, command: Command) {
+        let Some(Overlay::Diff(diff_list)) = &mut self.overlay else {
+            return;
+        };
+        match command {
+            System(Dismiss) => self.close_overlay(),
+            Move(command::Move::Up) => {
+                diff_list.select_previous();
+                self.update_overlay_hint();
+            }
+            Move(command::Move::Down) => {
+                diff_list.select_next();
+                self.update_overlay_hint();
+            }
+            Edit(command::Edit::InsertNewline) => {
+                let selected = diff_list.selected_line();
+                self.close_overlay();
+                if let Some(line_idx) = selected {
+                    self.view_mut().jump_to(Location {
+                        line_idx,
+                        grapheme_idx: 0,
+                    });
+                }
+            }
+            System(_) | Move(_) | Edit(_) => {}
+        }
+    }
+    // endregion
+
+    // region: bytes
+    // list the bytes of the caret's line from the one the caret is at, or go back to the text
+    fn toggle_byte_inspector(&mut self) {
+        if matches!(self.overlay, Some(Overlay::Bytes(_))) {
+            self.close_byte_inspector();
+            return;
+        }
+        let line_idx = self.view().caret_location().line_idx;
+        let text = self.view().line_text(line_idx);
+        let inspector = ByteInspector::new(line_idx, &text, self.view().caret_byte_idx());
+        self.open_overlay(Overlay::Bytes(inspector), PromptType::Bytes);
+    }
+
+    // with the caret on the grapheme holding the selected byte
+    fn close_byte_inspector(&mut self) {
+        let Some(Overlay::Bytes(inspector)) = &self.overlay else {
+            return;
+        };
+        let at = (inspector.line_idx(), inspector.byte_offset());
+        self.close_overlay();
+        self.view_mut().show_search_hit(at);
+    }
+
+    fn process_command_during_bytes(&mut self, command: Command) {
+        let Some(Overlay::Bytes(inspector)) = &mut self.overlay else {
+            return;
+        };
+        match command {
+            System(Dismiss) | Edit(command::Edit::InsertNewline) => self.close_byte_inspector(),
+            Move(command::Move::Left) => inspector.select(-1),
+            Move(command::Move::Right) => inspector.select(1),
+            Move(command::Move::Up) => inspector.select(-16),
+            Move(command::Move::Down) => inspector.select(16),
+            Move(command::Move::StartOfLine) => inspector.select_first(),
+            Move(command::Move::EndOfLine) => inspector.select_last(),
+            // the message bar is hidden behind the prompt, so the hint tells
+            Edit(_) => {
+                self.command_failed = true;
+                self.command_bar.set_hint("read-only, Esc to go back");
+                return;
+            }
+            System(_) | Move(_) => {}
+        }
+        self.update_overlay_hint();
+    }
+    // endregion
+
+    // region: buffers
+    fn list_buffers(&mut self) {
+        let buffer_list = BufferList::new(self.buffer_entries(), self.focused_buffer_idx());
+        self.open_overlay(Overlay::Buffers(buffer_list), PromptType::Buffers);
+    }
+
+    fn buffer_entries(&self) -> Vec<BufferEntry> {
+        let focused = self.focused_buffer_idx();
+        self.views
+            .iter()
+            .enumerate()
+            .map(|(idx, view)| {
+                // the focused pane may show the buffer somewhere else than the upper one
+                let status = if idx == focused { self.view() } else { view }.get_status();
+                BufferEntry {
+                    idx,
+                    name: status.filename,
+                    is_modified: status.is_modified,
+                    line_count: (!view.is_unread()).then_some(status.total_lines),
+                }
+            })
+            .collect()
+    }
+
+    // `d` closes the selected buffer while nothing is typed, anything else narrows the list
+    fn process_command_during_buffers(&mut self, command: Command) {
+        let Some(Overlay::Buffers(buffer_list)) = &mut self.overlay else {
+            return;
+        };
+        match command {
+            System(Dismiss) => self.close_overlay(),
+            Move(command::Move::Up) => {
+                buffer_list.select_previous();
+                self.update_overlay_hint();
+            }
+            Move(command::Move::Down) => {
+                buffer_list.select_next();
+                self.update_overlay_hint();
+            }
+            Edit(command::Edit::InsertNewline) => {
+                let selected = buffer_list.selected();
+                self.close_overlay();
+                if let Some(idx) = selected.filter(|idx| *idx != self.focused_buffer_idx()) {
+                    self.activate_buffer(idx);
+                }
+            }
+            Edit(command::Edit::Insert('d')) if self.command_bar.value().is_empty() => {
+                self.close_listed_buffer();
+            }
+            Edit(edit) => {
+                self.command_bar.handle_edit_command(edit);
+                buffer_list.filter(&self.command_bar.value());
+                self.update_overlay_hint();
+            }
+            System(_) | Move(_) => {}
+        }
+    }
+
+    // a buffer with unsaved changes needs a second `d`
+    fn close_listed_buffer(&mut self) {
+        let Some(Overlay::Buffers(buffer_list)) = &mut self.overlay else {
+            return;
+        };
+        let Some(idx) = buffer_list.selected() else {
+            return;
+        };
+        // the selected row tells which buffer it is
+        if !buffer_list.may_close() {
+            self.command_bar.set_hint("unsaved changes, d again closes");
+            return;
+        }
+        let filename = self.views[idx].get_status().filename;
+        self.remove_buffer(idx);
+        let entries = self.buffer_entries();
+        if let Some(Overlay::Buffers(buffer_list)) = &mut self.overlay {
+            buffer_list.set_entries(entries);
+        }
+        self.command_bar.set_hint(&format!("Closed {filename}"));
+    }
+    // endregion
+
+    // region: overlay
+    fn open_overlay(&mut self, overlay: Overlay, prompt_type: PromptType) {
+        self.overlay = Some(overlay);
+        self.set_prompt(prompt_type);
+        self.update_overlay_hint();
+        self.handle_resize_command(self.terminal_size);
+    }
+
+    fn update_overlay_hint(&mut self) {
+        let hint = match &self.overlay {
+            Some(Overlay::Matches(match_list)) => match_list.hint(),
+            Some(Overlay::Diff(diff_list)) => diff_list.hint(),
+            Some(Overlay::Bytes(inspector)) => inspector.hint(),
+            Some(Overlay::Buffers(buffer_list)) => buffer_list.hint(),
+            None => return,
+        };
+        self.command_bar.set_hint(&hint);
+    }
+
+    fn close_overlay(&mut self) {
+        self.overlay = None;
+        // a search through files still running stops
+        self.file_search = None;
+        self.dismiss_prompt();
+        self.handle_resize_command(self.terminal_size);
+    }
+    // endregion
+
+    // region: palette
+    fn run_command(&mut self, name: &str) {
+        let handler = self
+            .commands
+            .iter()
+            .find(|command| command.name == name)
+            .map(|command| command.handler);
+        debug_assert!(handler.is_some(), "{name} is not registered");
+        if let Some(handler) = handler {
+            handler(self);
+        }
+    }
+
+    fn select_command(&mut self, direction: command::Move) {
+        if matches!(direction, command::Move::Up) {
+            self.palette.select_previous();
+        } else {
+            self.palette.select_next();
+        }
+        self.command_bar
+            .set_hint(&self.palette.hint(&self.commands));
+    }
+
+    fn complete_command_name(&mut self) {
+        if let Some(idx) = self.palette.selected() {
+            self.command_bar.set_value(self.commands[idx].name);
+            self.filter_palette();
+        }
+    }
+
+    // the prompt is dismissed by now, the command may open one of its own
+    fn run_selected_command(&mut self) {
+        let selected = self.palette.selected().map(|idx| self.commands[idx].name);
+        if let Some(name) = selected {
+            self.run_command(name);
+        }
+    }
+
+    fn filter_palette(&mut self) {
+        self.palette
+            .filter(&self.commands, &self.command_bar.value());
+        self.command_bar
+            .set_hint(&self.palette.hint(&self.commands));
+    }
+    // endregion
+
+    fn go_to(&mut self, value: &str) {
+        let value = value.trim();
+        // "50%" goes half way through the buffer
+        if let Some(percent) = value.strip_suffix('%') {
+            match percent.trim().parse::<usize>() {
+                Ok(percent) if percent <= 100 => self.view_mut().go_to_percentage(percent),
+                _ => {
+                    self.command_failed = true;
+                    self.show_warning(&format!("Not a percentage: {value}"));
+                }
+            }
+            return;
+        }
+        // "12:5" goes to the fifth character of line 12, counting a tab or a wide one as one
+        let (line, col) = value.split_once(':').unwrap_or((value, "1"));
+        match (line.trim().parse::<usize>(), col.trim().parse::<usize>()) {
+            (Ok(line), Ok(col)) if line > 0 && col > 0 => self
+                .view_mut()
+                .go_to_line_and_grapheme(line.saturating_sub(1), col.saturating_sub(1)),
+            _ => {
+                self.command_failed = true;
+                self.show_warning(&format!("Not a line number: {value}"));
+            }
+        }
+    }
+
+    fn insert_unicode(&mut self, value: &str) {
+        match unicode::parse_char(value) {
+            // inserted like a typed character, so a combining one joins the grapheme before
+            // the caret
+            Ok(ch) => {
+                self.view_mut()
+                    .handle_edit_command(command::Edit::Insert(ch));
+                self.edited();
+            }
+            Err(err) => {
+                self.command_failed = true;
+                self.show_warning(&err);
+            }
+        }
+    }
+
+    // the first Tab completes the longest common prefix, further ones cycle through all candidates
+    fn complete_filename(&mut self) {
+        if self.cycle_completion() {
+            return;
+        }
+        let value = self.command_bar.value();
+        match completion::candidates(&value) {
+            Err(_) => self.command_bar.set_hint("[no such directory]"),
+            Ok(candidates) => {
+                let names: Vec<String> = candidates
+                    .iter()
+                    .map(|candidate| {
+                        let name = candidate.trim_end_matches('/').rsplit('/').next();
+                        name.unwrap_or(candidate).to_string()
+                    })
+                    .collect();
+                self.offer_candidates(candidates, &names);
+            }
+        }
+    }
+
+    // like `complete_filename`, for the names of file types
+    fn complete_file_type(&mut self) {
+        if self.cycle_completion() {
+            return;
+        }
+        let value = self.command_bar.value().to_lowercase();
+        let candidates: Vec<String> = known_file_types()
+            .filter(|name| name.starts_with(&value))
+            .map(String::from)
+            .collect();
+        let names = candidates.clone();
+        self.offer_candidates(candidates, &names);
+    }
+
+    // the next candidate of the completion going on, if there is one
+    fn cycle_completion(&mut self) -> bool {
+        let Some(completion) = &mut self.completion else {
+            return false;
+        };
+        if let Some(candidate) = completion.cycle() {
+            let candidate = candidate.to_string();
+            self.command_bar.set_value(&candidate);
+        }
+        true
+    }
+
+    // the only candidate, or what all of them start with, listing them by `names`
+    fn offer_candidates(&mut self, candidates: Vec<String>, names: &[String]) {
+        match candidates.as_slice() {
+            [] => self.command_bar.set_hint("[no match]"),
+            [candidate] => self.command_bar.set_value(candidate),
+            _ => {
+                let prefix = completion::longest_common_prefix(&candidates);
+                let hint = format!("[{} matches: {}]", candidates.len(), names.join(" "));
+                self.command_bar.set_value(&prefix);
+                self.command_bar.set_hint(&hint);
+                self.completion = Some(Completion::new(candidates));
+            }
+        }
+    }
+
+    // region: word completion
+    // Complete the word left of the caret with the first word of the buffer starting like it,
+    // or replace the last completion by the next one. With `or_tab`, nothing to complete
+    // inserts a tab instead.
+    fn complete_word(&mut self, or_tab: bool) {
+        let status = self.view().get_status();
+        let caret = (status.current_line_idx, status.grapheme_col);
+        let mut completion = match self.word_completion.take() {
+            Some(completion) if completion.caret == caret => completion,
+            _ => {
+                let prefix = self.view().word_before_caret().unwrap_or_default();
+                let candidates = if prefix.is_empty() {
+                    Vec::new()
+                } else {
+                    self.view().words_starting_with(&prefix)
+                };
+                let Some(completion) = WordCompletion::new(prefix, candidates) else {
+                    if or_tab {
+                        self.view_mut()
+                            .handle_edit_command(command::Edit::InsertTab);
+                        self.edited();
+                    } else {
+                        self.update_message("No completions");
+                        self.command_failed = true;
+                    }
+                    return;
+                };
+                completion
+            }
+        };
+
+        let (removed, inserted) = completion.cycle();
+        let removed = removed.graphemes(true).count();
+        if removed > 0 {
+            self.view_mut()
+                .handle_repeated_edit_command(command::Edit::DeleteBackward, removed);
+        }
+        let inserted = inserted.to_string();
+        self.view_mut().insert_text(&inserted);
+        self.edited();
+        self.update_message(&completion.describe());
+        let status = self.view().get_status();
+        completion.caret = (status.current_line_idx, status.grapheme_col);
+        self.word_completion = Some(completion);
+    }
+    // endregion
+
+    // region: confirm
+    fn confirm(&mut self, confirmation: Confirmation) {
+        self.confirmation = Some(confirmation);
+        self.set_prompt(PromptType::Confirm);
+    }
+
+    fn process_command_during_confirm(&mut self, command: &Command) {
+        // None if the question was dismissed
+        let answer = match command {
+            System(Dismiss) => None,
+            Edit(edit) => match self.command_bar.answer(*edit) {
+                Some(answer) => Some(answer == 'y'),
+                None => return,
+            },
+            System(_) | Move(_) => return,
+        };
+
+        self.dismiss_prompt();
+        let Some(confirmation) = self.confirmation.take() else {
+            return;
+        };
+        match (confirmation, answer) {
+            (Confirmation::Reload, Some(true)) => self.reload(),
+            (Confirmation::Reload, _) => self.update_message("Reload aborted"),
+            (Confirmation::Close, Some(true)) => self.discard_buffer(),
+            (Confirmation::Close, _) => self.update_message("Close aborted"),
+            (Confirmation::Overwrite(filename), Some(true)) => self.save_as(&filename),
+            (Confirmation::Overwrite(filename), Some(false)) => {
+                // let the user pick another name
+                self.set_prompt(PromptType::Save);
+                self.command_bar.set_value(&filename);
+            }
+            (Confirmation::Overwrite(_), None)
+            | (Confirmation::CreateDirectory { .. }, Some(false) | None) => self.abort("Save"),
+            (Confirmation::Restore(path), Some(true)) => self.restore(&path),
+            (Confirmation::Restore(path), Some(false)) => {
+                let _ = fs::remove_file(path);
+                self.update_message("Discarded the recovered changes");
+            }
+            (Confirmation::Restore(path), None) => {
+                self.update_message(&format!("Kept {} for later", path.display()));
+            }
+            (Confirmation::Windows1252(filename), Some(true)) => {
+                self.open_as(&filename, Encoding::Windows1252);
+            }
+            (Confirmation::Windows1252(filename), _) => {
+                self.show_error(&format!(
+                    "Error reading '{filename}': it is not valid UTF-8"
+                ));
+            }
+            (Confirmation::CreateDirectory { dir, filename }, Some(true)) => {
+                if let Err(err) = fs::create_dir_all(&dir) {
+                    let dir = dir.to_string_lossy();
+                    self.show_error(&io_error_message("creating", &dir, &err));
+                    self.pending_action = None;
+                } else if let Some(filename) = filename {
+                    self.save_as(&filename);
+                } else {
+                    self.save(None);
+                }
+            }
+        }
+    }
+    // endregion
+
+    // region: recovery
+    // ask whether to restore what was dumped when hecto crashed while editing this file
+    fn offer_recovery(&mut self) {
+        let recovery = match self.view().file_path() {
+            Some(path) => recovery::find(&path, self.recovery_dir.as_deref()),
+            None => recovery::find_unnamed(self.recovery_dir.as_deref()),
+        };
+        if let Some(recovery) = recovery {
+            self.confirm(Confirmation::Restore(recovery));
+        }
+    }
+
+    fn restore(&mut self, path: &PathBuf) {
+        match self.view_mut().restore_from(path) {
+            Ok(()) => {
+                let _ = fs::remove_file(path);
+                self.redraw_other_pane();
+                self.update_message("Restored the unsaved changes, save to keep them");
+            }
+            Err(err) => {
+                let msg = io_error_message("reading", &path.to_string_lossy(), &err);
+                self.show_error(&msg);
+            }
+        }
+    }
+
+    // dump every modified buffer into its recovery file, returning where they went
+    fn write_recovery_files(&self) -> Vec<PathBuf> {
+        self.views
+            .iter()
+            .filter(|view| view.get_status().is_modified)
+            .filter_map(|view| {
+                let path = recovery::recovery_path(
+                    view.file_path().as_deref(),
+                    self.recovery_dir.as_deref(),
+                )?;
+                match view.write_copy(&path) {
+                    Ok(()) => Some(path),
+                    Err(err) => {
+                        error!("Could not write {}: {err}", path.display());
+                        None
+                    }
+                }
+            })
+            .collect()
+    }
+    // endregion
+
+    // region: macro
+    fn toggle_macro_recording(&mut self) {
+        if self.keyboard_macro.is_recording() {
+            let count = self.keyboard_macro.stop();
+            self.update_message(&format!("Recorded a macro of {count} keys"));
+        } else {
+            self.keyboard_macro.start();
+            self.update_message("Recording a macro, <A-m> stops");
+        }
+    }
+
+    // replay the recorded keys, stopping early at the first command that fails
+    fn play_macro(&mut self) {
+        if self.keyboard_macro.is_recording() {
+            self.show_warning("Stop recording before playing the macro");
+            return;
+        }
+        let keys = self.keyboard_macro.recorded();
+        if keys.is_empty() {
+            self.show_warning("No macro recorded, <A-m> starts recording");
+            return;
+        }
+
+        for key in keys {
+            self.command_failed = false;
+            self.evaluate_event(Key(key));
+            self.wait_for_search();
+            if self.command_failed {
+                // leave no half-filled prompt behind
+                if self.in_prompt() {
+                    self.process_command(System(Dismiss));
+                }
+                self.show_warning("Macro stopped early, nothing was found");
+                return;
+            }
+            if self.should_quit {
+                return;
+            }
+        }
+    }
+    // endregion
+
+    // region: chords
+    // run the command bound to the keys typed so far, returning whether `key` was used up
+    fn process_chord_key(&mut self, key: KeyEvent) -> bool {
+        let is_pending = !self.pending_chord.is_empty();
+        if is_pending && key.code == KeyCode::Esc {
+            self.pending_chord.clear();
+            self.update_message("");
+            return true;
+        }
+
+        let mut keys = std::mem::take(&mut self.pending_chord);
+        keys.push(keymap::key(key));
+        match self.keymap.lookup(&keys) {
+            Lookup::Command(name) => {
+                let name = name.to_string();
+                if is_pending {
+                    self.update_message("");
+                }
+                self.run_command(&name);
+            }
+            Lookup::Prefix => {
+                self.update_message(&format!("{} -", keymap::describe(&keys)));
+                self.pending_chord = keys;
+                self.chord_started = Instant::now();
+            }
+            Lookup::Unbound if is_pending => {
+                self.show_warning(&format!("{} is not bound", keymap::describe(&keys)));
+            }
+            Lookup::Unbound => return false,
+        }
+        true
+    }
+
+    fn chord_timeout(&self) -> Option<Duration> {
+        (!self.pending_chord.is_empty())
+            .then(|| CHORD_TIMEOUT.saturating_sub(self.chord_started.elapsed()))
+    }
+
+    // a prefix without the rest of its chord in time does nothing
+    fn expire_chord(&mut self) {
+        if self
+            .chord_timeout()
+            .is_some_and(|timeout| timeout.is_zero())
+        {
+            let keys = std::mem::take(&mut self.pending_chord);
+            self.show_warning(&format!("{} is not bound", keymap::describe(&keys)));
+        }
+    }
+    // endregion
+
+    fn jump_to_edited_line(&mut self, forward: bool) {
+        if !self.view_mut().jump_to_edited_line(forward) {
+            self.command_failed = true;
+            self.show_warning("No lines edited since saving");
+        }
+    }
+
+    fn jump_to_git_hunk(&mut self, forward: bool) {
+        if !self.view_mut().jump_to_git_hunk(forward) {
+            self.command_failed = true;
+            self.show_warning("No changes since the last commit");
+        }
+    }
+
+    // region: spelling
+    fn toggle_spell_check(&mut self) {
+        let checks = self.view_mut().toggle_spell_check();
+        // another pane may show the same buffer
+        self.forget_spelling();
+        self.update_message(if checks {
+            "Spell checking on"
+        } else {
+            "Spell checking off"
+        });
+    }
+
+    // add the word under the caret to the user's dictionary
+    fn add_word(&mut self) {
+        let Some(word) = self.view().word_at_caret() else {
+            self.command_failed = true;
+            self.show_warning("No word at the caret");
+            return;
+        };
+        let added = self.dictionary.borrow_mut().add(&word);
+        self.forget_spelling();
+        match added {
+            Ok(_) => self.update_message(&format!("Added '{word}' to the dictionary")),
+            Err(err) => self.show_warning(&err),
+        }
+    }
+
+    fn forget_spelling(&mut self) {
+        for view in &mut self.views {
+            view.forget_spelling();
+        }
+        if let Some(split) = &mut self.split {
+            split.view.forget_spelling();
+        }
+    }
+    // endregion
+
+    // region: marks
+    fn await_mark(&mut self, action: MarkAction) {
+        self.pending_mark = Some(action);
+        self.update_message("Mark: type a letter or digit");
+    }
+
+    fn process_mark_key(&mut self, action: MarkAction, key: KeyEvent) {
+        let KeyCode::Char(name) = key.code else {
+            self.update_message("");
+            return;
+        };
+        match action {
+            MarkAction::Set if self.view_mut().set_mark(name) => {
+                self.update_message(&format!("Mark {name} set"));
+            }
+            MarkAction::Set => {
+                self.command_failed = true;
+                self.show_warning(&format!("Not a mark name: {name}"));
+            }
+            MarkAction::Jump if self.view_mut().jump_to_mark(name) => self.update_message(""),
+            MarkAction::Delete if self.view_mut().delete_mark(name) => {
+                self.update_message(&format!("Mark {name} deleted"));
+            }
+            MarkAction::Jump | MarkAction::Delete => {
+                self.command_failed = true;
+                self.show_warning(&format!("Mark {name} is not set"));
+            }
+        }
+    }
+
+    // the marks other than the last edit's in the order of their lines, whatever their names
+    fn jump_to_next_mark(&mut self, forward: bool) {
+        match self.view_mut().jump_to_next_mark(forward) {
+            Some(false) => self.update_message(""),
+            Some(true) if forward => self.update_message("Wrapped to the first mark"),
+            Some(true) => self.update_message("Wrapped to the last mark"),
+            None => {
+                self.command_failed = true;
+                self.show_warning("No marks set");
+            }
+        }
+    }
+    // endregion
+
+    // region: shell
+    fn insert_command_output(&mut self, command: &str, keep_newline: bool) {
+        if command.trim().is_empty() {
+            return;
+        }
+        match shell::run(command, keep_newline) {
+            Ok(output) => {
+                self.view_mut().insert_text(&output.text);
+                self.edited();
+                if let Some(line_count) = output.line_count {
+                    self.show_warning(&format!(
+                        "Inserted the first {} of {line_count} lines",
+                        shell::MAX_OUTPUT_LINES
+                    ));
+                } else if let Some(warning) = output.warning {
+                    self.show_warning(&warning);
+                } else {
+                    self.update_message("");
+                }
+            }
+            Err(err) => {
+                self.command_failed = true;
+                self.show_error(&err);
+            }
+        }
+    }
+
+    fn filter_buffer(&mut self, command: &str) {
+        if command.trim().is_empty() {
+            return;
+        }
+        let text = self.view().text();
+        match shell::filter(command, &text) {
+            Ok(output) => {
+                if self.view_mut().replace_text(&output.text) {
+                    self.edited();
+                    self.update_message(&format!("Filtered through '{command}'"));
+                } else {
+                    self.update_message("Filter changed nothing");
+                }
+                if let Some(warning) = output.warning {
+                    self.show_warning(&warning);
+                }
+            }
+            Err(err) => {
+                self.command_failed = true;
+                self.show_error(&err);
+            }
+        }
+    }
+    // endregion
+
+    // region: modal
+    fn process_modal_action(&mut self, action: Action) {
+        // commands use up the count themselves
+        let count = match action {
+            Action::Run(_) => None,
+            _ => self.pending_count.take(),
+        };
+        let lines = count.unwrap_or(1).max(1);
+
+        match action {
+            Action::Run(command) => self.process_command(command),
+            Action::GoToLine { last } => {
+                let line_idx = match count {
+                    Some(count) => count.saturating_sub(1),
+                    None if last => usize::MAX,
+                    None => 0,
+                };
+                self.view_mut().go_to_line(line_idx);
+            }
+            Action::DeleteLines => {
+                let deleted = self.view_mut().delete_lines(lines);
+                if !deleted.is_empty() {
+                    self.yanked = deleted;
+                    self.edited();
+                }
+            }
+            Action::YankLines => {
+                self.yanked = self.view().yank_lines(lines);
+                self.update_message(&format!("Yanked {} lines", self.yanked.len()));
+            }
+            Action::Paste => {
+                let yanked = self.yanked.clone();
+                self.view_mut().paste_lines(&yanked);
+                self.edited();
+            }
+            Action::Insert => self.modal.set_mode(Mode::Insert),
+            Action::Append => {
+                if !self.view().is_at_end_of_line() {
+                    self.view_mut().handle_move_command(command::Move::Right);
+                }
+                self.modal.set_mode(Mode::Insert);
+            }
+            Action::OpenBelow => {
+                self.view_mut()
+                    .handle_edit_command(command::Edit::InsertLineBelow);
+                self.edited();
+                self.modal.set_mode(Mode::Insert);
+            }
+            Action::OpenAbove => {
+                self.view_mut()
+                    .handle_edit_command(command::Edit::InsertLineAbove);
+                self.edited();
+                self.modal.set_mode(Mode::Insert);
+            }
+        }
+    }
+    // endregion
+
+    // region: config
+    fn reload_config(&mut self) {
+        if self.apply_config(Config::load(self.config_path.as_deref())) {
+            self.update_message("Config reloaded");
+        }
+    }
+
+    // use `config` in all views, or the defaults if it could not be loaded. Returns whether
+    // it was applied without a warning.
+    fn apply_config(&mut self, config: Result<Config, String>) -> bool {
+        let (config, mut warning) = match config {
+            Ok(config) => (config, None),
+            Err(err) => (Config::default(), Some(err)),
+        };
+
+        let theme = config
+            .theme_path()
+            .map_or_else(|| Ok(Theme::default()), |path| Theme::load(&path));
+        let theme = theme.unwrap_or_else(|err| {
+            warning.get_or_insert(err);
+            Theme::default()
+        });
+        self.terminal.set_theme(theme);
+        let _ = self.terminal.set_mouse_capture(config.mouse);
+
+        if (config.dictionary_path(), config.user_dictionary_path())
+            != (
+                self.config.dictionary_path(),
+                self.config.user_dictionary_path(),
+            )
+        {
+            self.dictionary = Rc::new(RefCell::new(dictionary_for(&config)));
+        }
+        for view in &mut self.views {
+            view.set_config(&config);
+            view.set_dictionary(&self.dictionary);
+        }
+        if let Some(split) = &mut self.split {
+            split.view.set_config(&config);
+            split.view.set_dictionary(&self.dictionary);
+        }
+        let keymap = Keymap::new(&config.keys, &self.commands);
+        self.keymap = keymap.unwrap_or_else(|err| {
+            warning.get_or_insert(format!("Config keys: {err}"));
+            Keymap::default()
+        });
+        let format = config
+            .status_line
+            .as_deref()
+            .map_or_else(|| Ok(StatusFormat::default()), StatusFormat::parse);
+        self.status_bar.set_format(format.unwrap_or_else(|err| {
+            warning.get_or_insert(format!("Config status_line: {err}"));
+            StatusFormat::default()
+        }));
+
+        // switching to modal editing starts in normal mode
+        if config.modal != self.config.modal {
+            self.modal = Modal::default();
+        }
+        self.config = config;
+
+        if let Some(warning) = warning {
+            warn!("{warning}");
+            self.show_warning(&warning);
+            return false;
+        }
+        true
+    }
+    // endregion
+
+    // region: autosave
+    fn autosave(&mut self) {
+        let Some(autosave) = &mut self.autosave else {
+            return;
+        };
+        if !autosave.take_due() {
+            return;
+        }
+
+        // buffers without a file are never autosaved, so no files show up unasked
+        let mut saved = false;
+        for view in self
+            .views
+            .iter()
+            .filter(|view| view.get_status().is_modified)
+        {
+            let Some(sidecar) = view.file_path().as_deref().and_then(autosave::sidecar_path) else {
+                continue;
+            };
+            match view.write_copy(&sidecar) {
+                Ok(()) => {
+                    autosave.written(sidecar);
+                    saved = true;
+                }
+                Err(err) => warn!("Could not autosave to {}: {err}", sidecar.display()),
+            }
+        }
+
+        // don't hide a more important message
+        if saved && !self.message_bar.is_showing() {
+            self.update_message("Autosaved");
+        }
+    }
+
+    // the sidecar is stale once the focused buffer was saved for real
+    fn remove_autosave_sidecar(&mut self, file: &Path) {
+        if let Some(autosave) = &mut self.autosave
+            && let Some(sidecar) = autosave.take_written(file)
+        {
+            let _ = fs::remove_file(sidecar);
+        }
+    }
+    // endregion
+
+    fn update_message(&mut self, new_message: &str) {
+        self.message_bar.update_message(new_message, Severity::Info);
+    }
+
+    // something didn't happen the way it was asked for
+    fn show_warning(&mut self, new_message: &str) {
+        self.message_bar
+            .update_message(new_message, Severity::Warning);
+    }
+
+    // something failed, shown until the next key
+    fn show_error(&mut self, new_message: &str) {
+        self.message_bar
+            .update_message(new_message, Severity::Error);
+    }
+
+    fn no_prompt(&self) -> bool {
+        self.prompt_type.is_none()
+    }
+
+    fn in_prompt(&self) -> bool {
+        !self.no_prompt()
+    }
+
+    fn set_prompt(&mut self, prompt_type: PromptType) {
+        match prompt_type {
+            PromptType::None => self.message_bar.set_needs_redraw(true),
+            PromptType::ConfirmQuit => {
+                let names = self.modified_buffer_names();
+                let question = match names.as_slice() {
+                    [name] => format!("{name} has unsaved changes. Save before quitting?"),
+                    names => format!(
+                        "{} buffers have unsaved changes: {}. Save all before quitting?",
+                        names.len(),
+                        names.join(", ")
+                    ),
+                };
+                self.command_bar
+                    .set_choice_prompt(&question, &['y', 'n', 'c']);
+            }
+            PromptType::Matches => self.command_bar.set_prompt("Jump to: "),
+            PromptType::Diff => self.command_bar.set_prompt("Diff: "),
+            PromptType::Bytes => self.command_bar.set_prompt("Byte: "),
+            PromptType::Buffers => self.command_bar.set_prompt("Buffer: "),
+            PromptType::Confirm => {
+                let question = self
+                    .confirmation
+                    .as_ref()
+                    .map(Confirmation::question)
+                    .unwrap_or_default();
+                self.command_bar.set_choice_prompt(&question, &['y', 'n']);
+            }
+            _ => {
+                let label = prompt::text_prompt(&prompt_type).map_or("", |prompt| prompt.label);
+                self.command_bar.set_prompt(label);
+            }
+        }
+        self.command_bar.clear_value();
+        self.completion = None;
+        self.prompt_type = prompt_type;
+        if self.prompt_type == PromptType::Palette {
+            self.filter_palette();
+        }
+    }
+
+    fn dismiss_prompt(&mut self) {
+        self.prompt_type = PromptType::None;
+        self.message_bar.set_needs_redraw(true);
+    }
+}
+
+// the word lists `config` names
+fn dictionary_for(config: &Config) -> Dictionary {
+    Dictionary::new(config.dictionary_path(), config.user_dictionary_path())
+}
+
+// "10,50/query" searches lines 10 to 50 only, anything else is a query of its own
+fn split_line_range(value: &str) -> (Option<Range<usize>>, &str) {
+    let range = value.split_once('/').and_then(|(range, query)| {
+        let (start, end) = range.split_once(',')?;
+        let start = start
+            .trim()
+            .parse::<usize>()
+            .ok()
+            .filter(|start| *start > 0)?;
+        let end = end
+            .trim()
+            .parse::<usize>()
+            .ok()
+            .filter(|end| *end >= start)?;
+        Some((start.saturating_sub(1)..end, query))
+    });
+    match range {
+        Some((lines, query)) => (Some(lines), query),
+        None => (None, value),
+    }
+}
+
+// e.g. "Error writing 'notes.txt': Permission denied (os error 13)", which is logged as well
+fn io_error_message(action: &str, path: &str, err: &std::io::Error) -> String {
+    let message = format!("Error {action} '{path}': {err}");
+    warn!("{message}");
+    message
+}
+
+// The directory `path` would go into if it doesn't exist yet, to be created before saving.
+// The error is a one-line message if the closest thing which does exist isn't a directory,
+// so nothing can be created there.
+fn missing_directory(path: &Path) -> Result<Option<PathBuf>, String> {
+    let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) else {
+        return Ok(None);
+    };
+    match dir.ancestors().find(|ancestor| ancestor.exists()) {
+        Some(existing) if !existing.is_dir() => Err(format!(
+            "Can't save, {} is not a directory",
+            existing.display()
+        )),
+        _ if dir.exists() => Ok(None),
+        _ => Ok(Some(dir.to_path_buf())),
+    }
+}
+
+impl Drop for Editor {
+    fn drop(&mut self) {
+        // don't lose unsaved changes when crashing or being killed
+        let recovered = if thread::panicking() || self.terminated_by.is_some() {
+            self.write_recovery_files()
+        } else {
+            // a clean exit leaves no autosave sidecars behind
+            for sidecar in self
+                .autosave
+                .as_mut()
+                .map(Autosave::take_all_written)
+                .unwrap_or_default()
+            {
+                let _ = fs::remove_file(sidecar);
+            }
+            Vec::new()
+        };
+        let session_error = match &self.session {
+            Some(path) if self.should_quit && !thread::panicking() => {
+                self.session().save(path).err()
+            }
+            _ => None,
+        };
+
+        let _ = self.terminal.terminate();
+        if self.should_quit {
+            let _ = self.terminal.print("Goodbye.\r\n");
+        }
+        for path in recovered {
+            let _ = self.terminal.print(&format!(
+                "Unsaved changes were written to {}\r\n",
+                path.display()
+            ));
+        }
+        if let Some(err) = session_error {
+            let _ = self
+                .terminal
+                .print(&format!("The session was not saved. {err}\r\n"));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use annotated_string::AnnotationType;
+    use crossterm::event::{KeyCode, KeyModifiers};
+    use line::AmbiguousWidth;
+    use std::{
+        fs,
+        panic::{self, AssertUnwindSafe},
+    };
+    use terminal::MockTerminal;
+
+    // where the editors of tests dump their unsaved changes when a test fails
+    fn test_recovery_dir() -> PathBuf {
+        env::temp_dir().join("hecto-test-recovery")
+    }
+
+    fn test_editor(terminal: &MockTerminal) -> Editor {
+        let mut editor = Editor::with_terminal(Box::new(terminal.clone())).unwrap();
+        editor.recovery_dir = Some(test_recovery_dir());
+        editor
+    }
+
+    fn editor_with_file(name: &str, contents: &str) -> (Editor, MockTerminal) {
+        let path = env::temp_dir().join(name);
+        fs::write(&path, contents).unwrap();
+        let terminal = MockTerminal::new(Size {
+            height: 10,
+            width: 40,
+        });
+        let mut editor = test_editor(&terminal);
+        editor.open(&path.to_string_lossy());
+        editor.refresh_status();
+        editor.refresh_screen();
+        (editor, terminal)
+    }
+
+    fn press(editor: &mut Editor, code: KeyCode, modifiers: KeyModifiers) {
+        editor.process_event(Key(KeyEvent::new(code, modifiers)));
+        editor.refresh_screen();
+    }
+
+    fn type_str(editor: &mut Editor, text: &str) {
+        for ch in text.chars() {
+            press(editor, KeyCode::Char(ch), KeyModifiers::NONE);
+        }
+    }
+
+    #[test]
+    fn renders_buffer_and_status_bar() {
+        let (_editor, terminal) = editor_with_file("hecto-render.txt", "hello world\nfoo\n");
+        assert_eq!(terminal.row(0), "hello world");
+        assert_eq!(terminal.row(1), "foo");
+        assert_eq!(terminal.row(2), "~");
+        assert!(terminal.is_inverted(8));
+        assert!(terminal.row(8).starts_with("hecto-render.txt - 2 lines"));
+        let path = env::temp_dir()
+            .canonicalize()
+            .unwrap()
+            .join("hecto-render.txt");
+        assert_eq!(terminal.title(), format!("{} - hecto", path.display()));
+    }
+
+    #[test]
+    fn title_shows_changes_and_is_restored_on_exit() {
+        let terminal = MockTerminal::new(Size {
+            height: 10,
+            width: 40,
+        });
+        let mut editor = test_editor(&terminal);
+        editor.refresh_status();
+        assert_eq!(terminal.title(), "[No Name] - hecto");
+        editor.run_with_events(parse_script("x").unwrap());
+        assert_eq!(terminal.title(), "[No Name] [+] - hecto");
+        drop(editor);
+        assert_eq!(terminal.title(), "");
+    }
+
+    #[test]
+    fn new_files_quit_without_asking() {
+        let path = env::temp_dir().join("hecto-new.txt");
+        let _ = fs::remove_file(&path);
+        let terminal = MockTerminal::new(Size {
+            height: 10,
+            width: 40,
+        });
+        let mut editor = test_editor(&terminal);
+        editor.open(&path.to_string_lossy());
+        editor.refresh_status();
+        editor.refresh_screen();
+        assert_eq!(terminal.row(8), "hecto-new.txt [new file]     Ln 1, Col 1");
+
+        // deleting at the end of the buffer changes nothing
+        editor.run_with_events(parse_script("<End><Del><C-t>").unwrap());
+        assert!(editor.should_quit);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn wrapped_lines_take_several_rows() {
+        let long = "one two three four five six seven eight nine ten eleven";
+        let (mut editor, terminal) = editor_with_file(
+            "hecto-wrap.txt",
+            &format!("{long}\nshort\n{long}\n{long}\n{long}\n{long}\n"),
+        );
+        editor.apply_config(Ok(Config {
+            wrap: true,
+            ..Config::default()
+        }));
+        editor.refresh_screen();
+        assert_eq!(terminal.row(0), "one two three four five six seven eight");
+        assert_eq!(terminal.row(1), "nine ten eleven");
+        assert_eq!(terminal.row(2), "short");
+
+        // continued rows have no line number
+        editor.apply_config(Ok(Config {
+            wrap: true,
+            show_line_numbers: true,
+            ..Config::default()
+        }));
+        editor.refresh_screen();
+        assert_eq!(terminal.row(1), "  eight nine ten eleven");
+        assert_eq!(terminal.row(2), "2 short");
+        editor.apply_config(Ok(Config {
+            wrap: true,
+            ..Config::default()
+        }));
+        editor.refresh_screen();
+        assert_eq!(terminal.row(1), "nine ten eleven");
+        assert_eq!(terminal.row(2), "short");
+
+        editor.run_with_events(parse_script("<Right><Right><Down>").unwrap());
+        let caret = terminal.caret();
+        assert_eq!((caret.row, caret.col), (1, 2));
+        editor.run_with_events(parse_script("<Down>").unwrap());
+        let caret = terminal.caret();
+        assert_eq!((caret.row, caret.col), (2, 2));
+        editor.run_with_events(parse_script("<A-Up><End>").unwrap());
+        let caret = terminal.caret();
+        assert_eq!((caret.row, caret.col), (1, 15));
+
+        // scrolling counts rows, not lines
+        editor.run_with_events(parse_script("<PageDown>").unwrap());
+        assert_eq!(terminal.row(0), "nine ten eleven");
+        assert_eq!(terminal.caret().row, 7);
+    }
+
+    #[test]
+    fn messages_are_coloured_by_severity() {
+        let (mut editor, terminal) = editor_with_file("hecto-severity.txt", "a\n");
+        editor.run_with_events(parse_script("<A-p>").unwrap());
+        assert!(matches!(
+            terminal.annotation_at(9, 0),
+            Some(AnnotationType::Warning)
+        ));
+
+        editor.run_with_events(parse_script("<C-o>/<Enter>").unwrap());
+        assert!(terminal.row(9).starts_with("Error reading"));
+        assert!(matches!(
+            terminal.annotation_at(9, 0),
+            Some(AnnotationType::Error)
+        ));
+        assert!(editor.message_bar.is_showing());
+
+        editor.run_with_events(parse_script("<C-s>").unwrap());
+        assert_eq!(terminal.row(9), "File saved successfully");
+        assert!(terminal.annotation_at(9, 0).is_none());
+    }
+
+    #[test]
+    fn typed_clusters_move_the_caret_once() {
+        let (mut editor, terminal) = editor_with_file("hecto-clusters.txt", "\n");
+        type_str(&mut editor, "e\u{301}x");
+        assert_eq!(terminal.row(0), "e\u{301}x");
+        assert_eq!(terminal.caret().col, 2);
+
+        press(&mut editor, KeyCode::Left, KeyModifiers::NONE);
+        type_str(&mut editor, "👨\u{200D}👩\u{200D}👧\u{1F1E9}\u{1F1EA}");
+        assert_eq!(editor.view().caret_location().grapheme_idx, 3);
+        assert_eq!(terminal.caret().col, 5);
+
+        // backspace takes whole clusters
+        press(&mut editor, KeyCode::Backspace, KeyModifiers::NONE);
+        press(&mut editor, KeyCode::Backspace, KeyModifiers::NONE);
+        assert_eq!(terminal.row(0), "e\u{301}x");
+        press(&mut editor, KeyCode::Backspace, KeyModifiers::NONE);
+        assert_eq!(terminal.row(0), "x");
+        assert_eq!(terminal.caret().col, 0);
+    }
+
+    #[test]
+    fn search_highlights_matches() {
+        let (mut editor, terminal) =
+            editor_with_file("hecto-search.log", "hello world\nfoo bar foo\n");
+        press(&mut editor, KeyCode::Char('f'), KeyModifiers::CONTROL);
+        type_str(&mut editor, "foo");
+
+        assert!(matches!(
+            terminal.annotation_at(1, 0),
+            Some(AnnotationType::SelectedMatch)
+        ));
+        assert!(matches!(
+            terminal.annotation_at(1, 8),
+            Some(AnnotationType::Match)
+        ));
+        assert!(terminal.annotation_at(1, 4).is_none());
+        assert!(terminal.annotation_at(0, 0).is_none());
+        assert_eq!(terminal.row(9), "Search: foo");
+
+        press(&mut editor, KeyCode::Enter, KeyModifiers::NONE);
+        assert_eq!(terminal.caret().row, 1);
+        assert_eq!(terminal.caret().col, 0);
+    }
+
+    #[test]
+    fn long_lines_are_not_highlighted() {
+        let path = env::temp_dir().join("hecto-long-line.txt");
+        fs::write(&path, "foo\nbar foo bar foo\n").unwrap();
+        let terminal = MockTerminal::new(Size {
+            height: 10,
+            width: 40,
+        });
+        let mut editor = test_editor(&terminal);
+        editor.apply_config(Ok(Config {
+            long_line_bytes: 10,
+            ..Config::default()
+        }));
+        editor.open(&path.to_string_lossy());
+        editor.refresh_screen();
+        assert!(terminal.row(9).starts_with("Line 2 is 15 bytes long"));
+
+        editor.run_with_events(parse_script("<C-f>foo<Enter><C-n>").unwrap());
+        assert!(matches!(
+            terminal.annotation_at(0, 0),
+            Some(AnnotationType::Match)
+        ));
+        assert!(terminal.annotation_at(1, 4).is_none());
+        // the caret still lands on the match
+        assert_eq!(terminal.caret().row, 1);
+        assert_eq!(terminal.caret().col, 4);
+    }
+
+    #[test]
+    fn edits_drop_highlights_of_matches_they_break() {
+        let (mut editor, terminal) =
+            editor_with_file("hecto-search-edit.log", "hello world\nfoo bar foo\n");
+        editor.run_with_events(parse_script("<C-f>foo<Enter><Del>").unwrap());
+        assert_eq!(terminal.row(1), "oo bar foo");
+        assert!(terminal.annotation_at(1, 0).is_none());
+        assert!(matches!(
+            terminal.annotation_at(1, 7),
+            Some(AnnotationType::Match)
+        ));
+
+        // typing it back highlights it again
+        editor.run_with_events(parse_script("f").unwrap());
+        assert!(matches!(
+            terminal.annotation_at(1, 0),
+            Some(AnnotationType::Match)
+        ));
+    }
+
+    #[test]
+    fn big_buffers_are_searched_in_the_background() {
+        let (mut editor, terminal) =
+            editor_with_file("hecto-search-background.txt", "hello world\nfoo bar foo\n");
+        editor.search_worker.min_lines = 0;
+        editor.run_with_events(parse_script("<C-f>foo").unwrap());
+        assert_eq!(terminal.row(9), "Search: foo [2 matches]");
+        assert_eq!(terminal.caret().row, 9);
+        assert!(matches!(
+            terminal.annotation_at(1, 0),
+            Some(AnnotationType::SelectedMatch)
+        ));
+
+        editor.run_with_events(parse_script("x").unwrap());
+        assert_eq!(terminal.row(9), "Search: foox [no match]");
+        editor.run_with_events(parse_script("<BS><BS><BS><BS>2,3/o<Enter>").unwrap());
+        assert_eq!(terminal.caret().row, 1);
+        assert_eq!(terminal.caret().col, 1);
+
+        // a search still running when it is aborted moves nothing
+        editor.run_with_events(parse_script("<C-f>").unwrap());
+        editor.process_event(Key(KeyEvent::new(KeyCode::Char('w'), KeyModifiers::NONE)));
+        editor.process_event(Key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE)));
+        editor.wait_for_search();
+        editor.refresh_screen();
+        assert_eq!(terminal.row(9), "Search aborted");
+        assert_eq!(terminal.caret().row, 1);
+        assert_eq!(terminal.caret().col, 1);
+    }
+
+    #[test]
+    fn big_files_load_in_the_background() {
+        let (mut editor, terminal) = editor_with_file("hecto-load-first.txt", "first\n");
+        editor.load_in_background_from = 0;
+        let path = env::temp_dir().join("hecto-load.txt");
+        fs::write(&path, "\u{feff}one\r\ntwo\r\n").unwrap();
+        editor.open(&path.to_string_lossy());
+        assert!(editor.view().is_loading());
+
+        // nothing can change the buffer until all of it is there
+        press(&mut editor, KeyCode::Char('x'), KeyModifiers::NONE);
+        assert!(
+            terminal
+                .row(9)
+                .starts_with("hecto-load.txt is still loading")
+        );
+        editor.receive_loaded(true);
+        assert!(!editor.view().is_loading());
+        editor.run_with_events(parse_script("x").unwrap());
+        assert_eq!(terminal.row(0), "xone");
+        assert_eq!(terminal.row(1), "two");
+        editor.run_with_events(parse_script("<C-s>").unwrap());
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "\u{feff}xone\r\ntwo\r\n"
+        );
+
+        // stopping closes the buffer again
+        let path = env::temp_dir().join("hecto-load-stopped.txt");
+        fs::write(&path, "one\n").unwrap();
+        editor.open(&path.to_string_lossy());
+        assert!(editor.view().is_loading());
+        press(&mut editor, KeyCode::Esc, KeyModifiers::NONE);
+        assert_eq!(editor.views.len(), 2);
+        assert_eq!(terminal.row(0), "xone");
+        assert_eq!(terminal.row(9), "Stopped loading hecto-load-stopped.txt");
+
+        let path = env::temp_dir().join("hecto-load-invalid.txt");
+        fs::write(&path, b"one\n\xff\n").unwrap();
+        editor.open(&path.to_string_lossy());
+        editor.receive_loaded(true);
+        editor.refresh_screen();
+        assert_eq!(editor.views.len(), 2);
+        assert!(
+            terminal
+                .row(9)
+                .starts_with("hecto-load-invalid.txt is not UTF-8")
+        );
+        editor.run_with_events(parse_script("n").unwrap());
+        assert!(terminal.row(9).starts_with("Error reading"));
+    }
+
+    #[test]
+    fn files_which_are_not_utf_8_can_be_read_as_windows_1252() {
+        let (mut editor, terminal) = editor_with_file("hecto-legacy-scratch.txt", "");
+        let path = env::temp_dir().join("hecto-legacy.txt");
+        fs::write(&path, b"caf\xe9 cr\xe8me\r\n\x93quoted\x94\r\n").unwrap();
+        editor.open(&path.to_string_lossy());
+        editor.refresh_screen();
+        assert!(
+            terminal
+                .row(9)
+                .starts_with("hecto-legacy.txt is not UTF-8. Open it")
+        );
+        editor.run_with_events(parse_script("y").unwrap());
+        assert_eq!(terminal.row(0), "café crème");
+        assert_eq!(terminal.row(1), "“quoted”");
+        assert_eq!(editor.view().get_status().encoding, "windows-1252");
+
+        // nothing is written while a character has no byte
+        editor.run_with_events(parse_script("<Down>✓<C-s>").unwrap());
+        assert_eq!(terminal.row(9), "Error writing 'hecto-legacy.txt': '✓' on");
+        assert_eq!(
+            fs::read(&path).unwrap(),
+            b"caf\xe9 cr\xe8me\r\n\x93quoted\x94\r\n"
+        );
+        editor.run_with_events(parse_script("<BS>\u{a7}<C-s>").unwrap());
+        assert_eq!(
+            fs::read(&path).unwrap(),
+            b"caf\xe9 cr\xe8me\r\n\xa7\x93quoted\x94\r\n"
+        );
+
+        editor.run_command("encoding-utf-8");
+        editor.run_with_events(parse_script("<C-s>").unwrap());
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "café crème\r\n§“quoted”\r\n"
+        );
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn moving_the_caret_prints_only_the_status_bar() {
+        let (mut editor, terminal) =
+            editor_with_file("hecto-caret-moves.txt", "hello world\nfoo bar\nbaz\n");
+        let mut config = editor.config.clone();
+        config.highlight_word = false;
+        editor.apply_config(Ok(config.clone()));
+        editor.refresh_screen();
+        terminal.take_printed_rows();
+        for code in [
+            KeyCode::Right,
+            KeyCode::Down,
+            KeyCode::End,
+            KeyCode::Up,
+            KeyCode::Left,
+            KeyCode::Home,
+        ] {
+            press(&mut editor, code, KeyModifiers::NONE);
+            assert_eq!(terminal.take_printed_rows(), [8], "after {code:?}");
+        }
+        assert_eq!(terminal.caret().row, 0);
+        // nothing changed at all
+        press(&mut editor, KeyCode::Home, KeyModifiers::NONE);
+        assert!(terminal.take_printed_rows().is_empty());
+
+        // another highlighted word only reprints the lines with the old or the new one
+        config.highlight_word = true;
+        editor.apply_config(Ok(config));
+        editor.refresh_screen();
+        terminal.take_printed_rows();
+        let moves = [
+            (KeyCode::Right, vec![8]),
+            (KeyCode::Down, vec![8, 0, 1]),
+            (KeyCode::End, vec![8, 1]),
+            (KeyCode::Right, vec![8, 1, 2]),
+        ];
+        for (code, rows) in moves {
+            press(&mut editor, code, KeyModifiers::NONE);
+            assert_eq!(terminal.take_printed_rows(), rows, "after {code:?}");
+        }
+    }
+
+    #[test]
+    fn search_stays_within_a_line_range() {
+        let (mut editor, terminal) =
+            editor_with_file("hecto-search-range.log", "foo\nfoo\nbar foo\nfoo\n");
+        let caret = |terminal: &MockTerminal| (terminal.caret().row, terminal.caret().col);
+        editor.run_with_events(parse_script("<C-f>2,3/foo").unwrap());
+        assert_eq!(terminal.row(9), "Search: 2,3/foo [in lines 2-3]");
+        assert!(terminal.annotation_at(0, 0).is_none());
+        assert!(terminal.annotation_at(3, 0).is_none());
+        assert!(matches!(
+            terminal.annotation_at(2, 4),
+            Some(AnnotationType::Match)
+        ));
+
+        editor.run_with_events(parse_script("<Enter>").unwrap());
+        assert_eq!(terminal.row(9), "Searching in lines 2-3");
+        assert_eq!(caret(&terminal), (1, 0));
+        editor.run_with_events(parse_script("<C-n>").unwrap());
+        assert_eq!(caret(&terminal), (2, 4));
+        // wrapping around within the lines
+        editor.run_with_events(parse_script("<C-n>").unwrap());
+        assert_eq!(caret(&terminal), (1, 0));
+        editor.run_with_events(parse_script("<C-p>").unwrap());
+        assert_eq!(caret(&terminal), (2, 4));
+    }
+
+    #[test]
+    fn splits_a_line_range_off_the_query() {
+        assert_eq!(split_line_range("10,50/foo"), (Some(9..50), "foo"));
+        assert_eq!(split_line_range("3, 3/a/b"), (Some(2..3), "a/b"));
+        assert_eq!(split_line_range("50,10/foo"), (None, "50,10/foo"));
+        assert_eq!(split_line_range("0,5/foo"), (None, "0,5/foo"));
+        assert_eq!(split_line_range("and/or"), (None, "and/or"));
+        assert_eq!(split_line_range("1,2"), (None, "1,2"));
+    }
+
+    #[test]
+    fn jumps_lead_back_and_forward() {
+        let (mut editor, terminal) = editor_with_file("hecto-jumps.txt", "one\ntwo\nthree\nfour\n");
+        let caret = |terminal: &MockTerminal| (terminal.caret().row, terminal.caret().col);
+        editor.run_with_events(parse_script("<Right><C-f>four<Enter><C-End>").unwrap());
+        assert_eq!(caret(&terminal), (3, 4));
+
+        editor.run_with_events(parse_script("<A-,>").unwrap());
+        assert_eq!(caret(&terminal), (3, 0));
+        editor.run_with_events(parse_script("<A-,><A-,>").unwrap());
+        assert_eq!(caret(&terminal), (0, 1));
+        editor.run_with_events(parse_script("<A-.>").unwrap());
+        assert_eq!(caret(&terminal), (3, 0));
+
+        // a jump from here forgets the end of the file
+        editor.run_with_events(parse_script("<C-Home><A-.>").unwrap());
+        assert_eq!(caret(&terminal), (0, 0));
+        editor.run_with_events(parse_script("<A-,>").unwrap());
+        assert_eq!(caret(&terminal), (3, 0));
+    }
+
+    #[test]
+    fn marks_follow_edits_above_them() {
+        let (mut editor, terminal) = editor_with_file("hecto-marks.txt", "one\ntwo\nthree\n");
+        let caret = |terminal: &MockTerminal| (terminal.caret().row, terminal.caret().col);
+        editor.run_with_events(parse_script("<Down><Right><A-b>a").unwrap());
+        assert_eq!(terminal.row(9), "Mark a set");
+
+        editor.run_with_events(parse_script("<C-Home><End><Enter>x<A-j>a").unwrap());
+        // the caret is right of the mark's name in the gutter
+        assert_eq!(caret(&terminal), (2, 3));
+        editor.run_with_events(parse_script("<A-j>.").unwrap());
+        assert_eq!(caret(&terminal), (1, 3));
+
+        editor.run_with_events(parse_script("<A-j>z").unwrap());
+        assert_eq!(terminal.row(9), "Mark z is not set");
+        editor.run_with_events(parse_script("<A-x>list-marks<Enter>").unwrap());
+        assert_eq!(terminal.row(9), "Marks: . 2:2, a 3:2");
+    }
+
+    #[test]
+    fn gutter_names_marks_and_cycles_through_them() {
+        let (mut editor, terminal) =
+            editor_with_file("hecto-mark-gutter.txt", "one\ntwo\nthree\nfour\n");
+        editor.run_with_events(parse_script("<Down><Down><A-b>b<Up><A-b>z").unwrap());
+        assert_eq!(terminal.row(0), "  one");
+        assert_eq!(terminal.row(1), "z two");
+        assert_eq!(terminal.row(2), "b three");
+        assert_eq!(terminal.annotation_at(2, 0), Some(AnnotationType::Mark));
+        assert_eq!(terminal.caret().col, 2);
+
+        // marks follow the lines inserted above them, the last edit's isn't shown
+        editor.run_with_events(parse_script("<C-Home><End><Enter><Enter>").unwrap());
+        assert_eq!(terminal.row(2), "");
+        assert_eq!(terminal.row(3), "z two");
+        assert_eq!(terminal.row(4), "b three");
+
+        // by line, whatever the names
+        editor.run_with_events(parse_script("<C-Home><C-k>]").unwrap());
+        assert_eq!(terminal.caret().row, 3);
+        editor.run_with_events(parse_script("<C-k>]").unwrap());
+        assert_eq!(terminal.caret().row, 4);
+        editor.run_with_events(parse_script("<C-k>]").unwrap());
+        assert_eq!(terminal.caret().row, 3);
+        assert_eq!(terminal.row(9), "Wrapped to the first mark");
+        editor.run_with_events(parse_script("<C-k>[").unwrap());
+        assert_eq!(terminal.caret().row, 4);
+        assert_eq!(terminal.row(9), "Wrapped to the last mark");
+
+        // with line numbers, the name goes left of them
+        editor.apply_config(Ok(Config {
+            show_line_numbers: true,
+            ..Config::default()
+        }));
+        editor.refresh_screen();
+        assert_eq!(terminal.row(0), "  1 one");
+        assert_eq!(terminal.row(3), "z 4 two");
+
+        editor.run_with_events(parse_script("<A-x>delete-mark<Enter>z").unwrap());
+        assert_eq!(terminal.row(9), "Mark z deleted");
+        assert_eq!(terminal.row(3), "  4 two");
+        editor.run_with_events(parse_script("<A-x>delete-mark<Enter>b").unwrap());
+        assert_eq!(terminal.row(4), "5 three");
+        editor.run_with_events(parse_script("<A-x>delete-mark<Enter>b").unwrap());
+        assert_eq!(terminal.row(9), "Mark b is not set");
+        editor.run_with_events(parse_script("<C-k>]").unwrap());
+        assert_eq!(terminal.row(9), "No marks set");
+    }
+
+    #[test]
+    fn inserts_command_output() {
+        let (mut editor, terminal) = editor_with_file("hecto-shell.txt", "ab\n");
+        editor.run_with_events(parse_script("<Right><C-k>!echo 1; echo 2<Enter>").unwrap());
+        assert_eq!(terminal.row(0), "a1");
+        assert_eq!(terminal.row(1), "2b");
+        assert_eq!((terminal.caret().row, terminal.caret().col), (1, 1));
+
+        editor.run_with_events(parse_script("<C-k>!echo nope >&2; false<Enter>").unwrap());
+        assert_eq!(terminal.row(9), "nope");
+        assert_eq!(terminal.row(1), "2b");
+    }
+
+    #[test]
+    fn filters_the_buffer() {
+        let (mut editor, terminal) = editor_with_file("hecto-filter.txt", "b\na\nc\n");
+        editor.run_with_events(parse_script("<Down><C-k>|sort -r<Enter>").unwrap());
+        assert_eq!(terminal.row(0), "c");
+        assert_eq!(terminal.row(2), "a");
+        assert_eq!((terminal.caret().row, terminal.caret().col), (0, 0));
+
+        editor.run_with_events(parse_script("<C-k>|cat<Enter>").unwrap());
+        assert_eq!(terminal.row(9), "Filter changed nothing");
+        editor.run_with_events(parse_script("<C-k>|echo no >&2; exit 1<Enter>").unwrap());
+        assert_eq!(terminal.row(9), "no");
+        assert_eq!(terminal.row(0), "c");
+    }
+
+    #[test]
+    fn formats_on_save_if_configured() {
+        let (mut editor, terminal) = editor_with_file("hecto-format.txt", "b\na\n");
+        let mut config = Config::default();
+        config
+            .formatters
+            .insert(String::from("txt"), String::from("sort"));
+        editor.apply_config(Ok(config));
+        editor.run_with_events(parse_script("<Down><End>c<C-s>").unwrap());
+        assert_eq!(terminal.row(0), "ac");
+        assert_eq!(terminal.row(1), "b");
+        assert_eq!((terminal.caret().row, terminal.caret().col), (1, 1));
+        assert_eq!(terminal.row(9), "File saved successfully");
+
+        let mut config = Config::default();
+        config
+            .formatters
+            .insert(String::from("txt"), String::from("exit 2"));
+        editor.apply_config(Ok(config));
+        editor.run_with_events(parse_script("x<C-s>").unwrap());
+        assert!(terminal.row(9).starts_with("Saved unformatted: 'exit 2'"));
+        assert!(!editor.view().get_status().is_modified);
+    }
+
+    #[test]
+    fn failing_formatters_abort_saving_all_to_quit() {
+        let path = env::temp_dir().join("hecto-format-quit.txt");
+        fs::write(&path, "a\n").unwrap();
+        // wide enough for the whole message
+        let terminal = MockTerminal::new(Size {
+            height: 10,
+            width: 120,
+        });
+        let mut editor = test_editor(&terminal);
+        editor.open(&path.to_string_lossy());
+        let mut config = Config::default();
+        config
+            .formatters
+            .insert(String::from("txt"), String::from("exit 2"));
+        editor.apply_config(Ok(config));
+        editor.run_with_events(parse_script("x<C-t>y").unwrap());
+        assert!(!editor.should_quit);
+        assert!(!editor.view().get_status().is_modified);
+        let message = terminal.row(9);
+        assert!(message.starts_with("Saved hecto-format-quit.txt unformatted: 'exit 2'"));
+        assert!(message.ends_with(". Quit aborted"));
+
+        // nothing is left unsaved to ask about the next time
+        editor.run_with_events(parse_script("<C-t>").unwrap());
+        assert!(editor.should_quit);
+    }
+
+    #[test]
+    fn completes_words_from_the_buffer() {
+        let (mut editor, terminal) = editor_with_file("hecto-complete.txt", "alpha alpine\n\n");
+        editor.run_with_events(parse_script("<Down>al<Tab>").unwrap());
+        assert_eq!(terminal.row(1), "alpha");
+        assert_eq!(terminal.row(9), "Completion 1 of 2");
+        editor.run_with_events(parse_script("<Tab>").unwrap());
+        assert_eq!(terminal.row(1), "alpine");
+        editor.run_with_events(parse_script("<A-/>").unwrap());
+        assert_eq!(terminal.row(1), "alpha");
+        assert_eq!((terminal.caret().row, terminal.caret().col), (1, 5));
+
+        // typing accepts the completion, a Tab after a space is a tab
+        editor.run_with_events(parse_script("s <Tab>zz<A-/>").unwrap());
+        assert_eq!(terminal.row(1), "alphas  zz");
+        assert_eq!(terminal.row(9), "No completions");
+    }
+
+    #[test]
+    fn sorts_and_deduplicates_lines() {
+        let (mut editor, terminal) = editor_with_file("hecto-sort.txt", "b\nA\na\nb\n");
+        editor.run_with_events(parse_script("<Down><Down><End>").unwrap());
+        editor.run_command("sort-lines");
+        editor.refresh_screen();
+        assert_eq!(
+            (0..4).map(|row| terminal.row(row)).collect::<Vec<_>>(),
+            ["A", "a", "b", "b"]
+        );
+        assert_eq!(terminal.row(9), "Lines sorted");
+        assert_eq!((terminal.caret().row, terminal.caret().col), (2, 1));
+
+        editor.run_command("remove-duplicate-lines");
+        editor.run_command("sort-lines-ignoring-case");
+        editor.refresh_screen();
+        assert_eq!(terminal.row(2), "b");
+        assert_eq!(terminal.row(3), "~");
+        assert_eq!(terminal.row(9), "Nothing changed");
+    }
+
+    #[test]
+    fn transposes_characters_and_lines() {
+        let (mut editor, terminal) = editor_with_file("hecto-transpose.txt", "one\ntwo\n");
+        editor.run_with_events(parse_script("<Down><Right><C-k>t<A-t>").unwrap());
+        assert_eq!(terminal.row(0), "wto");
+        assert_eq!(terminal.row(1), "one");
+        assert_eq!((terminal.caret().row, terminal.caret().col), (1, 2));
+        assert!(editor.view().get_status().is_modified);
+    }
+
+    #[test]
+    fn marks_lines_going_on_beyond_the_edges() {
+        let long = "0123456789".repeat(6);
+        let (mut editor, terminal) =
+            editor_with_file("hecto-scroll-markers.txt", &format!("{long}\nshort\n"));
+        let status_line = Some(String::from("%h%=Ln %l, Col %c"));
+        editor.apply_config(Ok(Config {
+            status_line: status_line.clone(),
+            ..Config::default()
+        }));
+        editor.refresh_status();
+        editor.refresh_screen();
+        assert_eq!(terminal.row(0), format!("{}>", &long[..39]));
+        assert_eq!(terminal.row(1), "short");
+        assert_eq!(
+            terminal.annotation_at(0, 39),
+            Some(AnnotationType::ScrollMarker)
+        );
+        assert!(terminal.row(8).starts_with("→ +21 cols "));
+
+        // the caret stays off the marker's column
+        editor.run_with_events(parse_script(&"<Right>".repeat(38)).unwrap());
+        assert_eq!(terminal.caret().col, 38);
+        editor.run_with_events(parse_script("<Right>").unwrap());
+        assert_eq!(terminal.caret().col, 38);
+        assert_eq!(terminal.row(0), format!("<{}>", &long[2..40]));
+        assert_eq!(terminal.row(1), "<ort");
+        assert!(terminal.row(8).starts_with("← 2 cols → +20 cols "));
+
+        // nothing beyond the end of the line needs a marker to keep clear of
+        editor.run_with_events(parse_script("<End>").unwrap());
+        assert_eq!(terminal.row(0), format!("<{}", &long[22..]));
+        assert_eq!(terminal.caret().col, 39);
+        assert!(terminal.row(8).starts_with("← 22 cols "));
+
+        // not while lines wrap
+        editor.apply_config(Ok(Config {
+            wrap: true,
+            status_line,
+            ..Config::default()
+        }));
+        editor.refresh_status();
+        editor.refresh_screen();
+        assert_eq!(terminal.row(0), &long[..40]);
+        assert!(terminal.row(8).starts_with(' '));
+    }
+
+    #[test]
+    fn lines_which_fit_exactly_dont_scroll() {
+        let fits = "0123456789".repeat(4);
+        let (mut editor, terminal) = editor_with_file(
+            "hecto-scroll-fits.txt",
+            &format!(
+                "{fits}
+{}
+",
+                &fits[1..]
+            ),
+        );
+        editor.run_with_events(parse_script(&"<Right>".repeat(39)).unwrap());
+        assert_eq!(terminal.row(0), fits);
+        assert_eq!(terminal.caret().col, 39);
+
+        // one shorter, the end of the line is the last column
+        editor.run_with_events(parse_script("<Down><End>").unwrap());
+        assert_eq!(terminal.row(1), &fits[1..]);
+        assert_eq!(terminal.caret().col, 39);
+
+        editor.run_with_events(parse_script("<Up><End>").unwrap());
+        assert_eq!(terminal.row(0), format!("<{}", &fits[2..]));
+        assert_eq!(terminal.caret().col, 39);
+    }
+
+    #[test]
+    fn search_highlights_stay_on_their_cells_at_the_edges() {
+        // scrolled by 12 columns, which cuts "老" on the left of the first line and on the
+        // right of the second next to the markers of more text, leaving it blank, the tab on
+        // the third is drawn as two spaces
+        let text = format!(
+            "{}老ab{}\n{}a老b\n{}x\t老\n",
+            "-".repeat(12),
+            "-".repeat(34),
+            "-".repeat(49),
+            "-".repeat(13)
+        );
+        let (mut editor, terminal) = editor_with_file("hecto-edge-highlights.txt", &text);
+        editor.run_with_events(parse_script("<End><C-f>老<Enter>").unwrap());
+        assert!(terminal.row(0).starts_with("< ab-"));
+        assert!(terminal.row(1).ends_with("-a >"));
+        assert!(terminal.row(2).starts_with("<x  老"));
+        let is_match = |row, col| {
+            matches!(
+                terminal.annotation_at(row, col),
+                Some(AnnotationType::Match | AnnotationType::SelectedMatch)
+            )
+        };
+        let matched = |row| {
+            (0..40)
+                .filter(|col| is_match(row, *col))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(matched(0), [1]);
+        assert_eq!(matched(1), [38]);
+        assert_eq!(matched(2), [4, 5]);
+        assert_eq!(
+            terminal.annotation_at(1, 39),
+            Some(AnnotationType::ScrollMarker)
+        );
+    }
+
+    #[test]
+    fn highlights_the_word_under_the_caret() {
+        let (mut editor, terminal) =
+            editor_with_file("hecto-word.txt", "let foo = 1;\nfoo(fool);\n");
+        editor.apply_config(Ok(Config {
+            highlight_word: true,
+            ..Config::default()
+        }));
+        editor.refresh_screen();
+        let is_match = |row, col| {
+            matches!(
+                terminal.annotation_at(row, col),
+                Some(AnnotationType::WordMatch)
+            )
+        };
+        assert!(is_match(0, 0));
+        assert!(!is_match(1, 0));
+
+        editor.run_with_events(parse_script("<Right><Right><Right><Right>").unwrap());
+        assert!(is_match(0, 4));
+        assert!(is_match(1, 0));
+        assert!(!is_match(1, 4));
+
+        // a search takes over
+        editor.run_with_events(parse_script("<C-f>fool<Enter>").unwrap());
+        assert!(!is_match(0, 4));
+    }
+
+    #[test]
+    fn resizing_to_tiny_sizes_does_not_panic() {
+        let (mut editor, terminal) = editor_with_file("hecto-resize.txt", "hello\n");
+        for (width, height) in [(40, 1), (1, 3), (0, 0), (2, 2), (40, 10)] {
+            let size = Size {
+                height: usize::from(height),
+                width: usize::from(width),
+            };
+            terminal.resize(size);
+            editor.evaluate_event(Event::Resize(width, height));
+            editor.refresh_screen();
+        }
+        assert_eq!(terminal.row(0), "hello");
+    }
+
+    #[test]
+    fn tiny_terminals_show_a_note_and_can_still_be_quit() {
+        let (mut editor, terminal) = editor_with_file("hecto-tiny.txt", "hello\n");
+        let resize = |editor: &mut Editor, width: u16, height: u16| {
+            terminal.resize(Size {
+                height: usize::from(height),
+                width: usize::from(width),
+            });
+            editor.run_with_events([Event::Resize(width, height)]);
+        };
+
+        resize(&mut editor, 1, 1);
+        assert_eq!(terminal.row(0), "t");
+        resize(&mut editor, 3, 2);
+        assert_eq!(terminal.row(0), "ter");
+        assert_eq!(terminal.row(1), "");
+        resize(&mut editor, 40, 2);
+        assert_eq!(terminal.row(0), "    terminal too small (need ≥10x3)");
+
+        // nothing else is typed blindly, but the question whether to save can be answered
+        editor.run_with_events(parse_script("x<C-t>").unwrap());
+        assert!(!editor.view().get_status().is_modified);
+        assert!(editor.should_quit);
+        editor.should_quit = false;
+        resize(&mut editor, 9, 3);
+        assert_eq!(terminal.row(1), "terminal");
+
+        // the smallest size which is drawn as usual
+        resize(&mut editor, 10, 3);
+        assert_eq!(terminal.row(0), "hello");
+        assert!(terminal.is_inverted(1));
+        assert!(terminal.row(1).starts_with("hecto-"));
+        editor.run_with_events(parse_script("x").unwrap());
+        resize(&mut editor, 40, 2);
+        editor.run_with_events(parse_script("<C-t>").unwrap());
+        assert!(!editor.should_quit);
+        assert_eq!(terminal.row(0), "    terminal too small (need ≥10x3)");
+        assert!(terminal.row(1).starts_with("hecto-tiny.txt has unsaved"));
+        editor.run_with_events(parse_script("n").unwrap());
+        assert!(editor.should_quit);
+    }
+
+    #[test]
+    fn prompt_follows_a_resize() {
+        let (mut editor, terminal) = editor_with_file("hecto-resize-prompt.txt", "hello\n");
+        editor.run_with_events(parse_script("<C-f>he").unwrap());
+        let size = Size {
+            height: 12,
+            width: 30,
+        };
+        terminal.resize(size);
+        editor.run_with_events([Event::Resize(30, 12)]);
+        assert_eq!(terminal.row(11), "Search: he");
+        assert_eq!(terminal.caret().row, 11);
+        assert_eq!(terminal.row(0), "hello");
+    }
+
+    #[test]
+    fn redraws_a_garbled_screen() {
+        let (mut editor, terminal) = editor_with_file("hecto-redraw.txt", "hello\n");
+        let size = Size {
+            height: 10,
+            width: 40,
+        };
+        // wipes the mock's screen without telling the editor
+        terminal.resize(size);
+        editor.run_with_events([]);
+        assert_eq!(terminal.row(0), "");
+        editor.run_with_events(parse_script("<C-l>").unwrap());
+        assert_eq!(terminal.row(0), "hello");
+        assert!(terminal.row(8).starts_with("hecto-redraw.txt"));
+
+        terminal.resize(size);
+        editor.run_with_events([Event::FocusGained]);
+        assert_eq!(terminal.row(0), "hello");
+    }
+
+    #[test]
+    fn search_edit_and_save() {
+        let (mut editor, terminal) =
+            editor_with_file("hecto-script.txt", "hello world\nfoo bar foo\n");
+        editor.run_with_events(parse_script("<C-f>foo<Enter>").unwrap());
+        assert_eq!(terminal.caret().row, 1);
+        assert_eq!(terminal.caret().col, 0);
+
+        editor.run_with_events(parse_script("x<C-s>").unwrap());
+        let saved = fs::read_to_string(env::temp_dir().join("hecto-script.txt")).unwrap();
+        assert_eq!(saved, "hello world\nxfoo bar foo\n");
+        assert_eq!(terminal.row(9), "File saved successfully");
+    }
+
+    #[test]
+    fn text_prompts_share_their_keys() {
+        let (mut editor, terminal) = editor_with_file("hecto-prompt-keys.txt", "one\ntwo\nthree\n");
+        // the search follows the query as it is typed, Esc goes back to where it started
+        editor.run_with_events(parse_script("<C-f>thr").unwrap());
+        assert_eq!(editor.view().get_status().current_line_idx, 2);
+        editor.run_with_events(parse_script("<Esc>").unwrap());
+        assert_eq!(editor.view().get_status().current_line_idx, 0);
+        assert_eq!(terminal.row(9), "Search aborted");
+
+        // moving the caret is ignored, and only what makes a line number is typed
+        editor.set_prompt(PromptType::GoToLine);
+        editor.run_with_events(parse_script("x2<Left><Up>y<Enter>").unwrap());
+        assert!(editor.no_prompt());
+        assert_eq!(editor.view().get_status().current_line_idx, 1);
+
+        editor.set_prompt(PromptType::Save);
+        editor.run_with_events(parse_script("<Down>").unwrap());
+        assert!(editor.prompt_type == PromptType::Save);
+    }
+
+    #[test]
+    fn opens_the_file_under_the_caret() {
+        fs::write(env::temp_dir().join("hecto-link-target.txt"), "linked\n").unwrap();
+        let (mut editor, terminal) = editor_with_file(
+            "hecto-link.txt",
+            " (hecto-link-target.txt) or hecto-link-missing.txt\n",
+        );
+        editor.run_command("open-under-caret");
+        editor.refresh_screen();
+        assert_eq!(terminal.row(9), "Nothing to open under the caret");
+
+        editor.run_with_events(parse_script("<End>").unwrap());
+        editor.run_command("open-under-caret");
+        editor.refresh_screen();
+        assert!(terminal.row(9).starts_with("No file '"));
+        assert_eq!(editor.views.len(), 1);
+
+        // relative to the directory of the file
+        editor.run_with_events(parse_script("<Home><Right><Right>").unwrap());
+        editor.run_command("open-under-caret");
+        editor.refresh_screen();
+        assert_eq!(editor.views.len(), 2);
+        assert_eq!(terminal.row(0), "linked");
+    }
+
+    #[test]
+    fn script_stops_after_quit() {
+        let (mut editor, terminal) = editor_with_file("hecto-quit.txt", "hello\n");
+        editor.run_with_events(parse_script("<C-t>abc").unwrap());
+        assert!(editor.should_quit);
+        assert_eq!(terminal.row(0), "hello");
+    }
+
+    #[test]
+    fn offers_to_restore_recovered_changes() {
+        let path = env::temp_dir().join("hecto-restore.txt");
+        fs::write(&path, "saved\n").unwrap();
+        let recovery = recovery::recovery_path(Some(&path), Some(&test_recovery_dir())).unwrap();
+        fs::write(&recovery, "unsaved 老虎\n").unwrap();
+
+        let terminal = MockTerminal::new(Size {
+            height: 10,
+            width: 80,
+        });
+        let mut editor = test_editor(&terminal);
+        editor.open(&path.to_string_lossy());
+        editor.refresh_screen();
+        assert!(
+            terminal
+                .row(9)
+                .starts_with("Found unsaved changes from a crash")
+        );
+
+        editor.run_with_events(parse_script("y").unwrap());
+        assert_eq!(terminal.row(0), "unsaved 老虎");
+        assert!(editor.view().get_status().is_modified);
+        assert!(!recovery.exists());
+    }
+
+    #[test]
+    fn crashing_round_trips_multibyte_changes_through_recovery_files() {
+        let dir = env::temp_dir().join("hecto-crash-recovery");
+        let _ = fs::remove_dir_all(&dir);
+        let (mut editor, _terminal) = editor_with_file("hecto-crash.txt", "saved\n");
+        let path = editor.view().file_path().unwrap();
+        editor.recovery_dir = Some(dir.clone());
+        editor.run_with_events(parse_script("<End> 老虎 e\u{301}👨\u{200d}👩").unwrap());
+
+        // dropped while panicking, like the editor of a crashing hecto
+        let crash = panic::catch_unwind(AssertUnwindSafe(move || {
+            let _editor = editor;
+            panic!("crash");
+        }));
+        assert!(crash.is_err());
+        let recovery = recovery::recovery_path(Some(&path), Some(&dir)).unwrap();
+        let unsaved = "saved 老虎 e\u{301}👨\u{200d}👩\n";
+        assert_eq!(fs::read_to_string(&recovery).unwrap(), unsaved);
+
+        let terminal = MockTerminal::new(Size {
+            height: 10,
+            width: 80,
+        });
+        let mut editor = test_editor(&terminal);
+        editor.recovery_dir = Some(dir.clone());
+        editor.open(&path.to_string_lossy());
+        editor.run_with_events(parse_script("y").unwrap());
+        assert_eq!(terminal.row(0), "saved 老虎 e\u{301}👨\u{200d}👩");
+        assert!(editor.view().get_status().is_modified);
+        assert!(!recovery.exists());
+        editor.run_with_events(parse_script("<C-s>").unwrap());
+        assert_eq!(fs::read_to_string(&path).unwrap(), unsaved);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn autosaves_to_a_sidecar_until_saved() {
+        let (mut editor, terminal) = editor_with_file("hecto-autosave.txt", "hello\n");
+        let path = env::temp_dir().join("hecto-autosave.txt");
+        let sidecar = autosave::sidecar_path(&path).unwrap();
+        editor.enable_autosave(Duration::ZERO);
+
+        editor.autosave();
+        assert!(!sidecar.exists());
+
+        editor.run_with_events(parse_script("x").unwrap());
+        editor.autosave();
+        editor.refresh_screen();
+        assert_eq!(fs::read_to_string(&sidecar).unwrap(), "xhello\n");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "hello\n");
+        assert_eq!(terminal.row(9), "Autosaved");
+
+        editor.run_with_events(parse_script("<C-s>").unwrap());
+        assert!(!sidecar.exists());
+    }
+
+    #[test]
+    fn tabs_line_up_at_tab_stops() {
+        let (mut editor, terminal) = editor_with_file("hecto-tab-stops.txt", "\tab\n老\tx\n");
+        assert_eq!(terminal.row(0), "        ab");
+        assert_eq!(terminal.row(1), "老      x");
+        editor.run_with_events(parse_script("<Down><End>").unwrap());
+        assert_eq!(terminal.caret().col, 9);
+        assert!(terminal.row(8).ends_with("Ln 2, Col 4-10"));
+        editor.run_with_events(parse_script("<Left>").unwrap());
+        assert_eq!(terminal.caret().col, 8);
+        assert!(terminal.row(8).ends_with("Ln 2, Col 3-9"));
+    }
+
+    #[test]
+    fn ambiguous_characters_can_be_wide() {
+        let (mut editor, terminal) = editor_with_file("hecto-ambiguous.txt", "→─x\n");
+        editor.run_with_events(parse_script("<End>").unwrap());
+        assert_eq!(terminal.caret().col, 3);
+        editor.apply_config(Ok(Config {
+            ambiguous_width: Some(AmbiguousWidth::Wide),
+            ..Config::default()
+        }));
+        editor.run_with_events(parse_script("<End>").unwrap());
+        assert_eq!(terminal.caret().col, 5);
+        editor.run_with_events(parse_script("<Left><Left>").unwrap());
+        assert_eq!(terminal.caret().col, 2);
+        assert!(terminal.row(8).ends_with("Ln 1, Col 2-3"));
+    }
+
+    #[test]
+    fn config_options_apply_to_views() {
+        let (mut editor, terminal) = editor_with_file("hecto-config.txt", "a\tb\nc  \n");
+        editor.apply_config(Ok(Config {
+            tab_width: 4,
+            expand_tab: true,
+            show_line_numbers: true,
+            trim_trailing_whitespace_on_save: true,
+            ..Config::default()
+        }));
+        editor.refresh_screen();
+        assert_eq!(terminal.row(0), "1 a   b");
+        assert!(matches!(
+            terminal.annotation_at(1, 0),
+            Some(AnnotationType::LineNumber)
+        ));
+        assert_eq!(terminal.caret().col, 2);
+
+        editor.run_with_events(parse_script("<Tab><C-s>").unwrap());
+        let saved = fs::read_to_string(env::temp_dir().join("hecto-config.txt")).unwrap();
+        assert_eq!(saved, "    a\tb\nc\n");
+    }
+
+    #[test]
+    fn gutter_marks_lines_edited_since_saving() {
+        let (mut editor, terminal) = editor_with_file("hecto-edited.txt", "a\nb\nc\nd\n");
+        editor.apply_config(Ok(Config {
+            show_line_numbers: true,
+            ..Config::default()
+        }));
+        editor.run_with_events(parse_script("<Down><Down>x<Up><Up><End><Enter>").unwrap());
+        assert_eq!(terminal.row(0), "1 a");
+        assert_eq!(terminal.row(1), "2▎");
+        assert!(matches!(
+            terminal.annotation_at(1, 1),
+            Some(AnnotationType::EditedLine)
+        ));
+        assert_eq!(terminal.row(3), "4▎xc");
+
+        editor.run_with_events(parse_script("<C-k>n").unwrap());
+        assert_eq!(terminal.caret().row, 3);
+        editor.run_with_events(parse_script("<C-k>n<C-k>p").unwrap());
+        assert_eq!(terminal.caret().row, 3);
+
+        editor.run_with_events(parse_script("<C-s>").unwrap());
+        assert_eq!(terminal.row(1), "2");
+        assert_eq!(terminal.row(3), "4 xc");
+        editor.run_with_events(parse_script("<C-k>n").unwrap());
+        assert_eq!(terminal.row(9), "No lines edited since saving");
+    }
+
+    #[test]
+    fn gutter_shows_how_lines_differ_from_the_file_checked_in() {
+        let dir = env::temp_dir().join("hecto-git-gutter");
+        git::test::repository(&dir, &[("a.txt", "one\ntwo\nthree\nfour\nfive\n")]);
+        let (mut editor, terminal) =
+            editor_with_file("hecto-git-gutter/a.txt", "one\nTWO\nthree\nfive\nsix\n");
+        editor.receive_git_changes(true);
+        editor.refresh_screen();
+        assert_eq!(terminal.row(0), "  one");
+        assert_eq!(terminal.row(1), "~ TWO");
+        assert_eq!(terminal.row(3), "- five");
+        assert_eq!(terminal.row(4), "+ six");
+        assert_eq!(terminal.annotation_at(4, 0), Some(AnnotationType::Inserted));
+        assert_eq!(terminal.caret().col, 2);
+
+        editor.run_with_events(parse_script("<C-k>.").unwrap());
+        assert_eq!(terminal.caret().row, 1);
+        editor.run_with_events(parse_script("<C-k>.<C-k>,").unwrap());
+        assert_eq!(terminal.caret().row, 1);
+
+        // the signs move along with edits, and are worked out again on save
+        editor.run_with_events(parse_script("<C-Home><Enter>").unwrap());
+        assert_eq!(terminal.row(2), "~ TWO");
+        editor.run_with_events(parse_script("<BS><C-s>").unwrap());
+        assert_eq!(terminal.row(1), "~ TWO");
+        git::test::git(&dir, &["commit", "--quiet", "-am", "edited"]);
+        editor.run_with_events(parse_script("<A-x>refresh-git<Enter>").unwrap());
+        assert_eq!(terminal.row(1), "TWO");
+        editor.run_with_events(parse_script("<C-k>.").unwrap());
+        assert_eq!(terminal.row(9), "No changes since the last commit");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn misspelled_words_are_underlined_in_prose() {
+        let dir = env::temp_dir().join("hecto-spelling");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("words.txt"), "the\nquick\nfox\n").unwrap();
+        let (mut editor, terminal) = editor_with_file("hecto-spelling.md", "The quick fox brwn\n");
+        editor.apply_config(Ok(Config {
+            dictionary: Some(dir.join("words.txt")),
+            user_dictionary: Some(dir.join("user.txt")),
+            ..Config::default()
+        }));
+        editor.refresh_screen();
+        assert_eq!(terminal.annotation_at(0, 4), None);
+        assert_eq!(
+            terminal.annotation_at(0, 14),
+            Some(AnnotationType::SpellError)
+        );
+
+        editor.run_command("toggle-spell-check");
+        editor.refresh_screen();
+        assert_eq!(terminal.annotation_at(0, 14), None);
+        assert_eq!(terminal.row(9), "Spell checking off");
+        editor.run_command("toggle-spell-check");
+        editor.refresh_screen();
+        assert_eq!(
+            terminal.annotation_at(0, 14),
+            Some(AnnotationType::SpellError)
+        );
+
+        editor.run_with_events(parse_script("<End><A-x>add-word<Enter>").unwrap());
+        assert_eq!(terminal.annotation_at(0, 14), None);
+        assert_eq!(terminal.row(9), "Added 'brwn' to the dictionary");
+        assert_eq!(fs::read_to_string(dir.join("user.txt")).unwrap(), "brwn\n");
+
+        // code isn't checked unless turned on
+        let (mut editor, terminal) = editor_with_file("hecto-spelling.rs", "// brwn\n");
+        editor.apply_config(Ok(Config {
+            dictionary: Some(dir.join("words.txt")),
+            ..Config::default()
+        }));
+        editor.refresh_screen();
+        assert_eq!(terminal.annotation_at(0, 3), None);
+        editor.run_command("toggle-spell-check");
+        editor.refresh_screen();
+        assert_eq!(
+            terminal.annotation_at(0, 3),
+            Some(AnnotationType::SpellError)
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn markdown_is_highlighted_outside_code_blocks() {
+        let (mut editor, terminal) = editor_with_file(
+            "hecto-markdown.md",
+            "# Title\n```\n# code\n```\nSome *em* and `x`\n",
+        );
+        editor.apply_config(Ok(Config {
+            spell_check: false,
+            ..Config::default()
+        }));
+        editor.refresh_screen();
+        assert_eq!(terminal.annotation_at(0, 2), Some(AnnotationType::Heading));
+        assert_eq!(terminal.annotation_at(2, 0), Some(AnnotationType::Code));
+        assert_eq!(terminal.annotation_at(4, 6), Some(AnnotationType::Emphasis));
+        assert_eq!(terminal.annotation_at(4, 15), Some(AnnotationType::Code));
+
+        // the lines below a fence which is no more are drawn again, though unchanged
+        editor.run_with_events(parse_script("<Down><Del>").unwrap());
+        assert_eq!(terminal.annotation_at(2, 0), Some(AnnotationType::Heading));
+        assert_eq!(terminal.annotation_at(4, 6), Some(AnnotationType::Code));
+
+        editor.run_with_events(parse_script("<C-f>Title").unwrap());
+        assert_eq!(
+            terminal.annotation_at(0, 2),
+            Some(AnnotationType::SelectedMatch)
+        );
+    }
+
+    #[test]
+    fn sessions_reopen_buffers_where_they_were_left() {
+        let text = (1..=20).map(|idx| idx.to_string()).collect::<Vec<_>>();
+        let long = env::temp_dir().join("hecto-session-long.txt");
+        fs::write(&long, text.join("\n")).unwrap();
+        let short = env::temp_dir().join("hecto-session-short.txt");
+        fs::write(&short, "ab\ncd").unwrap();
+        let path = env::temp_dir().join("hecto-session-restore.toml");
+        let buffer = |path: &Path, line_idx, grapheme_idx, scroll_row| BufferState {
+            path: path.to_path_buf(),
+            line_idx,
+            grapheme_idx,
+            scroll_row,
+            scroll_col: 0,
+        };
+        Session {
+            version: session::VERSION,
+            active: 2,
+            buffers: vec![
+                buffer(&short, 9, 9, 5),
+                buffer(&env::temp_dir().join("hecto-session-gone.txt"), 0, 0, 0),
+                buffer(&long, 12, 1, 10),
+            ],
+        }
+        .save(&path)
+        .unwrap();
+
+        let terminal = MockTerminal::new(Size {
+            height: 10,
+            width: 40,
+        });
+        let mut editor = test_editor(&terminal);
+        editor.restore_session(path.clone());
+        editor.refresh_screen();
+        assert_eq!(editor.views.len(), 2);
+        assert_eq!(terminal.row(0), "11");
+        assert_eq!(terminal.caret().row, 2);
+        assert_eq!(terminal.caret().col, 1);
+        assert!(terminal.row(9).starts_with("Skipped files which no longer"));
+
+        // positions past the end of the file are clamped to its last line
+        editor.activate_buffer(0);
+        assert_eq!(
+            editor.view().caret_location(),
+            Location {
+                line_idx: 1,
+                grapheme_idx: 2
+            }
+        );
+
+        editor.run_with_events(parse_script("<Up><C-t>").unwrap());
+        drop(editor);
+        let saved = Session::load(&path).unwrap().unwrap();
+        assert_eq!(saved.active, 0);
+        assert_eq!(
+            saved.buffers,
+            [buffer(&short, 0, 2, 0), buffer(&long, 12, 1, 10)]
+        );
+    }
+
+    #[test]
+    fn sessions_are_restored_when_the_first_file_cant_be_read() {
+        let file = env::temp_dir().join("hecto-session-after-error.txt");
+        fs::write(&file, "restored\n").unwrap();
+        let path = env::temp_dir().join("hecto-session-after-error.toml");
+        Session {
+            version: session::VERSION,
+            active: 0,
+            buffers: vec![BufferState {
+                path: file.clone(),
+                line_idx: 0,
+                grapheme_idx: 0,
+                scroll_row: 0,
+                scroll_col: 0,
+            }],
+        }
+        .save(&path)
+        .unwrap();
+
+        let terminal = MockTerminal::new(Size {
+            height: 10,
+            width: 80,
+        });
+        let mut editor = test_editor(&terminal);
+        // a directory can't be read as a file
+        let unreadable = [env::temp_dir().to_string_lossy().to_string()];
+        editor.open_startup_files(&unreadable, None, Some(path.clone()));
+        editor.refresh_screen();
+        assert_eq!(editor.session, Some(path));
+        assert_eq!(terminal.row(0), "restored");
+        assert!(terminal.row(8).starts_with("hecto-session-after-error.txt"));
+        assert!(
+            terminal
+                .row(9)
+                .starts_with(&format!("Error reading '{}'", unreadable[0]))
+        );
+    }
+
+    #[test]
+    fn scroll_off_keeps_lines_below_the_caret() {
+        let text = (1..=20).map(|idx| idx.to_string()).collect::<Vec<_>>();
+        let text = text.join("\n");
+        let (mut editor, terminal) = editor_with_file("hecto-scroll-off.txt", &text);
+        editor.apply_config(Ok(Config {
+            scroll_off: 2,
+            ..Config::default()
+        }));
+        editor.run_with_events(parse_script("<Down><Down><Down><Down><Down><Down>").unwrap());
+        assert_eq!(terminal.row(0), "2");
+        assert_eq!(terminal.caret().row, 5);
+    }
+
+    #[test]
+    fn broken_config_falls_back_to_defaults() {
+        let (mut editor, terminal) = editor_with_file("hecto-bad-config.txt", "hello\n");
+        editor.apply_config(Ok(Config {
+            quit_confirmation: false,
+            ..Config::default()
+        }));
+        assert!(!editor.apply_config(Err(String::from("line 1: unknown field `x`"))));
+        editor.refresh_screen();
+        assert_eq!(terminal.row(9), "line 1: unknown field `x`");
+        assert_eq!(editor.config, Config::default());
+    }
+
+    #[test]
+    fn esc_while_naming_a_buffer_goes_back_to_quitting() {
+        let path = env::temp_dir().join("hecto-quit-chain.txt");
+        let _ = fs::remove_file(&path);
+        let terminal = MockTerminal::new(Size {
+            height: 10,
+            width: 40,
+        });
+        let mut editor = test_editor(&terminal);
+        editor.run_with_events(parse_script("x<C-t>y").unwrap());
+        assert!(editor.prompt_type == PromptType::Save);
+
+        editor.run_with_events(parse_script("<Esc>").unwrap());
+        assert!(editor.prompt_type == PromptType::ConfirmQuit);
+        assert!(
+            terminal
+                .row(9)
+                .starts_with("[No Name] has unsaved changes.")
+        );
+        editor.run_with_events(parse_script("<Esc>").unwrap());
+        assert!(editor.no_prompt());
+        assert_eq!(terminal.row(9), "Quit aborted");
+        assert!(!editor.should_quit);
+
+        // the same once a name was typed, the save then continues with the quit
+        let script = format!("<C-t>y{}<Enter>", path.display());
+        editor.run_with_events(parse_script(&script).unwrap());
+        assert!(editor.should_quit);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "x\n");
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn saves_all_buffers_asking_names_for_unnamed_ones() {
+        let dir = env::temp_dir();
+        let named = dir.join("hecto-save-all-named.txt");
+        let unnamed = dir.join("hecto-save-all-unnamed.txt");
+        fs::write(&named, "a\n").unwrap();
+        let _ = fs::remove_file(&unnamed);
+        let terminal = MockTerminal::new(Size {
+            height: 10,
+            width: 40,
+        });
+        let mut editor = test_editor(&terminal);
+        editor.run_with_events(parse_script("x").unwrap());
+        editor.open(&named.to_string_lossy());
+        editor.run_with_events(parse_script("y<C-t>").unwrap());
+        assert!(
+            terminal
+                .row(9)
+                .starts_with("2 buffers have unsaved changes: [No")
+        );
+        editor.run_with_events(parse_script("c").unwrap());
+
+        editor.run_command("save-all");
+        assert!(editor.prompt_type == PromptType::Save);
+        let script = format!("{}<Enter>", unnamed.display());
+        editor.run_with_events(parse_script(&script).unwrap());
+        assert_eq!(terminal.row(9), "Saved 2 files");
+        assert_eq!(fs::read_to_string(&unnamed).unwrap(), "x\n");
+        assert_eq!(fs::read_to_string(&named).unwrap(), "ya\n");
+        assert!(editor.modified_buffer_names().is_empty());
+        fs::remove_file(&unnamed).unwrap();
+    }
+
+    #[test]
+    fn confirmations_take_a_single_key() {
+        let (mut editor, terminal) = editor_with_file("hecto-confirm-key.txt", "hello\n");
+        editor.run_with_events(parse_script("x<C-r>").unwrap());
+        assert!(terminal.row(9).starts_with("File has unsaved changes."));
+        // neither other keys nor the choices with a modifier answer it
+        editor.run_with_events(parse_script("q<A-y><Enter>").unwrap());
+        assert!(editor.prompt_type == PromptType::Confirm);
+        assert_eq!(terminal.row(0), "xhello");
+        editor.run_with_events(parse_script("Y").unwrap());
+        assert!(editor.no_prompt());
+        assert_eq!(terminal.row(0), "hello");
+        assert_eq!(terminal.row(9), "File reloaded");
+    }
+
+    #[test]
+    fn saving_creates_missing_directories() {
+        let root = env::temp_dir().join("hecto-missing-dirs");
+        let _ = fs::remove_dir_all(&root);
+        let (mut editor, terminal) = editor_with_file("hecto-create-dirs.txt", "hello\n");
+        let save_as = |editor: &mut Editor, path: &Path| {
+            editor.set_prompt(PromptType::Save);
+            let script = format!("{}<Enter>", path.display());
+            editor.run_with_events(parse_script(&script).unwrap());
+        };
+        let path = root.join("notes/2024/todo.txt");
+        editor.run_with_events(parse_script("x").unwrap());
+        save_as(&mut editor, &path);
+        assert!(editor.prompt_type == PromptType::Confirm);
+        assert!(terminal.row(9).starts_with("Directory "));
+        editor.run_with_events(parse_script("n").unwrap());
+        assert_eq!(terminal.row(9), "Save aborted");
+        assert!(!root.exists());
+
+        save_as(&mut editor, &path);
+        editor.run_with_events(parse_script("y").unwrap());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "xhello\n");
+
+        // nothing can go below a file
+        editor.run_with_events(parse_script("y").unwrap());
+        save_as(&mut editor, &path.join("todo.txt"));
+        assert!(editor.no_prompt());
+        assert!(terminal.row(9).starts_with("Can't save, "));
+        assert!(editor.view().get_status().is_modified);
+
+        editor.apply_config(Ok(Config {
+            create_directories: true,
+            ..Config::default()
+        }));
+        let path = root.join("a/b.txt");
+        save_as(&mut editor, &path);
+        assert!(editor.no_prompt());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "xyhello\n");
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn opens_every_file_given_reading_them_once_shown() {
+        let dir = env::temp_dir();
+        let files = ["hecto-args-a.txt", "hecto-args-b.txt"].map(|name| {
+            let path = dir.join(name);
+            fs::write(&path, format!("{name}\n")).unwrap();
+            path.to_string_lossy().to_string()
+        });
+        let missing = dir.join("hecto-args-missing").join("c.txt");
+        let filenames = [
+            files[0].clone(),
+            String::from("-"),
+            missing.to_string_lossy().to_string(),
+            files[1].clone(),
+        ];
+        let terminal = MockTerminal::new(Size {
+            height: 10,
+            width: 40,
+        });
+        let mut editor = test_editor(&terminal);
+        assert!(editor.open_files(&filenames, Some("piped\n")).is_ok());
+        editor.refresh_screen();
+        assert_eq!(terminal.row(0), "hecto-args-a.txt");
+        assert_eq!(terminal.row(9), "4 files to edit");
+        assert_eq!(editor.views.len(), 4);
+        assert!(editor.views[3].is_unread());
+        assert!(editor.views[3].is_same_file(&files[1]));
+
+        editor.run_command("next-buffer");
+        editor.refresh_screen();
+        assert_eq!(terminal.row(0), "piped");
+        assert!(!editor.view().get_status().is_modified);
+        // a file which can't be read is dropped once it is switched to
+        fs::write(&files[1], [0xff]).unwrap();
+        editor.run_command("next-buffer");
+        editor.refresh_screen();
+        assert!(terminal.row(0).is_empty());
+        assert!(editor.view().get_status().is_new_file);
+        editor.run_command("next-buffer");
+        editor.refresh_screen();
+        assert!(terminal.row(9).contains("is not UTF-8"));
+        editor.run_with_events(parse_script("<Esc>").unwrap());
+        assert!(terminal.row(9).starts_with("Error reading"));
+        assert_eq!(editor.views.len(), 3);
+    }
+
+    #[test]
+    fn read_only_buffers_are_only_saved_under_another_name() {
+        let (mut editor, terminal) = editor_with_file("hecto-read-only.txt", "hello\n");
+        let copy = env::temp_dir().join("hecto-read-only-copy.txt");
+        let _ = fs::remove_file(&copy);
+        editor.set_read_only();
+        editor.run_with_events(parse_script("x").unwrap());
+        assert!(terminal.row(8).starts_with("hecto-read-only.txt [+] [RO]"));
+
+        editor.run_with_events(parse_script("<C-s>").unwrap());
+        assert!(editor.prompt_type == PromptType::Save);
+        let own = env::temp_dir().join("hecto-read-only.txt");
+        let script = format!("{}<Enter>", own.display());
+        editor.run_with_events(parse_script(&script).unwrap());
+        assert!(terminal.row(9).starts_with("Error writing"));
+        assert_eq!(fs::read_to_string(&own).unwrap(), "hello\n");
+
+        editor.set_prompt(PromptType::Save);
+        let script = format!("{}<Enter>", copy.display());
+        editor.run_with_events(parse_script(&script).unwrap());
+        assert_eq!(fs::read_to_string(&copy).unwrap(), "xhello\n");
+        assert!(terminal.row(8).starts_with("hecto-read-only-copy.txt"));
+        assert!(!terminal.row(8).contains("[RO]"));
+        fs::remove_file(&copy).unwrap();
+    }
+
+    #[test]
+    fn quits_without_asking_if_configured() {
+        let (mut editor, _terminal) = editor_with_file("hecto-no-confirm.txt", "hello\n");
+        editor.apply_config(Ok(Config {
+            quit_confirmation: false,
+            ..Config::default()
+        }));
+        editor.run_with_events(parse_script("x<C-t>").unwrap());
+        assert!(editor.should_quit);
+    }
+
+    #[test]
+    fn macros_replay_until_a_search_fails() {
+        let (mut editor, terminal) = editor_with_file("hecto-macro.txt", "a-x\nb-x\nc\n");
+        editor.run_with_events(parse_script("<A-m><C-f>x<Enter><Del><A-m>").unwrap());
+        assert_eq!(terminal.row(9), "Recorded a macro of 4 keys");
+        assert_eq!(terminal.row(0), "a-");
+
+        editor.run_with_events(parse_script("<A-p>").unwrap());
+        assert_eq!(terminal.row(1), "b-");
+        editor.run_with_events(parse_script("<A-p>").unwrap());
+        assert_eq!(terminal.row(2), "c");
+        assert_eq!(terminal.row(9), "Macro stopped early, nothing was found");
+    }
+
+    #[test]
+    fn counts_repeat_the_next_move_or_edit() {
+        let (mut editor, terminal) = editor_with_file("hecto-count.txt", "a\nb\nc\nd\n");
+        editor.run_with_events(parse_script("<A-1><A-2>").unwrap());
+        assert!(terminal.row(8).ends_with("12 | Ln 1, Col 1"));
+        editor.run_with_events(parse_script("<Esc><Down>").unwrap());
+        assert_eq!(terminal.caret().row, 1);
+        assert!(terminal.row(8).ends_with(" Ln 2, Col 1"));
+
+        editor.run_with_events(parse_script("<A-3>x<A-2><Down>").unwrap());
+        assert_eq!(terminal.row(1), "xxxb");
+        assert_eq!(terminal.caret().row, 3);
+
+        // far more than there is to delete
+        editor.run_with_events(
+            parse_script("<Up><Up><Up><Home><A-9><A-9><A-9><A-9><A-9><Del>").unwrap(),
+        );
+        assert_eq!(editor.pending_count, None);
+        assert_eq!(terminal.row(0), "");
+        assert_eq!(terminal.row(1), "~");
+    }
+
+    #[test]
+    fn modal_editing() {
+        let (mut editor, terminal) =
+            editor_with_file("hecto-vi.txt", "one two\nthree\nfour\nfive\n");
+        editor.apply_config(Ok(Config {
+            modal: true,
+            ..Config::default()
+        }));
+        editor.run_with_events(parse_script("wx2").unwrap());
+        assert_eq!(terminal.row(0), "one wo");
+        assert!(terminal.row(8).ends_with("NORMAL 2 | Ln 1, Col 5"));
+
+        // the count moves down two lines
+        editor.run_with_events(parse_script("jddGp").unwrap());
+        assert_eq!(terminal.row(2), "five");
+        assert_eq!(terminal.row(3), "four");
+        assert_eq!(terminal.caret().row, 3);
+
+        editor.run_with_events(parse_script("ggOnew<Esc>ahi<Esc>").unwrap());
+        assert_eq!(terminal.row(0), "newhi");
+        assert!(terminal.row(8).ends_with("NORMAL | Ln 1, Col 6"));
+        editor.run_with_events(parse_script("3G$ax").unwrap());
+        assert_eq!(terminal.row(2), "threex");
+        assert!(terminal.row(8).ends_with("INSERT | Ln 3, Col 7"));
+    }
+
+    #[test]
+    fn chords_wait_for_their_second_key() {
+        let (mut editor, terminal) = editor_with_file("hecto-chord.txt", "a\n");
+        let path = env::temp_dir().join("hecto-chord.txt");
+        editor.run_with_events(parse_script("x<C-k>").unwrap());
+        assert_eq!(terminal.row(9), "C-k -");
+        editor.run_with_events(parse_script("s").unwrap());
+        assert_eq!(terminal.row(9), "Saved 1 file");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "xa\n");
+
+        editor.run_with_events(parse_script("<C-k>q").unwrap());
+        assert_eq!(terminal.row(9), "C-k q is not bound");
+        editor.run_with_events(parse_script("<C-k><Esc>s").unwrap());
+        assert_eq!(terminal.row(0), "xsa");
+
+        editor.run_with_events(parse_script("<C-k>").unwrap());
+        editor.chord_started = Instant::now().checked_sub(CHORD_TIMEOUT).unwrap();
+        editor.expire_chord();
+        editor.refresh_screen();
+        assert_eq!(terminal.row(9), "C-k is not bound");
+        assert!(editor.pending_chord.is_empty());
+
+        let other = env::temp_dir().join("hecto-chord-other.txt");
+        fs::write(&other, "b\n").unwrap();
+        editor.open(&other.to_string_lossy());
+        editor.run_with_events(parse_script("<C-k>w").unwrap());
+        assert_eq!(terminal.row(9), "Closed hecto-chord-other.txt");
+        assert_eq!(editor.views.len(), 1);
+        assert_eq!(terminal.row(0), "xsa");
+    }
+
+    #[test]
+    fn closing_a_buffer_goes_back_to_the_one_shown_before() {
+        let (mut editor, terminal) = editor_with_file("hecto-close-a.txt", "a\n");
+        let [a, b, c] = ["a", "b", "c"].map(|name| {
+            let path = env::temp_dir().join(format!("hecto-close-{name}.txt"));
+            fs::write(&path, format!("{name}\n")).unwrap();
+            path.to_string_lossy().to_string()
+        });
+        editor.open(&b);
+        editor.open(&c);
+        editor.open(&a);
+        editor.run_with_events(parse_script("<C-k>w").unwrap());
+        assert_eq!(terminal.row(9), "Closed hecto-close-a.txt");
+        assert_eq!(terminal.row(0), "c");
+        assert!(terminal.row(8).contains("[2/2]"));
+        assert!(terminal.title().ends_with("hecto-close-c.txt - hecto"));
+
+        // unsaved changes are only thrown away when asked to
+        editor.run_with_events(parse_script("x<C-k>w").unwrap());
+        assert!(
+            terminal
+                .row(9)
+                .starts_with("File has unsaved changes. Close anyway?")
+        );
+        editor.run_with_events(parse_script("n").unwrap());
+        assert_eq!(terminal.row(9), "Close aborted");
+        assert_eq!(terminal.row(0), "xc");
+
+        // a prompt may still need the buffer
+        editor.set_prompt(PromptType::Search);
+        editor.close_buffer();
+        assert_eq!(editor.views.len(), 2);
+        editor.run_with_events(parse_script("<Esc><C-k>wy").unwrap());
+        assert_eq!(terminal.row(9), "Closed hecto-close-c.txt");
+        assert_eq!(terminal.row(0), "b");
+        assert!(!terminal.row(8).contains("[1/1]"));
+
+        // the last one leaves an empty buffer behind
+        editor.run_with_events(parse_script("<C-k>w").unwrap());
+        assert_eq!(terminal.row(9), "Closed hecto-close-b.txt");
+        assert_eq!(editor.views.len(), 1);
+        assert!(terminal.row(0).is_empty());
+        assert_eq!(terminal.title(), "[No Name] - hecto");
+        assert!(!editor.should_quit);
+    }
+
+    #[test]
+    fn picks_and_closes_buffers_from_a_list() {
+        let (mut editor, terminal) = editor_with_file("hecto-pick-alpha.txt", "alpha\n");
+        for name in ["beta", "gamma", "delta"] {
+            let path = env::temp_dir().join(format!("hecto-pick-{name}.txt"));
+            fs::write(&path, format!("{name}\n")).unwrap();
+            editor.open(&path.to_string_lossy());
+        }
+        // the list scrolls to the focused buffer
+        editor.run_with_events(parse_script("x<C-k>b").unwrap());
+        assert!(terminal.is_inverted(5));
+        assert_eq!(terminal.row(5), "4 buffers");
+        assert_eq!(terminal.row(6), " 3 hecto-pick-gamma.txt  1 line");
+        assert_eq!(terminal.row(7), ">4 hecto-pick-delta.txt [+]  1 line");
+        assert_eq!(terminal.row(9), "Buffer:  [4/4] hecto-pick-delta.txt");
+        editor.run_with_events(parse_script("<Down>").unwrap());
+        assert_eq!(terminal.row(6), ">1 hecto-pick-alpha.txt  1 line");
+        editor.run_with_events(parse_script("<Up>").unwrap());
+
+        editor.run_with_events(parse_script("d").unwrap());
+        assert!(terminal.row(9).ends_with("unsaved changes, d again closes"));
+        editor.run_with_events(parse_script("d").unwrap());
+        assert_eq!(terminal.row(9), "Buffer:  Closed hecto-pick-delta.txt");
+        assert_eq!(terminal.row(5), "3 buffers");
+        assert_eq!(terminal.row(7), ">3 hecto-pick-gamma.txt  1 line");
+
+        // once something is typed, d narrows the list down as well
+        editor.run_with_events(parse_script("alp").unwrap());
+        assert_eq!(terminal.row(5), "1 of 3 buffers for 'alp'");
+        assert_eq!(terminal.row(6), ">1 hecto-pick-alpha.txt  1 line");
+        editor.run_with_events(parse_script("d").unwrap());
+        assert_eq!(terminal.row(9), "Buffer: alpd no match");
+        assert_eq!(editor.views.len(), 3);
+        editor.run_with_events(parse_script("<BS><Enter>").unwrap());
+        assert_eq!(terminal.row(0), "alpha");
+        assert_eq!(terminal.row(5), "~");
+        assert!(terminal.row(8).contains("[1/3]"));
+
+        editor.run_with_events(parse_script("<C-k>b<Esc>").unwrap());
+        assert_eq!(terminal.row(5), "~");
+        assert_eq!(terminal.row(0), "alpha");
+    }
+
+    #[test]
+    fn modified_enter_opens_lines_without_splitting() {
+        let (mut editor, terminal) = editor_with_file("hecto-open-line.txt", "ab\n");
+        editor.run_with_events(parse_script("<Right><C-Enter>c<S-Enter>d").unwrap());
+        assert_eq!(terminal.row(0), "ab");
+        assert_eq!(terminal.row(1), "d");
+        assert_eq!(terminal.row(2), "c");
+    }
+
+    #[test]
+    fn inserts_characters_by_code_point() {
+        let (mut editor, terminal) = editor_with_file("hecto-unicode.txt", "ex\n");
+        editor.run_with_events(parse_script("<Right><C-k>uU+301<Enter>").unwrap());
+        assert_eq!(terminal.row(0), "e\u{301}x");
+        // the accent joined the e, so the caret is still before the x
+        assert_eq!((terminal.caret().row, terminal.caret().col), (0, 1));
+        assert_eq!(editor.view().get_status().grapheme_col, 1);
+
+        editor.run_with_events(parse_script("<C-k>uemdash<Enter>").unwrap());
+        assert_eq!(terminal.row(0), "e\u{301}—x");
+        assert_eq!((terminal.caret().row, terminal.caret().col), (0, 2));
+
+        editor.run_with_events(parse_script("<C-k>uD800<Enter>").unwrap());
+        assert_eq!(terminal.row(9), "A surrogate is no character: U+D800");
+        assert_eq!(terminal.row(0), "e\u{301}—x");
+    }
+
+    #[test]
+    fn inspects_the_bytes_of_the_caret_line() {
+        let (mut editor, terminal) = editor_with_file("hecto-bytes.txt", "a\n\u{feff}é\tb\n");
+        editor.run_with_events(parse_script("<Down><Right><Right><C-k>x").unwrap());
+        assert!(terminal.is_inverted(5));
+        assert!(terminal.row(5).starts_with("line 2, 7 bytes"));
+        assert_eq!(terminal.row(6), "00000000  ef bb bf c3 a9 09 62");
+        assert!(matches!(
+            terminal.annotation_at(6, 25),
+            Some(AnnotationType::SelectedMatch)
+        ));
+        assert_eq!(terminal.row(9), "Byte:  0x09 9");
+        assert!(terminal.row(8).contains("BYTE 5"));
+
+        editor.run_with_events(parse_script("<Left>x").unwrap());
+        assert_eq!(terminal.row(9), "Byte:  read-only, Esc to go back");
+        assert!(terminal.row(8).contains("BYTE 4"));
+        editor.run_with_events(parse_script("<Left><Esc>").unwrap());
+        assert_eq!(terminal.row(1), "·é      b");
+        assert_eq!(editor.view().caret_location().grapheme_idx, 1);
+        assert!(!terminal.row(8).contains("BYTE"));
+
+        editor.run_with_events(parse_script("<C-k>x<Home><Enter>").unwrap());
+        assert_eq!(editor.view().caret_location().grapheme_idx, 0);
+        assert_eq!(editor.view().lines(), ["a", "\u{feff}é\tb"]);
+    }
+
+    #[test]
+    fn searches_files_and_opens_their_matches() {
+        let (mut editor, terminal) = editor_with_file("hecto-search-files.txt", "find\n");
+        let root = env::temp_dir().join("hecto-search-files");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::write(root.join("a.txt"), "x\n  find me\n").unwrap();
+        fs::write(root.join("sub").join("b.txt"), "find me too\n").unwrap();
+        editor.files_root.clone_from(&root);
+
+        editor.run_with_events(parse_script("<C-k>ffind<Enter>").unwrap());
+        assert!(terminal.is_inverted(5));
+        assert_eq!(terminal.row(5), "2 matches for 'find' in 2 files");
+        assert_eq!(terminal.row(6), ">a.txt:2:3       find me");
+        assert_eq!(terminal.row(7), " sub/b.txt:1:1 find me too");
+        assert_eq!(terminal.row(9), "Jump to:  [1/2] a.txt:2:3");
+
+        editor.run_with_events(parse_script("<Enter>").unwrap());
+        assert_eq!(terminal.row(1), "  find me");
+        assert_eq!(terminal.caret().row, 1);
+        assert_eq!(terminal.caret().col, 2);
+        editor.run_with_events(parse_script("<C-k>ffind<Enter><Down><Enter>").unwrap());
+        assert_eq!(terminal.row(0), "find me too");
+        assert!(terminal.row(8).contains("[3/3]"));
+        // a file already open is switched to
+        editor.run_with_events(parse_script("<C-k>ffind<Enter><Enter>").unwrap());
+        assert_eq!(terminal.row(9), "Switched to already open buffer");
+        assert_eq!(terminal.caret().row, 1);
+        assert_eq!(editor.views.len(), 3);
+
+        editor.run_with_events(parse_script("<C-k>fnowhere<Enter>").unwrap());
+        assert_eq!(terminal.row(5), "0 matches for 'nowhere' in 0 files");
+        editor.run_with_events(parse_script("<Esc>").unwrap());
+        assert_eq!(terminal.row(5), "~");
+        assert!(editor.file_search.is_none());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn lists_the_changes_since_saving() {
+        let (mut editor, terminal) = editor_with_file("hecto-diff.txt", "a\nb\nc\n");
+        editor.run_with_events(parse_script("<C-k>d").unwrap());
+        assert_eq!(terminal.row(9), "No changes since the file was saved");
+
+        editor.run_with_events(parse_script("x<C-k>d").unwrap());
+        assert!(terminal.is_inverted(5));
+        assert!(
+            terminal
+                .row(5)
+                .starts_with("1 hunk, +1 -1 lines since saved")
+        );
+        assert_eq!(terminal.row(6), " @@ -1,3 +1,3 @@");
+        assert_eq!(terminal.row(7), ">-a");
+        assert!(matches!(
+            terminal.annotation_at(7, 1),
+            Some(AnnotationType::Deleted)
+        ));
+        editor.run_with_events(parse_script("<Down>").unwrap());
+        assert_eq!(terminal.row(7), ">+xa");
+        assert_eq!(terminal.row(9), "Diff:  [hunk 1/1] line 1");
+
+        // the jump can be walked back
+        editor.run_with_events(parse_script("<Enter>").unwrap());
+        assert_eq!(terminal.row(5), "~");
+        assert_eq!(terminal.caret().col, 0);
+        editor.run_with_events(parse_script("<A-,>").unwrap());
+        assert_eq!(terminal.caret().col, 1);
+    }
+
+    #[test]
+    fn describes_the_character_under_the_caret() {
+        let (mut editor, terminal) = editor_with_file("hecto-describe.txt", "\u{e9}\n");
+        editor.run_with_events(parse_script("<C-k>i").unwrap());
+        assert_eq!(terminal.row(9), "\"\u{e9}\" U+00E9, 2 bytes, width 1");
+        editor.run_with_events(parse_script("<End><C-k>i").unwrap());
+        assert_eq!(terminal.row(9), "End of line");
+
+        let (mut editor, terminal) = editor_with_file("hecto-describe-empty.txt", "");
+        editor.run_with_events(parse_script("<C-k>i").unwrap());
+        assert_eq!(terminal.row(9), "Empty buffer");
+    }
+
+    #[test]
+    fn places_the_caret_line_in_the_view() {
+        let contents = (1..=20).fold(String::new(), |contents, line| {
+            format!("{contents}{line}\n")
+        });
+        let (mut editor, terminal) = editor_with_file("hecto-percent.txt", &contents);
+        editor.run_with_events(parse_script("<A-x>go-to-line<Enter>50%<Enter>").unwrap());
+        // line 10 in the middle of the 8 rows of text
+        assert_eq!(terminal.row(0), "7");
+        assert_eq!((terminal.caret().row, terminal.caret().col), (3, 0));
+
+        editor.run_with_events(parse_script("<C-k><Up>").unwrap());
+        assert_eq!(terminal.row(0), "10");
+        editor.run_with_events(parse_script("<C-k><Down>").unwrap());
+        assert_eq!(terminal.row(0), "3");
+        assert_eq!((terminal.caret().row, terminal.caret().col), (7, 0));
+
+        editor.run_with_events(parse_script("<A-x>go-to-line<Enter>101%<Enter>").unwrap());
+        assert_eq!(terminal.row(9), "Not a percentage: 101%");
+    }
+
+    #[test]
+    fn shows_the_character_and_the_drawn_column() {
+        let (mut editor, terminal) = editor_with_file("hecto-columns.txt", "\tab\n老虎x\nplain\n");
+        let column = |editor: &Editor| {
+            let status = editor.view().get_status();
+            (status.grapheme_col, status.visual_col)
+        };
+        // the column after a colon counts characters, not the columns they take
+        editor.run_with_events(parse_script("<A-x>go-to-line<Enter>1:2<Enter>").unwrap());
+        assert_eq!(column(&editor), (1, 8));
+        assert!(terminal.row(8).ends_with("Ln 1, Col 2-9"));
+        editor.run_with_events(parse_script("<A-x>go-to-line<Enter>2:3<Enter>").unwrap());
+        assert_eq!(column(&editor), (2, 4));
+        assert!(terminal.row(8).ends_with("Ln 2, Col 3-5"));
+        assert_eq!(terminal.caret().col, 4);
+        // past the end of the line goes to its end
+        editor.run_with_events(parse_script("<A-x>go-to-line<Enter>3:40<Enter>").unwrap());
+        assert_eq!(column(&editor), (5, 5));
+        assert!(terminal.row(8).ends_with("Ln 3, Col 6"));
+
+        editor.run_with_events(parse_script("<A-x>go-to-line<Enter>3:0<Enter>").unwrap());
+        assert_eq!(terminal.row(9), "Not a line number: 3:0");
+    }
+
+    #[test]
+    fn centering_stops_at_the_start_of_short_buffers() {
+        let (mut editor, terminal) = editor_with_file("hecto-center.txt", "a\nb\nc\n");
+        editor.run_with_events(parse_script("<Down><C-k>z").unwrap());
+        assert_eq!(terminal.row(0), "a");
+        assert_eq!((terminal.caret().row, terminal.caret().col), (1, 0));
+        editor.run_with_events(parse_script("<C-k><Up>").unwrap());
+        assert_eq!(terminal.row(0), "b");
+        assert_eq!(terminal.row(2), "~");
+        editor.run_with_events(parse_script("<C-k><Down>").unwrap());
+        assert_eq!(terminal.row(0), "a");
+    }
+
+    #[test]
+    fn lines_open_at_the_edges_of_the_buffer() {
+        let (mut editor, terminal) = editor_with_file("hecto-open-empty.txt", "");
+        editor.run_with_events(parse_script("<C-Enter>b<C-S-Enter>a").unwrap());
+        assert_eq!(terminal.row(0), "");
+        assert_eq!(terminal.row(1), "a");
+        assert_eq!(terminal.row(2), "b");
+        assert_eq!((terminal.caret().row, terminal.caret().col), (1, 1));
+
+        editor.run_with_events(parse_script("<C-End><C-Enter>c").unwrap());
+        assert_eq!(terminal.row(3), "c");
+        assert_eq!(terminal.row(4), "~");
+        assert!(editor.view().get_status().is_modified);
+    }
+
+    #[test]
+    fn palette_runs_commands_that_prompt() {
+        let (mut editor, terminal) = editor_with_file("hecto-palette.txt", "a\nb\nc\nd\n");
+        editor.run_with_events(parse_script("<A-x>go").unwrap());
+        assert!(
+            terminal
+                .row(9)
+                .starts_with("Command: go [1/1] go-to-line: Jump")
+        );
+
+        editor.run_with_events(parse_script("<Enter>3<Enter>").unwrap());
+        assert!(editor.no_prompt());
+        assert_eq!(terminal.caret().row, 2);
+
+        editor.run_with_events(parse_script("<A-x>s<Down><Down><Up><Enter>").unwrap());
+        assert!(editor.in_prompt());
+        assert_eq!(terminal.row(9), "Search:");
+        editor.run_with_events(parse_script("<Esc><A-x>sp<Enter>").unwrap());
+        assert!(editor.split.is_some());
+    }
+
+    #[test]
+    fn clicks_on_the_status_bar_run_its_segments() {
+        let (mut editor, terminal) = editor_with_file("hecto-click.txt", "a\nb\nc\n");
+        editor.apply_config(Ok(Config {
+            mouse: true,
+            status_line: Some(String::from("%f%( %m%)%=%y | Ln %l, Col %c")),
+            ..Config::default()
+        }));
+        assert!(terminal.captures_mouse());
+        let click = |editor: &mut Editor, column: usize| {
+            editor.process_event(Event::Mouse(MouseEvent {
+                kind: MouseEventKind::Down(MouseButton::Left),
+                column: u16::try_from(column).unwrap(),
+                row: 8,
+                modifiers: KeyModifiers::NONE,
+            }));
+            editor.refresh_screen();
+        };
+
+        editor.run_with_events(parse_script("x").unwrap());
+        let status = terminal.row(8);
+        click(&mut editor, status.find("[+]").unwrap().saturating_add(1));
+        assert!(!editor.view().get_status().is_modified);
+
+        // the padding between both sides stands for nothing
+        click(
+            &mut editor,
+            terminal.row(8).find("txt").unwrap().saturating_add(5),
+        );
+        assert!(editor.no_prompt());
+
+        click(
+            &mut editor,
+            terminal.row(8).find("Ln 1").unwrap().saturating_add(3),
+        );
+        assert_eq!(terminal.row(9), "Go to line:");
+        editor.run_with_events(parse_script("3<Enter>").unwrap());
+        assert_eq!(terminal.caret().row, 2);
+
+        click(&mut editor, terminal.row(8).find("text").unwrap());
+        editor.run_with_events(parse_script("ru<Tab><Enter>").unwrap());
+        assert!(terminal.row(8).contains("rust | Ln 3"));
+
+        // not while a prompt is open
+        editor.run_with_events(parse_script("<C-f>").unwrap());
+        click(
+            &mut editor,
+            terminal.row(8).find("Ln 3").unwrap().saturating_add(3),
+        );
+        assert_eq!(terminal.row(9), "Search:");
+    }
+
+    #[test]
+    fn the_scrollbar_shows_and_moves_through_long_buffers() {
+        let contents = (1..=20).fold(String::new(), |contents, line| {
+            format!("{contents}l{line}\n")
+        });
+        let (mut editor, terminal) = editor_with_file("hecto-scrollbar.txt", &contents);
+        editor.apply_config(Ok(Config {
+            mouse: true,
+            ..Config::default()
+        }));
+        assert_eq!(terminal.row(0), "l1");
+        editor.run_command("toggle-scrollbar");
+        editor.refresh_screen();
+        // eight of twenty lines in view make a thumb of three rows
+        let bar = |row: usize| terminal.row(row).chars().nth(39);
+        assert_eq!(terminal.row(0), format!("l1{:37}█", ""));
+        assert_eq!(bar(2), Some('█'));
+        assert_eq!(bar(3), Some('│'));
+        assert_eq!(bar(7), Some('│'));
+
+        let press = |editor: &mut Editor, kind: MouseEventKind, row: u16| {
+            editor.process_event(Event::Mouse(MouseEvent {
+                kind,
+                column: 39,
+                row,
+                modifiers: KeyModifiers::NONE,
+            }));
+            editor.refresh_screen();
+        };
+        press(&mut editor, MouseEventKind::Down(MouseButton::Left), 7);
+        assert!(terminal.row(8).contains("Ln 20"));
+        assert_eq!(terminal.row(7), format!("l20{:36}█", ""));
+        assert_eq!(bar(4), Some('│'));
+        assert_eq!(bar(5), Some('█'));
+        press(&mut editor, MouseEventKind::Drag(MouseButton::Left), 0);
+        assert!(terminal.row(8).contains("Ln 1,"));
+        assert_eq!(bar(0), Some('█'));
+
+        // not while everything fits
+        let (mut editor, terminal) = editor_with_file("hecto-scrollbar-short.txt", "a\nb\n");
+        editor.run_command("toggle-scrollbar");
+        editor.refresh_screen();
+        assert_eq!(terminal.row(0), "a");
+        assert_eq!(terminal.row(3), "~");
+    }
+
+    #[test]
+    fn nothing_is_drawn_below_the_last_line() {
+        let contents = (1..=20).fold(String::new(), |contents, line| {
+            format!("{contents}l{line}\n")
+        });
+        let (mut editor, terminal) = editor_with_file("hecto-below-last.txt", &contents);
+        // scrolling by rows stops at the last line like scrolling by lines
+        for wrap in [false, true] {
+            editor.apply_config(Ok(Config {
+                wrap,
+                scroll_off: 2,
+                ..Config::default()
+            }));
+            editor.run_with_events(parse_script("<C-Home><C-End><Down><PageDown>").unwrap());
+            assert_eq!(terminal.row(0), "l13", "wrap: {wrap}");
+            assert_eq!(terminal.row(7), "l20", "wrap: {wrap}");
+            assert!(terminal.row(8).ends_with("Ln 20, Col 4"));
+        }
+
+        // the first line of an empty buffer is where typing goes, not a tilde
+        let (mut editor, terminal) = editor_with_file("hecto-below-empty.txt", "");
+        assert_eq!(terminal.row(0), "");
+        assert_eq!(terminal.row(1), "~");
+        assert!(terminal.row(8).ends_with("Ln 1, Col 1"));
+        editor.apply_config(Ok(Config {
+            show_line_numbers: true,
+            ..Config::default()
+        }));
+        editor.refresh_screen();
+        assert_eq!(terminal.row(0), "1");
+        editor.run_with_events(parse_script("<Down>a").unwrap());
+        assert_eq!(terminal.row(0), "1▎a");
+        assert_eq!(terminal.row(1), "~");
     }
 }
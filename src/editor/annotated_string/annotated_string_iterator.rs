@@ -1,55 +1,64 @@
-use std::cmp::min;
-
 use super::{AnnotatedString, AnnotatedStringPart};
+use std::cmp::Reverse;
 
+/// The parts of an [`AnnotatedString`], each as far as the same annotation is shown. That is
+/// the one of the highest [priority](crate::AnnotationType::priority), and of those the one
+/// starting first, so the order annotations were added in doesn't matter.
 pub struct AnnotatedStringIterator<'a> {
-    pub annotated_string: &'a AnnotatedString,
-    pub current_idx: usize,
+    pub(crate) annotated_string: &'a AnnotatedString,
+    pub(crate) current_idx: usize,
 }
 
-impl<'a> Iterator for AnnotatedStringIterator<'a> {
-    type Item = AnnotatedStringPart<'a>;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.current_idx >= self.annotated_string.string.len() {
-            return None;
-        }
-
-        // Find the current active annotation
-        if let Some(annotation) = self
-            .annotated_string
+impl AnnotatedStringIterator<'_> {
+    // the index of the annotation shown at `byte_idx`
+    fn shown_at(&self, byte_idx: usize) -> Option<usize> {
+        self.annotated_string
             .annotations
             .iter()
-            .rfind(|annotation| {
-                annotation.start_byte_idx <= self.current_idx
-                    && annotation.end_byte_idx > self.current_idx
+            .enumerate()
+            .filter(|(_, annotation)| {
+                annotation.start_byte_idx <= byte_idx && byte_idx < annotation.end_byte_idx
+            })
+            .max_by_key(|(_, annotation)| {
+                (
+                    annotation.typ.priority(),
+                    Reverse(annotation.start_byte_idx),
+                    annotation.end_byte_idx,
+                )
             })
-        {
-            let end_idx = min(annotation.end_byte_idx, self.annotated_string.string.len());
-            let start_idx = self.current_idx;
-            self.current_idx = end_idx;
+            .map(|(idx, _)| idx)
+    }
 
-            return Some(AnnotatedStringPart {
-                string: &self.annotated_string.string[start_idx..end_idx],
-                typ: Some(annotation.typ),
-            });
-        }
+    // the next start or end of an annotation behind `byte_idx`
+    fn next_boundary(&self, byte_idx: usize) -> usize {
+        self.annotated_string
+            .annotations
+            .iter()
+            .flat_map(|annotation| [annotation.start_byte_idx, annotation.end_byte_idx])
+            .filter(|idx| *idx > byte_idx)
+            .fold(self.annotated_string.string.len(), usize::min)
+    }
+}
 
-        // Find the boundary of the nearest annotation
-        let mut end_idx = self.annotated_string.string.len();
+impl<'a> Iterator for AnnotatedStringIterator<'a> {
+    type Item = AnnotatedStringPart<'a>;
 
-        for annotation in &self.annotated_string.annotations {
-            if annotation.start_byte_idx > self.current_idx && annotation.start_byte_idx < end_idx {
-                end_idx = annotation.start_byte_idx;
-            }
+    fn next(&mut self) -> Option<Self::Item> {
+        let len = self.annotated_string.string.len();
+        if self.current_idx >= len {
+            return None;
         }
-
         let start_idx = self.current_idx;
+        let shown = self.shown_at(start_idx);
+        let mut end_idx = self.next_boundary(start_idx);
+        while end_idx < len && self.shown_at(end_idx) == shown {
+            end_idx = self.next_boundary(end_idx);
+        }
         self.current_idx = end_idx;
 
         Some(AnnotatedStringPart {
             string: &self.annotated_string.string[start_idx..end_idx],
-            typ: None,
+            typ: shown.map(|idx| self.annotated_string.annotations[idx].typ),
         })
     }
 }
@@ -1,7 +1,8 @@
 use super::AnnotationType;
 
+/// A part of an [`AnnotatedString`](crate::AnnotatedString) and the annotation shown on it,
+/// if any.
 #[derive(Debug)]
-
 pub struct AnnotatedStringPart<'a> {
     pub string: &'a str,
     pub typ: Option<AnnotationType>,
@@ -1,6 +1,73 @@
-#[derive(Debug, Clone, Copy)]
+/// What a part of an [`AnnotatedString`](crate::AnnotatedString) is, which decides how it is
+/// coloured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AnnotationType {
+    /// A match of the search query.
     Match,
+    /// The match the caret is on.
     SelectedMatch,
     Digit,
+    /// The number of a line, in the gutter.
+    LineNumber,
+    /// The word under the caret, wherever else it is.
+    WordMatch,
+    /// Spaces, tabs and line ends made visible.
+    Invisible,
+    /// A message in the message bar.
+    Warning,
+    Error,
+    /// A line of a diff.
+    Inserted,
+    Deleted,
+    /// The gutter marker of a line edited since saving.
+    EditedLine,
+    /// The gutter marker of a line changed since it was checked in with git, where
+    /// [`Inserted`](Self::Inserted) and [`Deleted`](Self::Deleted) mark added lines and
+    /// removed ones.
+    Changed,
+    /// The name of a mark, in the gutter next to its line.
+    Mark,
+    /// A word the spell checker doesn't know.
+    SpellError,
+    /// A heading of a Markdown file, the whole line of it.
+    Heading,
+    /// Text set in italics, like `*this*` in Markdown.
+    Emphasis,
+    /// Text set in bold, like `**this**` in Markdown.
+    Strong,
+    /// Code within text, like a Markdown code span or fenced code block.
+    Code,
+    /// What only formats the text, like list bullets and the targets of links.
+    Markup,
+    /// The `<` and `>` at the edges of the view where a line goes on beyond them.
+    ScrollMarker,
+}
+
+impl AnnotationType {
+    /// Where annotations overlap, the one of the highest priority is shown.
+    #[must_use]
+    pub const fn priority(self) -> u8 {
+        match self {
+            Self::Changed => 19,
+            Self::Mark => 18,
+            Self::ScrollMarker => 17,
+            Self::SelectedMatch => 16,
+            Self::Match => 15,
+            Self::WordMatch => 14,
+            Self::Error => 13,
+            Self::Warning => 12,
+            Self::Inserted => 11,
+            Self::Deleted => 10,
+            Self::EditedLine => 9,
+            Self::LineNumber => 8,
+            Self::Markup => 7,
+            Self::Code => 6,
+            Self::SpellError => 5,
+            Self::Digit => 4,
+            Self::Strong => 3,
+            Self::Emphasis => 2,
+            Self::Heading => 1,
+            Self::Invisible => 0,
+        }
+    }
 }
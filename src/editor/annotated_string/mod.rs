@@ -1,18 +1,17 @@
-use std::{
-    cmp::{max, min},
-    fmt::{self, Display},
-};
+use std::fmt::{self, Display};
 
 mod annotated_string_iterator;
 mod annotated_string_part;
 mod annotation;
 mod annotation_type;
 
-use annotated_string_iterator::AnnotatedStringIterator;
-use annotated_string_part::AnnotatedStringPart;
+pub use annotated_string_iterator::AnnotatedStringIterator;
+pub use annotated_string_part::AnnotatedStringPart;
 use annotation::Annotation;
 pub use annotation_type::AnnotationType;
 
+/// A string with parts of it annotated, like a row of a [`Line`](crate::Line) ready to be
+/// drawn. Iterating yields the string in parts, each with the annotation shown on it.
 #[derive(Default, Debug)]
 pub struct AnnotatedString {
     string: String,
@@ -20,14 +19,33 @@ pub struct AnnotatedString {
 }
 
 impl AnnotatedString {
-    pub fn from(string: &str) -> Self {
+    pub(crate) fn from(string: &str) -> Self {
         Self {
             string: String::from(string),
             annotations: Vec::new(),
         }
     }
 
-    pub fn add_annotation(
+    // start over with `string`, keeping the memory for the next row drawn
+    pub(crate) fn reset(&mut self, string: &str) {
+        self.string.clear();
+        self.string.push_str(string);
+        self.annotations.clear();
+    }
+
+    /// The string without its annotations.
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        &self.string
+    }
+
+    /// The parts of the string, each as far as the same annotation is shown on it.
+    #[must_use]
+    pub fn iter(&self) -> AnnotatedStringIterator<'_> {
+        self.into_iter()
+    }
+
+    pub(crate) fn add_annotation(
         &mut self,
         typ: AnnotationType,
         start_byte_idx: usize,
@@ -41,80 +59,45 @@ impl AnnotatedString {
         });
     }
 
-    // replace the internal string ranged by `start_byte_idx` and `end_byte_idx` with `new_string`
-    // and update annotations after replacing
-    pub fn replace(&mut self, start_byte_idx: usize, end_byte_idx: usize, new_string: &str) {
-        debug_assert!(start_byte_idx <= end_byte_idx);
+    // append `string` without extending any annotation over it
+    pub(crate) fn push_str(&mut self, string: &str) {
+        self.string.push_str(string);
+    }
 
-        debug_assert!(end_byte_idx <= self.string.len()); // right?
-        // let end_byte_idx = min(end_byte_idx, self.string.len());
-        // if start_byte_idx > end_byte_idx {
-        //     return;
-        // }
+    // Replace the bytes from `start_byte_idx` to `end_byte_idx` with `new_string`. Annotations
+    // before them keep their place and those behind them move along. One covering all of
+    // them covers `new_string` instead, one reaching into them only keeps what is left of it
+    // outside, which is nothing for one within them.
+    pub(crate) fn replace(&mut self, start_byte_idx: usize, end_byte_idx: usize, new_string: &str) {
+        debug_assert!(start_byte_idx <= end_byte_idx);
+        debug_assert!(end_byte_idx <= self.string.len());
         self.string
             .replace_range(start_byte_idx..end_byte_idx, new_string);
 
-        // update the annotations
-        let replaced_range_len = end_byte_idx.saturating_sub(start_byte_idx);
-        let shortened = new_string.len() < replaced_range_len;
-        let len_difference = new_string.len().abs_diff(replaced_range_len);
-
-        // No adjustment of annotations needed in case the replacement did not result in a change in length.
-        if len_difference == 0 {
-            return;
-        }
-
-        self.annotations.iter_mut().for_each(|annotation| {
-            annotation.start_byte_idx = if annotation.start_byte_idx >= end_byte_idx {
-                // For annotations starting after the replaced range, we move the start index by the difference in length.
-                if shortened {
-                    annotation.start_byte_idx.saturating_sub(len_difference)
-                } else {
-                    annotation.start_byte_idx.saturating_add(len_difference)
-                }
-            } else if annotation.start_byte_idx >= start_byte_idx {
-                // For annotations starting within the replaced range, we move the start index by the difference in length, constrained to the beginning or end of the replaced range.
-                if shortened {
-                    max(
-                        start_byte_idx,
-                        annotation.start_byte_idx.saturating_sub(len_difference),
-                    )
-                } else {
-                    min(
-                        end_byte_idx,
-                        annotation.start_byte_idx.saturating_add(len_difference),
-                    )
-                }
+        let replaced_end = start_byte_idx.saturating_add(new_string.len());
+        let moved = |idx: usize| {
+            idx.saturating_sub(end_byte_idx)
+                .saturating_add(replaced_end)
+        };
+        for annotation in &mut self.annotations {
+            let (start, end) = (annotation.start_byte_idx, annotation.end_byte_idx);
+            if end <= start_byte_idx && start < start_byte_idx {
+                continue;
+            }
+            annotation.start_byte_idx = if start >= end_byte_idx {
+                moved(start)
+            } else if start > start_byte_idx {
+                replaced_end
             } else {
-                annotation.start_byte_idx
+                start
             };
-
-            annotation.end_byte_idx = if annotation.end_byte_idx >= end_byte_idx {
-                // For annotations ending after the replaced range, we move the end index by the difference in length.
-                if shortened {
-                    annotation.end_byte_idx.saturating_sub(len_difference)
-                } else {
-                    annotation.end_byte_idx.saturating_add(len_difference)
-                }
-            } else if annotation.end_byte_idx >= start_byte_idx {
-                // For annotations ending within the replaced range, we move the end index by the difference in length, constrained to the beginning or end of the replaced range.
-                if shortened {
-                    max(
-                        start_byte_idx,
-                        annotation.end_byte_idx.saturating_sub(len_difference),
-                    )
-                } else {
-                    min(
-                        end_byte_idx,
-                        annotation.end_byte_idx.saturating_add(len_difference),
-                    )
-                }
+            annotation.end_byte_idx = if end >= end_byte_idx {
+                moved(end)
             } else {
-                annotation.end_byte_idx
-            }
-        });
+                start_byte_idx
+            };
+        }
 
-        // Filter out empty annotations, in case the previous step resulted in any.
         self.annotations.retain(|annotation| {
             annotation.start_byte_idx < annotation.end_byte_idx
                 && annotation.start_byte_idx < self.string.len()
@@ -139,3 +122,189 @@ impl<'a> IntoIterator for &'a AnnotatedString {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn annotated(string: &str, annotations: &[(usize, usize)]) -> AnnotatedString {
+        let mut result = AnnotatedString::from(string);
+        for (start, end) in annotations {
+            result.add_annotation(AnnotationType::Match, *start, *end);
+        }
+        result
+    }
+
+    fn ranges(annotated: &AnnotatedString) -> Vec<(usize, usize)> {
+        annotated
+            .annotations
+            .iter()
+            .map(|annotation| (annotation.start_byte_idx, annotation.end_byte_idx))
+            .collect()
+    }
+
+    #[test]
+    fn annotations_around_a_replacement_keep_their_text() {
+        // before, touching it on the left, and behind it
+        let mut result = annotated("ab\tcd", &[(0, 1), (1, 2), (3, 5)]);
+        result.replace(2, 3, "    ");
+        assert_eq!(result.as_str(), "ab    cd");
+        assert_eq!(ranges(&result), [(0, 1), (1, 2), (6, 8)]);
+
+        // a shorter replacement moves them the other way
+        result.replace(2, 6, "");
+        assert_eq!(ranges(&result), [(0, 1), (1, 2), (2, 4)]);
+    }
+
+    #[test]
+    fn annotations_covering_a_replacement_cover_what_replaces_it() {
+        let mut result = annotated("a\tb", &[(1, 2), (0, 3)]);
+        result.replace(1, 2, "→   ");
+        assert_eq!(ranges(&result), [(1, 7), (0, 8)]);
+    }
+
+    #[test]
+    fn annotations_reaching_into_a_replacement_are_clipped() {
+        // "老" is three bytes, which annotations reach into from either side
+        let mut result = annotated("a老b", &[(0, 2), (3, 5), (2, 3)]);
+        result.replace(1, 4, "⋯ ");
+        assert_eq!(result.as_str(), "a⋯ b");
+        assert_eq!(ranges(&result), [(0, 1), (5, 6)]);
+    }
+
+    #[test]
+    fn insertions_do_not_grow_annotations() {
+        let mut result = annotated("ab", &[(0, 1), (1, 2)]);
+        result.replace(1, 1, "x");
+        assert_eq!(result.as_str(), "axb");
+        assert_eq!(ranges(&result), [(0, 1), (2, 3)]);
+    }
+
+    fn parts(annotated: &AnnotatedString) -> Vec<(&str, Option<AnnotationType>)> {
+        annotated
+            .into_iter()
+            .map(|part| (part.string, part.typ))
+            .collect()
+    }
+
+    #[test]
+    fn the_highest_priority_is_shown() {
+        // a match within a highlighted word, added before or after it
+        for word_first in [true, false] {
+            let mut result = AnnotatedString::from("let foo1 = 2;");
+            let word = (AnnotationType::WordMatch, 4, 8);
+            let digit = (AnnotationType::Digit, 7, 8);
+            let found = (AnnotationType::Match, 5, 7);
+            let annotations = if word_first {
+                [word, found, digit]
+            } else {
+                [digit, found, word]
+            };
+            for (typ, start, end) in annotations {
+                result.add_annotation(typ, start, end);
+            }
+            assert_eq!(
+                parts(&result),
+                [
+                    ("let ", None),
+                    ("f", Some(AnnotationType::WordMatch)),
+                    ("oo", Some(AnnotationType::Match)),
+                    ("1", Some(AnnotationType::WordMatch)),
+                    (" = 2;", None),
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn identical_ranges_show_the_highest_priority() {
+        for typs in [
+            [AnnotationType::Match, AnnotationType::SelectedMatch],
+            [AnnotationType::SelectedMatch, AnnotationType::Match],
+        ] {
+            let mut result = AnnotatedString::from("abc");
+            for typ in typs {
+                result.add_annotation(typ, 1, 2);
+            }
+            assert_eq!(
+                parts(&result),
+                [
+                    ("a", None),
+                    ("b", Some(AnnotationType::SelectedMatch)),
+                    ("c", None)
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn partly_overlapping_annotations_split_where_the_other_shows() {
+        let mut result = AnnotatedString::from("abcdef");
+        result.add_annotation(AnnotationType::Match, 3, 6);
+        result.add_annotation(AnnotationType::Invisible, 0, 4);
+        result.add_annotation(AnnotationType::Match, 1, 4);
+        assert_eq!(
+            parts(&result),
+            [
+                ("a", Some(AnnotationType::Invisible)),
+                ("bcd", Some(AnnotationType::Match)),
+                ("ef", Some(AnnotationType::Match)),
+            ]
+        );
+    }
+
+    fn annotation() -> impl Strategy<Value = (AnnotationType, usize, usize)> {
+        let typ = prop::sample::select(vec![
+            AnnotationType::SelectedMatch,
+            AnnotationType::Match,
+            AnnotationType::WordMatch,
+            AnnotationType::Digit,
+            AnnotationType::Invisible,
+        ]);
+        (typ, 0_usize..12, 0_usize..12)
+    }
+
+    proptest! {
+        #[test]
+        fn parts_make_up_the_string_in_any_order(
+            string in "[a老→ ]{0,8}",
+            annotations in prop::collection::vec(annotation(), 0..6),
+        ) {
+            // on character boundaries
+            let boundary = |idx: usize| string.floor_char_boundary(idx);
+            let annotations: Vec<(AnnotationType, usize, usize)> = annotations
+                .into_iter()
+                .map(|(typ, a, b)| (typ, boundary(a.min(b)), boundary(a.max(b))))
+                .collect();
+            let annotated = |annotations: &[(AnnotationType, usize, usize)]| {
+                let mut result = AnnotatedString::from(&string);
+                for (typ, start, end) in annotations {
+                    result.add_annotation(*typ, *start, *end);
+                }
+                result
+            };
+            let forward = annotated(&annotations);
+            let reversed: Vec<_> = annotations.iter().rev().copied().collect();
+            let backward = annotated(&reversed);
+            prop_assert_eq!(parts(&forward), parts(&backward));
+            let parts = parts(&forward);
+            prop_assert_eq!(parts.iter().map(|(part, _)| *part).collect::<String>(), string.clone());
+            prop_assert!(parts.iter().all(|(part, _)| !part.is_empty()));
+
+            let mut byte_idx = 0_usize;
+            for (part, typ) in parts {
+                for (relative_idx, _) in part.char_indices() {
+                    let at = byte_idx.saturating_add(relative_idx);
+                    let highest = annotations
+                        .iter()
+                        .filter(|(_, start, end)| *start <= at && at < *end)
+                        .map(|(typ, _, _)| *typ)
+                        .max_by_key(|typ| typ.priority());
+                    prop_assert_eq!(typ, highest);
+                }
+                byte_idx = byte_idx.saturating_add(part.len());
+            }
+        }
+    }
+}
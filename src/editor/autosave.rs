@@ -0,0 +1,103 @@
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+/// How long no key was pressed before modified buffers are autosaved, unless given.
+pub const DEFAULT_DELAY: Duration = Duration::from_secs(30);
+
+// Where a buffer is autosaved to: a hidden sidecar next to the file, e.g.
+// `.notes.txt.autosave`, so the file itself only changes on an explicit save.
+pub fn sidecar_path(file: &Path) -> Option<PathBuf> {
+    let name = file.file_name()?.to_string_lossy();
+    Some(file.with_file_name(format!(".{name}.autosave")))
+}
+
+// decides when buffers are due to be autosaved and remembers which sidecars were written
+pub struct Autosave {
+    delay: Duration,
+    last_key: Instant,
+    // whether anything was edited since the last autosave
+    pending: bool,
+    written: Vec<PathBuf>,
+}
+
+impl Autosave {
+    pub fn new(delay: Duration) -> Self {
+        Self {
+            delay,
+            last_key: Instant::now(),
+            pending: false,
+            written: Vec::new(),
+        }
+    }
+
+    // any key press postpones the autosave
+    pub fn key_pressed(&mut self) {
+        self.last_key = Instant::now();
+    }
+
+    pub fn edited(&mut self) {
+        self.key_pressed();
+        self.pending = true;
+    }
+
+    // how long to wait for input before autosaving, None if there is nothing to save
+    pub fn timeout(&self) -> Option<Duration> {
+        self.pending
+            .then(|| self.delay.saturating_sub(self.last_key.elapsed()))
+    }
+
+    // whether buffers should be autosaved now, which then counts as done
+    pub fn take_due(&mut self) -> bool {
+        let is_due = self.timeout().is_some_and(|timeout| timeout.is_zero());
+        if is_due {
+            self.pending = false;
+        }
+        is_due
+    }
+
+    pub fn written(&mut self, path: PathBuf) {
+        if !self.written.contains(&path) {
+            self.written.push(path);
+        }
+    }
+
+    // forget the sidecar of `file`, returning it if this session wrote it
+    pub fn take_written(&mut self, file: &Path) -> Option<PathBuf> {
+        let sidecar = sidecar_path(file)?;
+        let idx = self.written.iter().position(|path| *path == sidecar)?;
+        Some(self.written.swap_remove(idx))
+    }
+
+    pub fn take_all_written(&mut self) -> Vec<PathBuf> {
+        std::mem::take(&mut self.written)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn sidecar_is_hidden_next_to_the_file() {
+        assert_eq!(
+            sidecar_path(Path::new("/tmp/notes.txt")),
+            Some(PathBuf::from("/tmp/.notes.txt.autosave"))
+        );
+    }
+
+    #[test]
+    fn only_due_after_an_edit_and_the_delay() {
+        let mut autosave = Autosave::new(Duration::ZERO);
+        assert!(!autosave.take_due());
+        autosave.edited();
+        assert!(autosave.take_due());
+        assert!(!autosave.take_due());
+
+        let mut autosave = Autosave::new(Duration::from_secs(45));
+        autosave.edited();
+        assert!(!autosave.take_due());
+        assert!(autosave.timeout().is_some());
+    }
+}
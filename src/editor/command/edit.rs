@@ -1,11 +1,21 @@
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 
+/// A change to the text at the caret, applied to a buffer by
+/// [`Buffer::apply`](crate::Buffer::apply).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Edit {
     Insert(char),
     InsertTab,
     InsertNewline,
+    // open a new line without splitting the current one, needs the kitty keyboard protocol
+    InsertLineBelow,
+    InsertLineAbove,
     Delete,
     DeleteBackward,
+    // swap the graphemes around the caret
+    TransposeGraphemes,
+    // swap the caret's line with the one above
+    TransposeLines,
 }
 
 impl TryFrom<KeyEvent> for Edit {
@@ -19,8 +29,16 @@ impl TryFrom<KeyEvent> for Edit {
             (KeyCode::Char(ch), KeyModifiers::NONE | KeyModifiers::SHIFT) => Ok(Self::Insert(ch)),
             (KeyCode::Tab, KeyModifiers::NONE) => Ok(Self::InsertTab),
             (KeyCode::Enter, KeyModifiers::NONE) => Ok(Self::InsertNewline),
+            (KeyCode::Enter, KeyModifiers::CONTROL) => Ok(Self::InsertLineBelow),
+            (KeyCode::Enter, KeyModifiers::SHIFT) => Ok(Self::InsertLineAbove),
+            (KeyCode::Enter, modifiers)
+                if modifiers == KeyModifiers::CONTROL | KeyModifiers::SHIFT =>
+            {
+                Ok(Self::InsertLineAbove)
+            }
             (KeyCode::Delete, KeyModifiers::NONE) => Ok(Self::Delete),
             (KeyCode::Backspace, KeyModifiers::NONE) => Ok(Self::DeleteBackward),
+            (KeyCode::Char('t'), KeyModifiers::ALT) => Ok(Self::TransposeLines),
             _ => Err(format!(
                 "Unsupported code: {code:?} with modifiers {modifiers:?}"
             )),
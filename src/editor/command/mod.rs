@@ -8,6 +8,8 @@ mod edit;
 mod r#move;
 mod system;
 
+/// What a key stands for, see its `TryFrom<Event>`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Command {
     Move(Move),
     Edit(Edit),
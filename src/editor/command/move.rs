@@ -1,14 +1,27 @@
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 
+/// A move of the caret.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Move {
     PageUp,
     PageDown,
     StartOfLine,
     EndOfLine,
+    StartOfFile,
+    EndOfFile,
     Up,
     Left,
     Right,
     Down,
+    // by line even when lines wrap
+    LineUp,
+    LineDown,
+    // only reachable through modal editing
+    NextWord,
+    PreviousWord,
+    // through the locations left by far moves
+    JumpBack,
+    JumpForward,
 }
 
 impl TryFrom<KeyEvent> for Move {
@@ -30,6 +43,20 @@ impl TryFrom<KeyEvent> for Move {
                 KeyCode::End => Ok(Move::EndOfLine),
                 _ => Err(format!("Unsupported code: {code:?}")),
             }
+        } else if modifiers == KeyModifiers::ALT {
+            match code {
+                KeyCode::Up => Ok(Move::LineUp),
+                KeyCode::Down => Ok(Move::LineDown),
+                KeyCode::Char(',') => Ok(Move::JumpBack),
+                KeyCode::Char('.') => Ok(Move::JumpForward),
+                _ => Err(format!("Unsupported code: ALT+{code:?}")),
+            }
+        } else if modifiers == KeyModifiers::CONTROL {
+            match code {
+                KeyCode::Home => Ok(Move::StartOfFile),
+                KeyCode::End => Ok(Move::EndOfFile),
+                _ => Err(format!("Unsupported code: CONTROL+{code:?}")),
+            }
         } else {
             Err(format!(
                 "Unsupported key code {code:?} or modifier {modifiers:?}"
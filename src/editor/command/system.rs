@@ -1,16 +1,70 @@
 use crate::editor::size::Size;
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 
+/// What the editor does besides moving and editing, like saving or searching.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum System {
     Save,
+    Open,
+    Reload,
+    ReloadConfig,
     Search,
     SearchNext,
     SearchPrevious,
+    NextBuffer,
+    PrevBuffer,
+    SplitView,
+    CloseSplit,
+    SwitchPane,
+    CommandPalette,
+    RecordMacro,
+    PlayMacro,
+    // wait for the name of a mark
+    SetMark,
+    JumpToMark,
+    // complete the word left of the caret, or cycle on to the next completion
+    CompleteWord,
+    // a digit of the count to repeat the next move or edit with
+    Count(u8),
     Dismiss,
+    Redraw,
     Resize(Size),
     Quit,
 }
 
+impl System {
+    /// The name of the command in the palette which the key runs.
+    #[must_use]
+    pub const fn name(&self) -> Option<&'static str> {
+        match self {
+            Self::Save => Some("save"),
+            Self::Open => Some("open"),
+            Self::Reload => Some("reload"),
+            Self::ReloadConfig => Some("reload-config"),
+            Self::Search => Some("search"),
+            Self::SearchNext => Some("search-next"),
+            Self::SearchPrevious => Some("search-previous"),
+            Self::NextBuffer => Some("next-buffer"),
+            Self::PrevBuffer => Some("previous-buffer"),
+            Self::SplitView => Some("split"),
+            Self::CloseSplit => Some("close-split"),
+            Self::SwitchPane => Some("switch-pane"),
+            Self::SetMark => Some("set-mark"),
+            Self::JumpToMark => Some("jump-to-mark"),
+            Self::CompleteWord => Some("complete-word"),
+            Self::Redraw => Some("redraw"),
+            Self::Quit => Some("quit"),
+            // these control what is recorded, so only keys run them
+            Self::CommandPalette
+            | Self::RecordMacro
+            | Self::PlayMacro
+            | Self::Count(_)
+            | Self::Dismiss
+            | Self::Resize(_) => None,
+        }
+    }
+}
+
 impl TryFrom<KeyEvent> for System {
     type Error = String;
 
@@ -22,11 +76,35 @@ impl TryFrom<KeyEvent> for System {
             match code {
                 KeyCode::Char('t') => Ok(Self::Quit),
                 KeyCode::Char('s') => Ok(Self::Save),
+                KeyCode::Char('r') => Ok(Self::Reload),
+                KeyCode::Char('o') => Ok(Self::Open),
                 KeyCode::Char('f') => Ok(Self::Search),
                 KeyCode::Char('n') => Ok(Self::SearchNext),
                 KeyCode::Char('p') => Ok(Self::SearchPrevious),
+                KeyCode::Char('l') => Ok(Self::Redraw),
+                KeyCode::PageDown => Ok(Self::NextBuffer),
+                KeyCode::PageUp => Ok(Self::PrevBuffer),
                 _ => Err(format!("Unknown not CONTROL+{code:?} combination")),
             }
+        } else if modifiers == KeyModifiers::ALT {
+            match code {
+                KeyCode::Right => Ok(Self::NextBuffer),
+                KeyCode::Left => Ok(Self::PrevBuffer),
+                KeyCode::Char('s') => Ok(Self::SplitView),
+                KeyCode::Char('q') => Ok(Self::CloseSplit),
+                KeyCode::Char('o') => Ok(Self::SwitchPane),
+                KeyCode::Char('r') => Ok(Self::ReloadConfig),
+                KeyCode::Char('x') => Ok(Self::CommandPalette),
+                KeyCode::Char('m') => Ok(Self::RecordMacro),
+                KeyCode::Char('p') => Ok(Self::PlayMacro),
+                KeyCode::Char('b') => Ok(Self::SetMark),
+                KeyCode::Char('j') => Ok(Self::JumpToMark),
+                KeyCode::Char('/') => Ok(Self::CompleteWord),
+                KeyCode::Char(digit @ '0'..='9') => {
+                    Ok(Self::Count((digit as u8).saturating_sub(b'0')))
+                }
+                _ => Err(format!("Unknown not ALT+{code:?} combination")),
+            }
         } else if modifiers == KeyModifiers::NONE && matches!(code, KeyCode::Esc) {
             Ok(Self::Dismiss)
         } else {
@@ -0,0 +1,219 @@
+use std::{
+    env,
+    fs::read_dir,
+    mem,
+    path::{MAIN_SEPARATOR, PathBuf},
+};
+
+// expand a leading `~` to the home directory
+pub fn expand_tilde(path: &str) -> PathBuf {
+    let home = || env::var_os("HOME").map(PathBuf::from);
+    if path == "~" {
+        if let Some(home) = home() {
+            return home;
+        }
+    } else if let Some(rest) = path.strip_prefix("~/")
+        && let Some(home) = home()
+    {
+        return home.join(rest);
+    }
+    PathBuf::from(path)
+}
+
+// list all entries of the directory `input` points into whose names start with the
+// typed prefix, as they would be typed. Directories get a trailing separator.
+pub fn candidates(input: &str) -> Result<Vec<String>, std::io::Error> {
+    let (dir, prefix) = input
+        .rfind(['/', MAIN_SEPARATOR])
+        .map_or(("", input), |idx| input.split_at(idx.saturating_add(1)));
+
+    let dir_path = if dir.is_empty() {
+        PathBuf::from(".")
+    } else {
+        expand_tilde(dir)
+    };
+
+    let mut candidates: Vec<String> = read_dir(dir_path)?
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            // hidden files are only offered if asked for
+            if !name.starts_with(prefix) || (prefix.is_empty() && name.starts_with('.')) {
+                return None;
+            }
+            let is_dir = entry.path().is_dir();
+            Some(if is_dir {
+                format!("{dir}{name}/")
+            } else {
+                format!("{dir}{name}")
+            })
+        })
+        .collect();
+    candidates.sort();
+    Ok(candidates)
+}
+
+// the longest prefix shared by all candidates, never splitting a character
+pub fn longest_common_prefix(candidates: &[String]) -> String {
+    let Some(first) = candidates.first() else {
+        return String::new();
+    };
+
+    let mut end = first.len();
+    for candidate in candidates.iter().skip(1) {
+        let common = first
+            .char_indices()
+            .zip(candidate.chars())
+            .take_while(|((_, a), b)| a == b)
+            .last()
+            .map_or(0, |((idx, ch), _)| idx.saturating_add(ch.len_utf8()));
+        end = end.min(common);
+    }
+    first[..end].to_string()
+}
+
+// the state of repeatedly pressing Tab in a prompt
+#[derive(Default)]
+pub struct Completion {
+    candidates: Vec<String>,
+    next_idx: usize,
+}
+
+impl Completion {
+    pub const fn new(candidates: Vec<String>) -> Self {
+        Self {
+            candidates,
+            next_idx: 0,
+        }
+    }
+
+    // the next candidate, wrapping around after the last one
+    pub fn cycle(&mut self) -> Option<&str> {
+        let idx = self.next_idx.checked_rem(self.candidates.len())?;
+        self.next_idx = idx.saturating_add(1);
+        self.candidates.get(idx).map(String::as_str)
+    }
+}
+
+// the state of repeatedly completing the word left of the caret from the words of the buffer
+pub struct WordCompletion {
+    prefix: String,
+    candidates: Completion,
+    count: usize,
+    // counting from 1, 0 before the first candidate
+    current: usize,
+    // what the current candidate added after the prefix
+    inserted: String,
+    // where the caret was left, anywhere else ends the completion
+    pub caret: (usize, usize),
+}
+
+impl WordCompletion {
+    pub fn new(prefix: String, candidates: Vec<String>) -> Option<Self> {
+        (!candidates.is_empty()).then(|| Self {
+            prefix,
+            count: candidates.len(),
+            candidates: Completion::new(candidates),
+            current: 0,
+            inserted: String::new(),
+            caret: (0, 0),
+        })
+    }
+
+    // move on to the next candidate, returning what the previous one inserted, which is to be
+    // removed, and what to insert instead
+    pub fn cycle(&mut self) -> (String, &str) {
+        let suffix = self
+            .candidates
+            .cycle()
+            .and_then(|candidate| candidate.strip_prefix(self.prefix.as_str()))
+            .unwrap_or_default()
+            .to_string();
+        self.current = self
+            .current
+            .checked_rem(self.count)
+            .unwrap_or(0)
+            .saturating_add(1);
+        (mem::replace(&mut self.inserted, suffix), &self.inserted)
+    }
+
+    // e.g. "Completion 2 of 3"
+    pub fn describe(&self) -> String {
+        format!("Completion {} of {}", self.current, self.count)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs;
+
+    fn setup(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(name);
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("dösen")).unwrap();
+        fs::write(dir.join("döner.txt"), "").unwrap();
+        fs::write(dir.join("doner.txt"), "").unwrap();
+        fs::write(dir.join(".hidden"), "").unwrap();
+        dir
+    }
+
+    #[test]
+    fn lists_matching_entries() {
+        let dir = setup("hecto-completion-list");
+        let input = format!("{}/dö", dir.display());
+        let found = candidates(&input).unwrap();
+        assert_eq!(
+            found,
+            vec![
+                format!("{}/döner.txt", dir.display()),
+                format!("{}/dösen/", dir.display()),
+            ]
+        );
+        assert_eq!(
+            longest_common_prefix(&found),
+            format!("{}/dö", dir.display())
+        );
+    }
+
+    #[test]
+    fn hides_hidden_files_unless_asked() {
+        let dir = setup("hecto-completion-hidden");
+        let all = candidates(&format!("{}/", dir.display())).unwrap();
+        assert_eq!(all.len(), 3);
+        let hidden = candidates(&format!("{}/.", dir.display())).unwrap();
+        assert_eq!(hidden, vec![format!("{}/.hidden", dir.display())]);
+    }
+
+    #[test]
+    fn missing_directory_is_an_error() {
+        assert!(candidates("/this/does/not/exist/foo").is_err());
+    }
+
+    #[test]
+    fn common_prefix_respects_char_boundaries() {
+        let candidates = vec![String::from("aö"), String::from("aü")];
+        assert_eq!(longest_common_prefix(&candidates), "a");
+    }
+
+    #[test]
+    fn cycles_through_candidates() {
+        let mut completion = Completion::new(vec![String::from("a"), String::from("b")]);
+        assert_eq!(completion.cycle(), Some("a"));
+        assert_eq!(completion.cycle(), Some("b"));
+        assert_eq!(completion.cycle(), Some("a"));
+        assert_eq!(Completion::default().cycle(), None);
+    }
+
+    #[test]
+    fn word_completion_replaces_the_previous_candidate() {
+        let candidates = vec![String::from("größe"), String::from("grün")];
+        let mut completion = WordCompletion::new(String::from("gr"), candidates).unwrap();
+        assert_eq!(completion.cycle(), (String::new(), "öße"));
+        assert_eq!(completion.describe(), "Completion 1 of 2");
+        assert_eq!(completion.cycle(), (String::from("öße"), "ün"));
+        assert_eq!(completion.cycle(), (String::from("ün"), "öße"));
+        assert_eq!(completion.describe(), "Completion 1 of 2");
+        assert!(WordCompletion::new(String::from("gr"), Vec::new()).is_none());
+    }
+}
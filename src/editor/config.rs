@@ -0,0 +1,261 @@
+use super::{
+    completion::expand_tilde,
+    line::{AmbiguousWidth, CellWidths, DEFAULT_TAB_WIDTH, Invisibles},
+};
+use crate::dirs;
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+const FILENAME: &str = "config.toml";
+const USER_DICTIONARY: &str = "words.txt";
+const DEFAULT_LONG_LINE_BYTES: usize = 1 << 20;
+
+// Options read from `config.toml`, every one of them may be left out.
+// The defaults behave like hecto without a config file.
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub tab_width: usize,
+    // insert `tab_width` spaces instead of a tab
+    pub expand_tab: bool,
+    pub show_line_numbers: bool,
+    // break long lines at the width of the view instead of scrolling
+    pub wrap: bool,
+    // "marked", "shown" or "hidden"
+    pub invisibles: Invisibles,
+    // "narrow" or "wide", how the terminal draws characters like box drawing and arrows.
+    // Left out, it is wide in Chinese, Japanese and Korean locales.
+    pub ambiguous_width: Option<AmbiguousWidth>,
+    // highlight the word under the caret wherever else it is visible
+    pub highlight_word: bool,
+    // lines to keep visible above and below the caret
+    pub scroll_off: usize,
+    // lines longer than this many bytes aren't highlighted, which would slow down drawing
+    pub long_line_bytes: usize,
+    // ask before quitting with unsaved changes
+    pub quit_confirmation: bool,
+    pub trim_trailing_whitespace_on_save: bool,
+    // create missing directories when saving instead of asking first
+    pub create_directories: bool,
+    // vi-like normal and insert modes
+    pub modal: bool,
+    // clicks go to hecto instead of selecting text, e.g. on the status bar
+    pub mouse: bool,
+    // a bar on the right edge telling how far through the buffer the view is
+    pub scrollbar: bool,
+    // e.g. "%f %m%=%l/%L:%c %p%%", see `statusbar::DEFAULT_FORMAT`
+    pub status_line: Option<String>,
+    // relative paths, here and for the dictionaries, are relative to the directory of the
+    // config
+    pub theme: Option<PathBuf>,
+    // chords like "ctrl+k ctrl+s" bound to the names of the command palette
+    pub keys: BTreeMap<String, String>,
+    // underline misspelled words in Markdown and text files
+    pub spell_check: bool,
+    // a word list, one word to a line. The system's if left out, or a small English one
+    // without it.
+    pub dictionary: Option<PathBuf>,
+    // where words added to the dictionary are kept, `words.txt` in the config directory if
+    // left out
+    pub user_dictionary: Option<PathBuf>,
+    // shell commands formatting files on save by their extension, e.g. "rs" = "rustfmt"
+    pub formatters: BTreeMap<String, String>,
+    // where the config was read from, for the theme. The config directory if None.
+    #[serde(skip)]
+    pub dir: Option<PathBuf>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            tab_width: DEFAULT_TAB_WIDTH,
+            expand_tab: false,
+            show_line_numbers: false,
+            wrap: false,
+            invisibles: Invisibles::Marked,
+            ambiguous_width: None,
+            highlight_word: false,
+            scroll_off: 0,
+            long_line_bytes: DEFAULT_LONG_LINE_BYTES,
+            quit_confirmation: true,
+            trim_trailing_whitespace_on_save: false,
+            create_directories: false,
+            modal: false,
+            mouse: false,
+            scrollbar: false,
+            status_line: None,
+            theme: None,
+            keys: BTreeMap::new(),
+            spell_check: true,
+            dictionary: None,
+            user_dictionary: None,
+            formatters: BTreeMap::new(),
+            dir: None,
+        }
+    }
+}
+
+impl Config {
+    // `path`, or `$XDG_CONFIG_HOME/hecto/config.toml` and the defaults if there is none. The
+    // error is a one-line message to show in the message bar.
+    pub fn load(path: Option<&Path>) -> Result<Self, String> {
+        let is_default = path.is_none();
+        let Some(path) = path
+            .map(Path::to_path_buf)
+            .or_else(|| dirs::config_dir().map(|dir| dir.join(FILENAME)))
+        else {
+            return Ok(Self::default().with_locale());
+        };
+        match fs::read_to_string(&path) {
+            Ok(toml) => Self::parse(&toml)
+                .map(|config| Self {
+                    dir: path.parent().map(Path::to_path_buf),
+                    ..config.with_locale()
+                })
+                .map_err(|err| format!("Config {}: {err}", path.display())),
+            Err(err) if is_default && err.kind() == ErrorKind::NotFound => {
+                Ok(Self::default().with_locale())
+            }
+            Err(err) => Err(format!("Error reading '{}': {err}", path.display())),
+        }
+    }
+
+    // what is left out and depends on the environment, taken from there
+    fn with_locale(mut self) -> Self {
+        if self.ambiguous_width.is_none() {
+            let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+                .into_iter()
+                .filter_map(|variable| env::var(variable).ok())
+                .find(|value| !value.is_empty());
+            self.ambiguous_width = locale.as_deref().map(ambiguous_width_in);
+        }
+        self
+    }
+
+    pub fn cell_widths(&self) -> CellWidths {
+        CellWidths {
+            tab: self.tab_width,
+            ambiguous: self.ambiguous_width.unwrap_or_default(),
+        }
+    }
+
+    fn parse(toml: &str) -> Result<Self, String> {
+        toml::from_str(toml).map_err(|err| describe_toml_error(toml, &err))
+    }
+
+    pub fn formatter(&self, path: &Path) -> Option<&str> {
+        let extension = path.extension()?.to_str()?;
+        self.formatters.get(extension).map(String::as_str)
+    }
+
+    pub fn theme_path(&self) -> Option<PathBuf> {
+        self.resolve(self.theme.as_ref()?)
+    }
+
+    pub fn dictionary_path(&self) -> Option<PathBuf> {
+        self.resolve(self.dictionary.as_ref()?)
+    }
+
+    pub fn user_dictionary_path(&self) -> Option<PathBuf> {
+        match &self.user_dictionary {
+            Some(path) => self.resolve(path),
+            None => dirs::config_dir().map(|dir| dir.join(USER_DICTIONARY)),
+        }
+    }
+
+    // relative paths are relative to the directory of the config
+    fn resolve(&self, path: &Path) -> Option<PathBuf> {
+        let path = expand_tilde(&path.to_string_lossy());
+        if path.is_relative() {
+            self.dir
+                .clone()
+                .or_else(dirs::config_dir)
+                .map(|dir| dir.join(path))
+        } else {
+            Some(path)
+        }
+    }
+}
+
+// e.g. "ja_JP.UTF-8" is wide, like other Chinese, Japanese and Korean locales
+fn ambiguous_width_in(locale: &str) -> AmbiguousWidth {
+    let language = locale.split(['_', '.', '@']).next().unwrap_or_default();
+    if ["ja", "ko", "zh"].contains(&language) {
+        AmbiguousWidth::Wide
+    } else {
+        AmbiguousWidth::Narrow
+    }
+}
+
+// e.g. "line 2: invalid type: ...", on a single line
+pub fn describe_toml_error(toml: &str, err: &toml::de::Error) -> String {
+    let message = err.message().trim_end().replace('\n', " ");
+    match err.span() {
+        Some(span) => {
+            let line = toml[..span.start].matches('\n').count().saturating_add(1);
+            format!("line {line}: {message}")
+        }
+        None => message,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn missing_options_keep_their_defaults() {
+        assert_eq!(Config::parse(""), Ok(Config::default()));
+        let config = Config::parse("tab_width = 4\nexpand_tab = true\n").unwrap();
+        assert_eq!(config.tab_width, 4);
+        assert!(config.expand_tab);
+        assert!(config.quit_confirmation);
+    }
+
+    #[test]
+    fn ambiguous_width_follows_the_locale() {
+        let config = Config::parse("ambiguous_width = \"wide\"\n").unwrap();
+        assert_eq!(config.cell_widths().ambiguous, AmbiguousWidth::Wide);
+        assert_eq!(
+            Config::default().cell_widths().ambiguous,
+            AmbiguousWidth::Narrow
+        );
+        assert_eq!(ambiguous_width_in("ja_JP.UTF-8"), AmbiguousWidth::Wide);
+        assert_eq!(ambiguous_width_in("zh"), AmbiguousWidth::Wide);
+        assert_eq!(ambiguous_width_in("en_US.UTF-8"), AmbiguousWidth::Narrow);
+        assert_eq!(ambiguous_width_in("C"), AmbiguousWidth::Narrow);
+    }
+
+    #[test]
+    fn loads_a_given_config_with_its_theme_beside_it() {
+        let dir = std::env::temp_dir().join("hecto-config-path");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("hecto.toml");
+        fs::write(&path, "tab_width = 2\ntheme = \"dark.toml\"\n").unwrap();
+        let config = Config::load(Some(&path)).unwrap();
+        assert_eq!(config.tab_width, 2);
+        assert_eq!(config.theme_path(), Some(dir.join("dark.toml")));
+        // unlike the default one, a config which was asked for has to be there
+        fs::remove_file(&path).unwrap();
+        let err = Config::load(Some(&path)).unwrap_err();
+        assert!(err.starts_with("Error reading"), "{err}");
+    }
+
+    #[test]
+    fn errors_name_the_line() {
+        let err = Config::parse("tab_width = 4\nscroll_off = \"far\"\n").unwrap_err();
+        assert!(err.starts_with("line 2: invalid type"), "{err}");
+        assert!(!err.contains('\n'));
+        let err = Config::parse("tab_wdith = 4\n").unwrap_err();
+        assert!(
+            err.starts_with("line 1: unknown field `tab_wdith`"),
+            "{err}"
+        );
+    }
+}
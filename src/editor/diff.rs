@@ -0,0 +1,419 @@
+use std::collections::HashMap;
+
+// unchanged lines shown around the changes of a hunk
+const CONTEXT: usize = 3;
+// the most lines deleted and inserted the diff looks for, beyond that everything between the
+// common start and end counts as replaced, so files which changed completely don't take long
+const MAX_EDITS: usize = 1_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Change {
+    Kept,
+    Deleted,
+    Inserted,
+}
+
+// how a line differs from the old lines, for the gutter
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineChange {
+    Added,
+    Modified,
+    // lines were deleted right above it, or below it if it is the last line
+    Removed,
+}
+
+// a line of a hunk with where it is in the old and the new lines. Deleted lines have no new
+// index of their own, they take the one of the new line in their place, and inserted ones
+// the old index likewise.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DiffLine {
+    pub change: Change,
+    pub old_idx: usize,
+    pub new_idx: usize,
+}
+
+// changed lines close to each other, with some unchanged ones around them
+#[derive(Debug, PartialEq, Eq)]
+pub struct Hunk {
+    pub lines: Vec<DiffLine>,
+}
+
+impl Hunk {
+    // e.g. "@@ -3,4 +3,5 @@" like in a unified diff, with lines counted from 1
+    pub fn header(&self) -> String {
+        let count = |change| {
+            self.lines
+                .iter()
+                .filter(|line| line.change == Change::Kept || line.change == change)
+                .count()
+        };
+        let (old_start, new_start) = self
+            .lines
+            .first()
+            .map_or((0, 0), |line| (line.old_idx, line.new_idx));
+        format!(
+            "@@ -{},{} +{},{} @@",
+            old_start.saturating_add(1),
+            count(Change::Deleted),
+            new_start.saturating_add(1),
+            count(Change::Inserted)
+        )
+    }
+
+    pub fn count(&self, change: Change) -> usize {
+        self.lines
+            .iter()
+            .filter(|line| line.change == change)
+            .count()
+    }
+}
+
+// the hunks turning `old` into `new`, none if they are the same
+pub fn diff(old: &[String], new: &[String]) -> Vec<Hunk> {
+    hunks(&changes(old, new))
+}
+
+// for each line of `new`, the line of `old` it was kept from, if any
+pub fn kept_lines(old: &[String], new: &[String]) -> Vec<Option<usize>> {
+    let mut kept = vec![None; new.len()];
+    for line in changes(old, new) {
+        if line.change == Change::Kept
+            && let Some(kept) = kept.get_mut(line.new_idx)
+        {
+            *kept = Some(line.old_idx);
+        }
+    }
+    kept
+}
+
+// For each line of `new`, how it differs from `old`. Inserted lines take the place of the lines
+// deleted right before them as modified ones, those left over count as added.
+pub fn line_changes(old: &[String], new: &[String]) -> Vec<Option<LineChange>> {
+    let mut line_changes = vec![None; new.len()];
+    let changes = changes(old, new);
+    let mut idx = 0;
+    while let Some(line) = changes.get(idx) {
+        if line.change == Change::Kept {
+            idx = idx.saturating_add(1);
+            continue;
+        }
+        let run = changes[idx..]
+            .iter()
+            .take_while(|line| line.change != Change::Kept);
+        let deleted = run
+            .clone()
+            .filter(|line| line.change == Change::Deleted)
+            .count();
+        let inserted = run.filter(|line| line.change == Change::Inserted);
+        let mut inserted_count: usize = 0;
+        for (nth, line) in inserted.enumerate() {
+            inserted_count = nth.saturating_add(1);
+            if let Some(change) = line_changes.get_mut(line.new_idx) {
+                *change = Some(if nth < deleted {
+                    LineChange::Modified
+                } else {
+                    LineChange::Added
+                });
+            }
+        }
+        if inserted_count == 0 {
+            let below = line.new_idx.min(new.len().saturating_sub(1));
+            if let Some(change) = line_changes.get_mut(below) {
+                *change = Some(LineChange::Removed);
+            }
+        }
+        idx = idx.saturating_add(deleted).saturating_add(inserted_count);
+    }
+    line_changes
+}
+
+// every line of `old` and `new` in the order of a unified diff
+fn changes<'a>(old: &'a [String], new: &'a [String]) -> Vec<DiffLine> {
+    let prefix = old
+        .iter()
+        .zip(new)
+        .take_while(|(old, new)| old == new)
+        .count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(old, new)| old == new)
+        .count();
+    let old_end = old.len().saturating_sub(suffix);
+    let new_end = new.len().saturating_sub(suffix);
+
+    // numbers compare faster than lines
+    let mut ids: HashMap<&str, usize> = HashMap::new();
+    let mut id = |line: &'a String| {
+        let next = ids.len();
+        *ids.entry(line.as_str()).or_insert(next)
+    };
+    let old_ids: Vec<usize> = old[prefix..old_end].iter().map(&mut id).collect();
+    let new_ids: Vec<usize> = new[prefix..new_end].iter().map(&mut id).collect();
+
+    let kept = |old_idx, new_idx| DiffLine {
+        change: Change::Kept,
+        old_idx,
+        new_idx,
+    };
+    let mut changes: Vec<DiffLine> = (0..prefix).map(|idx| kept(idx, idx)).collect();
+    let middle = shortest_edit(&old_ids, &new_ids).unwrap_or_else(|| {
+        let deleted = (0..old_ids.len()).map(|old_idx| DiffLine {
+            change: Change::Deleted,
+            old_idx,
+            new_idx: 0,
+        });
+        let inserted = (0..new_ids.len()).map(|new_idx| DiffLine {
+            change: Change::Inserted,
+            old_idx: old_ids.len(),
+            new_idx,
+        });
+        deleted.chain(inserted).collect()
+    });
+    changes.extend(middle.into_iter().map(|line| DiffLine {
+        old_idx: line.old_idx.saturating_add(prefix),
+        new_idx: line.new_idx.saturating_add(prefix),
+        ..line
+    }));
+    changes.extend(
+        (0..suffix).map(|idx| kept(old_end.saturating_add(idx), new_end.saturating_add(idx))),
+    );
+    changes
+}
+
+// Myers' diff of `old` and `new`, None if it takes more than `MAX_EDITS` deletions and
+// insertions. Diagonal k, where the old index minus the new one is k, is at `offset + k`.
+fn shortest_edit(old: &[usize], new: &[usize]) -> Option<Vec<DiffLine>> {
+    let max = old.len().saturating_add(new.len()).min(MAX_EDITS);
+    let offset = max.saturating_add(1);
+    // how far along each diagonal the furthest path so far reaches in `old`
+    let mut furthest = vec![0; offset.saturating_mul(2).saturating_add(1)];
+    // `furthest` before each number of edits, to walk the path back
+    let mut trace = Vec::new();
+    for edits in 0..=max {
+        trace.push(furthest.clone());
+        let lowest = offset.saturating_sub(edits);
+        let highest = offset.saturating_add(edits);
+        for diagonal in (lowest..=highest).step_by(2) {
+            let mut old_idx = if came_down(&furthest, diagonal, lowest, highest) {
+                furthest[diagonal.saturating_add(1)]
+            } else {
+                furthest[diagonal.saturating_sub(1)].saturating_add(1)
+            };
+            let mut new_idx = (old_idx.saturating_add(offset)).saturating_sub(diagonal);
+            while old
+                .get(old_idx)
+                .is_some_and(|line| new.get(new_idx) == Some(line))
+            {
+                old_idx = old_idx.saturating_add(1);
+                new_idx = new_idx.saturating_add(1);
+            }
+            furthest[diagonal] = old_idx;
+            if old_idx >= old.len() && new_idx >= new.len() {
+                return Some(backtrack(&trace, offset, old.len(), new.len()));
+            }
+        }
+    }
+    None
+}
+
+// whether the path onto `diagonal` comes from an insertion rather than a deletion
+fn came_down(furthest: &[usize], diagonal: usize, lowest: usize, highest: usize) -> bool {
+    diagonal == lowest
+        || (diagonal != highest
+            && furthest[diagonal.saturating_sub(1)] < furthest[diagonal.saturating_add(1)])
+}
+
+fn backtrack(trace: &[Vec<usize>], offset: usize, old_len: usize, new_len: usize) -> Vec<DiffLine> {
+    let mut lines = Vec::new();
+    let (mut old_idx, mut new_idx) = (old_len, new_len);
+    for (edits, furthest) in trace.iter().enumerate().skip(1).rev() {
+        let diagonal = old_idx.saturating_add(offset).saturating_sub(new_idx);
+        let lowest = offset.saturating_sub(edits);
+        let highest = offset.saturating_add(edits);
+        let previous = if came_down(furthest, diagonal, lowest, highest) {
+            diagonal.saturating_add(1)
+        } else {
+            diagonal.saturating_sub(1)
+        };
+        let previous_old_idx = furthest[previous];
+        let previous_new_idx = previous_old_idx
+            .saturating_add(offset)
+            .saturating_sub(previous);
+        while old_idx > previous_old_idx && new_idx > previous_new_idx {
+            old_idx = old_idx.saturating_sub(1);
+            new_idx = new_idx.saturating_sub(1);
+            lines.push(DiffLine {
+                change: Change::Kept,
+                old_idx,
+                new_idx,
+            });
+        }
+        if old_idx == previous_old_idx {
+            new_idx = previous_new_idx;
+            lines.push(DiffLine {
+                change: Change::Inserted,
+                old_idx,
+                new_idx,
+            });
+        } else {
+            old_idx = previous_old_idx;
+            lines.push(DiffLine {
+                change: Change::Deleted,
+                old_idx,
+                new_idx,
+            });
+        }
+    }
+    while old_idx > 0 && new_idx > 0 {
+        old_idx = old_idx.saturating_sub(1);
+        new_idx = new_idx.saturating_sub(1);
+        lines.push(DiffLine {
+            change: Change::Kept,
+            old_idx,
+            new_idx,
+        });
+    }
+    lines.reverse();
+    lines
+}
+
+// changes less than twice `CONTEXT` lines apart end up in the same hunk
+fn hunks(changes: &[DiffLine]) -> Vec<Hunk> {
+    let mut hunks = Vec::new();
+    let mut edited = changes
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| line.change != Change::Kept)
+        .map(|(idx, _)| idx)
+        .peekable();
+    while let Some(first) = edited.next() {
+        let mut last = first;
+        while let Some(next) = edited.next_if(|next| {
+            *next
+                <= last
+                    .saturating_add(CONTEXT.saturating_mul(2))
+                    .saturating_add(1)
+        }) {
+            last = next;
+        }
+        let start = first.saturating_sub(CONTEXT);
+        let end = last
+            .saturating_add(CONTEXT)
+            .saturating_add(1)
+            .min(changes.len());
+        hunks.push(Hunk {
+            lines: changes[start..end].to_vec(),
+        });
+    }
+    hunks
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(ToString::to_string).collect()
+    }
+
+    // the hunk as a unified diff would show it
+    fn render(hunk: &Hunk, old: &[String], new: &[String]) -> Vec<String> {
+        let mut rendered = vec![hunk.header()];
+        rendered.extend(hunk.lines.iter().map(|line| match line.change {
+            Change::Kept => format!(" {}", new[line.new_idx]),
+            Change::Deleted => format!("-{}", old[line.old_idx]),
+            Change::Inserted => format!("+{}", new[line.new_idx]),
+        }));
+        rendered
+    }
+
+    #[test]
+    fn same_lines_have_no_hunks() {
+        let old = lines("a\nb\nc\n");
+        assert!(diff(&old, &old).is_empty());
+        assert!(diff(&[], &[]).is_empty());
+    }
+
+    #[test]
+    fn changes_come_with_context() {
+        let old = lines("1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n11\n12\n13\n14\n15\n16\n");
+        let new = lines("1\n2\n3\n4\nfive\n6\n7\n8\n9\n10\n11\n12\n13\n14\n16\nseventeen\n");
+        let hunks = diff(&old, &new);
+        assert_eq!(hunks.len(), 2);
+        assert_eq!(
+            render(&hunks[0], &old, &new),
+            [
+                "@@ -2,7 +2,7 @@",
+                " 2",
+                " 3",
+                " 4",
+                "-5",
+                "+five",
+                " 6",
+                " 7",
+                " 8"
+            ]
+        );
+        assert_eq!(
+            render(&hunks[1], &old, &new),
+            [
+                "@@ -12,5 +12,5 @@",
+                " 12",
+                " 13",
+                " 14",
+                "-15",
+                " 16",
+                "+seventeen"
+            ]
+        );
+        assert_eq!(hunks[0].count(Change::Inserted), 1);
+    }
+
+    #[test]
+    fn changes_close_together_share_a_hunk() {
+        let old = lines("a\nb\nc\nd\ne\nf\ng\nh\n");
+        let new = lines("A\nb\nc\nd\ne\nf\ng\nH\n");
+        assert_eq!(diff(&old, &new).len(), 1);
+        let new = lines("a\nb\nc\nd\ne\nf\ng\nh\ni\n");
+        let hunks = diff(&old, &new);
+        assert_eq!(
+            render(&hunks[0], &old, &new),
+            ["@@ -6,3 +6,4 @@", " f", " g", " h", "+i"]
+        );
+    }
+
+    #[test]
+    fn kept_lines_lead_back_to_the_old_ones() {
+        let old = lines("a\nb\nc\n");
+        let new = lines("b\nx\nc\na\n");
+        assert_eq!(kept_lines(&old, &new), [Some(1), None, Some(2), None]);
+    }
+
+    #[test]
+    fn line_changes_tell_added_modified_and_removed_lines() {
+        use LineChange::{Added, Modified, Removed};
+        let old = lines("a\nb\nc\nd\ne\n");
+        let new = lines("a\nB\nx\nc\ne\n");
+        assert_eq!(
+            line_changes(&old, &new),
+            [None, Some(Modified), Some(Added), None, Some(Removed)]
+        );
+        let new = lines("a\nb\n");
+        assert_eq!(line_changes(&old, &new), [None, Some(Removed)]);
+        assert_eq!(line_changes(&old, &[]), []);
+        assert_eq!(line_changes(&[], &new), [Some(Added), Some(Added)]);
+    }
+
+    #[test]
+    fn completely_different_lines_are_replaced() {
+        let old: Vec<String> = (0..2_000).map(|idx| idx.to_string()).collect();
+        let new: Vec<String> = (0..2_000).map(|idx| format!("new {idx}")).collect();
+        let hunks = diff(&old, &new);
+        assert_eq!(hunks.len(), 1);
+        assert_eq!(hunks[0].count(Change::Deleted), 2_000);
+        assert_eq!(hunks[0].count(Change::Inserted), 2_000);
+        assert_eq!(hunks[0].lines[0].change, Change::Deleted);
+    }
+}
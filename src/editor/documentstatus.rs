@@ -1,9 +1,33 @@
+#[allow(clippy::struct_excessive_bools)]
 #[derive(Default, PartialEq)]
 pub struct DocumentStatus {
     pub total_lines: usize,
     pub current_line_idx: usize,
+    // the grapheme the caret is at, what `go-to-line` takes after a colon
+    pub grapheme_col: usize,
+    // the column the caret is drawn at, past tabs and wide characters
+    pub visual_col: usize,
+    // the columns scrolled past on the left, and those of the caret's line past the right
+    // edge, while lines don't wrap
+    pub cols_before: usize,
+    pub cols_after: usize,
     pub is_modified: bool,
+    pub is_new_file: bool,
+    pub is_read_only: bool,
     pub filename: String,
+    // the whole path, with the home directory as "~"
+    pub path: String,
+    pub buffer_idx: usize,
+    pub buffer_count: usize,
+    pub is_recording: bool,
+    pub pending_count: Option<usize>,
+    // only with modal editing
+    pub mode: Option<&'static str>,
+    // the byte selected while the bytes of a line are inspected
+    pub byte_offset: Option<usize>,
+    pub file_type: String,
+    pub encoding: &'static str,
+    pub line_ending: &'static str,
 }
 
 impl DocumentStatus {
@@ -15,15 +39,79 @@ impl DocumentStatus {
         }
     }
 
-    pub fn line_count_to_string(&self) -> String {
-        format!("{} lines", self.total_lines)
+    pub fn read_only_indicator_to_string(&self) -> String {
+        if self.is_read_only {
+            String::from("[RO]")
+        } else {
+            String::new()
+        }
+    }
+
+    pub fn new_file_indicator_to_string(&self) -> String {
+        if self.is_new_file {
+            String::from("[new file]")
+        } else {
+            String::new()
+        }
+    }
+
+    // only shown if there's more than one buffer open
+    pub fn buffer_indicator_to_string(&self) -> String {
+        if self.buffer_count > 1 {
+            format!(
+                "[{}/{}]",
+                self.buffer_idx.saturating_add(1),
+                self.buffer_count
+            )
+        } else {
+            String::new()
+        }
+    }
+
+    // e.g. "NORMAL 5 recording @" or "BYTE 12", empty unless modal editing is on, a count is
+    // pending, a macro is recording or bytes are inspected
+    pub fn mode_indicator_to_string(&self) -> String {
+        let mode = self.mode.map(String::from);
+        let byte = self.byte_offset.map(|offset| format!("BYTE {offset}"));
+        let count = self.pending_count.map(|count| count.to_string());
+        let recording = self.is_recording.then(|| String::from("recording @"));
+        [mode, byte, count, recording]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    // e.g. "3", or "2-9" like vim when a tab or wide character before the caret draws it
+    // further right than it is characters into the line
+    pub fn column_to_string(&self) -> String {
+        let grapheme_col = self.grapheme_col.saturating_add(1);
+        let visual_col = self.visual_col.saturating_add(1);
+        if grapheme_col == visual_col {
+            grapheme_col.to_string()
+        } else {
+            format!("{grapheme_col}-{visual_col}")
+        }
+    }
+
+    // e.g. "← 12 cols → +38 cols", empty while all of the caret's line is in view
+    pub fn scroll_hint_to_string(&self) -> String {
+        let before = (self.cols_before > 0).then(|| format!("← {} cols", self.cols_before));
+        let after = (self.cols_after > 0).then(|| format!("→ +{} cols", self.cols_after));
+        [before, after]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join(" ")
     }
 
-    pub fn position_indicator_to_string(&self) -> String {
-        format!(
-            "{}:{}",
-            self.current_line_idx.saturating_add(1),
-            self.total_lines,
-        )
+    // how far through the file the caret is
+    pub fn percent(&self) -> usize {
+        self.current_line_idx
+            .saturating_add(1)
+            .saturating_mul(100)
+            .checked_div(self.total_lines)
+            .unwrap_or(0)
+            .min(100)
     }
 }
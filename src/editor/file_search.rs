@@ -0,0 +1,204 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver},
+    },
+    thread,
+};
+
+// bigger files are skipped, they are more likely logs or data than something to edit
+const MAX_FILE_BYTES: u64 = 1 << 20;
+// the search stops once it found this many matches
+const MAX_MATCHES: usize = 5_000;
+
+// a match of the query in `path`, at the byte `byte_idx` of the line `line_idx`
+#[derive(Debug, PartialEq, Eq)]
+pub struct FileMatch {
+    pub path: PathBuf,
+    pub line_idx: usize,
+    pub byte_idx: usize,
+    pub text: String,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Found {
+    Match(FileMatch),
+    // every file was searched, or the search stopped at `MAX_MATCHES` if `capped` is set
+    Done { capped: bool },
+}
+
+// Searches the files below a directory on a thread of its own and reports the matches as
+// they are found. Dropping it stops the search.
+pub struct FileSearch {
+    results: Receiver<Found>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl FileSearch {
+    pub fn start(root: PathBuf, query: String) -> Self {
+        let (sender, results) = mpsc::channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let stopped = Arc::clone(&cancelled);
+        thread::spawn(move || {
+            let is_wanted = || !stopped.load(Ordering::Relaxed);
+            search(&root, &query, is_wanted, |found| sender.send(found).is_ok());
+        });
+        Self { results, cancelled }
+    }
+
+    // The next thing found, waiting for it if `wait` is set. None if there is nothing new,
+    // or the search is done.
+    pub fn next(&self, wait: bool) -> Option<Found> {
+        if wait {
+            self.results.recv().ok()
+        } else {
+            self.results.try_recv().ok()
+        }
+    }
+}
+
+impl Drop for FileSearch {
+    // the thread ends before the next file
+    fn drop(&mut self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+// Reports every match of `query` in the text files below `root`, file by file in the order
+// of their paths, then `Done`. Gives up quietly once nobody wants the results anymore.
+fn search(root: &Path, query: &str, is_wanted: impl Fn() -> bool, report: impl Fn(Found) -> bool) {
+    let mut count: usize = 0;
+    let complete = walk(root, &mut |path| {
+        if !is_wanted() {
+            return false;
+        }
+        for (line_idx, text) in read_text(path).unwrap_or_default().lines().enumerate() {
+            for (byte_idx, _) in text.match_indices(query) {
+                if count >= MAX_MATCHES {
+                    return false;
+                }
+                count = count.saturating_add(1);
+                let found = Found::Match(FileMatch {
+                    path: path.to_path_buf(),
+                    line_idx,
+                    byte_idx,
+                    text: text.to_string(),
+                });
+                if !report(found) {
+                    return false;
+                }
+            }
+        }
+        true
+    });
+    if complete || (count >= MAX_MATCHES && is_wanted()) {
+        report(Found::Done { capped: !complete });
+    }
+}
+
+// Visits the files below `dir` in the order of their names, leaving out hidden ones like
+// `.git` and not following symlinks, so a link to a parent can't lead around in circles.
+// False if `visit` asked to stop.
+fn walk(dir: &Path, visit: &mut impl FnMut(&Path) -> bool) -> bool {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return true;
+    };
+    let mut entries: Vec<_> = entries
+        .filter_map(Result::ok)
+        .filter(|entry| !entry.file_name().to_string_lossy().starts_with('.'))
+        .filter_map(|entry| Some((entry.path(), entry.file_type().ok()?)))
+        .collect();
+    entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+    for (path, file_type) in entries {
+        let keep_going = if file_type.is_dir() {
+            walk(&path, visit)
+        } else if file_type.is_file() {
+            visit(&path)
+        } else {
+            true
+        };
+        if !keep_going {
+            return false;
+        }
+    }
+    true
+}
+
+// the text of a file, None if it is too big, binary or not UTF-8
+fn read_text(path: &Path) -> Option<String> {
+    if fs::metadata(path).ok()?.len() > MAX_FILE_BYTES {
+        return None;
+    }
+    let bytes = fs::read(path).ok()?;
+    if bytes.contains(&0) {
+        return None;
+    }
+    String::from_utf8(bytes).ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::{cell::RefCell, env};
+
+    fn found(root: &Path, query: &str) -> Vec<Found> {
+        let found = RefCell::new(Vec::new());
+        search(
+            root,
+            query,
+            || true,
+            |next| {
+                found.borrow_mut().push(next);
+                true
+            },
+        );
+        found.into_inner()
+    }
+
+    #[test]
+    fn searches_text_files_in_order_of_their_paths() {
+        let root = env::temp_dir().join("hecto-file-search");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::write(root.join("b.txt"), "one needle\r\nneedle, needle\n").unwrap();
+        fs::write(root.join("src").join("a.txt"), "a needle\n").unwrap();
+        fs::write(root.join(".git").join("config"), "needle\n").unwrap();
+        fs::write(root.join("binary"), b"needle\0").unwrap();
+        let big = format!(
+            "needle\n{}",
+            "x".repeat(usize::try_from(MAX_FILE_BYTES).unwrap())
+        );
+        fs::write(root.join("big.log"), big).unwrap();
+
+        let at = |path: PathBuf, line_idx, byte_idx, text: &str| {
+            Found::Match(FileMatch {
+                path,
+                line_idx,
+                byte_idx,
+                text: text.to_string(),
+            })
+        };
+        assert_eq!(
+            found(&root, "needle"),
+            [
+                at(root.join("b.txt"), 0, 4, "one needle"),
+                at(root.join("b.txt"), 1, 0, "needle, needle"),
+                at(root.join("b.txt"), 1, 8, "needle, needle"),
+                at(root.join("src").join("a.txt"), 0, 2, "a needle"),
+                Found::Done { capped: false },
+            ]
+        );
+        assert_eq!(found(&root, "thread"), [Found::Done { capped: false }]);
+
+        let many = "needle ".repeat(MAX_MATCHES);
+        fs::write(root.join("many.txt"), many).unwrap();
+        let found = found(&root, "needle");
+        assert_eq!(found.len(), MAX_MATCHES.saturating_add(1));
+        assert_eq!(found.last(), Some(&Found::Done { capped: true }));
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,157 @@
+use std::{
+    ffi::OsString,
+    io::Read,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+    time::{Duration, Instant},
+};
+
+// git is given up on after this long, e.g. on a slow network drive or waiting for a lock
+const TIMEOUT: Duration = Duration::from_secs(2);
+// how often a running git is looked at meanwhile
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+// what git read of a file at HEAD
+#[derive(Debug, PartialEq, Eq)]
+pub enum Head {
+    Bytes(Vec<u8>),
+    // see `show_head` for why there may be nothing to compare with
+    Missing,
+}
+
+// The bytes of a file as checked in at HEAD, which git reads on a thread of its own so the
+// editor doesn't wait for it.
+pub struct HeadRequest {
+    bytes: Receiver<Option<Vec<u8>>>,
+}
+
+impl HeadRequest {
+    pub fn start(path: &Path) -> Self {
+        let (sender, bytes) = mpsc::channel();
+        let path = path.to_path_buf();
+        thread::spawn(move || {
+            // nobody may be waiting anymore
+            let _ = sender.send(show_head(&path, TIMEOUT));
+        });
+        Self { bytes }
+    }
+
+    // None while git is still running, unless `wait` is set, then what it read
+    pub fn next(&self, wait: bool) -> Option<Head> {
+        let received = if wait {
+            self.bytes.recv().map_err(|_| TryRecvError::Disconnected)
+        } else {
+            self.bytes.try_recv()
+        };
+        match received {
+            Ok(Some(bytes)) => Some(Head::Bytes(bytes)),
+            Err(TryRecvError::Empty) => None,
+            Ok(None) | Err(TryRecvError::Disconnected) => Some(Head::Missing),
+        }
+    }
+}
+
+// The file at `path` as checked in at HEAD. None outside a work tree, for a file which isn't
+// checked in, without git, or if git takes longer than `timeout`.
+fn show_head(path: &Path, timeout: Duration) -> Option<Vec<u8>> {
+    let dir = path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .map_or_else(|| PathBuf::from("."), Path::to_path_buf);
+    // relative to `dir`, wherever in the work tree that is
+    let mut object = OsString::from("HEAD:./");
+    object.push(path.file_name()?);
+    let mut child = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .arg("show")
+        .arg(object)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+    // read meanwhile, so git can't block on a full pipe
+    let mut stdout = child.stdout.take()?;
+    let reader = thread::spawn(move || {
+        let mut bytes = Vec::new();
+        stdout.read_to_end(&mut bytes).map(|_| bytes)
+    });
+    let deadline = Instant::now().checked_add(timeout)?;
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) if status.success() => return reader.join().ok()?.ok(),
+            Ok(None) if Instant::now() < deadline => thread::sleep(POLL_INTERVAL),
+            Ok(Some(_)) => return None,
+            _ => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+    use std::{env, fs};
+
+    // run git in `dir`, without any config of the user's
+    pub fn git(dir: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args([
+                "-c",
+                "user.name=hecto",
+                "-c",
+                "user.email=hecto@example.com",
+            ])
+            .args(args)
+            .env("GIT_CONFIG_GLOBAL", "/dev/null")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .unwrap();
+        assert!(status.success());
+    }
+
+    // a new repository at `dir` with `files` checked in
+    pub fn repository(dir: &Path, files: &[(&str, &str)]) {
+        let _ = fs::remove_dir_all(dir);
+        fs::create_dir_all(dir).unwrap();
+        git(dir, &["init", "--quiet"]);
+        for (name, text) in files {
+            let path = dir.join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, text).unwrap();
+        }
+        git(dir, &["add", "."]);
+        git(dir, &["commit", "--quiet", "-m", "files"]);
+    }
+
+    #[test]
+    fn shows_files_as_checked_in() {
+        let dir = env::temp_dir().join("hecto-git-show");
+        repository(&dir, &[("a.txt", "one\n"), ("sub/b.txt", "two\n")]);
+        fs::write(dir.join("a.txt"), "changed\n").unwrap();
+        fs::write(dir.join("new.txt"), "new\n").unwrap();
+
+        assert_eq!(
+            show_head(&dir.join("a.txt"), TIMEOUT),
+            Some(b"one\n".to_vec())
+        );
+        let request = HeadRequest::start(&dir.join("sub").join("b.txt"));
+        assert_eq!(request.next(true), Some(Head::Bytes(b"two\n".to_vec())));
+        assert_eq!(show_head(&dir.join("new.txt"), TIMEOUT), None);
+        assert_eq!(
+            show_head(&env::temp_dir().join("hecto-no-repo"), TIMEOUT),
+            None
+        );
+        assert_eq!(show_head(&dir.join("a.txt"), Duration::ZERO), None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,68 @@
+use super::command::{
+    Command::{self, System},
+    System::{PlayMacro, RecordMacro},
+};
+use crossterm::event::{Event, KeyEvent};
+
+// Keys recorded before they are translated, so prompt input like a search query and keys
+// which mean something else in another mode are replayed as well.
+#[derive(Default)]
+pub struct KeyboardMacro {
+    recording: Option<Vec<KeyEvent>>,
+    recorded: Vec<KeyEvent>,
+}
+
+impl KeyboardMacro {
+    pub const fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    pub fn start(&mut self) {
+        self.recording = Some(Vec::new());
+    }
+
+    // keep what was recorded for playback, returning how many keys that was
+    pub fn stop(&mut self) -> usize {
+        if let Some(recording) = self.recording.take() {
+            self.recorded = recording;
+        }
+        self.recorded.len()
+    }
+
+    pub fn record(&mut self, key: KeyEvent) {
+        if let Some(recording) = &mut self.recording
+            && !matches!(
+                Command::try_from(Event::Key(key)),
+                Ok(System(RecordMacro | PlayMacro))
+            )
+        {
+            recording.push(key);
+        }
+    }
+
+    pub fn recorded(&self) -> Vec<KeyEvent> {
+        self.recorded.clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crossterm::event::{KeyCode, KeyModifiers};
+
+    #[test]
+    fn records_until_stopped() {
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        let mut keyboard_macro = KeyboardMacro::default();
+        keyboard_macro.record(key(KeyCode::Char('a')));
+        keyboard_macro.start();
+        keyboard_macro.record(key(KeyCode::Char('b')));
+        keyboard_macro.record(KeyEvent::new(KeyCode::Char('m'), KeyModifiers::ALT));
+        assert_eq!(keyboard_macro.stop(), 1);
+        assert!(!keyboard_macro.is_recording());
+        assert_eq!(keyboard_macro.recorded(), [key(KeyCode::Char('b'))]);
+
+        // stopping again keeps the last recording
+        assert_eq!(keyboard_macro.stop(), 1);
+    }
+}
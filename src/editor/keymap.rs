@@ -0,0 +1,201 @@
+use super::{palette::NamedCommand, script::key_code};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::collections::BTreeMap;
+
+// a key without the details of how it was pressed
+pub type Key = (KeyCode, KeyModifiers);
+
+// chords bound without any config
+const DEFAULT_BINDINGS: [(&str, &str); 24] = [
+    ("ctrl+k s", "save-all"),
+    ("ctrl+k ctrl+s", "save-all"),
+    ("ctrl+k w", "close-buffer"),
+    ("ctrl+k ctrl+w", "close-buffer"),
+    ("ctrl+k b", "list-buffers"),
+    ("ctrl+k !", "insert-command-output"),
+    ("ctrl+k |", "filter-buffer"),
+    ("ctrl+k t", "transpose-characters"),
+    ("ctrl+k ctrl+t", "transpose-characters"),
+    ("ctrl+k u", "insert-unicode"),
+    ("ctrl+k i", "describe-character"),
+    ("ctrl+k l", "list-matches"),
+    ("ctrl+k f", "search-files"),
+    ("ctrl+k d", "diff-saved"),
+    ("ctrl+k x", "inspect-bytes"),
+    ("ctrl+k n", "next-edited-line"),
+    ("ctrl+k p", "previous-edited-line"),
+    ("ctrl+k ]", "next-mark"),
+    ("ctrl+k [", "previous-mark"),
+    ("ctrl+k .", "next-hunk"),
+    ("ctrl+k ,", "previous-hunk"),
+    ("ctrl+k z", "center-caret"),
+    ("ctrl+k up", "scroll-caret-to-top"),
+    ("ctrl+k down", "scroll-caret-to-bottom"),
+];
+
+pub fn key(event: KeyEvent) -> Key {
+    // an uppercase letter already says it was shifted
+    let modifiers = match event.code {
+        KeyCode::Char(_) => event.modifiers.difference(KeyModifiers::SHIFT),
+        _ => event.modifiers,
+    };
+    (event.code, modifiers)
+}
+
+// e.g. "C-k s", like keys are written in scripts
+pub fn describe(keys: &[Key]) -> String {
+    keys.iter()
+        .map(|(code, modifiers)| {
+            let ctrl = if modifiers.contains(KeyModifiers::CONTROL) {
+                "C-"
+            } else {
+                ""
+            };
+            let alt = if modifiers.contains(KeyModifiers::ALT) {
+                "A-"
+            } else {
+                ""
+            };
+            match code {
+                KeyCode::Char(ch) => format!("{ctrl}{alt}{ch}"),
+                _ => format!("{ctrl}{alt}{code:?}"),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// a chord like "ctrl+k ctrl+s": keys separated by spaces, each with its modifiers
+fn parse_chord(chord: &str) -> Result<Vec<Key>, String> {
+    let unknown = || format!("unknown key '{chord}'");
+    let keys: Vec<Key> = chord
+        .split_whitespace()
+        .map(|part| {
+            let (modifier_names, name) = part.rsplit_once('+').unwrap_or(("", part));
+            let mut modifiers = KeyModifiers::NONE;
+            for modifier in modifier_names.split('+').filter(|name| !name.is_empty()) {
+                modifiers |= match modifier.to_ascii_lowercase().as_str() {
+                    "ctrl" => KeyModifiers::CONTROL,
+                    "alt" => KeyModifiers::ALT,
+                    "shift" => KeyModifiers::SHIFT,
+                    _ => return Err(unknown()),
+                };
+            }
+            let code = key_code(name).ok_or_else(unknown)?;
+            Ok(key(KeyEvent::new(code, modifiers)))
+        })
+        .collect::<Result<_, _>>()?;
+    if keys.is_empty() {
+        return Err(unknown());
+    }
+    Ok(keys)
+}
+
+pub enum Lookup<'a> {
+    Command(&'a str),
+    // more keys have to follow
+    Prefix,
+    Unbound,
+}
+
+// Named commands bound to keys, chords of several keys included. These are looked at
+// before the built-in keys, so they can replace them.
+pub struct Keymap {
+    bindings: Vec<(Vec<Key>, String)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let bindings = DEFAULT_BINDINGS
+            .iter()
+            .filter_map(|(chord, name)| Some((parse_chord(chord).ok()?, (*name).to_string())))
+            .collect();
+        Self { bindings }
+    }
+}
+
+impl Keymap {
+    // the default chords plus the `[keys]` of the config, which win over them
+    pub fn new(keys: &BTreeMap<String, String>, commands: &[NamedCommand]) -> Result<Self, String> {
+        let mut keymap = Self::default();
+        for (chord, name) in keys {
+            let chord = parse_chord(chord)?;
+            if !commands.iter().any(|command| command.name == name) {
+                return Err(format!("unknown command '{name}'"));
+            }
+            keymap.bindings.retain(|(keys, _)| *keys != chord);
+            keymap.bindings.push((chord, name.clone()));
+        }
+        Ok(keymap)
+    }
+
+    pub fn lookup(&self, keys: &[Key]) -> Lookup<'_> {
+        if let Some((_, name)) = self.bindings.iter().find(|(chord, _)| chord == keys) {
+            Lookup::Command(name)
+        } else if self
+            .bindings
+            .iter()
+            .any(|(chord, _)| chord.starts_with(keys))
+        {
+            Lookup::Prefix
+        } else {
+            Lookup::Unbound
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::editor::palette::registry;
+
+    #[test]
+    fn chords_need_every_key() {
+        let ctrl_k = (KeyCode::Char('k'), KeyModifiers::CONTROL);
+        let keymap = Keymap::default();
+        assert!(matches!(keymap.lookup(&[ctrl_k]), Lookup::Prefix));
+        let shifted_s = key(KeyEvent::new(KeyCode::Char('s'), KeyModifiers::SHIFT));
+        assert!(matches!(
+            keymap.lookup(&[ctrl_k, shifted_s]),
+            Lookup::Command("save-all")
+        ));
+        assert!(matches!(
+            keymap.lookup(&[(KeyCode::Char('k'), KeyModifiers::NONE)]),
+            Lookup::Unbound
+        ));
+        assert_eq!(
+            describe(&[ctrl_k, (KeyCode::Enter, KeyModifiers::NONE)]),
+            "C-k Enter"
+        );
+    }
+
+    #[test]
+    fn config_adds_and_replaces_bindings() {
+        let commands = registry();
+        let keys = BTreeMap::from([
+            (String::from("ctrl+k s"), String::from("split")),
+            (String::from("alt+g"), String::from("go-to-line")),
+        ]);
+        let keymap = Keymap::new(&keys, &commands).unwrap();
+        let ctrl_k = (KeyCode::Char('k'), KeyModifiers::CONTROL);
+        assert!(matches!(
+            keymap.lookup(&[ctrl_k, (KeyCode::Char('s'), KeyModifiers::NONE)]),
+            Lookup::Command("split")
+        ));
+        assert!(matches!(
+            keymap.lookup(&[(KeyCode::Char('g'), KeyModifiers::ALT)]),
+            Lookup::Command("go-to-line")
+        ));
+
+        let keys = BTreeMap::from([(String::from("ctrl+k s"), String::from("nothing"))]);
+        assert_eq!(
+            Keymap::new(&keys, &commands).err(),
+            Some(String::from("unknown command 'nothing'"))
+        );
+        let keys = BTreeMap::from([(String::from("hyper+k"), String::from("save"))]);
+        assert_eq!(
+            Keymap::new(&keys, &commands).err(),
+            Some(String::from("unknown key 'hyper+k'"))
+        );
+    }
+}
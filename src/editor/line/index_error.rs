@@ -0,0 +1,21 @@
+use super::GraphemeIdx;
+use std::fmt;
+
+/// A grapheme index pointing behind the end of a [`Line`](crate::Line).
+#[derive(Debug, PartialEq, Eq)]
+pub struct IndexError {
+    pub grapheme_idx: GraphemeIdx,
+    pub grapheme_count: GraphemeIdx,
+}
+
+impl fmt::Display for IndexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "grapheme index {} is out of range for a line of {} graphemes",
+            self.grapheme_idx, self.grapheme_count
+        )
+    }
+}
+
+impl std::error::Error for IndexError {}
@@ -0,0 +1,20 @@
+use super::ByteIdx;
+use std::fmt;
+
+/// A line break in the text given for a single [`Line`](crate::Line).
+#[derive(Debug, PartialEq, Eq)]
+pub struct LineBreakError {
+    pub byte_idx: ByteIdx,
+}
+
+impl fmt::Display for LineBreakError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "a line break at byte {} where a single line was expected",
+            self.byte_idx
+        )
+    }
+}
+
+impl std::error::Error for LineBreakError {}
@@ -1,12 +1,22 @@
 mod grapheme_width;
+mod index_error;
+mod line_break_error;
+#[cfg(test)]
+mod properties;
 mod text_fragment;
 
 use grapheme_width::GraphemeWidth;
+pub use index_error::IndexError;
+pub use line_break_error::LineBreakError;
+use serde::Deserialize;
 use std::{
+    borrow::Cow,
+    cell::OnceCell,
     fmt,
     ops::{Deref, Range},
+    sync::atomic::{AtomicU64, Ordering},
 };
-use text_fragment::TextFragment;
+pub use text_fragment::TextFragment;
 use unicode_segmentation::UnicodeSegmentation;
 use unicode_width::UnicodeWidthStr;
 
@@ -17,59 +27,224 @@ use super::annotated_string::AnnotatedString;
 type GraphemeIdx = usize;
 type ByteIdx = usize;
 type ColIdx = usize;
+pub type Revision = u64;
+
+// how many columns apart tab stops are unless configured otherwise
+pub const DEFAULT_TAB_WIDTH: ColIdx = 8;
+
+// What replaces graphemes, padded with spaces, so drawing can borrow the replacement of
+// all but the widest ones. Each starts with a single character.
+const SPACES: &str = "                                ";
+const SHOWN_TAB: &str = "→                               ";
+const CLIPPED: &str = "⋯                               ";
+
+// every change to any line gets a new revision, so views can tell whether a row is stale
+fn next_revision() -> Revision {
+    static NEXT_REVISION: AtomicU64 = AtomicU64::new(1);
+    NEXT_REVISION.fetch_add(1, Ordering::Relaxed)
+}
+
+/// How wide characters of ambiguous width are, like box drawing, arrows and degree signs. Most
+/// terminals draw them in one column, those set up for Chinese, Japanese or Korean in two.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AmbiguousWidth {
+    #[default]
+    Narrow,
+    Wide,
+}
+
+/// How many columns the terminal takes for what a line holds.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CellWidths {
+    /// The columns between tab stops, 0 for lines that were never given one, which is
+    /// treated like 1.
+    pub tab: ColIdx,
+    pub ambiguous: AmbiguousWidth,
+}
+
+impl CellWidths {
+    pub const DEFAULT: Self = Self {
+        tab: DEFAULT_TAB_WIDTH,
+        ambiguous: AmbiguousWidth::Narrow,
+    };
+}
+
+/// How spaces, tabs and other characters which can't be seen are rendered.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Invisibles {
+    /// Odd whitespace as ␣, control characters as ▯, the rest as it is.
+    #[default]
+    Marked,
+    /// Additionally spaces as ·, tabs as → and the end of the line as ¶.
+    Shown,
+    /// Nothing marked, all of it blank.
+    Hidden,
+}
+
+/// What is rendered besides the text itself.
+#[derive(Clone, Copy, Default)]
+pub struct RenderOptions<'a> {
+    pub invisibles: Invisibles,
+    /// Whole words highlighted like the one under the caret.
+    pub word: Option<&'a str>,
+    /// Where the matches of the query start, if already known, instead of searching for them.
+    pub matches: Option<&'a [GraphemeIdx]>,
+    /// The byte ranges of the words the spell checker doesn't know.
+    pub misspelled: Option<&'a [Range<ByteIdx>]>,
+    /// The byte ranges highlighted by the syntax of the file, like the headings of Markdown.
+    pub highlights: Option<&'a [(AnnotationType, Range<ByteIdx>)]>,
+    /// Whether markers of more of the line go next to the start and the end of the visible
+    /// range. A grapheme cut off at an edge with a marker is blank instead of an ellipsis.
+    pub scroll_markers: (bool, bool),
+}
 
+// words are runs of graphemes of the same class
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum CharClass {
+    Space,
+    Word,
+    Punctuation,
+}
+
+/// A line of text without its line break, split into graphemes which know the columns they
+/// take on the terminal.
+///
+/// ```
+/// use hecto::Line;
+///
+/// let mut line = Line::try_from("老虎 ok")?;
+/// assert_eq!((line.grapheme_count(), line.width()), (5, 7));
+/// assert_eq!(line.col_to_grapheme_idx(3), 1);
+/// let tail = line.split(2);
+/// assert_eq!((line.to_string(), tail.to_string()), ("老虎".into(), " ok".into()));
+/// # Ok::<(), hecto::LineBreakError>(())
+/// ```
 #[derive(Default)]
 pub struct Line {
     string: String,
-    fragments: Vec<TextFragment>,
+    // built when first needed, most lines of a big file are never shown or edited
+    fragments: OnceCell<Vec<TextFragment>>,
+    revision: Revision,
+    widths: CellWidths,
+    // edited since the buffer was last saved
+    edited: bool,
 }
 
 impl Line {
     // build a `Line` from a string without \n
-    pub fn from(line_str: &str) -> Self {
+    pub(crate) fn from(line_str: &str) -> Self {
+        Self::with_widths(line_str, CellWidths::DEFAULT)
+    }
+
+    pub(crate) fn with_widths(line_str: &str, widths: CellWidths) -> Self {
         debug_assert!(line_str.is_empty() || line_str.lines().count() == 1);
-        let source = line_str.to_string();
-        let fragments = Self::str_to_fragments(line_str);
         Self {
-            string: source,
-            fragments,
+            string: line_str.to_string(),
+            fragments: OnceCell::new(),
+            revision: next_revision(),
+            widths,
+            edited: false,
         }
     }
 
-    fn str_to_fragments(line_str: &str) -> Vec<TextFragment> {
-        let grapheme_to_fragment = |(byte_idx, grapheme): (ByteIdx, &str)| {
-            let (replacement, rendered_width) = Self::get_replacement_character(grapheme)
-                .map_or_else(
-                    || {
-                        let unicode_width = grapheme.width();
-                        let rendered_width = match unicode_width {
-                            0 | 1 => GraphemeWidth::Half,
-                            _ => GraphemeWidth::Full,
-                        };
-                        (None, rendered_width)
-                    },
-                    |replacement| (Some(replacement), GraphemeWidth::Half),
-                );
+    /// Tabs reach the next multiple of `widths.tab` columns, so their width depends on what
+    /// comes before them.
+    pub fn set_widths(&mut self, widths: CellWidths) {
+        if widths != self.widths {
+            self.widths = widths;
+            self.fragments = OnceCell::new();
+            self.revision = next_revision();
+        }
+    }
 
-            TextFragment {
-                start_byte_idx: byte_idx,
-                grapheme: grapheme.to_string(),
-                rendered_width,
-                replacement,
-            }
-        };
+    // whether the graphemes of the line were looked at yet
+    #[cfg(test)]
+    pub(crate) fn has_fragments(&self) -> bool {
+        self.fragments.get().is_some()
+    }
+
+    fn fragments(&self) -> &[TextFragment] {
+        self.fragments
+            .get_or_init(|| Self::str_to_fragments(&self.string, self.widths))
+    }
 
+    fn str_to_fragments(line_str: &str, widths: CellWidths) -> Vec<TextFragment> {
+        let mut col_idx = 0;
         line_str
             .grapheme_indices(true)
-            .map(grapheme_to_fragment)
+            .map(|(byte_idx, grapheme)| {
+                let fragment = Self::grapheme_to_fragment(byte_idx, col_idx, grapheme, widths);
+                col_idx = fragment.end_col_idx();
+                fragment
+            })
             .collect()
     }
 
+    fn grapheme_to_fragment(
+        byte_idx: ByteIdx,
+        col_idx: ColIdx,
+        grapheme: &str,
+        widths: CellWidths,
+    ) -> TextFragment {
+        if grapheme == "\t" {
+            return TextFragment {
+                start_byte_idx: byte_idx,
+                start_col_idx: col_idx,
+                grapheme: grapheme.to_string(),
+                rendered_width: Self::tab_at(col_idx, widths.tab),
+                replacement: Some(' '),
+            };
+        }
+
+        let (replacement, rendered_width) = Self::get_replacement_character(grapheme).map_or_else(
+            || {
+                let unicode_width = match widths.ambiguous {
+                    AmbiguousWidth::Narrow => grapheme.width(),
+                    AmbiguousWidth::Wide => grapheme.width_cjk(),
+                };
+                let rendered_width = match unicode_width {
+                    0 | 1 => GraphemeWidth::Half,
+                    _ => GraphemeWidth::Full,
+                };
+                (None, rendered_width)
+            },
+            |replacement| (Some(replacement), GraphemeWidth::Half),
+        );
+
+        TextFragment {
+            start_byte_idx: byte_idx,
+            start_col_idx: col_idx,
+            grapheme: grapheme.to_string(),
+            rendered_width,
+            replacement,
+        }
+    }
+
+    // a tab starting at `col_idx` reaches the next tab stop
+    fn tab_at(col_idx: ColIdx, tab_width: ColIdx) -> GraphemeWidth {
+        let tab_width = tab_width.max(1);
+        let past_stop = col_idx.checked_rem(tab_width).unwrap_or(0);
+        GraphemeWidth::Tab(tab_width.saturating_sub(past_stop))
+    }
+
+    // Lay out `fragments` one after the other from `col_idx` on, measuring their tabs again
+    // at their new columns.
+    fn place(fragments: &mut [TextFragment], mut col_idx: ColIdx, tab_width: ColIdx) {
+        for fragment in fragments {
+            fragment.start_col_idx = col_idx;
+            if let GraphemeWidth::Tab(_) = fragment.rendered_width {
+                fragment.rendered_width = Self::tab_at(col_idx, tab_width);
+            }
+            col_idx = fragment.end_col_idx();
+        }
+    }
+
     fn get_replacement_character(for_str: &str) -> Option<char> {
         let width = for_str.width();
         match for_str {
             " " => None,
-            "\t" => Some(' '),
             _ if for_str.chars().all(char::is_control) => Some('▯'),
             _ if width > 0 && for_str.trim().is_empty() => Some('␣'),
             _ if width == 0 => Some('·'),
@@ -77,199 +252,652 @@ impl Line {
         }
     }
 
-    // Gets the visible graphemes in the given column index.
-    // The column index is not the same as the grapheme index:
-    // A grapheme can have a width of 2 columns.
+    /// The text drawn in the columns of `range`, which are not the same as grapheme indices:
+    /// a grapheme can be 2 columns wide. One cut by either end of the range is drawn as `⋯`.
+    ///
+    /// ```
+    /// let line = hecto::Line::try_from("a老虎")?;
+    /// assert_eq!(line.get_visible_graphemes(1..3), "老");
+    /// assert_eq!(line.get_visible_graphemes(2..5), "⋯虎");
+    /// # Ok::<(), hecto::LineBreakError>(())
+    /// ```
+    #[must_use]
     pub fn get_visible_graphemes(&self, range: Range<ColIdx>) -> String {
-        self.get_annotated_visible_substr(range, None, None)
+        self.get_annotated_visible_substr(range, None, None, RenderOptions::default())
             .to_string()
     }
 
-    // Gets the annotated string in the given column index.
-    // The column index is not the same as the grapheme index:
-    // A grapheme can have a width of 2 columns.
-    // Parameters:
-    // - range: The range of columns to get the annotated string from.
-    // - query: The query to highlight in the annotated string.
-    // - selected_match: The selected match to highlight in the annotated string. This is only applied if the query is not empty.
+    /// Like [`Line::get_visible_graphemes`], annotated with digits, the matches of `query`,
+    /// the one starting at the grapheme `selected_match` and what `options` asks for. A range
+    /// ending before it starts is empty.
+    ///
+    /// ```
+    /// use hecto::{AnnotationType, Line, RenderOptions};
+    ///
+    /// let line = Line::try_from("x = 42; x")?;
+    /// let row = line.get_annotated_visible_substr(2..9, Some("x"), Some(8), RenderOptions::default());
+    /// let parts: Vec<_> = row.into_iter().map(|part| (part.string, part.typ)).collect();
+    /// assert_eq!(
+    ///     parts,
+    ///     [
+    ///         ("= ", None),
+    ///         ("4", Some(AnnotationType::Digit)),
+    ///         ("2", Some(AnnotationType::Digit)),
+    ///         ("; ", None),
+    ///         ("x", Some(AnnotationType::SelectedMatch)),
+    ///     ]
+    /// );
+    /// # Ok::<(), hecto::LineBreakError>(())
+    /// ```
+    #[must_use]
     pub fn get_annotated_visible_substr(
         &self,
         range: Range<ColIdx>,
         query: Option<&str>,
         selected_match: Option<GraphemeIdx>,
+        options: RenderOptions,
     ) -> AnnotatedString {
-        debug_assert!(range.start <= range.end);
+        let mut result = AnnotatedString::default();
+        self.annotate_visible_substr(&mut result, range, query, selected_match, options);
+        result
+    }
+
+    // like `get_annotated_visible_substr`, but into `result`, whose memory is reused
+    pub(crate) fn annotate_visible_substr(
+        &self,
+        result: &mut AnnotatedString,
+        range: Range<ColIdx>,
+        query: Option<&str>,
+        selected_match: Option<GraphemeIdx>,
+        options: RenderOptions,
+    ) {
+        let range = range.start..range.end.max(range.start);
 
-        let mut result = AnnotatedString::from(&self.string);
+        // Only the graphemes which are at least partly visible are looked at, so a long
+        // line costs no more than a short one. The fragments before the first one end left
+        // of the range, those from `visible_end` on start right of it.
+        let first = self.col_to_grapheme_idx(range.start);
+        let visible_end = self
+            .col_to_grapheme_idx(range.end)
+            .saturating_add(1)
+            .min(self.grapheme_count())
+            .max(first);
+        let bytes = self
+            .grapheme_idx_to_byte_idx(first)
+            .unwrap_or(self.string.len())
+            ..self
+                .grapheme_idx_to_byte_idx(visible_end)
+                .unwrap_or(self.string.len());
+        result.reset(&self.string[bytes.clone()]);
 
         // highlight digits
-        self.string
-            .chars()
-            .enumerate()
-            .for_each(|(grapheme_idx, ch)| {
-                if ch.is_ascii_digit() {
-                    let start_byte_idx = self.grapheme_idx_to_byte_idx(grapheme_idx);
-                    result.add_annotation(
-                        AnnotationType::Digit,
-                        start_byte_idx,
-                        start_byte_idx.saturating_add(1),
-                    );
-                }
+        self.string[bytes.clone()]
+            .char_indices()
+            .filter(|(_, ch)| ch.is_ascii_digit())
+            .for_each(|(start_byte_idx, _)| {
+                result.add_annotation(
+                    AnnotationType::Digit,
+                    start_byte_idx,
+                    start_byte_idx.saturating_add(1),
+                );
             });
 
-        // highlight searching matches
+        // highlight the syntax of the file, which most other annotations show over
+        for (typ, highlight) in options.highlights.unwrap_or_default() {
+            Self::add_visible_annotation(result, &bytes, *typ, highlight.clone());
+        }
+
+        // underline misspelled words, unless they are highlighted otherwise
+        for word in options.misspelled.unwrap_or_default() {
+            Self::add_visible_annotation(result, &bytes, AnnotationType::SpellError, word.clone());
+        }
+
+        // highlight the word under the caret
+        if let Some(word) = options.word {
+            self.annotate_word(result, &range, &bytes, word);
+        }
+
+        // highlight searching matches, including those reaching in from either side
         if let Some(query) = query
             && !query.is_empty()
         {
-            self.find_all(query, 0..self.string.len()).iter().for_each(
-                |(start_byte_idx, grapheme_idx)| {
-                    if let Some(selected_match) = selected_match
-                        && *grapheme_idx == selected_match
-                    {
-                        result.add_annotation(
-                            AnnotationType::SelectedMatch,
-                            *start_byte_idx,
-                            start_byte_idx.saturating_add(query.len()),
-                        );
-                        return;
-                    }
-
-                    result.add_annotation(
-                        AnnotationType::Match,
-                        *start_byte_idx,
-                        start_byte_idx.saturating_add(query.len()),
-                    );
-                },
-            );
+            for (start_byte_idx, grapheme_idx) in
+                self.matches_reaching_into(query, &bytes, options.matches)
+            {
+                let typ = if selected_match == Some(grapheme_idx) {
+                    AnnotationType::SelectedMatch
+                } else {
+                    AnnotationType::Match
+                };
+                Self::add_visible_annotation(
+                    result,
+                    &bytes,
+                    typ,
+                    start_byte_idx..start_byte_idx.saturating_add(query.len()),
+                );
+            }
         }
 
         // Insert replacement characters, and truncate if needed.
         // We do this backwards, otherwise the byte indices would be off in case a replacement character has a different width than the original character.
 
-        let mut fragment_start = self.width();
-        for fragment in self.fragments.iter().rev() {
-            let fragment_end = fragment_start;
-            fragment_start = fragment_start.saturating_sub(fragment.rendered_width.into());
+        for fragment in self.fragments()[first..visible_end].iter().rev() {
+            let fragment_start = fragment.start_col_idx;
+            let fragment_end = fragment.end_col_idx();
+            let start_byte_idx = fragment.start_byte_idx.saturating_sub(bytes.start);
+            let end_byte_idx = start_byte_idx.saturating_add(fragment.grapheme.len());
 
-            if fragment_start > range.end {
-                continue; // No  processing needed if we haven't reached the visible range yet.
-            }
-
-            // clip right if the fragment is partially visible
+            // clip right if the fragment is partially visible, it may reach out on the left
+            // as well
             if fragment_start < range.end && fragment_end > range.end {
-                result.replace(fragment.start_byte_idx, self.string.len(), "⋯");
+                let visible = range.end.saturating_sub(fragment_start.max(range.start));
+                let clipped = Self::clipped(visible, options.scroll_markers.1);
+                result.replace(start_byte_idx, end_byte_idx, &clipped);
                 continue;
             } else if fragment_start == range.end {
                 // Truncate right if we've reached the end of the visible range
-                result.replace(fragment.start_byte_idx, self.string.len(), "");
+                result.replace(start_byte_idx, end_byte_idx, "");
                 continue;
             }
 
-            // Fragment ends at the start of the range: Remove the entire left side of the string (if not already at start of string)
-            if fragment_end <= range.start {
-                result.replace(
-                    0,
-                    fragment
-                        .start_byte_idx
-                        .saturating_add(fragment.grapheme.len()),
-                    "",
-                );
-                break; //End processing since all remaining fragments will be invisible.
-            } else if fragment_start < range.start && fragment_end > range.start {
-                // Fragment overlaps with the start of range: Remove the left side of the string and add an ellipsis
+            // Fragment overlaps with the start of range: replace it with an ellipsis. It is
+            // the leftmost one, so nothing is left to process.
+            if fragment_start < range.start {
                 result.replace(
                     0,
-                    fragment
-                        .start_byte_idx
-                        .saturating_add(fragment.grapheme.len()),
-                    "⋯",
+                    end_byte_idx,
+                    &Self::clipped(
+                        fragment_end.saturating_sub(range.start),
+                        options.scroll_markers.0,
+                    ),
                 );
-                break; //End processing since all remaining fragments will be invisible.
+                break;
             }
 
             // Fragment is fully within range: Apply replacement characters if appropriate
-            if fragment_start >= range.start
-                && fragment_end <= range.end
-                && let Some(replacement) = fragment.replacement
-            {
-                let start_byte_idx = fragment.start_byte_idx;
-                let end_byte_idx = start_byte_idx.saturating_add(fragment.grapheme.len());
-                result.replace(start_byte_idx, end_byte_idx, &replacement.to_string());
+            if let Some(replacement) = Self::replacement(fragment, options.invisibles) {
+                result.replace(start_byte_idx, end_byte_idx, &replacement);
+                // search matches stay highlighted over them, see `AnnotationType::priority`
+                if !replacement.trim().is_empty() {
+                    result.add_annotation(
+                        AnnotationType::Invisible,
+                        start_byte_idx,
+                        start_byte_idx.saturating_add(replacement.len()),
+                    );
+                }
             }
         }
 
-        result
+        // the end of the line only if there is a free column for it, so nothing moves
+        let width = self.width();
+        if options.invisibles == Invisibles::Shown && range.contains(&width) {
+            let end = result.as_str().len();
+            result.push_str("¶");
+            result.add_annotation(
+                AnnotationType::Invisible,
+                end,
+                end.saturating_add("¶".len()),
+            );
+        }
+    }
+
+    // the columns of `range` which `annotate_visible_substr` fills, the end of the line
+    // included if it is shown
+    pub(crate) fn visible_width(&self, range: Range<ColIdx>, invisibles: Invisibles) -> ColIdx {
+        let end = if invisibles == Invisibles::Shown {
+            self.width().saturating_add(1)
+        } else {
+            self.width()
+        };
+        range.end.min(end).saturating_sub(range.start)
+    }
+
+    // what is rendered in place of the grapheme, if anything
+    fn replacement(fragment: &TextFragment, invisibles: Invisibles) -> Option<Cow<'static, str>> {
+        let width: ColIdx = fragment.rendered_width.into();
+        match invisibles {
+            Invisibles::Shown if fragment.grapheme == " " => Some(Cow::Borrowed("·")),
+            Invisibles::Shown if fragment.grapheme == "\t" => Some(Self::padded(SHOWN_TAB, width)),
+            Invisibles::Hidden => fragment.replacement.map(|_| Self::spaces(width)),
+            _ => fragment.replacement.map(|replacement| match replacement {
+                ' ' => Self::spaces(width),
+                _ => Cow::Owned(std::iter::repeat_n(replacement, width).collect()),
+            }),
+        }
+    }
+
+    // what is shown of a grapheme cut off by the edge of the visible range, filling the
+    // `visible` columns which are left of it, blank next to a scroll marker
+    fn clipped(visible: ColIdx, marked: bool) -> Cow<'static, str> {
+        if visible == 0 {
+            Cow::Borrowed("")
+        } else if marked {
+            Self::spaces(visible)
+        } else {
+            Self::padded(CLIPPED, visible)
+        }
+    }
+
+    fn spaces(width: ColIdx) -> Cow<'static, str> {
+        SPACES
+            .get(..width)
+            .map_or_else(|| Cow::Owned(" ".repeat(width)), Cow::Borrowed)
+    }
+
+    // the first character of `padded` and spaces up to `width` columns, or the character
+    // alone if `width` is 0
+    fn padded(padded: &'static str, width: ColIdx) -> Cow<'static, str> {
+        let first_len = padded.chars().next().map_or(0, char::len_utf8);
+        let spaces = width.saturating_sub(1);
+        padded.get(..first_len.saturating_add(spaces)).map_or_else(
+            || Cow::Owned(format!("{}{}", &padded[..first_len], " ".repeat(spaces))),
+            Cow::Borrowed,
+        )
+    }
+
+    pub(crate) const fn revision(&self) -> Revision {
+        self.revision
+    }
+
+    pub(crate) const fn is_edited(&self) -> bool {
+        self.edited
     }
 
+    pub(crate) const fn set_edited(&mut self, edited: bool) {
+        self.edited = edited;
+    }
+
+    pub(crate) fn grapheme_at(&self, grapheme_idx: GraphemeIdx) -> Option<&TextFragment> {
+        self.fragments().get(grapheme_idx)
+    }
+
+    /// How many graphemes, which are what a reader takes for a character, the line holds.
     pub fn grapheme_count(&self) -> GraphemeIdx {
-        self.fragments.len()
+        self.fragments().len()
     }
 
-    pub fn width(&self) -> GraphemeIdx {
-        self.width_until(self.grapheme_count())
+    /// How many columns the line takes on the terminal.
+    pub fn width(&self) -> ColIdx {
+        self.fragments().last().map_or(0, TextFragment::end_col_idx)
     }
 
-    pub fn width_until(&self, grapheme_idx: GraphemeIdx) -> GraphemeIdx {
-        self.fragments
-            .iter()
-            .take(grapheme_idx)
-            .map(|fragment| match fragment.rendered_width {
-                GraphemeWidth::Half => 1,
-                GraphemeWidth::Full => 2,
-            })
-            .sum()
+    /// The column the grapheme at `grapheme_idx` starts at, or the width behind the end.
+    pub fn width_until(&self, grapheme_idx: GraphemeIdx) -> ColIdx {
+        self.fragments()
+            .get(grapheme_idx)
+            .map_or_else(|| self.width(), |fragment| fragment.start_col_idx)
     }
 
-    // region: edit
-    fn rebuild_fragments(&mut self) {
-        self.fragments = Self::str_to_fragments(&self.string);
+    /// The grapheme rendered at column `col_idx`, or `grapheme_count` if the line is shorter.
+    pub fn col_to_grapheme_idx(&self, col_idx: ColIdx) -> GraphemeIdx {
+        self.fragments()
+            .partition_point(|fragment| fragment.end_col_idx() <= col_idx)
     }
 
-    // insert a character into the line, or appends it at the end if `at == grapheme_count + 1`
-    pub fn insert_char(&mut self, ch: char, at: GraphemeIdx) {
-        debug_assert!(at.saturating_sub(1) <= self.grapheme_count());
-        if let Some(fragment) = self.fragments.get(at) {
-            self.string.insert(fragment.start_byte_idx, ch);
-        } else {
-            self.string.push(ch);
+    // region: edit
+    // Update the fragments after `removed` bytes at `edit_start` have been replaced by
+    // `inserted` bytes. Segmentation restarts one grapheme before the edit, since
+    // the edit may merge with it (e.g. a combining mark), and stops as soon as it is in
+    // sync with the old fragments again. The fragments behind that are only shifted.
+    fn update_fragments(&mut self, edit_start: ByteIdx, removed: usize, inserted: usize) {
+        self.revision = next_revision();
+        self.edited = true;
+        // fragments which were never built are built from the edited string once needed
+        let Some(fragments) = self.fragments.get_mut() else {
+            return;
+        };
+        let edit_grapheme_idx =
+            fragments.partition_point(|fragment| fragment.start_byte_idx <= edit_start);
+        let rebuild_from = edit_grapheme_idx.saturating_sub(2);
+        let rebuild_start_byte = fragments
+            .get(rebuild_from)
+            .map_or(0, |fragment| fragment.start_byte_idx);
+
+        // the old fragments behind the edit will be kept, their start in the new string
+        // is shifted by the change in length
+        let old_edit_end = edit_start.saturating_add(removed);
+        let new_edit_end = edit_start.saturating_add(inserted);
+        let shifted = |fragment: &TextFragment| {
+            fragment
+                .start_byte_idx
+                .saturating_sub(removed)
+                .saturating_add(inserted)
+        };
+        let mut tail_idx =
+            fragments.partition_point(|fragment| fragment.start_byte_idx < old_edit_end);
+
+        let mut col_idx = rebuild_from
+            .checked_sub(1)
+            .and_then(|idx| fragments.get(idx))
+            .map_or(0, TextFragment::end_col_idx);
+        let mut rebuilt = Vec::new();
+        // the old fragments up to the end of the line are replaced unless it gets in sync
+        let mut in_sync = false;
+        for (relative_idx, grapheme) in self.string[rebuild_start_byte..].grapheme_indices(true) {
+            let byte_idx = rebuild_start_byte.saturating_add(relative_idx);
+            while fragments
+                .get(tail_idx)
+                .is_some_and(|fragment| shifted(fragment) < byte_idx)
+            {
+                tail_idx = tail_idx.saturating_add(1);
+            }
+            if byte_idx >= new_edit_end
+                && fragments
+                    .get(tail_idx)
+                    .is_some_and(|fragment| shifted(fragment) == byte_idx)
+            {
+                in_sync = true;
+                break;
+            }
+            let fragment = Self::grapheme_to_fragment(byte_idx, col_idx, grapheme, self.widths);
+            col_idx = fragment.end_col_idx();
+            rebuilt.push(fragment);
+        }
+        if !in_sync {
+            tail_idx = fragments.len();
         }
-        self.rebuild_fragments();
-    }
 
-    // delete the character at `at`
-    pub fn delete(&mut self, at: GraphemeIdx) {
-        debug_assert!(at <= self.grapheme_count());
-        if let Some(fragment) = self.fragments.get(at) {
-            let start = fragment.start_byte_idx;
-            let end = start.saturating_add(fragment.grapheme.len());
-            self.string.drain(start..end);
+        // the kept fragments move by the change in width as well, which changes the width of
+        // the tabs among them
+        let old_tail_col = fragments
+            .get(tail_idx)
+            .map_or(col_idx, |fragment| fragment.start_col_idx);
+
+        let tail_start = rebuild_from.saturating_add(rebuilt.len());
+        fragments.splice(rebuild_from..tail_idx, rebuilt);
+        if removed != inserted {
+            for fragment in fragments.iter_mut().skip(tail_start) {
+                fragment.start_byte_idx = shifted(fragment);
+            }
+        }
+        if old_tail_col != col_idx {
+            Self::place(&mut fragments[tail_start..], col_idx, self.widths.tab);
         }
-        self.rebuild_fragments();
     }
 
+    /// Inserts a character before the grapheme at `at`, or appends it if `at == grapheme_count`.
+    /// Returns the index behind the grapheme the character ended up in, which is `at + 1`
+    /// unless it merged with a neighbour, like a combining mark or a zero-width joiner does.
+    ///
+    /// ```
+    /// let mut line = hecto::Line::try_from("cafe")?;
+    /// assert_eq!(line.insert_char('\u{301}', 4), Ok(4));
+    /// assert_eq!(line.grapheme_count(), 4);
+    /// assert!(line.insert_char('x', 9).is_err());
+    /// # Ok::<(), hecto::LineBreakError>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// If `at` is behind the end of the line.
+    pub fn insert_char(&mut self, ch: char, at: GraphemeIdx) -> Result<GraphemeIdx, IndexError> {
+        let byte_idx = self.grapheme_idx_to_byte_idx(at)?;
+        self.string.insert(byte_idx, ch);
+        self.update_fragments(byte_idx, 0, ch.len_utf8());
+        Ok(self
+            .byte_idx_to_grapheme_idx(byte_idx)
+            .map_or(self.grapheme_count(), |grapheme_idx| {
+                grapheme_idx.saturating_add(1)
+            }))
+    }
+
+    /// Deletes the grapheme at `at`.
+    ///
+    /// # Errors
+    ///
+    /// If there is no grapheme at `at`.
+    pub fn delete(&mut self, at: GraphemeIdx) -> Result<(), IndexError> {
+        let fragment = self
+            .fragments()
+            .get(at)
+            .ok_or_else(|| self.index_error(at))?;
+        let start = fragment.start_byte_idx;
+        let len = fragment.grapheme.len();
+        self.string.drain(start..start.saturating_add(len));
+        self.update_fragments(start, len, 0);
+        Ok(())
+    }
+
+    /// Swaps the grapheme at `first` with the one behind it.
+    ///
+    /// # Errors
+    ///
+    /// If there are not two graphemes from `first` on.
+    pub fn swap_graphemes(&mut self, first: GraphemeIdx) -> Result<(), IndexError> {
+        let second = first.saturating_add(1);
+        let (Some(a), Some(b)) = (self.fragments().get(first), self.fragments().get(second)) else {
+            return Err(self.index_error(second));
+        };
+        let start = a.start_byte_idx;
+        let len = a.grapheme.len().saturating_add(b.grapheme.len());
+        let swapped = format!("{}{}", b.grapheme, a.grapheme);
+        self.string
+            .replace_range(start..start.saturating_add(len), &swapped);
+        self.update_fragments(start, len, len);
+        Ok(())
+    }
+
+    /// Appends the text of `other`, which may merge with the last grapheme.
     pub fn append(&mut self, other: &Self) {
+        let end = self.string.len();
         self.string.push_str(&other.string);
-        self.rebuild_fragments();
+        self.update_fragments(end, 0, other.string.len());
     }
 
-    pub fn append_char(&mut self, ch: char) {
-        self.insert_char(ch, self.grapheme_count());
+    pub(crate) fn append_char(&mut self, ch: char) {
+        let end = self.string.len();
+        self.string.push(ch);
+        self.update_fragments(end, 0, ch.len_utf8());
     }
 
+    /// Splits the line at the grapheme index `at`, returning everything behind it.
+    #[must_use = "the text behind `at` is gone from the line"]
     pub fn split(&mut self, at: GraphemeIdx) -> Self {
+        let byte_idx = self
+            .fragments()
+            .get(at)
+            .map_or(self.string.len(), |fragment| fragment.start_byte_idx);
+        let string = self.string.split_off(byte_idx);
+        let tab_width = self.widths.tab;
+        let fragments = self
+            .fragments
+            .get_mut()
+            .map_or_else(OnceCell::new, |fragments| {
+                let mut behind = fragments.split_off(at.min(fragments.len()));
+                for fragment in &mut behind {
+                    fragment.start_byte_idx = fragment.start_byte_idx.saturating_sub(byte_idx);
+                }
+                Self::place(&mut behind, 0, tab_width);
+                OnceCell::from(behind)
+            });
+        self.revision = next_revision();
+        // splitting at the end leaves the line as it was
+        self.edited |= !string.is_empty();
         Self {
-            string: self.string.split_off(at),
-            fragments: self.fragments.split_off(at),
+            string,
+            fragments,
+            revision: next_revision(),
+            widths: self.widths,
+            edited: true,
+        }
+    }
+
+    pub(crate) fn delete_last(&mut self) {
+        if let Some(fragment) = self.fragments().last() {
+            let start = fragment.start_byte_idx;
+            let len = fragment.grapheme.len();
+            self.string.truncate(start);
+            self.update_fragments(start, len, 0);
+        }
+    }
+    // endregion
+
+    // region: words
+    // the start of the next word behind `from` in this line
+    pub(crate) fn next_word_start(&self, from: GraphemeIdx) -> Option<GraphemeIdx> {
+        let mut idx = from;
+        if let Some(class) = self
+            .class_at(idx)
+            .filter(|class| *class != CharClass::Space)
+        {
+            while self.class_at(idx) == Some(class) {
+                idx = idx.saturating_add(1);
+            }
+        }
+        while self.class_at(idx) == Some(CharClass::Space) {
+            idx = idx.saturating_add(1);
+        }
+        (idx < self.grapheme_count()).then_some(idx)
+    }
+
+    // the start of the word before `from` in this line
+    pub(crate) fn previous_word_start(&self, from: GraphemeIdx) -> Option<GraphemeIdx> {
+        let mut idx = from.min(self.grapheme_count());
+        while idx > 0 && self.class_at(idx.saturating_sub(1)) == Some(CharClass::Space) {
+            idx = idx.saturating_sub(1);
+        }
+        let class = self.class_at(idx.checked_sub(1)?)?;
+        while idx > 0 && self.class_at(idx.saturating_sub(1)) == Some(class) {
+            idx = idx.saturating_sub(1);
+        }
+        Some(idx)
+    }
+
+    // the graphemes of the word at `grapheme_idx`, or of the word ending there
+    pub(crate) fn word_at(&self, grapheme_idx: GraphemeIdx) -> Option<Range<GraphemeIdx>> {
+        let idx = if self.class_at(grapheme_idx) == Some(CharClass::Word) {
+            grapheme_idx
+        } else {
+            grapheme_idx
+                .checked_sub(1)
+                .filter(|idx| self.class_at(*idx) == Some(CharClass::Word))?
+        };
+        let mut start = idx;
+        while start > 0 && self.class_at(start.saturating_sub(1)) == Some(CharClass::Word) {
+            start = start.saturating_sub(1);
+        }
+        let mut end = idx.saturating_add(1);
+        while self.class_at(end) == Some(CharClass::Word) {
+            end = end.saturating_add(1);
+        }
+        Some(start..end)
+    }
+
+    // the graphemes up to the next whitespace either way from `grapheme_idx`, or from the
+    // grapheme before it, e.g. a path
+    pub(crate) fn token_at(&self, grapheme_idx: GraphemeIdx) -> Option<Range<GraphemeIdx>> {
+        let is_token = |idx| {
+            self.class_at(idx)
+                .is_some_and(|class| class != CharClass::Space)
+        };
+        let idx = if is_token(grapheme_idx) {
+            grapheme_idx
+        } else {
+            grapheme_idx.checked_sub(1).filter(|idx| is_token(*idx))?
+        };
+        let mut start = idx;
+        while start > 0 && is_token(start.saturating_sub(1)) {
+            start = start.saturating_sub(1);
+        }
+        let mut end = idx.saturating_add(1);
+        while is_token(end) {
+            end = end.saturating_add(1);
+        }
+        Some(start..end)
+    }
+
+    // every run of word characters, in order
+    pub(crate) fn words(&self) -> Vec<&str> {
+        let mut words = Vec::new();
+        let mut start = None;
+        for grapheme_idx in 0..=self.grapheme_count() {
+            let is_word = self.class_at(grapheme_idx) == Some(CharClass::Word);
+            match start {
+                None if is_word => start = Some(grapheme_idx),
+                Some(word_start) if !is_word => {
+                    words.push(self.text_of(word_start..grapheme_idx));
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+        words
+    }
+
+    // the text of the graphemes in `range`
+    pub(crate) fn text_of(&self, range: Range<GraphemeIdx>) -> &str {
+        let start = self.grapheme_idx_to_byte_idx(range.start).unwrap_or(0);
+        let end = self
+            .grapheme_idx_to_byte_idx(range.end)
+            .unwrap_or(self.string.len());
+        self.string.get(start..end).unwrap_or_default()
+    }
+
+    // where a word motion coming from another line stops: the first word, or an empty line
+    pub(crate) fn first_word_start(&self) -> Option<GraphemeIdx> {
+        match self.class_at(0) {
+            Some(CharClass::Space) => self.next_word_start(0),
+            _ => Some(0),
+        }
+    }
+
+    pub(crate) fn last_word_start(&self) -> Option<GraphemeIdx> {
+        if self.is_empty() {
+            Some(0)
+        } else {
+            self.previous_word_start(self.grapheme_count())
+        }
+    }
+
+    // The columns at which the rows of the line start when it is wrapped at `width` columns.
+    // Rows break after a space where there is one and never inside a grapheme. A full last
+    // row is followed by an empty one, so there is room for the caret at the end.
+    pub(crate) fn wrap(&self, width: ColIdx) -> Vec<ColIdx> {
+        let mut starts = vec![0];
+        if width == 0 {
+            return starts;
+        }
+        let mut row_start: ColIdx = 0;
+        let mut after_space = None;
+        for fragment in self.fragments() {
+            while fragment.end_col_idx() > row_start.saturating_add(width)
+                && fragment.start_col_idx > row_start
+            {
+                row_start = after_space
+                    .filter(|col| *col > row_start)
+                    .unwrap_or(fragment.start_col_idx);
+                after_space = None;
+                starts.push(row_start);
+            }
+            if fragment.grapheme.chars().all(char::is_whitespace) {
+                after_space = Some(fragment.end_col_idx());
+            }
         }
+        if self.width() >= row_start.saturating_add(width) {
+            starts.push(self.width());
+        }
+        starts
     }
 
-    pub fn delete_last(&mut self) {
-        self.delete(self.grapheme_count().saturating_sub(1));
+    fn class_at(&self, grapheme_idx: GraphemeIdx) -> Option<CharClass> {
+        let ch = self
+            .fragments()
+            .get(grapheme_idx)?
+            .grapheme
+            .chars()
+            .next()?;
+        Some(if ch.is_whitespace() {
+            CharClass::Space
+        } else if ch.is_alphanumeric() || ch == '_' {
+            CharClass::Word
+        } else {
+            CharClass::Punctuation
+        })
     }
     // endregion
 
     // region: search
-    pub fn search_forward(
+    pub(crate) fn search_forward(
         &self,
         query: &str,
         from_grapheme_idx: GraphemeIdx,
@@ -278,77 +906,185 @@ impl Line {
             return None;
         }
 
-        let start_byte_idx = self.grapheme_idx_to_byte_idx(from_grapheme_idx);
+        let start_byte_idx = self.grapheme_idx_to_byte_idx(from_grapheme_idx).ok()?;
         self.string
             .get(start_byte_idx..)
             .and_then(|substr| substr.find(query))
-            .map(|byte_idx| {
-                self.byte_idx_to_grapheme_idx(byte_idx)
-                    .saturating_add(from_grapheme_idx)
+            .and_then(|byte_idx| {
+                self.byte_idx_to_grapheme_idx(byte_idx.saturating_add(start_byte_idx))
             })
     }
 
-    pub fn search_backward(
+    pub(crate) fn search_backward(
         &self,
         query: &str,
         from_grapheme_idx: GraphemeIdx,
     ) -> Option<GraphemeIdx> {
-        debug_assert!(from_grapheme_idx <= self.grapheme_count());
         if self.is_empty() || from_grapheme_idx == 0 {
             return None;
         }
 
-        let end_byte_idx = if from_grapheme_idx == self.grapheme_count() {
-            self.string.len()
-        } else {
-            self.grapheme_idx_to_byte_idx(from_grapheme_idx)
-        };
+        // searching from behind the end searches the whole line
+        let end_byte_idx = self
+            .grapheme_idx_to_byte_idx(from_grapheme_idx)
+            .unwrap_or(self.string.len());
 
         self.string
             .get(..end_byte_idx)
             .and_then(|substr| substr.match_indices(query).last())
-            .map(|(idx, _)| self.byte_idx_to_grapheme_idx(idx))
+            .and_then(|(idx, _)| self.byte_idx_to_grapheme_idx(idx))
     }
 
-    // get the grapheme index from byte
-    fn byte_idx_to_grapheme_idx(&self, byte_idx: ByteIdx) -> GraphemeIdx {
-        debug_assert!(byte_idx <= self.string.len());
-        self.fragments
-            .iter()
-            .position(|fragment| fragment.start_byte_idx >= byte_idx)
-            .unwrap_or_else(|| {
-                #[cfg(debug_assertions)]
-                {
-                    panic!("Invalid byte_idx passed to byte_idx_to_grapheme_idx: {byte_idx:?}");
-                }
-                #[cfg(not(debug_assertions))]
-                {
-                    0
-                }
-            })
+    // the grapheme containing the byte at `byte_idx`, or `grapheme_count` for the end of the
+    // line. None if `byte_idx` is behind the end.
+    pub(crate) fn byte_idx_to_grapheme_idx(&self, byte_idx: ByteIdx) -> Option<GraphemeIdx> {
+        if byte_idx > self.string.len() {
+            return None;
+        }
+        if byte_idx == self.string.len() {
+            return Some(self.grapheme_count());
+        }
+        self.fragments()
+            .partition_point(|fragment| fragment.start_byte_idx <= byte_idx)
+            .checked_sub(1)
+    }
+
+    // the text before the grapheme at `grapheme_idx`, all of it if that is past the end
+    pub(crate) fn text_before(&self, grapheme_idx: GraphemeIdx) -> &str {
+        let byte_idx = self
+            .grapheme_idx_to_byte_idx(grapheme_idx)
+            .unwrap_or(self.string.len());
+        &self.string[..byte_idx]
     }
 
-    // get the start byte from grapheme index
-    fn grapheme_idx_to_byte_idx(&self, grapheme_idx: GraphemeIdx) -> ByteIdx {
-        debug_assert!(grapheme_idx <= self.grapheme_count());
-        if grapheme_idx == 0 || self.grapheme_count() == 0 {
-            return 0;
+    // the first byte of the grapheme at `grapheme_idx`, or the length of the line for
+    // `grapheme_count`
+    fn grapheme_idx_to_byte_idx(&self, grapheme_idx: GraphemeIdx) -> Result<ByteIdx, IndexError> {
+        if grapheme_idx == self.grapheme_count() {
+            return Ok(self.string.len());
         }
-        self.fragments.get(grapheme_idx).map_or_else(
-            || {
-                #[cfg(debug_assertions)]
-                {
-                    panic!(
-                        "Invalid grapheme_idx passed to grapheme_idx_to_byte_idx: {grapheme_idx:?}"
-                    );
-                }
-                #[cfg(not(debug_assertions))]
-                {
-                    0
-                }
-            },
-            |fragment| fragment.start_byte_idx,
-        )
+        self.fragments()
+            .get(grapheme_idx)
+            .map(|fragment| fragment.start_byte_idx)
+            .ok_or_else(|| self.index_error(grapheme_idx))
+    }
+
+    fn index_error(&self, grapheme_idx: GraphemeIdx) -> IndexError {
+        IndexError {
+            grapheme_idx,
+            grapheme_count: self.fragments().len(),
+        }
+    }
+
+    // highlight `word` where it can be seen in the columns of `range`, whose bytes `result`
+    // holds as `visible`
+    fn annotate_word(
+        &self,
+        result: &mut AnnotatedString,
+        range: &Range<ColIdx>,
+        visible: &Range<ByteIdx>,
+        word: &str,
+    ) {
+        if word.is_empty() {
+            return;
+        }
+        let margin = word.graphemes(true).count();
+        let start = self.col_to_grapheme_idx(range.start).saturating_sub(margin);
+        let end = self
+            .col_to_grapheme_idx(range.end)
+            .saturating_add(margin)
+            .min(self.grapheme_count());
+        let bytes = self.grapheme_idx_to_byte_idx(start).unwrap_or(0)
+            ..self
+                .grapheme_idx_to_byte_idx(end)
+                .unwrap_or(self.string.len());
+        for start_byte_idx in self.find_words(word, bytes) {
+            Self::add_visible_annotation(
+                result,
+                visible,
+                AnnotationType::WordMatch,
+                start_byte_idx..start_byte_idx.saturating_add(word.len()),
+            );
+        }
+    }
+
+    // annotate the part of `bytes` of the line which is among the `visible` bytes `result`
+    // holds
+    fn add_visible_annotation(
+        result: &mut AnnotatedString,
+        visible: &Range<ByteIdx>,
+        typ: AnnotationType,
+        bytes: Range<ByteIdx>,
+    ) {
+        let start = bytes.start.max(visible.start);
+        let end = bytes.end.min(visible.end);
+        if start < end {
+            result.add_annotation(
+                typ,
+                start.saturating_sub(visible.start),
+                end.saturating_sub(visible.start),
+            );
+        }
+    }
+
+    // The matches of `query` which reach into `bytes`, taken from the sorted `matches` if
+    // given. Otherwise a query which can overlap itself, like "aa" in "aaa", is looked for
+    // from the start of the line, so the same matches are found however far the line is
+    // scrolled.
+    fn matches_reaching_into(
+        &self,
+        query: &str,
+        bytes: &Range<ByteIdx>,
+        matches: Option<&[GraphemeIdx]>,
+    ) -> Vec<(ByteIdx, GraphemeIdx)> {
+        let Some(matches) = matches else {
+            let search_start = if overlaps_itself(query) {
+                0
+            } else {
+                bytes.start.saturating_sub(query.len())
+            };
+            let search_bytes = self.string.floor_char_boundary(search_start)
+                ..self
+                    .string
+                    .ceil_char_boundary(bytes.end.saturating_add(query.len()));
+            return self.find_all(query, search_bytes);
+        };
+        let byte_idx = |grapheme_idx: GraphemeIdx| {
+            self.grapheme_idx_to_byte_idx(grapheme_idx)
+                .unwrap_or(self.string.len())
+        };
+        let first = matches.partition_point(|grapheme_idx| {
+            byte_idx(*grapheme_idx).saturating_add(query.len()) <= bytes.start
+        });
+        matches[first..]
+            .iter()
+            .map(|grapheme_idx| (byte_idx(*grapheme_idx), *grapheme_idx))
+            .take_while(|(start_byte_idx, _)| *start_byte_idx < bytes.end)
+            .collect()
+    }
+
+    // where every match of `query` starts
+    pub(crate) fn find_all_graphemes(&self, query: &str) -> Vec<GraphemeIdx> {
+        self.find_all(query, 0..self.string.len())
+            .into_iter()
+            .map(|(_, grapheme_idx)| grapheme_idx)
+            .collect()
+    }
+
+    // the starts of `word` where it isn't part of a longer word
+    fn find_words(&self, word: &str, range: Range<ByteIdx>) -> Vec<ByteIdx> {
+        let len = word.graphemes(true).count();
+        self.find_all(word, range)
+            .into_iter()
+            .filter(|(_, grapheme_idx)| {
+                let before = grapheme_idx
+                    .checked_sub(1)
+                    .and_then(|idx| self.class_at(idx));
+                let after = self.class_at(grapheme_idx.saturating_add(len));
+                before != Some(CharClass::Word) && after != Some(CharClass::Word)
+            })
+            .map(|(byte_idx, _)| byte_idx)
+            .collect()
     }
 
     fn find_all(&self, query: &str, range: Range<ByteIdx>) -> Vec<(ByteIdx, GraphemeIdx)> {
@@ -360,10 +1096,10 @@ impl Line {
             .map_or_else(Vec::new, |substr| {
                 substr
                     .match_indices(query)
-                    .map(|(relative_start_idx, _)| {
+                    .filter_map(|(relative_start_idx, _)| {
                         let absolute_start_idx = relative_start_idx.saturating_add(start_byte_idx);
-                        let grapheme_idx = self.byte_idx_to_grapheme_idx(absolute_start_idx);
-                        (absolute_start_idx, grapheme_idx)
+                        let grapheme_idx = self.byte_idx_to_grapheme_idx(absolute_start_idx)?;
+                        Some((absolute_start_idx, grapheme_idx))
                     })
                     .collect()
             })
@@ -371,6 +1107,24 @@ impl Line {
     // endregion
 }
 
+// whether the end of `query` can be the start of another match, like with "aa" or "abab"
+fn overlaps_itself(query: &str) -> bool {
+    let bytes = query.as_bytes();
+    (1..bytes.len()).any(|shift| bytes[shift..] == bytes[..bytes.len().saturating_sub(shift)])
+}
+
+impl TryFrom<&str> for Line {
+    type Error = LineBreakError;
+
+    /// A line of `text`, with tabs 8 columns apart.
+    fn try_from(text: &str) -> Result<Self, Self::Error> {
+        match text.find('\n') {
+            Some(byte_idx) => Err(LineBreakError { byte_idx }),
+            None => Ok(Self::from(text)),
+        }
+    }
+}
+
 impl fmt::Display for Line {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.string)
@@ -395,6 +1149,21 @@ impl Deref for Line {
 mod test {
     use super::*;
 
+    #[test]
+    fn lines_hold_no_line_breaks() {
+        assert_eq!(
+            Line::try_from("a\rb").map(|line| line.grapheme_count()),
+            Ok(3)
+        );
+        assert_eq!(
+            Line::try_from("ab\ncd").map(|line| line.to_string()),
+            Err(LineBreakError { byte_idx: 2 })
+        );
+        // a reversed range of columns is empty
+        let (line, start) = (Line::from("abc"), 2);
+        assert_eq!(line.get_visible_graphemes(start..1), "");
+    }
+
     #[test]
     fn forward() {
         let s = "Löwe 老虎 Léopard Gepardi";
@@ -410,4 +1179,628 @@ mod test {
         let grapheme_idx = line.search_backward("pard", 22);
         assert_eq!(grapheme_idx, Some(18));
     }
+
+    fn tabs(tab: ColIdx) -> CellWidths {
+        CellWidths {
+            tab,
+            ..CellWidths::DEFAULT
+        }
+    }
+
+    // the fragments must be the same as if the line had been built from scratch
+    fn assert_consistent(line: &Line) {
+        let expected: Vec<(usize, usize, String)> =
+            Line::str_to_fragments(&line.string, line.widths)
+                .into_iter()
+                .map(|fragment| {
+                    (
+                        fragment.start_byte_idx,
+                        fragment.start_col_idx,
+                        fragment.grapheme,
+                    )
+                })
+                .collect();
+        let actual: Vec<(usize, usize, String)> = line
+            .fragments()
+            .iter()
+            .map(|fragment| {
+                (
+                    fragment.start_byte_idx,
+                    fragment.start_col_idx,
+                    fragment.grapheme.clone(),
+                )
+            })
+            .collect();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn fragments_are_built_when_first_needed() {
+        let mut line = Line::with_widths("a\tb", tabs(4));
+        assert!(line.fragments.get().is_none());
+        line.append_char('c');
+        line.set_widths(tabs(2));
+        assert!(line.fragments.get().is_none());
+        assert_eq!(line.width(), 4);
+        assert!(line.fragments.get().is_some());
+        assert_consistent(&line);
+
+        line.set_widths(tabs(4));
+        let rest = line.split(1);
+        assert_consistent(&line);
+        assert_consistent(&rest);
+        assert_eq!(rest.width(), 6);
+    }
+
+    #[test]
+    fn lazy_lines_behave_like_built_ones() {
+        let edits: [fn(&mut Line) -> String; 7] = [
+            |line| format!("{:?}", line.insert_char('\u{301}', 1)),
+            |line| format!("{:?}", line.insert_char('老', 2)),
+            |line| format!("{:?}", line.delete(0)),
+            |line| format!("{:?}", line.swap_graphemes(0)),
+            |line| {
+                line.append_char('x');
+                String::new()
+            },
+            |line| line.split(1).to_string(),
+            |line| {
+                line.set_widths(tabs(2));
+                String::new()
+            },
+        ];
+        for text in ["", "a\tb", "Löwe 老虎", "e\u{301}x", "\u{1}ctl"] {
+            for edit in edits {
+                let mut lazy = Line::with_widths(text, tabs(4));
+                let mut built = Line::with_widths(text, tabs(4));
+                built.width();
+                assert_eq!(edit(&mut lazy), edit(&mut built), "editing {text:?}");
+                assert_eq!(lazy.to_string(), built.to_string());
+                assert_eq!(
+                    (lazy.grapheme_count(), lazy.width()),
+                    (built.grapheme_count(), built.width())
+                );
+                assert_consistent(&lazy);
+            }
+        }
+    }
+
+    #[test]
+    fn insert_keeps_fragments_consistent() {
+        let mut line = Line::from("Löwe 老虎 Léopard");
+        line.insert_char('x', 0).unwrap();
+        line.insert_char('🐯', 6).unwrap();
+        line.append_char('!');
+        assert_eq!(line.to_string(), "xLöwe 🐯老虎 Léopard!");
+        assert_consistent(&line);
+    }
+
+    #[test]
+    fn insert_combining_mark_merges_with_previous_grapheme() {
+        let mut line = Line::from("abc");
+        line.insert_char('\u{301}', 1).unwrap();
+        assert_eq!(line.grapheme_count(), 3);
+        assert_consistent(&line);
+
+        // a second regional indicator turns two letters into a flag
+        let mut line = Line::from("x\u{1F1E9}y");
+        line.insert_char('\u{1F1EA}', 2).unwrap();
+        assert_eq!(line.grapheme_count(), 3);
+        assert_consistent(&line);
+
+        // the grapheme it merges with is the last one of the line
+        let mut line = Line::from("\u{301}");
+        line.width();
+        line.insert_char('\u{301}', 0).unwrap();
+        assert_eq!(line.grapheme_count(), 1);
+        assert_consistent(&line);
+    }
+
+    #[test]
+    fn typed_clusters_leave_the_caret_behind_them() {
+        // the grapheme, and how wide it is
+        let clusters: [(&str, usize); 4] = [
+            ("\u{E9}", 1),
+            ("e\u{301}", 1),
+            ("👨\u{200D}👩\u{200D}👧", 2),
+            ("\u{1F1E9}\u{1F1EA}", 2),
+        ];
+        for (cluster, width) in clusters {
+            let mut line = Line::from("ab");
+            let mut caret = 1;
+            for ch in cluster.chars() {
+                caret = line.insert_char(ch, caret).unwrap();
+                assert_consistent(&line);
+            }
+            assert_eq!(line.to_string(), format!("a{cluster}b"));
+            assert_eq!(caret, 2, "{cluster:?}");
+            assert_eq!(line.grapheme_count(), 3, "{cluster:?}");
+            assert_eq!(line.width(), width.saturating_add(2), "{cluster:?}");
+
+            // all of its characters go at once
+            line.delete(1).unwrap();
+            assert_eq!(line.to_string(), "ab");
+            assert_consistent(&line);
+        }
+
+        // a base character typed before a lone combining mark takes it on
+        let mut line = Line::from("\u{301}x");
+        assert_eq!(line.insert_char('e', 0), Ok(1));
+        assert_eq!(line.grapheme_count(), 2);
+        assert_consistent(&line);
+    }
+
+    #[test]
+    fn delete_keeps_fragments_consistent() {
+        let mut line = Line::from("a👨\u{200D}👩\u{200D}👧b老虎");
+        line.delete(1).unwrap();
+        assert_eq!(line.to_string(), "ab老虎");
+        line.delete(2).unwrap();
+        line.delete(0).unwrap();
+        assert_eq!(line.to_string(), "b虎");
+        assert_consistent(&line);
+    }
+
+    #[test]
+    fn split_and_append_round_trip() {
+        let s = "Löwe 老虎 Léopard";
+        let mut line = Line::from(s);
+        let mut tail = line.split(6);
+        assert_eq!(line.to_string(), "Löwe 老");
+        assert_eq!(tail.to_string(), "虎 Léopard");
+        assert_consistent(&line);
+        assert_consistent(&tail);
+
+        tail.insert_char('x', 1).unwrap();
+        assert_eq!(tail.to_string(), "虎x Léopard");
+        line.append(&tail);
+        assert_eq!(line.to_string(), "Löwe 老虎x Léopard");
+        assert_consistent(&line);
+    }
+
+    #[test]
+    fn appending_is_not_quadratic() {
+        // rebuilding all fragments on every keystroke takes many seconds for this
+        let mut line = Line::default();
+        let start = std::time::Instant::now();
+        for _ in 0..10_000 {
+            line.append_char('a');
+        }
+        for _ in 0..1_000 {
+            line.insert_char('b', 5_000).unwrap();
+        }
+        assert!(start.elapsed() < std::time::Duration::from_secs(2));
+        assert_eq!(line.grapheme_count(), 11_000);
+        assert_consistent(&line);
+    }
+
+    #[test]
+    fn widths_and_columns() {
+        let mut line = Line::from("a老b");
+        assert_eq!(line.width(), 4);
+        assert_eq!(line.width_until(2), 3);
+        assert_eq!(line.width_until(10), 4);
+        assert_eq!(line.col_to_grapheme_idx(1), 1);
+        assert_eq!(line.col_to_grapheme_idx(2), 1);
+        assert_eq!(line.col_to_grapheme_idx(3), 2);
+        assert_eq!(line.col_to_grapheme_idx(4), 3);
+
+        line.insert_char('虎', 2).unwrap();
+        assert_eq!(line.width(), 6);
+        assert_eq!(line.col_to_grapheme_idx(4), 2);
+        assert_consistent(&line);
+        let tail = line.split(2);
+        assert_eq!(tail.width(), 3);
+        assert_consistent(&tail);
+    }
+
+    #[test]
+    fn visible_substring_is_clipped() {
+        let line = Line::from("a老b\tc");
+        let visible = line.get_annotated_visible_substr(2..5, None, None, RenderOptions::default());
+        assert_eq!(visible.to_string(), "⋯b⋯");
+
+        // a tab reaching out on both sides fills the range alone
+        let line = Line::from("\tx");
+        let visible = |range| line.get_visible_graphemes(range);
+        assert_eq!(visible(1..3), "⋯ ");
+        assert_eq!(visible(1..1), "");
+    }
+
+    #[test]
+    fn ambiguous_characters_take_the_configured_width() {
+        for (ambiguous, widths) in [
+            (AmbiguousWidth::Narrow, [0, 1, 2, 3, 8, 9]),
+            (AmbiguousWidth::Wide, [0, 2, 4, 5, 8, 10]),
+        ] {
+            let mut line = Line::from("→─x\t°");
+            line.set_widths(CellWidths {
+                ambiguous,
+                ..CellWidths::DEFAULT
+            });
+            let width_until: Vec<ColIdx> = (0..=5).map(|idx| line.width_until(idx)).collect();
+            assert_eq!(width_until, widths, "{ambiguous:?}");
+            for (grapheme_idx, col_idx) in widths.into_iter().enumerate() {
+                assert_eq!(line.col_to_grapheme_idx(col_idx), grapheme_idx);
+            }
+            let rendered = line
+                .get_annotated_visible_substr(0..line.width(), None, None, RenderOptions::default())
+                .to_string();
+            let rendered_width = match ambiguous {
+                AmbiguousWidth::Narrow => rendered.width(),
+                AmbiguousWidth::Wide => rendered.width_cjk(),
+            };
+            assert_eq!(rendered_width, line.width());
+
+            line.insert_char('·', 1).unwrap();
+            assert_consistent(&line);
+        }
+    }
+
+    #[test]
+    fn tabs_reach_the_next_tab_stop() {
+        let mut line = Line::from("a\tb");
+        assert_eq!(line.width(), 9);
+        line.set_widths(tabs(4));
+        assert_eq!(line.width(), 5);
+        assert_eq!(line.width_until(2), 4);
+        assert_eq!(line.col_to_grapheme_idx(3), 1);
+        assert_eq!(
+            line.get_annotated_visible_substr(0..5, None, None, RenderOptions::default())
+                .to_string(),
+            "a   b"
+        );
+        // a partially visible tab still fills its columns
+        assert_eq!(
+            line.get_annotated_visible_substr(2..5, None, None, RenderOptions::default())
+                .to_string(),
+            "⋯ b"
+        );
+
+        // the tabs behind an edit move to the next stop
+        line.insert_char('\t', 0).unwrap();
+        assert_eq!(line.to_string(), "\ta\tb");
+        assert_eq!(line.width_until(3), 8);
+        assert_consistent(&line);
+        line.insert_char('老', 1).unwrap();
+        assert_eq!(line.width_until(4), 8);
+        line.insert_char('x', 3).unwrap();
+        assert_eq!(line.width_until(5), 12);
+        assert_consistent(&line);
+        line.delete(0).unwrap();
+        assert_eq!(line.to_string(), "老ax\tb");
+        assert_eq!(line.width_until(4), 8);
+        assert_consistent(&line);
+        assert_eq!(line.split(2).width(), 5);
+        assert_eq!(line.width(), 3);
+    }
+
+    #[test]
+    fn word_starts() {
+        let line = Line::from("  foo.bar  baz");
+        assert_eq!(line.next_word_start(0), Some(2));
+        assert_eq!(line.next_word_start(2), Some(5));
+        assert_eq!(line.next_word_start(5), Some(6));
+        assert_eq!(line.next_word_start(6), Some(11));
+        assert_eq!(line.next_word_start(11), None);
+        assert_eq!(line.previous_word_start(14), Some(11));
+        assert_eq!(line.previous_word_start(11), Some(6));
+        assert_eq!(line.previous_word_start(2), None);
+    }
+
+    #[test]
+    fn wraps_after_spaces() {
+        assert_eq!(Line::from("aaaa bbbb").wrap(5), vec![0, 5]);
+        assert_eq!(Line::from("aa bbbbbbb").wrap(5), vec![0, 3, 8]);
+        assert_eq!(Line::from("abcde").wrap(5), vec![0, 5]);
+        assert_eq!(Line::from("").wrap(5), vec![0]);
+        // a wide grapheme never straddles two rows
+        assert_eq!(Line::from("ab老虎").wrap(3), vec![0, 2, 4]);
+    }
+
+    #[test]
+    fn edits_change_the_revision() {
+        let mut line = Line::from("abc");
+        let revision = line.revision();
+        line.insert_char('x', 1).unwrap();
+        assert_ne!(line.revision(), revision);
+        let revision = line.revision();
+        let tail = line.split(2);
+        assert_ne!(line.revision(), revision);
+        assert_ne!(tail.revision(), line.revision());
+    }
+
+    #[test]
+    fn edits_mark_the_line_edited() {
+        let mut line = Line::from("abc");
+        assert!(!line.is_edited());
+        let tail = line.split(3);
+        assert!(!line.is_edited());
+        assert!(tail.is_edited());
+        line.delete_last();
+        assert!(line.is_edited());
+    }
+
+    #[test]
+    fn byte_idx_maps_to_containing_grapheme() {
+        let line = Line::from("Löwe 老虎");
+        assert_eq!(line.byte_idx_to_grapheme_idx(0), Some(0));
+        assert_eq!(line.byte_idx_to_grapheme_idx(2), Some(1));
+        assert_eq!(line.byte_idx_to_grapheme_idx(7), Some(5));
+        assert_eq!(line.byte_idx_to_grapheme_idx(12), Some(7));
+        assert_eq!(line.byte_idx_to_grapheme_idx(13), None);
+
+        assert_eq!(line.grapheme_idx_to_byte_idx(6), Ok(9));
+        assert_eq!(line.grapheme_idx_to_byte_idx(7), Ok(12));
+        assert_eq!(
+            line.grapheme_idx_to_byte_idx(8),
+            Err(IndexError {
+                grapheme_idx: 8,
+                grapheme_count: 7
+            })
+        );
+    }
+
+    #[test]
+    fn search_inside_combined_grapheme_finds_that_grapheme() {
+        let line = Line::from("xe\u{301}e\u{301}");
+        assert_eq!(line.search_forward("\u{301}", 0), Some(1));
+        assert_eq!(line.search_forward("\u{301}", 2), Some(2));
+        assert_eq!(line.search_backward("\u{301}", 3), Some(2));
+        assert_eq!(line.search_backward("\u{301}", 2), Some(1));
+    }
+
+    #[test]
+    fn out_of_range_edits_are_errors() {
+        let mut line = Line::from("a老");
+        assert!(line.insert_char('x', 3).is_err());
+        assert!(line.delete(2).is_err());
+        assert_eq!(line.to_string(), "a老");
+        assert_eq!(line.insert_char('x', 2), Ok(3));
+        assert_eq!(line.to_string(), "a老x");
+
+        let mut empty = Line::default();
+        empty.delete_last();
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn digits_behind_combining_characters_are_highlighted() {
+        // char and grapheme indices differ here
+        let line = Line::from("e\u{301}e\u{301}12");
+        let visible =
+            line.get_annotated_visible_substr(0..10, Some("1"), Some(2), RenderOptions::default());
+        assert_eq!(visible.to_string(), "e\u{301}e\u{301}12");
+    }
+
+    #[test]
+    fn digits_before_replaced_graphemes_keep_their_width() {
+        let shown = RenderOptions {
+            invisibles: Invisibles::Shown,
+            ..RenderOptions::default()
+        };
+        let line = Line::with_widths("12 x\t3", tabs(3));
+        let visible = line.get_annotated_visible_substr(0..7, None, None, shown);
+        let parts: Vec<(&str, Option<AnnotationType>)> = visible
+            .into_iter()
+            .map(|part| (part.string, part.typ))
+            .collect();
+        assert!(matches!(
+            parts.as_slice(),
+            [
+                ("1", Some(AnnotationType::Digit)),
+                ("2", Some(AnnotationType::Digit)),
+                ("·", Some(AnnotationType::Invisible)),
+                ("x", None),
+                ("→ ", Some(AnnotationType::Invisible)),
+                ("3", Some(AnnotationType::Digit)),
+            ]
+        ));
+    }
+
+    #[test]
+    fn long_lines_render_only_what_is_visible() {
+        // a match straddling either edge stays highlighted on the visible side
+        let line = Line::from(&format!(
+            "{}foo{}foo{}",
+            "a".repeat(98),
+            "b".repeat(4),
+            "c".repeat(98)
+        ));
+        let visible = line.get_annotated_visible_substr(
+            100..105,
+            Some("foo"),
+            Some(105),
+            RenderOptions::default(),
+        );
+        let parts: Vec<(&str, Option<AnnotationType>)> = visible
+            .into_iter()
+            .map(|part| (part.string, part.typ))
+            .collect();
+        assert!(matches!(
+            parts.as_slice(),
+            [("o", Some(AnnotationType::Match)), ("bbbb", None),]
+        ));
+        let visible = line.get_annotated_visible_substr(
+            104..107,
+            Some("foo"),
+            Some(105),
+            RenderOptions::default(),
+        );
+        let parts: Vec<(&str, Option<AnnotationType>)> = visible
+            .into_iter()
+            .map(|part| (part.string, part.typ))
+            .collect();
+        assert!(matches!(
+            parts.as_slice(),
+            [("b", None), ("fo", Some(AnnotationType::SelectedMatch))]
+        ));
+
+        // matches of a query overlapping itself are the same wherever the line is cut
+        let line = Line::from("aaaaa");
+        let visible =
+            line.get_annotated_visible_substr(1..5, Some("aa"), None, RenderOptions::default());
+        let parts: Vec<(&str, Option<AnnotationType>)> = visible
+            .into_iter()
+            .map(|part| (part.string, part.typ))
+            .collect();
+        assert!(matches!(
+            parts.as_slice(),
+            [
+                ("a", Some(AnnotationType::Match)),
+                ("aa", Some(AnnotationType::Match)),
+                ("a", None)
+            ]
+        ));
+    }
+
+    #[test]
+    fn known_matches_are_highlighted_like_found_ones() {
+        let line = Line::from("aaaaa 老虎aa\tx");
+        let matches = line.find_all_graphemes("aa");
+        assert_eq!(matches, [0, 2, 8]);
+        let known = RenderOptions {
+            matches: Some(&matches),
+            ..RenderOptions::default()
+        };
+        for start in 0..line.width() {
+            for end in start..=line.width() {
+                let found =
+                    line.get_annotated_visible_substr(start..end, Some("aa"), Some(2), known);
+                let searched = line.get_annotated_visible_substr(
+                    start..end,
+                    Some("aa"),
+                    Some(2),
+                    RenderOptions::default(),
+                );
+                let parts = |text: AnnotatedString| -> Vec<(String, Option<AnnotationType>)> {
+                    text.into_iter()
+                        .map(|part| (part.string.to_string(), part.typ))
+                        .collect()
+                };
+                assert_eq!(
+                    format!("{:?}", parts(found)),
+                    format!("{:?}", parts(searched)),
+                    "{start}..{end}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn rendering_a_window_of_a_long_line_is_cheap() {
+        // building the whole megabyte line for each of these takes many seconds
+        let text = "{\"key\": [1, 2, 3], \"name\": \"hecto\"}, ".repeat(28_000);
+        let line = Line::from(&text);
+        assert!(text.len() >= 1_000_000);
+        let options = RenderOptions {
+            word: Some("name"),
+            ..RenderOptions::default()
+        };
+        let start = std::time::Instant::now();
+        for offset in (0..line.width()).step_by(1_000) {
+            let visible =
+                line.get_annotated_visible_substr(offset..offset + 80, Some("key"), None, options);
+            assert_eq!(visible.to_string(), &text[offset..offset + 80]);
+        }
+        assert!(start.elapsed() < std::time::Duration::from_secs(2));
+    }
+
+    #[test]
+    fn swaps_graphemes_of_any_width() {
+        let mut line = Line::from("a界e\u{301}b");
+        line.swap_graphemes(1).unwrap();
+        assert_eq!(line.to_string(), "ae\u{301}界b");
+        assert_eq!(line.width_until(2), 2);
+        assert_eq!(line.width(), 5);
+        line.swap_graphemes(2).unwrap();
+        assert_eq!(line.to_string(), "ae\u{301}b界");
+        assert!(line.swap_graphemes(3).is_err());
+    }
+
+    #[test]
+    fn describes_graphemes() {
+        let line = Line::from("e\u{301}🇩🇪\u{7}a\t");
+        let describe = |idx| line.grapheme_at(idx).map(TextFragment::describe);
+        assert_eq!(
+            describe(0).unwrap(),
+            "\"e\u{301}\" U+0065 U+0301, 3 bytes, width 1"
+        );
+        assert_eq!(
+            describe(1).unwrap(),
+            "\"🇩🇪\" U+1F1E9 U+1F1EA, 8 bytes, width 2"
+        );
+        assert_eq!(
+            describe(2).unwrap(),
+            "\"\\u{7}\" U+0007, 1 byte, width 1, shown as '▯'"
+        );
+        assert_eq!(describe(3).unwrap(), "\"a\" U+0061, 1 byte, width 1");
+        assert_eq!(
+            describe(4).unwrap(),
+            "\"\\t\" U+0009, 1 byte, width 3, shown as ' '"
+        );
+        assert_eq!(describe(5), None);
+    }
+
+    #[test]
+    fn tokens_reach_up_to_whitespace() {
+        let line = Line::from("see (~/notes.md), then");
+        assert_eq!(line.token_at(6), Some(4..17));
+        assert_eq!(line.token_at(17), Some(4..17));
+        assert_eq!(line.token_at(18), Some(18..22));
+        assert_eq!(line.token_at(22), Some(18..22));
+        assert_eq!(Line::from("a  b").token_at(2), None);
+    }
+
+    #[test]
+    fn only_whole_words_are_highlighted() {
+        let line = Line::from("foo food (foo) foo_bar foo");
+        assert_eq!(line.word_at(1), Some(0..3));
+        assert_eq!(line.word_at(3), Some(0..3));
+        assert_eq!(line.word_at(9), None);
+        assert_eq!(line.text_of(0..3), "foo");
+        assert_eq!(line.words(), ["foo", "food", "foo", "foo_bar", "foo"]);
+        assert_eq!(Line::from("naïve café!").words(), ["naïve", "café"]);
+
+        let options = RenderOptions {
+            word: Some("foo"),
+            ..RenderOptions::default()
+        };
+        let visible = line.get_annotated_visible_substr(0..40, None, None, options);
+        let highlighted: Vec<_> = (&visible)
+            .into_iter()
+            .filter(|part| matches!(part.typ, Some(AnnotationType::WordMatch)))
+            .map(|part| part.string)
+            .collect();
+        assert_eq!(highlighted, ["foo", "foo", "foo"]);
+    }
+
+    #[test]
+    fn invisibles_are_shown_without_moving_the_edge() {
+        let shown = RenderOptions {
+            invisibles: Invisibles::Shown,
+            ..RenderOptions::default()
+        };
+        let mut line = Line::from("a b\t\tc");
+        line.set_widths(tabs(4));
+        let visible = line.get_annotated_visible_substr(0..10, None, None, shown);
+        assert_eq!(visible.to_string(), "a·b→→   c¶");
+        assert!(
+            (&visible)
+                .into_iter()
+                .any(|part| part.string == "·"
+                    && matches!(part.typ, Some(AnnotationType::Invisible)))
+        );
+        // no column is left for the end of the line
+        let visible = line.get_annotated_visible_substr(0..9, None, None, shown);
+        assert_eq!(visible.to_string(), "a·b→→   c");
+
+        let hidden = RenderOptions {
+            invisibles: Invisibles::Hidden,
+            ..RenderOptions::default()
+        };
+        let line = Line::from("a\u{7}b");
+        let visible = line.get_annotated_visible_substr(0..10, None, None, hidden);
+        assert_eq!(visible.to_string(), "a b");
+    }
 }
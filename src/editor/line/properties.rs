@@ -0,0 +1,192 @@
+// Invariants of `Line` checked on generated text and edits. The text leans on what has broken
+// before: combining marks, joiners, regional indicators, tabs, control and wide characters.
+use super::{CellWidths, Invisibles, Line};
+use proptest::{prelude::*, sample::Index};
+use unicode_segmentation::UnicodeSegmentation;
+
+fn piece() -> impl Strategy<Value = String> {
+    prop_oneof![
+        "[a-z ]{1,3}",
+        Just(String::from("\t")),
+        Just(String::from("\u{301}")),
+        Just(String::from("\u{200D}")),
+        Just(String::from("\u{FE0F}")),
+        Just(String::from("\u{1F1E9}")),
+        Just(String::from("\u{1F1EA}")),
+        Just(String::from("👨")),
+        Just(String::from("老虎")),
+        Just(String::from("\u{1}")),
+        Just(String::from("\r")),
+        any::<char>()
+            .prop_filter("a line has no line break", |ch| *ch != '\n')
+            .prop_map(String::from),
+    ]
+}
+
+fn text() -> impl Strategy<Value = String> {
+    prop::collection::vec(piece(), 0..12).prop_map(|pieces| pieces.concat())
+}
+
+fn ch() -> impl Strategy<Value = char> {
+    piece().prop_filter_map("a single character", |piece| {
+        let mut chars = piece.chars();
+        chars.next().filter(|_| chars.next().is_none())
+    })
+}
+
+// indices are into the graphemes and the end of the line
+#[derive(Clone, Debug)]
+enum Edit {
+    Insert(char, Index),
+    Delete(Index),
+    Swap(Index),
+    Append(String),
+    Split(Index),
+    Tab(usize),
+}
+
+fn edit() -> impl Strategy<Value = Edit> {
+    prop_oneof![
+        (ch(), any::<Index>()).prop_map(|(ch, at)| Edit::Insert(ch, at)),
+        any::<Index>().prop_map(Edit::Delete),
+        any::<Index>().prop_map(Edit::Swap),
+        text().prop_map(Edit::Append),
+        any::<Index>().prop_map(Edit::Split),
+        (1_usize..9).prop_map(Edit::Tab),
+    ]
+}
+
+fn apply(line: &mut Line, edit: &Edit) {
+    let index = |at: &Index| at.index(line.grapheme_count().saturating_add(1));
+    match edit {
+        Edit::Insert(ch, at) => {
+            let _ = line.insert_char(*ch, index(at));
+        }
+        Edit::Delete(at) => {
+            let _ = line.delete(index(at));
+        }
+        Edit::Swap(at) => {
+            let _ = line.swap_graphemes(index(at));
+        }
+        Edit::Append(text) => line.append(&Line::from(text)),
+        Edit::Split(at) => {
+            let _ = line.split(index(at));
+        }
+        Edit::Tab(tab) => line.set_widths(CellWidths {
+            tab: *tab,
+            ..CellWidths::DEFAULT
+        }),
+    }
+}
+
+fn check_invariants(line: &Line) -> Result<(), TestCaseError> {
+    let fragments = line.fragments();
+    for pair in fragments.windows(2) {
+        prop_assert!(pair[0].start_byte_idx < pair[1].start_byte_idx);
+        prop_assert_eq!(pair[0].end_col_idx(), pair[1].start_col_idx);
+    }
+    for fragment in fragments {
+        let start = fragment.start_byte_idx;
+        prop_assert_eq!(
+            line.string
+                .get(start..start.saturating_add(fragment.grapheme.len())),
+            Some(fragment.grapheme.as_str())
+        );
+    }
+    let graphemes: Vec<&str> = line.string.graphemes(true).collect();
+    prop_assert_eq!(line.grapheme_count(), graphemes.len());
+    let fresh = Line::with_widths(&line.string, line.widths);
+    prop_assert_eq!(line.width(), fresh.width());
+    for grapheme_idx in 0..=line.grapheme_count() {
+        prop_assert!(
+            line.width_until(grapheme_idx) <= line.width_until(grapheme_idx.saturating_add(1))
+        );
+        prop_assert_eq!(
+            line.width_until(grapheme_idx),
+            fresh.width_until(grapheme_idx)
+        );
+    }
+    for byte_idx in 0..=line.string.len().saturating_add(1) {
+        let grapheme_idx = line.byte_idx_to_grapheme_idx(byte_idx);
+        if byte_idx > line.string.len() {
+            prop_assert_eq!(grapheme_idx, None);
+        } else if let Some(fragment) = grapheme_idx.and_then(|idx| fragments.get(idx)) {
+            prop_assert!(fragment.start_byte_idx <= byte_idx);
+            prop_assert!(
+                byte_idx
+                    < fragment
+                        .start_byte_idx
+                        .saturating_add(fragment.grapheme.len())
+            );
+        } else {
+            prop_assert_eq!(byte_idx, line.string.len());
+            prop_assert_eq!(grapheme_idx, Some(line.grapheme_count()));
+        }
+    }
+    Ok(())
+}
+
+proptest! {
+    #[test]
+    fn edits_keep_lines_consistent(
+        text in text(),
+        edits in prop::collection::vec(edit(), 0..16),
+        built in any::<bool>(),
+    ) {
+        let mut line = Line::from(&text);
+        if built {
+            line.width();
+        }
+        for edit in &edits {
+            apply(&mut line, edit);
+            check_invariants(&line)?;
+        }
+    }
+
+    #[test]
+    fn inserting_and_deleting_a_grapheme_round_trips(
+        text in text(),
+        ch in ch(),
+        at in any::<Index>(),
+    ) {
+        let mut line = Line::from(&text);
+        let at = at.index(line.grapheme_count().saturating_add(1));
+        line.insert_char(ch, at).unwrap();
+        check_invariants(&line)?;
+        // unless it merged with its neighbours, which is a different grapheme to delete
+        let grapheme = line.grapheme_at(at).map(|fragment| fragment.grapheme.clone());
+        if grapheme == Some(String::from(ch)) {
+            line.delete(at).unwrap();
+            prop_assert_eq!(line.to_string(), text);
+            check_invariants(&line)?;
+        }
+    }
+
+    #[test]
+    fn splitting_and_appending_round_trips(text in text(), at in any::<Index>()) {
+        let mut line = Line::from(&text);
+        let at = at.index(line.grapheme_count().saturating_add(2));
+        let count = line.grapheme_count();
+        let tail = line.split(at);
+        prop_assert_eq!(line.grapheme_count(), at.min(count));
+        check_invariants(&line)?;
+        check_invariants(&tail)?;
+        line.append(&tail);
+        prop_assert_eq!(line.to_string(), text);
+        check_invariants(&line)?;
+    }
+
+    #[test]
+    fn any_visible_range_can_be_drawn(text in text(), start in any::<Index>(), end in any::<Index>()) {
+        let line = Line::from(&text);
+        // from 0 to two columns behind the end
+        let columns = line.width().saturating_add(3);
+        let (start, end) = (start.index(columns), end.index(columns));
+        let (start, end) = (start.min(end), start.max(end));
+        // every column of the range the line reaches into is filled, and nothing else
+        let visible = line.get_visible_graphemes(start..end);
+        let covered = end.min(line.width()).saturating_sub(start);
+        prop_assert_eq!(Line::from(&visible).width(), covered, "{:?}", visible);
+        prop_assert_eq!(line.visible_width(start..end, Invisibles::Hidden), covered);
+    }
+}
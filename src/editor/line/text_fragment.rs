@@ -1,10 +1,44 @@
 use super::ByteIdx;
+use super::ColIdx;
 use super::GraphemeWidth;
+use std::fmt::Write;
 
 #[derive(Debug)]
 pub struct TextFragment {
     pub start_byte_idx: ByteIdx,
+    pub start_col_idx: ColIdx,
     pub grapheme: String,
     pub rendered_width: GraphemeWidth,
     pub replacement: Option<char>,
 }
+
+impl TextFragment {
+    pub fn end_col_idx(&self) -> ColIdx {
+        self.start_col_idx
+            .saturating_add(self.rendered_width.into())
+    }
+
+    // e.g. `"é" U+0065 U+0301, 3 bytes, width 1`, with what is shown instead if anything
+    pub fn describe(&self) -> String {
+        let shown = if self.replacement.is_some() {
+            self.grapheme.escape_debug().to_string()
+        } else {
+            self.grapheme.clone()
+        };
+        let mut description = format!("\"{shown}\"");
+        for ch in self.grapheme.chars() {
+            let _ = write!(description, " U+{:04X}", u32::from(ch));
+        }
+        let bytes = self.grapheme.len();
+        let _ = write!(
+            description,
+            ", {bytes} byte{}, width {}",
+            if bytes == 1 { "" } else { "s" },
+            usize::from(self.rendered_width)
+        );
+        if let Some(replacement) = self.replacement {
+            let _ = write!(description, ", shown as '{replacement}'");
+        }
+        description
+    }
+}
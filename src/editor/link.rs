@@ -0,0 +1,98 @@
+use super::completion::expand_tilde;
+use std::{
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    thread,
+};
+
+// what the text under the caret points to
+#[derive(Debug, PartialEq)]
+pub enum Target {
+    Url(String),
+    Path(PathBuf),
+}
+
+// Make out what `token` points to, without the brackets, quotes and punctuation around it
+// in prose. Relative paths are relative to `dir`, the directory of the file they are in.
+pub fn target(token: &str, dir: Option<&Path>) -> Option<Target> {
+    let token = token
+        .trim_start_matches(['(', '[', '{', '<', '"', '\'', '`'])
+        .trim_end_matches([
+            ')', ']', '}', '>', '"', '\'', '`', ',', '.', ';', ':', '!', '?',
+        ]);
+    if token.is_empty() {
+        return None;
+    }
+    if let Some(path) = token.strip_prefix("file://") {
+        return Some(Target::Path(PathBuf::from(path)));
+    }
+    if is_url(token) {
+        return Some(Target::Url(token.to_string()));
+    }
+    let path = expand_tilde(token);
+    Some(Target::Path(match dir {
+        Some(dir) if path.is_relative() => dir.join(path),
+        _ => path,
+    }))
+}
+
+// e.g. "https://..." or "mailto:..."
+fn is_url(token: &str) -> bool {
+    if token.starts_with("mailto:") {
+        return true;
+    }
+    token.split_once("://").is_some_and(|(scheme, rest)| {
+        !rest.is_empty()
+            && scheme.starts_with(|ch: char| ch.is_ascii_alphabetic())
+            && scheme
+                .chars()
+                .all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '+' | '-' | '.'))
+    })
+}
+
+// Hand `target` to the system's opener, like a browser for a URL. It keeps running on its
+// own, only whether it could be started is waited for.
+pub fn open_externally(target: &str) -> Result<(), String> {
+    let opener = if cfg!(target_os = "macos") {
+        "open"
+    } else {
+        "xdg-open"
+    };
+    let mut child = Command::new(opener)
+        .arg(target)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|err| format!("Error running '{opener}': {err}"))?;
+    // reaped once it exits, so it doesn't linger as a zombie
+    thread::spawn(move || child.wait());
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn finds_urls_and_paths_in_prose() {
+        let url = |url: &str| Some(Target::Url(url.to_string()));
+        let path = |path: &str| Some(Target::Path(PathBuf::from(path)));
+        assert_eq!(
+            target("(https://example.com/a?b=c).", None),
+            url("https://example.com/a?b=c")
+        );
+        assert_eq!(
+            target("<mailto:me@example.com>", None),
+            url("mailto:me@example.com")
+        );
+        assert_eq!(target("file:///tmp/x.txt", None), path("/tmp/x.txt"));
+        let dir = Path::new("/notes");
+        assert_eq!(target("\"todo.md\",", Some(dir)), path("/notes/todo.md"));
+        assert_eq!(target("/etc/hosts", Some(dir)), path("/etc/hosts"));
+        assert_eq!(target("todo.md", None), path("todo.md"));
+        // a scheme needs something behind it
+        assert_eq!(target("a://", None), path("a://"));
+        assert_eq!(target("...", None), None);
+    }
+}
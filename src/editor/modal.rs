@@ -0,0 +1,172 @@
+use super::command::{
+    Command::{self, Edit as EditCommand, Move as MoveCommand, System},
+    Edit, Move,
+    System::Count,
+};
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Mode {
+    #[default]
+    Normal,
+    Insert,
+}
+
+impl Mode {
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Normal => "NORMAL",
+            Self::Insert => "INSERT",
+        }
+    }
+}
+
+// what a key typed in normal mode does, a pending count applies to each of them
+#[derive(Clone, Copy)]
+pub enum Action {
+    Run(Command),
+    // `G` goes to the last line and `gg` to the first, or both to the line of the count
+    GoToLine { last: bool },
+    DeleteLines,
+    YankLines,
+    Paste,
+    Insert,
+    Append,
+    OpenBelow,
+    OpenAbove,
+}
+
+pub enum Translation {
+    // translate the key into a command as usual
+    Unchanged,
+    // the key started a sequence or means nothing in this mode
+    Ignored,
+    Action(Action),
+}
+
+// Vi-like keys for those who set `modal = true`. Keys are translated here before they
+// become commands, so nothing changes for anyone else.
+#[derive(Default)]
+pub struct Modal {
+    mode: Mode,
+    // the first key of `gg`, `dd` or `yy`
+    pending: Option<char>,
+}
+
+impl Modal {
+    pub const fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: Mode) {
+        self.mode = mode;
+        self.pending = None;
+    }
+
+    // `has_count` tells whether `0` continues a count or moves to the start of the line
+    pub fn translate(&mut self, key: KeyEvent, has_count: bool) -> Translation {
+        match self.mode {
+            Mode::Insert if key.code == KeyCode::Esc && key.modifiers == KeyModifiers::NONE => {
+                self.set_mode(Mode::Normal);
+                Translation::Ignored
+            }
+            Mode::Insert => Translation::Unchanged,
+            Mode::Normal => self.translate_normal(key, has_count),
+        }
+    }
+
+    fn translate_normal(&mut self, key: KeyEvent, has_count: bool) -> Translation {
+        let KeyEvent {
+            code, modifiers, ..
+        } = key;
+        let KeyCode::Char(ch) = code else {
+            return self.translate_other(key);
+        };
+        if modifiers != KeyModifiers::NONE && modifiers != KeyModifiers::SHIFT {
+            return self.translate_other(key);
+        }
+
+        if let Some(pending) = self.pending.take() {
+            return match (pending, ch) {
+                ('g', 'g') => Translation::Action(Action::GoToLine { last: false }),
+                ('d', 'd') => Translation::Action(Action::DeleteLines),
+                ('y', 'y') => Translation::Action(Action::YankLines),
+                _ => Translation::Ignored,
+            };
+        }
+
+        let action = match ch {
+            '0' if has_count => Action::Run(System(Count(0))),
+            '1'..='9' => Action::Run(System(Count((ch as u8).saturating_sub(b'0')))),
+            'h' => Action::Run(MoveCommand(Move::Left)),
+            'j' => Action::Run(MoveCommand(Move::Down)),
+            'k' => Action::Run(MoveCommand(Move::Up)),
+            'l' => Action::Run(MoveCommand(Move::Right)),
+            '0' => Action::Run(MoveCommand(Move::StartOfLine)),
+            '$' => Action::Run(MoveCommand(Move::EndOfLine)),
+            'w' => Action::Run(MoveCommand(Move::NextWord)),
+            'b' => Action::Run(MoveCommand(Move::PreviousWord)),
+            'x' => Action::Run(EditCommand(Edit::Delete)),
+            'G' => Action::GoToLine { last: true },
+            'p' => Action::Paste,
+            'i' => Action::Insert,
+            'a' => Action::Append,
+            'o' => Action::OpenBelow,
+            'O' => Action::OpenAbove,
+            'g' | 'd' | 'y' => {
+                self.pending = Some(ch);
+                return Translation::Ignored;
+            }
+            _ => return Translation::Ignored,
+        };
+        Translation::Action(action)
+    }
+
+    // keys like the arrows and Ctrl shortcuts keep working, but nothing types text
+    fn translate_other(&mut self, key: KeyEvent) -> Translation {
+        self.pending = None;
+        match Command::try_from(Event::Key(key)) {
+            Ok(EditCommand(_)) => Translation::Ignored,
+            _ => Translation::Unchanged,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn translate(modal: &mut Modal, ch: char) -> Translation {
+        modal.translate(KeyEvent::new(KeyCode::Char(ch), KeyModifiers::NONE), false)
+    }
+
+    #[test]
+    fn sequences_need_both_keys() {
+        let mut modal = Modal::default();
+        assert!(matches!(translate(&mut modal, 'd'), Translation::Ignored));
+        assert!(matches!(
+            translate(&mut modal, 'd'),
+            Translation::Action(Action::DeleteLines)
+        ));
+        assert!(matches!(translate(&mut modal, 'g'), Translation::Ignored));
+        assert!(matches!(translate(&mut modal, 'j'), Translation::Ignored));
+        assert!(matches!(
+            translate(&mut modal, 'j'),
+            Translation::Action(Action::Run(MoveCommand(Move::Down)))
+        ));
+    }
+
+    #[test]
+    fn insert_mode_only_takes_esc() {
+        let mut modal = Modal::default();
+        modal.set_mode(Mode::Insert);
+        assert!(matches!(translate(&mut modal, 'j'), Translation::Unchanged));
+        let esc = KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE);
+        assert!(matches!(modal.translate(esc, false), Translation::Ignored));
+        assert_eq!(modal.mode(), Mode::Normal);
+        assert!(matches!(
+            modal.translate(esc, false),
+            Translation::Unchanged
+        ));
+    }
+}
@@ -0,0 +1,20 @@
+use super::ui::{BufferList, ByteInspector, DiffList, MatchList, UIComponent};
+
+// a list borrowing the bottom third of the rows above the status bar while its prompt is open
+pub enum Overlay {
+    Matches(MatchList),
+    Diff(DiffList),
+    Bytes(ByteInspector),
+    Buffers(BufferList),
+}
+
+impl Overlay {
+    pub fn component(&mut self) -> &mut dyn UIComponent {
+        match self {
+            Self::Matches(match_list) => match_list,
+            Self::Diff(diff_list) => diff_list,
+            Self::Bytes(inspector) => inspector,
+            Self::Buffers(buffer_list) => buffer_list,
+        }
+    }
+}
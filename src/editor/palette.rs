@@ -0,0 +1,515 @@
+use super::{
+    Editor, Encoding, LineEnding, MarkAction, PromptType,
+    command::{Edit, Move},
+    line::Invisibles,
+};
+
+// a command which can be run by name from the palette, keys run them through their name as well
+pub struct NamedCommand {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub handler: fn(&mut Editor),
+}
+
+impl NamedCommand {
+    const fn new(name: &'static str, description: &'static str, handler: fn(&mut Editor)) -> Self {
+        Self {
+            name,
+            description,
+            handler,
+        }
+    }
+}
+
+// every named command, new features register theirs here
+#[allow(clippy::too_many_lines)]
+pub fn registry() -> Vec<NamedCommand> {
+    vec![
+        NamedCommand::new("save", "Save the buffer", Editor::handle_save),
+        NamedCommand::new("open", "Open a file in a new buffer", |editor| {
+            editor.set_prompt(PromptType::Open);
+        }),
+        NamedCommand::new("reload", "Reload the file from disk", |editor| {
+            editor.handle_reload();
+            editor.redraw_other_pane();
+        }),
+        NamedCommand::new(
+            "reload-config",
+            "Reload the configuration",
+            Editor::reload_config,
+        ),
+        NamedCommand::new("search", "Search in the buffer", Editor::handle_search),
+        NamedCommand::new(
+            "search-files",
+            "List the matches in every file below the working directory",
+            |editor| editor.set_prompt(PromptType::SearchFiles),
+        ),
+        NamedCommand::new(
+            "search-next",
+            "Jump to the next match",
+            Editor::handle_search_next,
+        ),
+        NamedCommand::new(
+            "search-previous",
+            "Jump to the previous match",
+            Editor::handle_search_previous,
+        ),
+        NamedCommand::new(
+            "list-matches",
+            "List every match of the search to jump to one",
+            Editor::list_matches,
+        ),
+        NamedCommand::new(
+            "diff-saved",
+            "List the changes since the file was saved",
+            Editor::diff_against_saved,
+        ),
+        NamedCommand::new(
+            "inspect-bytes",
+            "Show the bytes of the caret's line in hex, or go back to the text",
+            Editor::toggle_byte_inspector,
+        ),
+        NamedCommand::new("go-to-line", "Jump to a line number", |editor| {
+            editor.set_prompt(PromptType::GoToLine);
+        }),
+        NamedCommand::new(
+            "center-caret",
+            "Scroll the caret's line to the middle of the view",
+            |editor| editor.view_mut().center_caret(),
+        ),
+        NamedCommand::new(
+            "scroll-caret-to-top",
+            "Scroll the caret's line to the top of the view",
+            |editor| editor.view_mut().scroll_caret_to_top(),
+        ),
+        NamedCommand::new(
+            "scroll-caret-to-bottom",
+            "Scroll the caret's line to the bottom of the view",
+            |editor| editor.view_mut().scroll_caret_to_bottom(),
+        ),
+        NamedCommand::new(
+            "next-edited-line",
+            "Jump to the next lines edited since saving",
+            |editor| editor.jump_to_edited_line(true),
+        ),
+        NamedCommand::new(
+            "previous-edited-line",
+            "Jump to the previous lines edited since saving",
+            |editor| editor.jump_to_edited_line(false),
+        ),
+        NamedCommand::new(
+            "next-hunk",
+            "Jump to the next lines differing from the file checked in with git",
+            |editor| editor.jump_to_git_hunk(true),
+        ),
+        NamedCommand::new(
+            "previous-hunk",
+            "Jump to the previous lines differing from the file checked in with git",
+            |editor| editor.jump_to_git_hunk(false),
+        ),
+        NamedCommand::new(
+            "refresh-git",
+            "Compare the buffer with the file checked in with git again",
+            |editor| editor.view_mut().compare_with_head(),
+        ),
+        NamedCommand::new(
+            "jump-back",
+            "Go back to where the caret jumped from",
+            |editor| editor.view_mut().handle_move_command(Move::JumpBack),
+        ),
+        NamedCommand::new(
+            "jump-forward",
+            "Go forward again after jumping back",
+            |editor| editor.view_mut().handle_move_command(Move::JumpForward),
+        ),
+        NamedCommand::new(
+            "set-mark",
+            "Name the caret's location with the next key",
+            |editor| editor.await_mark(MarkAction::Set),
+        ),
+        NamedCommand::new(
+            "jump-to-mark",
+            "Jump to the mark named by the next key",
+            |editor| editor.await_mark(MarkAction::Jump),
+        ),
+        NamedCommand::new(
+            "delete-mark",
+            "Delete the mark named by the next key",
+            |editor| editor.await_mark(MarkAction::Delete),
+        ),
+        NamedCommand::new("next-mark", "Jump to the next line with a mark", |editor| {
+            editor.jump_to_next_mark(true);
+        }),
+        NamedCommand::new(
+            "previous-mark",
+            "Jump to the previous line with a mark",
+            |editor| editor.jump_to_next_mark(false),
+        ),
+        NamedCommand::new(
+            "complete-word",
+            "Complete the word left of the caret from the buffer",
+            |editor| editor.complete_word(false),
+        ),
+        NamedCommand::new("list-marks", "Show the marks of the buffer", |editor| {
+            let marks = editor.view().describe_marks();
+            if marks.is_empty() {
+                editor.update_message("No marks set");
+            } else {
+                editor.update_message(&format!("Marks: {}", marks.join(", ")));
+            }
+        }),
+        NamedCommand::new(
+            "statistics",
+            "Count the lines, words, graphemes and bytes",
+            |editor| {
+                let statistics = editor.view().describe_statistics();
+                editor.update_message(&statistics);
+            },
+        ),
+        NamedCommand::new(
+            "describe-character",
+            "Show what the character under the caret is made of",
+            |editor| {
+                let description = editor.view().describe_grapheme();
+                editor.update_message(&description);
+            },
+        ),
+        NamedCommand::new(
+            "insert-command-output",
+            "Insert what a shell command prints",
+            |editor| {
+                editor.set_prompt(PromptType::Shell {
+                    keep_newline: false,
+                });
+            },
+        ),
+        NamedCommand::new(
+            "insert-command-lines",
+            "Insert what a shell command prints, with its last line break",
+            |editor| {
+                editor.set_prompt(PromptType::Shell { keep_newline: true });
+            },
+        ),
+        NamedCommand::new(
+            "filter-buffer",
+            "Replace the buffer by what a shell command makes of it",
+            |editor| editor.set_prompt(PromptType::Filter),
+        ),
+        NamedCommand::new(
+            "format-buffer",
+            "Run the formatter configured for the file type",
+            Editor::format_buffer,
+        ),
+        NamedCommand::new(
+            "open-under-caret",
+            "Open the file or URL under the caret",
+            Editor::open_under_caret,
+        ),
+        NamedCommand::new(
+            "insert-unicode",
+            "Insert a character by its code point or name",
+            |editor| editor.set_prompt(PromptType::Unicode),
+        ),
+        NamedCommand::new(
+            "open-line-below",
+            "Insert an empty line below and move onto it",
+            |editor| {
+                editor.view_mut().handle_edit_command(Edit::InsertLineBelow);
+                editor.edited();
+            },
+        ),
+        NamedCommand::new(
+            "open-line-above",
+            "Insert an empty line above and move onto it",
+            |editor| {
+                editor.view_mut().handle_edit_command(Edit::InsertLineAbove);
+                editor.edited();
+            },
+        ),
+        NamedCommand::new(
+            "transpose-characters",
+            "Swap the characters around the caret",
+            |editor| {
+                editor
+                    .view_mut()
+                    .handle_edit_command(Edit::TransposeGraphemes);
+                editor.edited();
+            },
+        ),
+        NamedCommand::new(
+            "transpose-lines",
+            "Swap the line with the one above",
+            |editor| {
+                editor.view_mut().handle_edit_command(Edit::TransposeLines);
+                editor.edited();
+            },
+        ),
+        NamedCommand::new("sort-lines", "Sort the lines", |editor| {
+            editor.transform_lines("Lines sorted", |mut lines| {
+                lines.sort_by(|a, b| (**a).cmp(b));
+                lines
+            });
+        }),
+        NamedCommand::new(
+            "sort-lines-descending",
+            "Sort the lines, the last first",
+            |editor| {
+                editor.transform_lines("Lines sorted", |mut lines| {
+                    lines.sort_by(|a, b| (**b).cmp(a));
+                    lines
+                });
+            },
+        ),
+        NamedCommand::new(
+            "sort-lines-ignoring-case",
+            "Sort the lines regardless of case",
+            |editor| {
+                editor.transform_lines("Lines sorted", |mut lines| {
+                    lines.sort_by_cached_key(|line| line.to_lowercase());
+                    lines
+                });
+            },
+        ),
+        NamedCommand::new(
+            "reverse-lines",
+            "Reverse the order of the lines",
+            |editor| {
+                editor.transform_lines("Lines reversed", |mut lines| {
+                    lines.reverse();
+                    lines
+                });
+            },
+        ),
+        NamedCommand::new(
+            "remove-duplicate-lines",
+            "Remove lines which repeat the one before",
+            |editor| {
+                editor.transform_lines("Duplicate lines removed", |mut lines| {
+                    lines.dedup_by(|a, b| **a == **b);
+                    lines
+                });
+            },
+        ),
+        NamedCommand::new("next-buffer", "Switch to the next buffer", |editor| {
+            editor.switch_buffer(editor.focused_buffer_idx().saturating_add(1));
+        }),
+        NamedCommand::new(
+            "previous-buffer",
+            "Switch to the previous buffer",
+            |editor| {
+                editor.switch_buffer(
+                    editor
+                        .focused_buffer_idx()
+                        .checked_sub(1)
+                        .unwrap_or_else(|| editor.views.len().saturating_sub(1)),
+                );
+            },
+        ),
+        NamedCommand::new(
+            "list-buffers",
+            "Pick one of the open buffers from a list",
+            Editor::list_buffers,
+        ),
+        NamedCommand::new("split", "Split the view", Editor::split_view),
+        NamedCommand::new("close-split", "Close the split", Editor::close_split),
+        NamedCommand::new(
+            "switch-pane",
+            "Move the focus to the other pane",
+            Editor::switch_pane,
+        ),
+        NamedCommand::new("redraw", "Repaint the whole screen", |editor| {
+            editor.redraw(true);
+        }),
+        NamedCommand::new(
+            "toggle-line-numbers",
+            "Show or hide line numbers",
+            |editor| {
+                let mut config = editor.config.clone();
+                config.show_line_numbers = !config.show_line_numbers;
+                editor.apply_config(Ok(config));
+            },
+        ),
+        NamedCommand::new(
+            "set-file-type",
+            "Pick the file type instead of going by the extension",
+            |editor| editor.set_prompt(PromptType::FileType),
+        ),
+        NamedCommand::new(
+            "line-endings-unix",
+            "Use Unix (LF) line endings",
+            |editor| editor.set_line_ending(LineEnding::Unix),
+        ),
+        NamedCommand::new(
+            "line-endings-dos",
+            "Use Windows (CRLF) line endings",
+            |editor| editor.set_line_ending(LineEnding::Dos),
+        ),
+        NamedCommand::new(
+            "encoding-utf-8",
+            "Save without a byte order mark",
+            |editor| editor.set_encoding(Encoding::Utf8),
+        ),
+        NamedCommand::new(
+            "encoding-utf-8-bom",
+            "Save with a UTF-8 byte order mark",
+            |editor| editor.set_encoding(Encoding::Utf8Bom),
+        ),
+        NamedCommand::new(
+            "encoding-windows-1252",
+            "Save as Windows-1252, for tools which don't read UTF-8",
+            |editor| editor.set_encoding(Encoding::Windows1252),
+        ),
+        NamedCommand::new(
+            "toggle-invisibles",
+            "Show or mark spaces, tabs and line ends",
+            |editor| {
+                let mut config = editor.config.clone();
+                config.invisibles = if config.invisibles == Invisibles::Shown {
+                    Invisibles::Marked
+                } else {
+                    Invisibles::Shown
+                };
+                editor.apply_config(Ok(config));
+            },
+        ),
+        NamedCommand::new(
+            "toggle-word-highlight",
+            "Highlight the word under the caret wherever it is",
+            |editor| {
+                let mut config = editor.config.clone();
+                config.highlight_word = !config.highlight_word;
+                editor.apply_config(Ok(config));
+            },
+        ),
+        NamedCommand::new(
+            "toggle-spell-check",
+            "Underline misspelled words in this buffer, or stop",
+            Editor::toggle_spell_check,
+        ),
+        NamedCommand::new(
+            "add-word",
+            "Add the word under the caret to the dictionary",
+            Editor::add_word,
+        ),
+        NamedCommand::new("toggle-wrap", "Wrap long lines or scroll them", |editor| {
+            let mut config = editor.config.clone();
+            config.wrap = !config.wrap;
+            editor.apply_config(Ok(config));
+        }),
+        NamedCommand::new(
+            "toggle-scrollbar",
+            "Show or hide the scrollbar on the right",
+            |editor| {
+                let mut config = editor.config.clone();
+                config.scrollbar = !config.scrollbar;
+                editor.apply_config(Ok(config));
+            },
+        ),
+        NamedCommand::new("save-all", "Save every modified buffer", Editor::save_all),
+        NamedCommand::new(
+            "close-buffer",
+            "Close the buffer, asking if it has unsaved changes",
+            Editor::close_buffer,
+        ),
+        NamedCommand::new("quit", "Quit hecto", Editor::handle_quit),
+    ]
+}
+
+// the commands matching what was typed into the palette, one of which is selected
+#[derive(Default)]
+pub struct Palette {
+    matches: Vec<usize>,
+    selected: usize,
+}
+
+impl Palette {
+    // select the first of the commands starting with `prefix`
+    pub fn filter(&mut self, commands: &[NamedCommand], prefix: &str) {
+        let prefix = prefix.to_lowercase();
+        self.matches = commands
+            .iter()
+            .enumerate()
+            .filter(|(_, command)| command.name.starts_with(&prefix))
+            .map(|(idx, _)| idx)
+            .collect();
+        self.selected = 0;
+    }
+
+    // the index of the selected command in the registry
+    pub fn selected(&self) -> Option<usize> {
+        self.matches.get(self.selected).copied()
+    }
+
+    pub fn select_next(&mut self) {
+        self.selected = self
+            .selected
+            .saturating_add(1)
+            .checked_rem(self.matches.len())
+            .unwrap_or(0);
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self
+            .selected
+            .checked_sub(1)
+            .unwrap_or_else(|| self.matches.len().saturating_sub(1));
+    }
+
+    // e.g. "[2/5] search-next: Jump to the next match"
+    pub fn hint(&self, commands: &[NamedCommand]) -> String {
+        self.selected()
+            .and_then(|idx| commands.get(idx))
+            .map_or_else(
+                || String::from("[no match]"),
+                |command| {
+                    format!(
+                        "[{}/{}] {}: {}",
+                        self.selected.saturating_add(1),
+                        self.matches.len(),
+                        command.name,
+                        command.description
+                    )
+                },
+            )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn filters_by_prefix_and_cycles() {
+        let commands = registry();
+        let mut palette = Palette::default();
+        palette.filter(&commands, "Sea");
+        assert_eq!(palette.matches.len(), 4);
+        assert_eq!(commands[palette.selected().unwrap()].name, "search");
+
+        palette.select_previous();
+        assert_eq!(
+            commands[palette.selected().unwrap()].name,
+            "search-previous"
+        );
+        palette.select_next();
+        assert_eq!(commands[palette.selected().unwrap()].name, "search");
+
+        palette.filter(&commands, "nothing");
+        assert_eq!(palette.selected(), None);
+        assert_eq!(palette.hint(&commands), "[no match]");
+    }
+
+    #[test]
+    fn names_are_unique() {
+        let commands = registry();
+        for (idx, command) in commands.iter().enumerate() {
+            assert!(
+                commands[..idx]
+                    .iter()
+                    .all(|other| other.name != command.name),
+                "{} is registered twice",
+                command.name
+            );
+        }
+    }
+}
@@ -1,7 +1,7 @@
 pub type Row = usize;
 pub type Col = usize;
 
-#[derive(Default)]
+#[derive(Default, Clone, Copy)]
 pub struct Position {
     pub row: Row,
     pub col: Col,
@@ -0,0 +1,94 @@
+use super::{Editor, PromptType, command::Move};
+
+// A prompt asking for text, and what the editor does with what is typed. The prompt is
+// dismissed before `on_submit` and `on_cancel` run, so they may open another one.
+pub struct Prompt {
+    pub label: &'static str,
+    // whether a typed character goes into the value, others are ignored
+    pub accepts: fn(char) -> bool,
+    // after every edit of the value, e.g. to search while typing
+    pub on_change: fn(&mut Editor),
+    // what Tab does, without it a tab is typed like any other character
+    pub on_tab: Option<fn(&mut Editor)>,
+    // what Up and Down do, for prompts choosing from a list
+    pub on_select: Option<fn(&mut Editor, Move)>,
+    // Enter, with the value typed
+    pub on_submit: fn(&mut Editor, &str),
+    // Esc
+    pub on_cancel: fn(&mut Editor),
+}
+
+impl Prompt {
+    fn new(label: &'static str, on_submit: fn(&mut Editor, &str)) -> Self {
+        Self {
+            label,
+            accepts: |_| true,
+            on_change: |_| {},
+            on_tab: None,
+            on_select: None,
+            on_submit,
+            on_cancel: |_| {},
+        }
+    }
+}
+
+// every prompt asking for text, new ones declare theirs here. None for the lists and
+// questions, which take keys of their own.
+pub fn text_prompt(prompt_type: &PromptType) -> Option<Prompt> {
+    let prompt = match prompt_type {
+        PromptType::Save => Prompt {
+            on_change: |editor| editor.completion = None,
+            on_tab: Some(Editor::complete_filename),
+            on_cancel: |editor| editor.abort("Save"),
+            ..Prompt::new("Save as: ", Editor::handle_save_as)
+        },
+        PromptType::Open => Prompt {
+            on_change: |editor| editor.completion = None,
+            on_tab: Some(Editor::complete_filename),
+            on_cancel: |editor| editor.update_message("Open aborted"),
+            ..Prompt::new("Open: ", Editor::open)
+        },
+        PromptType::Search => Prompt {
+            on_change: Editor::search_while_typing,
+            on_cancel: Editor::abort_search,
+            ..Prompt::new("Search: ", Editor::submit_search)
+        },
+        PromptType::SearchFiles => Prompt {
+            on_cancel: |editor| editor.update_message("Search aborted"),
+            ..Prompt::new("Search files: ", Editor::search_files)
+        },
+        PromptType::Palette => Prompt {
+            on_change: Editor::filter_palette,
+            on_tab: Some(Editor::complete_command_name),
+            on_select: Some(Editor::select_command),
+            ..Prompt::new("Command: ", |editor, _| editor.run_selected_command())
+        },
+        PromptType::GoToLine => Prompt {
+            accepts: |ch| ch.is_ascii_digit() || matches!(ch, '%' | ':' | ' '),
+            ..Prompt::new("Go to line: ", Editor::go_to)
+        },
+        PromptType::Shell { keep_newline } => {
+            let on_submit: fn(&mut Editor, &str) = if *keep_newline {
+                |editor, command| editor.insert_command_output(command, true)
+            } else {
+                |editor, command| editor.insert_command_output(command, false)
+            };
+            Prompt::new("Insert output of: ", on_submit)
+        }
+        PromptType::Filter => Prompt::new("Filter through: ", Editor::filter_buffer),
+        PromptType::Unicode => Prompt::new("Insert character: ", Editor::insert_unicode),
+        PromptType::FileType => Prompt {
+            on_change: |editor| editor.completion = None,
+            on_tab: Some(Editor::complete_file_type),
+            ..Prompt::new("File type: ", Editor::set_file_type)
+        },
+        PromptType::Matches
+        | PromptType::Diff
+        | PromptType::Bytes
+        | PromptType::Buffers
+        | PromptType::Confirm
+        | PromptType::ConfirmQuit
+        | PromptType::None => return None,
+    };
+    Some(prompt)
+}
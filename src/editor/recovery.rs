@@ -0,0 +1,96 @@
+use super::autosave;
+use crate::dirs;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process,
+    time::SystemTime,
+};
+
+const EXTENSION: &str = "hecto-recover";
+
+// Where unsaved changes are dumped after a crash: next to the file, e.g.
+// `notes.txt.hecto-recover`, or in the cache directory for unnamed buffers. With a `dir`,
+// all of them go there instead.
+pub fn recovery_path(file: Option<&Path>, dir: Option<&Path>) -> Option<PathBuf> {
+    let name = match file {
+        Some(file) => format!("{}.{EXTENSION}", file.file_name()?.to_string_lossy()),
+        None => format!("unnamed-{}.{EXTENSION}", process::id()),
+    };
+    match (file, dir) {
+        (Some(file), None) => Some(file.with_file_name(name)),
+        (_, dir) => {
+            let dir = dir.map_or_else(dirs::cache_dir, |dir| Some(dir.to_path_buf()))?;
+            fs::create_dir_all(&dir).ok()?;
+            Some(dir.join(name))
+        }
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+// a recovery file or autosave sidecar for `file` which is newer than the file itself
+pub fn find(file: &Path, dir: Option<&Path>) -> Option<PathBuf> {
+    let saved_at = modified(file);
+    [recovery_path(Some(file), dir), autosave::sidecar_path(file)]
+        .into_iter()
+        .flatten()
+        .find(|recovery| {
+            modified(recovery).is_some_and(|recovered_at| {
+                saved_at.is_none_or(|saved_at| saved_at <= recovered_at)
+            })
+        })
+}
+
+// the newest recovery file of an unnamed buffer
+pub fn find_unnamed(dir: Option<&Path>) -> Option<PathBuf> {
+    let dir = dir.map_or_else(dirs::cache_dir, |dir| Some(dir.to_path_buf()))?;
+    fs::read_dir(dir)
+        .ok()?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == EXTENSION)
+                && path
+                    .file_name()
+                    .is_some_and(|name| name.to_string_lossy().starts_with("unnamed-"))
+        })
+        .max_by_key(|path| modified(path))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::{env, thread, time::Duration};
+
+    #[test]
+    fn recovery_file_sits_next_to_the_file() {
+        assert_eq!(
+            recovery_path(Some(Path::new("/tmp/notes.txt")), None),
+            Some(PathBuf::from("/tmp/notes.txt.hecto-recover"))
+        );
+    }
+
+    #[test]
+    fn only_newer_recovery_files_are_found() {
+        let file = env::temp_dir().join("hecto-recovery-age.txt");
+        let recovery = recovery_path(Some(&file), None).unwrap();
+        let _ = fs::remove_file(&recovery);
+        fs::write(&file, "old").unwrap();
+        assert_eq!(find(&file, None), None);
+
+        fs::write(&recovery, "new").unwrap();
+        assert_eq!(find(&file, None), Some(recovery.clone()));
+
+        // saving the file afterwards makes the recovery file stale
+        thread::sleep(Duration::from_millis(20));
+        fs::write(&file, "saved").unwrap();
+        assert_eq!(find(&file, None), None);
+        fs::remove_file(recovery).unwrap();
+    }
+}
@@ -0,0 +1,116 @@
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+
+/// Parses a keystroke script as replayed by `--script`. Characters are typed as they are,
+/// other keys are written like in the help message (`<C-f>`, `<A-s>`, `<S-Enter>`) and
+/// `<lt>` types a `<`. Line breaks are ignored so long scripts can be wrapped.
+///
+/// ```
+/// let events = hecto::parse_script("hi<lt><C-s>\n<Esc>")?;
+/// assert_eq!(events.len(), 5);
+/// # Ok::<(), String>(())
+/// ```
+///
+/// # Errors
+///
+/// If a key between `<` and `>` is unknown or not closed, naming it.
+pub fn parse_script(script: &str) -> Result<Vec<Event>, String> {
+    let mut events = Vec::new();
+    let mut chars = script.chars();
+    while let Some(ch) = chars.next() {
+        let key_event = match ch {
+            '\n' | '\r' => continue,
+            '<' => {
+                let (name, rest) = chars
+                    .as_str()
+                    .split_once('>')
+                    .ok_or_else(|| format!("Missing '>' in <{}", chars.as_str()))?;
+                chars = rest.chars();
+                parse_key(name).ok_or_else(|| format!("Unknown key <{name}>"))?
+            }
+            _ => KeyEvent::new(KeyCode::Char(ch), KeyModifiers::NONE),
+        };
+        events.push(Event::Key(key_event));
+    }
+    Ok(events)
+}
+
+fn parse_key(name: &str) -> Option<KeyEvent> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut key = name;
+    loop {
+        if let Some(rest) = key.strip_prefix("C-").filter(|rest| !rest.is_empty()) {
+            modifiers |= KeyModifiers::CONTROL;
+            key = rest;
+        } else if let Some(rest) = key.strip_prefix("A-").filter(|rest| !rest.is_empty()) {
+            modifiers |= KeyModifiers::ALT;
+            key = rest;
+        } else if let Some(rest) = key.strip_prefix("S-").filter(|rest| !rest.is_empty()) {
+            modifiers |= KeyModifiers::SHIFT;
+            key = rest;
+        } else {
+            break;
+        }
+    }
+
+    Some(KeyEvent::new(key_code(key)?, modifiers))
+}
+
+// a single character, or the name of a key like `Enter`, in any case
+pub fn key_code(name: &str) -> Option<KeyCode> {
+    let mut chars = name.chars();
+    let code = match (chars.next(), chars.next()) {
+        (Some(ch), None) => KeyCode::Char(ch),
+        _ => match name.to_ascii_lowercase().as_str() {
+            "lt" => KeyCode::Char('<'),
+            "enter" | "cr" => KeyCode::Enter,
+            "esc" => KeyCode::Esc,
+            "tab" => KeyCode::Tab,
+            "bs" => KeyCode::Backspace,
+            "del" => KeyCode::Delete,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            _ => return None,
+        },
+    };
+    Some(code)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> Event {
+        Event::Key(KeyEvent::new(code, modifiers))
+    }
+
+    #[test]
+    fn parses_characters_and_keys() {
+        let events = parse_script("a<lt>\n<C-f><A-PageDown><Enter><S-Enter>").unwrap();
+        assert_eq!(
+            events,
+            vec![
+                key(KeyCode::Char('a'), KeyModifiers::NONE),
+                key(KeyCode::Char('<'), KeyModifiers::NONE),
+                key(KeyCode::Char('f'), KeyModifiers::CONTROL),
+                key(KeyCode::PageDown, KeyModifiers::ALT),
+                key(KeyCode::Enter, KeyModifiers::NONE),
+                key(KeyCode::Enter, KeyModifiers::SHIFT),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_keys() {
+        assert_eq!(
+            parse_script("<C-Nope>"),
+            Err(String::from("Unknown key <C-Nope>"))
+        );
+        assert!(parse_script("<Enter").is_err());
+    }
+}
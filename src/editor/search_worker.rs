@@ -0,0 +1,323 @@
+use std::{
+    ops::Range,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+        mpsc::{self, Receiver, Sender},
+    },
+    thread,
+};
+
+// buffers with fewer lines are searched right away, it takes no longer than handling a key
+pub const MIN_LINES: usize = 100_000;
+
+// counts the searches, results are only wanted from the latest one
+type Generation = u64;
+type Requests = Sender<(Generation, Request)>;
+type Results = Receiver<(Generation, Progress)>;
+
+// how many lines are searched between looking whether the search is still wanted
+const LINES_BETWEEN_CHECKS: usize = 4_096;
+
+// where the first match of `query` is looked for: a snapshot of the buffer's lines, of which
+// those in `range` are searched, starting at the byte `from.1` of the line `from.0`
+pub struct Request {
+    pub lines: Arc<[String]>,
+    pub query: String,
+    pub from: (usize, usize),
+    pub range: Range<usize>,
+}
+
+// what the worker found so far: the line and byte of the first match, and how many there are
+#[derive(Debug, PartialEq, Eq)]
+pub struct Progress {
+    pub hit: Option<(usize, usize)>,
+    pub match_count: usize,
+    // set once all lines were searched, the match count is final then
+    pub done: bool,
+}
+
+// Searches big buffers on a thread of its own, so typing a query never waits for the scan.
+// Each search gets a new generation, and results of older ones are dropped unseen.
+pub struct SearchWorker {
+    // None until the first search starts the thread
+    channels: Option<(Requests, Results)>,
+    // the generation of the latest search, the worker gives up on any other
+    latest: Arc<AtomicU64>,
+    // the generation whose results are still to come
+    pending: Option<Generation>,
+    // buffers with fewer lines aren't searched in the background
+    pub min_lines: usize,
+}
+
+impl Default for SearchWorker {
+    fn default() -> Self {
+        Self {
+            channels: None,
+            latest: Arc::default(),
+            pending: None,
+            min_lines: MIN_LINES,
+        }
+    }
+}
+
+impl SearchWorker {
+    // search in the background, in place of any search still running
+    pub fn start(&mut self, request: Request) {
+        let generation = self.latest.fetch_add(1, Ordering::Relaxed).wrapping_add(1);
+        let (requests, _) = self.channels.get_or_insert_with(|| {
+            let (requests, received) = mpsc::channel();
+            let (sender, results) = mpsc::channel();
+            let latest = Arc::clone(&self.latest);
+            thread::spawn(move || run(&received, &sender, &latest));
+            (requests, results)
+        });
+        if requests.send((generation, request)).is_ok() {
+            self.pending = Some(generation);
+        } else {
+            // the thread is gone, the next search starts another one
+            self.channels = None;
+            self.pending = None;
+        }
+    }
+
+    // stop the running search, none of its results are reported
+    pub fn cancel(&mut self) {
+        if self.pending.take().is_some() {
+            self.latest.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub const fn is_searching(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    // The next result of the running search, waiting for it if `wait` is set. None if there
+    // is nothing new, or no search running.
+    pub fn next(&mut self, wait: bool) -> Option<Progress> {
+        let pending = self.pending?;
+        let (_, results) = self.channels.as_ref()?;
+        loop {
+            let received = if wait {
+                results.recv().ok()
+            } else {
+                match results.try_recv() {
+                    Ok(received) => Some(received),
+                    Err(mpsc::TryRecvError::Empty) => return None,
+                    Err(mpsc::TryRecvError::Disconnected) => None,
+                }
+            };
+            let Some((generation, progress)) = received else {
+                self.channels = None;
+                self.pending = None;
+                return None;
+            };
+            if generation == pending {
+                if progress.done {
+                    self.pending = None;
+                }
+                return Some(progress);
+            }
+        }
+    }
+}
+
+impl Drop for SearchWorker {
+    // the thread ends once it notices the channels are closed
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+fn run(
+    requests: &Receiver<(Generation, Request)>,
+    results: &Sender<(Generation, Progress)>,
+    latest: &AtomicU64,
+) {
+    while let Ok(mut next) = requests.recv() {
+        // only the latest request is still wanted
+        while let Ok(newer) = requests.try_recv() {
+            next = newer;
+        }
+        let (generation, request) = next;
+        let is_wanted = || latest.load(Ordering::Relaxed) == generation;
+        let report = |progress| results.send((generation, progress)).is_ok();
+        if !search(&request, is_wanted, report) {
+            return;
+        }
+    }
+}
+
+// Reports the first match from `request.from` on, wrapping around to the start of the range
+// like `Buffer::search_forward`, then the count of all matches. Returns false if nobody is
+// listening for results anymore.
+fn search(
+    request: &Request,
+    is_wanted: impl Fn() -> bool,
+    report: impl Fn(Progress) -> bool,
+) -> bool {
+    let Request {
+        lines,
+        query,
+        from,
+        range,
+    } = request;
+    let range = range.start.min(lines.len())..range.end.min(lines.len());
+    let (from_line, from_byte) = if range.contains(&from.0) {
+        *from
+    } else {
+        (range.start, 0)
+    };
+    let mut hit = None;
+    let mut match_count: usize = 0;
+    let line_idxs = range
+        .clone()
+        .cycle()
+        .skip(from_line.saturating_sub(range.start))
+        .take(range.len());
+    for (searched, line_idx) in line_idxs.enumerate() {
+        if searched % LINES_BETWEEN_CHECKS == 0 && !is_wanted() {
+            return true;
+        }
+        let line = &lines[line_idx];
+        match_count = match_count.saturating_add(line.match_indices(query.as_str()).count());
+        if hit.is_some() {
+            continue;
+        }
+        let found = if searched == 0 {
+            line.get(from_byte..)
+                .and_then(|rest| rest.find(query.as_str()))
+                .map(|byte_idx| byte_idx.saturating_add(from_byte))
+        } else {
+            line.find(query.as_str())
+        };
+        if let Some(byte_idx) = found {
+            hit = Some((line_idx, byte_idx));
+            if !report(Progress {
+                hit,
+                match_count,
+                done: false,
+            }) {
+                return false;
+            }
+        }
+    }
+    // wrapped around, matches before `from` on its line come last
+    if hit.is_none() {
+        hit = lines
+            .get(from_line)
+            .filter(|_| range.contains(&from_line))
+            .and_then(|line| line.find(query.as_str()))
+            .map(|byte_idx| (from_line, byte_idx));
+    }
+    !is_wanted()
+        || report(Progress {
+            hit,
+            match_count,
+            done: true,
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn request(text: &str, query: &str, from: (usize, usize), range: Range<usize>) -> Request {
+        Request {
+            lines: text.lines().map(str::to_string).collect(),
+            query: query.to_string(),
+            from,
+            range,
+        }
+    }
+
+    // the progress reported for `request`
+    fn progress(request: &Request) -> Vec<Progress> {
+        let reported = std::cell::RefCell::new(Vec::new());
+        let listening = search(
+            request,
+            || true,
+            |progress| {
+                reported.borrow_mut().push(progress);
+                true
+            },
+        );
+        assert!(listening);
+        reported.into_inner()
+    }
+
+    #[test]
+    fn reports_the_first_match_and_then_the_count() {
+        let text = "foo\nbar foo\nfoofoo\n";
+        assert_eq!(
+            progress(&request(text, "foo", (1, 1), 0..3)),
+            [
+                Progress {
+                    hit: Some((1, 4)),
+                    match_count: 1,
+                    done: false,
+                },
+                Progress {
+                    hit: Some((1, 4)),
+                    match_count: 4,
+                    done: true,
+                },
+            ]
+        );
+        // wrapping around to the start of the line searched from
+        assert_eq!(
+            progress(&request(text, "bar", (1, 1), 0..3)),
+            [Progress {
+                hit: Some((1, 0)),
+                match_count: 1,
+                done: true,
+            }]
+        );
+        // only within the range, starting at its top when searching from outside of it
+        let [first, _] = progress(&request(text, "foo", (0, 0), 1..2))
+            .try_into()
+            .unwrap();
+        assert_eq!(first.hit, Some((1, 4)));
+        assert_eq!(
+            progress(&request(text, "baz", (0, 0), 0..3)),
+            [Progress {
+                hit: None,
+                match_count: 0,
+                done: true,
+            }]
+        );
+    }
+
+    #[test]
+    fn reports_nothing_once_unwanted() {
+        let reported = search(
+            &request("foo\n", "foo", (0, 0), 0..1),
+            || false,
+            |_| panic!("reported an unwanted search"),
+        );
+        assert!(reported);
+    }
+
+    #[test]
+    fn only_the_latest_search_is_reported() {
+        let text = "a\n".repeat(10_000);
+        let mut worker = SearchWorker::default();
+        worker.start(request(&text, "a", (0, 0), 0..10_000));
+        worker.start(request(&text, "b", (0, 0), 0..10_000));
+        assert!(worker.is_searching());
+        assert_eq!(
+            worker.next(true),
+            Some(Progress {
+                hit: None,
+                match_count: 0,
+                done: true,
+            })
+        );
+        assert!(!worker.is_searching());
+        assert_eq!(worker.next(true), None);
+
+        worker.start(request(&text, "a", (0, 0), 0..10_000));
+        worker.cancel();
+        assert_eq!(worker.next(true), None);
+    }
+}
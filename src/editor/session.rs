@@ -0,0 +1,150 @@
+use super::config::describe_toml_error;
+use crate::dirs;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+// bumped whenever the format changes in a way older sessions can't be read as
+pub const VERSION: u32 = 1;
+// the session of `--session` without a name, in the working directory
+const LOCAL_FILENAME: &str = ".hecto-session";
+
+// The open buffers, written on quit and reopened by the next `--session` with the same name.
+#[derive(Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct Session {
+    pub version: u32,
+    // index into `buffers` of the one shown last
+    #[serde(default)]
+    pub active: usize,
+    #[serde(default)]
+    pub buffers: Vec<BufferState>,
+}
+
+// where a buffer's caret and scroll offset were, counted from 0 like `Location`
+#[derive(Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct BufferState {
+    pub path: PathBuf,
+    #[serde(default)]
+    pub line_idx: usize,
+    #[serde(default)]
+    pub grapheme_idx: usize,
+    #[serde(default)]
+    pub scroll_row: usize,
+    #[serde(default)]
+    pub scroll_col: usize,
+}
+
+/// `.hecto-session` in the working directory without a name, otherwise
+/// `$XDG_STATE_HOME/hecto/sessions/NAME.toml`.
+#[must_use]
+pub fn session_path(name: Option<&str>) -> Option<PathBuf> {
+    match name {
+        None => Some(PathBuf::from(LOCAL_FILENAME)),
+        Some(name) => Some(
+            dirs::state_dir()?
+                .join("sessions")
+                .join(format!("{name}.toml")),
+        ),
+    }
+}
+
+impl Session {
+    // None if there is no session at `path` yet. The error is a one-line message to show in
+    // the message bar.
+    pub fn load(path: &Path) -> Result<Option<Self>, String> {
+        match fs::read_to_string(path) {
+            Ok(toml) => Self::parse(&toml)
+                .map(Some)
+                .map_err(|err| format!("Session {}: {err}", path.display())),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(format!("Error reading '{}': {err}", path.display())),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let toml = toml::to_string(self).map_err(|err| err.to_string())?;
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)
+                .map_err(|err| format!("Error creating '{}': {err}", dir.display()))?;
+        }
+        fs::write(path, toml).map_err(|err| format!("Error writing '{}': {err}", path.display()))
+    }
+
+    fn parse(toml: &str) -> Result<Self, String> {
+        let session: Self = toml::from_str(toml).map_err(|err| describe_toml_error(toml, &err))?;
+        if session.version != VERSION {
+            return Err(format!(
+                "version {} is not supported, expected {VERSION}",
+                session.version
+            ));
+        }
+        Ok(session)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::env;
+
+    fn session() -> Session {
+        Session {
+            version: VERSION,
+            active: 1,
+            buffers: vec![
+                BufferState {
+                    path: PathBuf::from("/tmp/notes.txt"),
+                    line_idx: 12,
+                    grapheme_idx: 3,
+                    scroll_row: 4,
+                    scroll_col: 0,
+                },
+                BufferState {
+                    path: PathBuf::from("/tmp/ünïcode file.rs"),
+                    line_idx: 0,
+                    grapheme_idx: 0,
+                    scroll_row: 0,
+                    scroll_col: 8,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn round_trips_through_toml() {
+        let toml = toml::to_string(&session()).unwrap();
+        assert!(toml.starts_with("version = 1\n"), "{toml}");
+        assert_eq!(Session::parse(&toml), Ok(session()));
+        let empty = Session {
+            version: VERSION,
+            ..Session::default()
+        };
+        assert_eq!(Session::parse(&toml::to_string(&empty).unwrap()), Ok(empty));
+    }
+
+    #[test]
+    fn round_trips_through_a_file() {
+        let path = env::temp_dir()
+            .join("hecto-session-test")
+            .join("round-trip.toml");
+        let _ = fs::remove_file(&path);
+        assert_eq!(Session::load(&path), Ok(None));
+        session().save(&path).unwrap();
+        assert_eq!(Session::load(&path), Ok(Some(session())));
+    }
+
+    #[test]
+    fn rejects_other_versions_and_unknown_fields() {
+        let err = Session::parse("version = 2\n").unwrap_err();
+        assert_eq!(err, "version 2 is not supported, expected 1");
+        let err =
+            Session::parse("version = 1\n[[buffers]]\npath = \"a\"\ncaret = 3\n").unwrap_err();
+        assert!(err.starts_with("line 4: unknown field `caret`"), "{err}");
+        assert!(Session::parse("active = 0\n").is_err());
+    }
+}
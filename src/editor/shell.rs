@@ -0,0 +1,132 @@
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+    thread,
+};
+
+// lines of output beyond this are left out
+pub const MAX_OUTPUT_LINES: usize = 10_000;
+
+pub struct Output {
+    pub text: String,
+    // the first line of what the command wrote to stderr, if anything
+    pub warning: Option<String>,
+    // how many lines there were if some were left out
+    pub line_count: Option<usize>,
+}
+
+// Run `command` with `sh -c` and capture its output. The command gets no terminal, so
+// hecto stays in raw mode meanwhile. A command which fails returns its first line of
+// stderr, or its exit status if it wrote nothing there.
+pub fn run(command: &str, keep_trailing_newline: bool) -> Result<Output, String> {
+    let (mut text, warning) = execute(command, None)?;
+    if !keep_trailing_newline {
+        let trimmed = text
+            .strip_suffix("\r\n")
+            .or_else(|| text.strip_suffix('\n'))
+            .map(str::len);
+        if let Some(len) = trimmed {
+            text.truncate(len);
+        }
+    }
+    let line_count = text.lines().count();
+    let line_count = (line_count > MAX_OUTPUT_LINES).then(|| {
+        if let Some((end, _)) = text
+            .match_indices('\n')
+            .nth(MAX_OUTPUT_LINES.saturating_sub(1))
+        {
+            text.truncate(end);
+        }
+        line_count
+    });
+    Ok(Output {
+        text,
+        warning,
+        line_count,
+    })
+}
+
+// like `run`, but with `input` written to the command, whose output is kept whole
+pub fn filter(command: &str, input: &str) -> Result<Output, String> {
+    let (text, warning) = execute(command, Some(input))?;
+    Ok(Output {
+        text,
+        warning,
+        line_count: None,
+    })
+}
+
+fn execute(command: &str, input: Option<&str>) -> Result<(String, Option<String>), String> {
+    let error = |err: std::io::Error| format!("Error running '{command}': {err}");
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(if input.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(error)?;
+    // written meanwhile, so a filter can't block on a full pipe to us
+    let stdin = child.stdin.take();
+    let output = thread::scope(|scope| {
+        if let Some((mut stdin, input)) = stdin.zip(input) {
+            // a filter may well exit before reading all of it
+            scope.spawn(move || stdin.write_all(input.as_bytes()));
+        }
+        child.wait_with_output()
+    })
+    .map_err(error)?;
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let warning = stderr
+        .lines()
+        .find(|line| !line.trim().is_empty())
+        .map(ToString::to_string);
+    if !output.status.success() {
+        return Err(warning.unwrap_or_else(|| format!("'{command}' {}", output.status)));
+    }
+    Ok((
+        String::from_utf8_lossy(&output.stdout).into_owned(),
+        warning,
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn captures_output_or_the_failure() {
+        let output = run("printf 'one\\ntwo\\n'", false).unwrap();
+        assert_eq!(output.text, "one\ntwo");
+        assert!(output.warning.is_none());
+        assert!(output.line_count.is_none());
+        assert_eq!(run("echo one", true).unwrap().text, "one\n");
+
+        let output = run("seq 20000", false).unwrap();
+        assert_eq!(output.text.lines().count(), MAX_OUTPUT_LINES);
+        assert_eq!(output.line_count, Some(20_000));
+
+        assert_eq!(
+            run("echo oops >&2; exit 3", false).err(),
+            Some(String::from("oops"))
+        );
+        assert_eq!(
+            run("exit 3", false).err(),
+            Some(String::from("'exit 3' exit status: 3"))
+        );
+    }
+
+    #[test]
+    fn filters_input() {
+        let output = filter("sort", "b\na\n").unwrap();
+        assert_eq!(output.text, "a\nb\n");
+        // more input than a pipe holds, which the command doesn't read
+        let input = "x".repeat(1 << 20);
+        assert_eq!(filter("echo done", &input).unwrap().text, "done\n");
+    }
+}
@@ -1,4 +1,5 @@
-#[derive(Default, Debug, Clone, Copy)]
+/// The size of the terminal or a part of it, in rows and columns.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Size {
     pub height: usize,
     pub width: usize,
@@ -0,0 +1,181 @@
+use std::{
+    cell::OnceCell,
+    collections::HashSet,
+    fs::{self, OpenOptions},
+    io::Write,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+use unicode_segmentation::UnicodeSegmentation;
+
+// a small list of common English words, for systems without a word list of their own
+const BUNDLED_WORDS: &str = include_str!("words.txt");
+// where most systems keep a word list, one word to a line
+const SYSTEM_WORDS: &str = "/usr/share/dict/words";
+// endings which are tried off words the word list doesn't know, so a small one goes further
+const SUFFIXES: [&str; 7] = ["'s", "s", "es", "ed", "d", "ing", "ly"];
+
+// The words known to be spelled right: a word list and the words the user added. Nothing is
+// read until a word is first looked up.
+pub struct Dictionary {
+    // None for the system's word list, or the bundled one without it
+    path: Option<PathBuf>,
+    // where the words the user added are kept, one to a line
+    user_path: Option<PathBuf>,
+    words: OnceCell<HashSet<String>>,
+}
+
+impl Dictionary {
+    pub const fn new(path: Option<PathBuf>, user_path: Option<PathBuf>) -> Self {
+        Self {
+            path,
+            user_path,
+            words: OnceCell::new(),
+        }
+    }
+
+    // The byte ranges of the words in `text` which aren't known. Words with digits, like
+    // `utf8`, in camelCase or in ALL CAPS aren't checked, nor those in other scripts than
+    // Latin, which the word lists are for.
+    pub fn misspelled(&self, text: &str) -> Vec<Range<usize>> {
+        text.unicode_word_indices()
+            .filter(|(_, word)| is_checked(word) && !self.is_known(word))
+            .map(|(start, word)| start..start.saturating_add(word.len()))
+            .collect()
+    }
+
+    // Known as it is or lowercased, like at the start of a sentence, or without one of the
+    // `SUFFIXES`
+    pub fn is_known(&self, word: &str) -> bool {
+        let words = self.words();
+        let word = word.replace('’', "'");
+        let lowercase = word.to_lowercase();
+        [word.as_str(), lowercase.as_str()].iter().any(|word| {
+            words.contains(*word)
+                || SUFFIXES.iter().any(|suffix| {
+                    word.strip_suffix(suffix)
+                        .is_some_and(|stem| !stem.is_empty() && words.contains(stem))
+                })
+        })
+    }
+
+    // Add `word` to the user's words, returning where they are kept. The word is known from
+    // now on even if it couldn't be written there.
+    pub fn add(&mut self, word: &str) -> Result<PathBuf, String> {
+        self.words();
+        if let Some(words) = self.words.get_mut() {
+            words.insert(word.to_string());
+        }
+        let path = self
+            .user_path
+            .clone()
+            .ok_or_else(|| String::from("No directory for the user's words"))?;
+        let error = |err: std::io::Error| format!("Error writing '{}': {err}", path.display());
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(error)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(error)?;
+        writeln!(file, "{word}").map_err(error)?;
+        Ok(path)
+    }
+
+    fn words(&self) -> &HashSet<String> {
+        self.words.get_or_init(|| {
+            let list = match &self.path {
+                Some(path) => read_words(path),
+                None => read_words(Path::new(SYSTEM_WORDS)),
+            };
+            let list = list.unwrap_or_else(|| BUNDLED_WORDS.to_string());
+            let user = self.user_path.as_deref().and_then(read_words);
+            list.lines()
+                .chain(user.iter().flat_map(|user| user.lines()))
+                .map(str::trim)
+                .filter(|word| !word.is_empty())
+                .map(ToString::to_string)
+                .collect()
+        })
+    }
+}
+
+fn read_words(path: &Path) -> Option<String> {
+    fs::read_to_string(path).ok()
+}
+
+// the word in `text` at the byte `byte_idx`, or ending there
+pub fn word_at(text: &str, byte_idx: usize) -> Option<&str> {
+    text.unicode_word_indices()
+        .find(|(start, word)| (*start..=start.saturating_add(word.len())).contains(&byte_idx))
+        .map(|(_, word)| word)
+}
+
+fn is_checked(word: &str) -> bool {
+    let mut chars = word.chars();
+    let is_latin = |ch: char| ch.is_alphabetic() && ch <= '\u{24f}';
+    word.chars().all(|ch| is_latin(ch) || ch == '\'' || ch == '’')
+        && word.chars().filter(|ch| ch.is_alphabetic()).count() > 1
+        // an uppercase letter after the first is camelCase or ALL CAPS
+        && chars.next().is_some()
+        && !chars.any(char::is_uppercase)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::env;
+
+    fn bundled() -> Dictionary {
+        Dictionary::new(Some(PathBuf::from("/nonexistent")), None)
+    }
+
+    fn misspelled<'a>(dictionary: &Dictionary, text: &'a str) -> Vec<&'a str> {
+        dictionary
+            .misspelled(text)
+            .into_iter()
+            .map(|range| &text[range])
+            .collect()
+    }
+
+    #[test]
+    fn finds_unknown_words() {
+        let dictionary = bundled();
+        assert_eq!(
+            misspelled(&dictionary, "The quick brwn fox's tail, isn’t it?"),
+            ["brwn", "fox's"]
+        );
+        assert!(dictionary.is_known("Words"));
+        assert!(dictionary.is_known("reading"));
+        assert!(!dictionary.is_known("teh"));
+    }
+
+    #[test]
+    fn leaves_out_code_like_words() {
+        let dictionary = bundled();
+        assert!(misspelled(&dictionary, "camelCase ALLCAPS utf8 x snake_case 老虎").is_empty());
+        assert_eq!(misspelled(&dictionary, "Zzyzx zzyzx"), ["Zzyzx", "zzyzx"]);
+    }
+
+    #[test]
+    fn words_added_are_kept() {
+        let path = env::temp_dir().join("hecto-spell").join("words.txt");
+        let _ = fs::remove_file(&path);
+        let mut dictionary =
+            Dictionary::new(Some(PathBuf::from("/nonexistent")), Some(path.clone()));
+        assert!(!dictionary.is_known("hecto"));
+        assert_eq!(dictionary.add("hecto"), Ok(path.clone()));
+        assert!(dictionary.is_known("hecto"));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "hecto\n");
+        let dictionary = Dictionary::new(Some(PathBuf::from("/nonexistent")), Some(path));
+        assert!(dictionary.is_known("hecto"));
+    }
+
+    #[test]
+    fn finds_the_word_at_a_byte() {
+        assert_eq!(word_at("one two", 4), Some("two"));
+        assert_eq!(word_at("one two", 3), Some("one"));
+        assert_eq!(word_at("one  two", 4), None);
+    }
+}
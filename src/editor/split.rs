@@ -0,0 +1,22 @@
+use super::ui::{StatusBar, View};
+
+// the lower pane of a horizontal split, the upper pane is always the active buffer
+pub struct Split {
+    pub view: View,
+    // index of the buffer shown in the lower pane
+    pub buffer_idx: usize,
+    // the status of the upper pane, drawn between both panes
+    pub divider: StatusBar,
+    pub focused: bool,
+}
+
+impl Split {
+    pub fn new(view: View, buffer_idx: usize) -> Self {
+        Self {
+            view,
+            buffer_idx,
+            divider: StatusBar::default(),
+            focused: true,
+        }
+    }
+}
@@ -0,0 +1,335 @@
+use super::{Highlight, State};
+use crate::editor::annotated_string::AnnotationType;
+use std::ops::Range;
+
+// a fence opens or closes a code block with at least this many backticks or tildes
+const MIN_FENCE: usize = 3;
+// headings and fences indented further are part of an indented code block instead
+const MAX_INDENT: usize = 3;
+// `#` to `######`
+const MAX_HEADING_LEVEL: usize = 6;
+// longer numbers don't start an ordered list item
+const MAX_NUMBER_DIGITS: usize = 9;
+
+pub fn next_state(line: &str, state: State) -> State {
+    match state {
+        State::Text => fence(line).map_or(State::Text, |(fence, len)| State::Fenced { fence, len }),
+        State::Fenced { fence, len } if closes(line, fence, len) => State::Text,
+        State::Fenced { .. } => state,
+    }
+}
+
+pub fn highlight(line: &str, state: State, highlights: &mut Vec<Highlight>) {
+    // a fenced code block is code as a whole, with its fences, whatever it looks like
+    if matches!(state, State::Fenced { .. }) || fence(line).is_some() {
+        highlights.push((AnnotationType::Code, 0..line.len()));
+        return;
+    }
+    if is_heading(line) {
+        highlights.push((AnnotationType::Heading, 0..line.len()));
+    } else if let Some(bullet) = bullet(line) {
+        highlights.push((AnnotationType::Markup, bullet));
+    }
+    let code = code_spans(line);
+    let in_code = |byte_idx: usize| code.iter().any(|span| span.contains(&byte_idx));
+    emphasis(line, &in_code, highlights);
+    links(line, &in_code, highlights);
+    highlights.extend(code.iter().map(|span| (AnnotationType::Code, span.clone())));
+}
+
+// the rest of `line` behind an indentation of up to `MAX_INDENT` spaces
+fn unindented(line: &str) -> Option<&str> {
+    let rest = line.trim_start_matches(' ');
+    (line.len().saturating_sub(rest.len()) <= MAX_INDENT).then_some(rest)
+}
+
+// how many of `ch`, which is ASCII, `text` starts with
+fn run(text: &str, ch: char) -> usize {
+    text.len().saturating_sub(text.trim_start_matches(ch).len())
+}
+
+// the character and length of the fence opening a code block, like "```rust"
+fn fence(line: &str) -> Option<(char, usize)> {
+    let rest = unindented(line)?;
+    let fence = rest.chars().next().filter(|ch| matches!(ch, '`' | '~'))?;
+    let len = run(rest, fence);
+    // backticks in the info string make it a code span instead
+    let info = rest.get(len..).unwrap_or_default();
+    (len >= MIN_FENCE && !(fence == '`' && info.contains('`'))).then_some((fence, len))
+}
+
+// whether `line` closes a block opened by `len` of `fence`
+fn closes(line: &str, fence: char, len: usize) -> bool {
+    unindented(line).is_some_and(|rest| {
+        let run = run(rest, fence);
+        run >= len && rest.get(run..).is_some_and(|rest| rest.trim().is_empty())
+    })
+}
+
+fn is_heading(line: &str) -> bool {
+    unindented(line).is_some_and(|rest| {
+        let level = run(rest, '#');
+        (1..=MAX_HEADING_LEVEL).contains(&level)
+            && rest
+                .get(level..)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with([' ', '\t']))
+    })
+}
+
+// The bytes of the bullet of a list item, like `-` or `12.`. Nested lists are indented
+// further than headings may be.
+fn bullet(line: &str) -> Option<Range<usize>> {
+    let rest = line.trim_start_matches([' ', '\t']);
+    let start = line.len().saturating_sub(rest.len());
+    let digits = rest.len().saturating_sub(
+        rest.trim_start_matches(|ch: char| ch.is_ascii_digit())
+            .len(),
+    );
+    let len = match rest.chars().next()? {
+        '-' | '*' | '+' => 1,
+        _ if (1..=MAX_NUMBER_DIGITS).contains(&digits)
+            && rest.get(digits..)?.starts_with(['.', ')']) =>
+        {
+            digits.saturating_add(1)
+        }
+        _ => return None,
+    };
+    rest.get(len..)?
+        .starts_with([' ', '\t'])
+        .then(|| start..start.saturating_add(len))
+}
+
+// A run of backticks up to the next run of as many, which may hold shorter or longer ones.
+// Without one, the backticks are text.
+fn code_spans(line: &str) -> Vec<Range<usize>> {
+    let mut spans = Vec::new();
+    let mut idx = 0;
+    while let Some(start) = find_from(line, idx, "`") {
+        let len = run(&line[start..], '`');
+        let opened = start.saturating_add(len);
+        idx = opened;
+        let mut from = opened;
+        while let Some(close) = find_from(line, from, "`") {
+            let closed = close.saturating_add(run(&line[close..], '`'));
+            if closed.saturating_sub(close) == len {
+                spans.push(start..closed);
+                idx = closed;
+                break;
+            }
+            from = closed;
+        }
+    }
+    spans
+}
+
+// `*emphasis*`, `**strong**` and the same with underscores, which don't work within words
+fn emphasis(line: &str, in_code: &impl Fn(usize) -> bool, highlights: &mut Vec<Highlight>) {
+    // where the runs closing emphasis found start, which don't open any
+    let mut closers = Vec::new();
+    let mut idx = 0;
+    while let Some(start) = line
+        .get(idx..)
+        .and_then(|rest| rest.find(['*', '_']))
+        .map(|found| idx.saturating_add(found))
+    {
+        let delimiter = if line[start..].starts_with('*') {
+            '*'
+        } else {
+            '_'
+        };
+        let len = run(&line[start..], delimiter);
+        idx = start.saturating_add(len);
+        if in_code(start) || closers.contains(&start) || !opens(line, start..idx, delimiter) {
+            continue;
+        }
+        let mut from = idx;
+        while let Some(close) = find_from(line, from, &line[start..=start]) {
+            let closed = close.saturating_add(run(&line[close..], delimiter));
+            if closed.saturating_sub(close) == len
+                && !in_code(close)
+                && closes_emphasis(line, close..closed, delimiter)
+            {
+                let typ = if len == 1 {
+                    AnnotationType::Emphasis
+                } else {
+                    AnnotationType::Strong
+                };
+                highlights.push((typ, start..closed));
+                closers.push(close);
+                break;
+            }
+            from = closed;
+        }
+    }
+}
+
+// whether the delimiters at `run` can open emphasis: followed by text, and with underscores
+// not within a word
+fn opens(line: &str, run: Range<usize>, delimiter: char) -> bool {
+    let before = line[..run.start].chars().next_back();
+    let after = line[run.end..].chars().next();
+    after.is_some_and(|ch| !ch.is_whitespace())
+        && (delimiter == '*' || !before.is_some_and(char::is_alphanumeric))
+}
+
+// whether the delimiters at `run` can close emphasis: following text, and with underscores
+// not within a word
+fn closes_emphasis(line: &str, run: Range<usize>, delimiter: char) -> bool {
+    let before = line[..run.start].chars().next_back();
+    let after = line[run.end..].chars().next();
+    before.is_some_and(|ch| !ch.is_whitespace())
+        && (delimiter == '*' || !after.is_some_and(char::is_alphanumeric))
+}
+
+// the `(target)` of links like `[text](target)`, and autolinks like `<https://example.com>`
+fn links(line: &str, in_code: &impl Fn(usize) -> bool, highlights: &mut Vec<Highlight>) {
+    let mut idx = 0;
+    while let Some(start) = find_from(line, idx, "](").map(|found| found.saturating_add(1)) {
+        let Some(end) = find_from(line, start, ")").map(|found| found.saturating_add(1)) else {
+            break;
+        };
+        if !in_code(start) {
+            highlights.push((AnnotationType::Markup, start..end));
+        }
+        idx = end;
+    }
+    idx = 0;
+    while let Some(start) = find_from(line, idx, "<") {
+        idx = start.saturating_add(1);
+        let Some(end) = find_from(line, idx, ">") else {
+            break;
+        };
+        let target = &line[idx..end];
+        let is_autolink = (target.contains("://") || target.starts_with("mailto:"))
+            && !target.contains(char::is_whitespace);
+        if is_autolink && !in_code(start) {
+            idx = end.saturating_add(1);
+            highlights.push((AnnotationType::Markup, start..idx));
+        }
+    }
+}
+
+// where `pattern` is next found in `line` from the byte `from` on
+fn find_from(line: &str, from: usize, pattern: &str) -> Option<usize> {
+    line.get(from..)?
+        .find(pattern)
+        .map(|found| from.saturating_add(found))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use AnnotationType::{Code, Emphasis, Heading, Markup, Strong};
+
+    // the highlighted parts of each line, in the order of the text
+    fn highlighted<'a>(lines: &[&'a str]) -> Vec<Vec<(AnnotationType, &'a str)>> {
+        let mut state = State::default();
+        lines
+            .iter()
+            .map(|line| {
+                let mut highlights = Vec::new();
+                highlight(line, state, &mut highlights);
+                state = next_state(line, state);
+                highlights.sort_by_key(|(_, bytes)| (bytes.start, bytes.end));
+                highlights
+                    .into_iter()
+                    .map(|(typ, bytes)| (typ, &line[bytes]))
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn headings_take_the_whole_line() {
+        assert_eq!(
+            highlighted(&[
+                "# Title",
+                "###### Six",
+                "#hashtag",
+                "####### seven",
+                "    # code"
+            ]),
+            [
+                vec![(Heading, "# Title")],
+                vec![(Heading, "###### Six")],
+                vec![],
+                vec![],
+                vec![],
+            ]
+        );
+    }
+
+    #[test]
+    fn fenced_code_blocks_hide_what_they_hold() {
+        let lines = [
+            "```rust",
+            "# not a heading",
+            "~~~",
+            "````",
+            "# heading",
+            "~~~~",
+            "```",
+            "~~~",
+            "*not emphasis*",
+        ];
+        let code = |line| vec![(Code, line)];
+        assert_eq!(
+            highlighted(&lines),
+            [
+                code("```rust"),
+                code("# not a heading"),
+                code("~~~"),
+                code("````"),
+                vec![(Heading, "# heading")],
+                code("~~~~"),
+                code("```"),
+                code("~~~"),
+                code("*not emphasis*"),
+            ]
+        );
+        // backticks after a fence of backticks make a code span instead
+        assert_eq!(highlighted(&["``` a` b```"]), [vec![(Code, "``` a` b```")]]);
+        assert_eq!(next_state("``` a` b```", State::Text), State::Text);
+    }
+
+    #[test]
+    fn code_spans_emphasis_and_strong_are_found_within_lines() {
+        assert_eq!(
+            highlighted(&["Some `code`, *em*, **strong** and __more__ in a_snake_case"]),
+            [vec![
+                (Code, "`code`"),
+                (Emphasis, "*em*"),
+                (Strong, "**strong**"),
+                (Strong, "__more__"),
+            ]]
+        );
+        assert_eq!(
+            highlighted(&["`*no*` and `` a`b `` here", "2 * 3 * 4", "*a **b** c*"]),
+            [
+                vec![(Code, "`*no*`"), (Code, "`` a`b ``")],
+                vec![],
+                vec![(Emphasis, "*a **b** c*"), (Strong, "**b**")],
+            ]
+        );
+    }
+
+    #[test]
+    fn bullets_and_link_targets_are_markup() {
+        assert_eq!(
+            highlighted(&[
+                "- see [docs](https://example.com) or <https://example.org>",
+                "  12. item",
+                "-not a bullet <b> tag",
+            ]),
+            [
+                vec![
+                    (Markup, "-"),
+                    (Markup, "(https://example.com)"),
+                    (Markup, "<https://example.org>"),
+                ],
+                vec![(Markup, "12.")],
+                vec![],
+            ]
+        );
+    }
+}
@@ -0,0 +1,52 @@
+use super::annotated_string::AnnotationType;
+use std::ops::Range;
+
+mod markdown;
+
+// an annotation for the bytes of a line
+pub type Highlight = (AnnotationType, Range<usize>);
+
+// The languages highlighted. Each line is highlighted on its own, only knowing the state the
+// lines above left it in, like being inside a fenced code block.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Syntax {
+    Markdown,
+}
+
+// what the lines above tell about a line
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum State {
+    #[default]
+    Text,
+    // inside a block which a line of at least `len` of `fence` closes
+    Fenced {
+        fence: char,
+        len: usize,
+    },
+}
+
+impl Syntax {
+    pub const ALL: [Self; 1] = [Self::Markdown];
+
+    // the file type it is highlighted for, see `FileInfo::file_type`
+    pub const fn file_type(self) -> &'static str {
+        match self {
+            Self::Markdown => "markdown",
+        }
+    }
+
+    // the state the line after `line` starts in, if `line` starts in `state`
+    pub fn next_state(self, line: &str, state: State) -> State {
+        match self {
+            Self::Markdown => markdown::next_state(line, state),
+        }
+    }
+
+    pub fn highlight(self, line: &str, state: State) -> Vec<Highlight> {
+        let mut highlights = Vec::new();
+        match self {
+            Self::Markdown => markdown::highlight(line, state, &mut highlights),
+        }
+        highlights
+    }
+}
@@ -2,9 +2,14 @@ use crossterm::style::Color;
 
 use crate::editor::annotated_string::AnnotationType;
 
+#[derive(Clone, Copy, Default)]
 pub struct Attribute {
     pub foreground: Option<Color>,
     pub background: Option<Color>,
+    // shown as an underline in this colour
+    pub underline: Option<Color>,
+    pub bold: bool,
+    pub italic: bool,
 }
 
 // use proper color for annotation types
@@ -14,16 +19,72 @@ impl From<AnnotationType> for Attribute {
             AnnotationType::Match => Self {
                 foreground: Some(Color::Black),
                 background: Some(Color::Yellow),
+                ..Self::default()
             },
 
             AnnotationType::SelectedMatch => Self {
                 foreground: Some(Color::Black),
                 background: Some(Color::Green),
+                ..Self::default()
             },
 
-            AnnotationType::Digit => Self {
+            AnnotationType::WordMatch | AnnotationType::Code => Self {
+                background: Some(Color::DarkGrey),
+                ..Self::default()
+            },
+
+            AnnotationType::Digit | AnnotationType::Error | AnnotationType::Deleted => Self {
                 foreground: Some(Color::Red),
-                background: None,
+                ..Self::default()
+            },
+
+            AnnotationType::LineNumber
+            | AnnotationType::Invisible
+            | AnnotationType::ScrollMarker
+            | AnnotationType::Markup => Self {
+                foreground: Some(Color::DarkGrey),
+                ..Self::default()
+            },
+
+            AnnotationType::Warning | AnnotationType::Changed => Self {
+                foreground: Some(Color::Yellow),
+                ..Self::default()
+            },
+
+            AnnotationType::Inserted => Self {
+                foreground: Some(Color::Green),
+                ..Self::default()
+            },
+
+            AnnotationType::EditedLine => Self {
+                foreground: Some(Color::Blue),
+                ..Self::default()
+            },
+
+            AnnotationType::Mark => Self {
+                foreground: Some(Color::Cyan),
+                ..Self::default()
+            },
+
+            AnnotationType::SpellError => Self {
+                underline: Some(Color::Red),
+                ..Self::default()
+            },
+
+            AnnotationType::Heading => Self {
+                foreground: Some(Color::Magenta),
+                bold: true,
+                ..Self::default()
+            },
+
+            AnnotationType::Emphasis => Self {
+                italic: true,
+                ..Self::default()
+            },
+
+            AnnotationType::Strong => Self {
+                bold: true,
+                ..Self::default()
             },
         }
     }
@@ -0,0 +1,230 @@
+use super::super::{Position, Size};
+use super::TerminalIo;
+use crate::editor::annotated_string::{AnnotatedString, AnnotationType};
+use std::{cell::RefCell, rc::Rc};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+#[derive(Clone, Default)]
+struct Cell {
+    // empty for the second half of a wide grapheme
+    grapheme: String,
+    annotation: Option<AnnotationType>,
+    inverted: bool,
+}
+
+#[derive(Default)]
+struct Screen {
+    size: Size,
+    cells: Vec<Vec<Cell>>,
+    caret: Position,
+    title: String,
+    // the titles before initializing, restored when terminating
+    saved_titles: Vec<String>,
+    // the rows printed since they were last taken, in order
+    printed_rows: Vec<usize>,
+    captures_mouse: bool,
+}
+
+// An in-memory terminal recording what is on screen. Clones share the same screen,
+// so a test can keep one to inspect what the editor drew into the other.
+#[derive(Clone, Default)]
+pub struct MockTerminal {
+    screen: Rc<RefCell<Screen>>,
+}
+
+impl MockTerminal {
+    pub fn new(size: Size) -> Self {
+        let terminal = Self::default();
+        terminal.resize(size);
+        terminal
+    }
+
+    // like a real terminal window being resized, everything on screen is lost
+    pub fn resize(&self, size: Size) {
+        let mut screen = self.screen.borrow_mut();
+        screen.size = size;
+        screen.cells = vec![vec![Cell::default(); size.width]; size.height];
+    }
+
+    // the text of a row, without trailing blanks
+    pub fn row(&self, row: usize) -> String {
+        self.screen
+            .borrow()
+            .cells
+            .get(row)
+            .map_or_else(String::new, |cells| {
+                cells
+                    .iter()
+                    .map(|cell| cell.grapheme.as_str())
+                    .collect::<String>()
+                    .trim_end()
+                    .to_string()
+            })
+    }
+
+    pub fn annotation_at(&self, row: usize, col: usize) -> Option<AnnotationType> {
+        self.screen
+            .borrow()
+            .cells
+            .get(row)
+            .and_then(|cells| cells.get(col))
+            .and_then(|cell| cell.annotation)
+    }
+
+    pub fn captures_mouse(&self) -> bool {
+        self.screen.borrow().captures_mouse
+    }
+
+    pub fn is_inverted(&self, row: usize) -> bool {
+        self.screen
+            .borrow()
+            .cells
+            .get(row)
+            .and_then(|cells| cells.first())
+            .is_some_and(|cell| cell.inverted)
+    }
+
+    pub fn caret(&self) -> Position {
+        self.screen.borrow().caret
+    }
+
+    pub fn title(&self) -> String {
+        self.screen.borrow().title.clone()
+    }
+
+    // which rows were printed since the last call, so tests can tell what was redrawn
+    pub fn take_printed_rows(&self) -> Vec<usize> {
+        std::mem::take(&mut self.screen.borrow_mut().printed_rows)
+    }
+
+    // overwrite a row with the given parts, clearing whatever is behind them
+    fn write_row<'a>(
+        &self,
+        row: usize,
+        parts: impl Iterator<Item = (&'a str, Option<AnnotationType>)>,
+        inverted: bool,
+    ) -> Result<(), std::io::Error> {
+        let mut screen = self.screen.borrow_mut();
+        let width = screen.size.width;
+        let Some(cells) = screen.cells.get_mut(row) else {
+            return Err(std::io::Error::other(format!("row {row} is off screen")));
+        };
+
+        let blank = Cell {
+            grapheme: String::from(" "),
+            annotation: None,
+            inverted,
+        };
+        let mut written = Vec::with_capacity(width);
+        for (string, annotation) in parts {
+            for grapheme in string.graphemes(true) {
+                let grapheme_width = grapheme.width().max(1);
+                // like with line wrap disabled, anything behind the last column is lost
+                if written.len().saturating_add(grapheme_width) > width {
+                    break;
+                }
+                written.push(Cell {
+                    grapheme: grapheme.to_string(),
+                    annotation,
+                    inverted,
+                });
+                for _ in 1..grapheme_width {
+                    written.push(Cell {
+                        grapheme: String::new(),
+                        ..written.last().cloned().unwrap_or_default()
+                    });
+                }
+            }
+        }
+        written.resize(width, blank);
+        *cells = written;
+        screen.printed_rows.push(row);
+        Ok(())
+    }
+}
+
+impl TerminalIo for MockTerminal {
+    fn initialize(&mut self) -> Result<(), std::io::Error> {
+        let mut screen = self.screen.borrow_mut();
+        let title = screen.title.clone();
+        screen.saved_titles.push(title);
+        drop(screen);
+        self.clear_screen()
+    }
+
+    fn terminate(&mut self) -> Result<(), std::io::Error> {
+        let mut screen = self.screen.borrow_mut();
+        screen.title = screen.saved_titles.pop().unwrap_or_default();
+        screen.captures_mouse = false;
+        Ok(())
+    }
+
+    fn clear_screen(&mut self) -> Result<(), std::io::Error> {
+        let size = self.screen.borrow().size;
+        self.resize(size);
+        Ok(())
+    }
+
+    fn begin_synchronized_update(&mut self) -> Result<(), std::io::Error> {
+        Ok(())
+    }
+
+    fn end_synchronized_update(&mut self) -> Result<(), std::io::Error> {
+        Ok(())
+    }
+
+    fn move_caret_to(&mut self, p: &Position) -> Result<(), std::io::Error> {
+        self.screen.borrow_mut().caret = *p;
+        Ok(())
+    }
+
+    fn hide_caret(&mut self) -> Result<(), std::io::Error> {
+        Ok(())
+    }
+
+    fn show_caret(&mut self) -> Result<(), std::io::Error> {
+        Ok(())
+    }
+
+    fn set_title(&mut self, title: &str) -> Result<(), std::io::Error> {
+        self.screen.borrow_mut().title = title.to_string();
+        Ok(())
+    }
+
+    fn set_mouse_capture(&mut self, enabled: bool) -> Result<(), std::io::Error> {
+        self.screen.borrow_mut().captures_mouse = enabled;
+        Ok(())
+    }
+
+    // only used for the farewell message after the screen is gone
+    fn print(&mut self, _s: &str) -> Result<(), std::io::Error> {
+        Ok(())
+    }
+
+    fn print_inverted_row(&mut self, row: usize, line_text: &str) -> Result<(), std::io::Error> {
+        self.write_row(row, std::iter::once((line_text, None)), true)
+    }
+
+    fn print_annotated_row(
+        &mut self,
+        row: usize,
+        annotated_string: &AnnotatedString,
+    ) -> Result<(), std::io::Error> {
+        self.write_row(
+            row,
+            annotated_string
+                .into_iter()
+                .map(|part| (part.string, part.typ)),
+            false,
+        )
+    }
+
+    fn size(&self) -> Result<Size, std::io::Error> {
+        Ok(self.screen.borrow().size)
+    }
+
+    fn execute(&mut self) -> Result<(), std::io::Error> {
+        Ok(())
+    }
+}
@@ -1,22 +1,45 @@
 use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{
+    DisableFocusChange, DisableMouseCapture, EnableFocusChange, EnableMouseCapture,
+    KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+};
 use crossterm::style::{
-    Attribute::{Reset, Reverse},
-    Print, ResetColor,
+    Attribute::{Bold, Italic, Reset, Reverse, Underlined},
+    Print, ResetColor, SetAttribute,
 };
-use crossterm::style::{SetBackgroundColor, SetForegroundColor};
+use crossterm::style::{SetBackgroundColor, SetForegroundColor, SetUnderlineColor};
 use crossterm::terminal::{
-    Clear, ClearType, DisableLineWrap, EnableLineWrap, EnterAlternateScreen, LeaveAlternateScreen,
-    SetTitle, disable_raw_mode, enable_raw_mode, size,
+    BeginSynchronizedUpdate, Clear, ClearType, DisableLineWrap, EnableLineWrap,
+    EndSynchronizedUpdate, EnterAlternateScreen, LeaveAlternateScreen, SetTitle, disable_raw_mode,
+    enable_raw_mode, size, supports_keyboard_enhancement,
 };
 use crossterm::{Command, queue};
 use std::io::{Write, stdout};
+use std::sync::atomic::{AtomicBool, Ordering};
+use unicode_width::UnicodeWidthStr;
 
 use super::{Position, Size};
 use crate::editor::annotated_string::AnnotatedString;
 
 mod attribute;
+#[cfg(test)]
+mod mock;
+mod terminal_io;
+mod theme;
+
+#[cfg(test)]
+pub use mock::MockTerminal;
+pub use terminal_io::TerminalIo;
+pub use theme::Theme;
+
+// xterm's title stack, terminals without one ignore these
+const PUSH_TITLE: &str = "\x1b[22;0t";
+const POP_TITLE: &str = "\x1b[23;0t";
 
-use attribute::Attribute;
+// whether the flags were pushed, global so the panic hook pops them as well
+static KEYBOARD_ENHANCED: AtomicBool = AtomicBool::new(false);
+// the same for reporting the mouse
+static MOUSE_CAPTURED: AtomicBool = AtomicBool::new(false);
 
 /// Represents the Terminal.
 /// Edge Case for platforms where `usize` < `u16`:
@@ -24,27 +47,12 @@ use attribute::Attribute;
 /// only spans over at most `usize::MAX` or `u16::size` rows/columns, whichever is smaller.
 /// Each size returned truncates to min(`usize::MAX`, `u16::MAX`)
 /// And should you attempt to set the caret out of these bounds, it will also be truncated.
-pub struct Terminal;
+#[derive(Default)]
+pub struct Terminal {
+    theme: Theme,
+}
 
 impl Terminal {
-    pub fn initialize() -> Result<(), std::io::Error> {
-        enable_raw_mode()?;
-        Self::enter_alternate_screen()?;
-        Self::disable_line_wrap()?;
-        Self::clear_screen()?;
-        Self::execute()?;
-        Ok(())
-    }
-
-    pub fn terminate() -> Result<(), std::io::Error> {
-        Self::leave_alternate_screen()?;
-        Self::enable_line_wrap()?;
-        Self::show_caret()?;
-        Self::execute()?;
-        disable_raw_mode()?;
-        Ok(())
-    }
-
     fn enter_alternate_screen() -> Result<(), std::io::Error> {
         Self::queue_command(EnterAlternateScreen)?;
         Ok(())
@@ -65,97 +73,145 @@ impl Terminal {
         Ok(())
     }
 
-    fn clear_screen() -> Result<(), std::io::Error> {
+    // the kitty keyboard protocol tells apart keys like Ctrl-Enter and Enter, terminals
+    // without it keep sending keys as before
+    fn enable_keyboard_enhancement() -> Result<(), std::io::Error> {
+        if matches!(supports_keyboard_enhancement(), Ok(true)) {
+            Self::queue_command(PushKeyboardEnhancementFlags(
+                KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES,
+            ))?;
+            KEYBOARD_ENHANCED.store(true, Ordering::Relaxed);
+        }
+        Ok(())
+    }
+
+    fn disable_keyboard_enhancement() -> Result<(), std::io::Error> {
+        if KEYBOARD_ENHANCED.swap(false, Ordering::Relaxed) {
+            Self::queue_command(PopKeyboardEnhancementFlags)?;
+        }
+        Ok(())
+    }
+
+    fn queue_command<T: Command>(command: T) -> Result<(), std::io::Error> {
+        queue!(stdout(), command)?;
+        Ok(())
+    }
+}
+
+impl TerminalIo for Terminal {
+    fn initialize(&mut self) -> Result<(), std::io::Error> {
+        enable_raw_mode()?;
+        Self::queue_command(EnableFocusChange)?;
+        Self::queue_command(Print(PUSH_TITLE))?;
+        Self::enter_alternate_screen()?;
+        // terminals keep the flags of the alternate screen apart from the main one's
+        Self::enable_keyboard_enhancement()?;
+        Self::disable_line_wrap()?;
+        self.clear_screen()?;
+        self.execute()?;
+        Ok(())
+    }
+
+    fn terminate(&mut self) -> Result<(), std::io::Error> {
+        // popped from the alternate screen's flags, which they were pushed to
+        Self::disable_keyboard_enhancement()?;
+        self.set_mouse_capture(false)?;
+        Self::queue_command(DisableFocusChange)?;
+        Self::leave_alternate_screen()?;
+        Self::enable_line_wrap()?;
+        // terminals without a title stack are at least left without hecto's title
+        self.set_title("")?;
+        Self::queue_command(Print(POP_TITLE))?;
+        self.show_caret()?;
+        self.execute()?;
+        disable_raw_mode()?;
+        Ok(())
+    }
+
+    fn clear_screen(&mut self) -> Result<(), std::io::Error> {
         Self::queue_command(Clear(ClearType::All))?;
         Ok(())
     }
 
+    fn begin_synchronized_update(&mut self) -> Result<(), std::io::Error> {
+        Self::queue_command(BeginSynchronizedUpdate)?;
+        Ok(())
+    }
+
+    fn end_synchronized_update(&mut self) -> Result<(), std::io::Error> {
+        Self::queue_command(EndSynchronizedUpdate)?;
+        Ok(())
+    }
+
     /// Moves the caret to the given Position.
     /// # Arguments
     /// * `Position` - the `Position` to move the caret to. Will be truncated to `u16::MAX` if bigger.
-    pub fn move_caret_to(p: &Position) -> Result<(), std::io::Error> {
+    fn move_caret_to(&mut self, p: &Position) -> Result<(), std::io::Error> {
         #[allow(clippy::as_conversions, clippy::cast_possible_truncation)]
         Self::queue_command(MoveTo(p.col as u16, p.row as u16))?;
         Ok(())
     }
 
-    pub fn hide_caret() -> Result<(), std::io::Error> {
+    fn hide_caret(&mut self) -> Result<(), std::io::Error> {
         Self::queue_command(Hide)?;
         Ok(())
     }
 
-    pub fn show_caret() -> Result<(), std::io::Error> {
+    fn show_caret(&mut self) -> Result<(), std::io::Error> {
         Self::queue_command(Show)?;
         Ok(())
     }
 
-    pub fn set_title(title: &str) -> Result<(), std::io::Error> {
+    fn set_title(&mut self, title: &str) -> Result<(), std::io::Error> {
         Self::queue_command(SetTitle(title))?;
         Ok(())
     }
 
-    pub fn print(s: &str) -> Result<(), std::io::Error> {
-        Self::queue_command(Print(s))?;
+    fn set_mouse_capture(&mut self, enabled: bool) -> Result<(), std::io::Error> {
+        if MOUSE_CAPTURED.swap(enabled, Ordering::Relaxed) != enabled {
+            if enabled {
+                Self::queue_command(EnableMouseCapture)?;
+            } else {
+                Self::queue_command(DisableMouseCapture)?;
+            }
+        }
         Ok(())
     }
 
-    pub fn print_row(row: usize, line_text: &str) -> Result<(), std::io::Error> {
-        Self::move_caret_to(&Position { row, col: 0 })?;
-        Self::clear_line()?;
-        Self::print(line_text)?;
+    fn print(&mut self, s: &str) -> Result<(), std::io::Error> {
+        Self::queue_command(Print(s))?;
         Ok(())
     }
 
-    pub fn print_inverted_row(row: usize, line_text: &str) -> Result<(), std::io::Error> {
-        let width = Self::size()?.width;
-        Self::print_row(row, &format!("{Reverse}{line_text:width$.width$}{Reset}"))
+    // like `print_annotated_row` for a text without annotations, which needs no copy of it
+    fn print_row(&mut self, row: usize, line_text: &str) -> Result<(), std::io::Error> {
+        let width = self.size()?.width;
+        queue_plain_row(&mut stdout(), row, line_text, width)
+    }
+
+    // the text is padded to the full width, so nothing is left to clear
+    fn print_inverted_row(&mut self, row: usize, line_text: &str) -> Result<(), std::io::Error> {
+        let width = self.size()?.width;
+        queue_inverted_row(&mut stdout(), row, line_text, width)
     }
 
-    pub fn print_annotated_row(
+    fn print_annotated_row(
+        &mut self,
         row: usize,
         annotated_string: &AnnotatedString,
     ) -> Result<(), std::io::Error> {
-        Self::move_caret_to(&Position { row, col: 0 })?;
-        Self::clear_line()?;
-
-        annotated_string
-            .into_iter()
-            .try_for_each(|part| -> Result<(), std::io::Error> {
-                if let Some(typ) = part.typ {
-                    let attribute = Attribute::from(typ);
-                    Self::set_attribute(&attribute)?;
-                }
-                Self::print(part.string)?;
-                Self::reset_color()?;
-                Ok(())
-            })?;
-        Ok(())
-    }
-
-    fn set_attribute(attribute: &Attribute) -> Result<(), std::io::Error> {
-        if let Some(foreground_color) = attribute.foreground {
-            Self::queue_command(SetForegroundColor(foreground_color))?;
-        }
-        if let Some(background_color) = attribute.background {
-            Self::queue_command(SetBackgroundColor(background_color))?;
-        }
-        Ok(())
-    }
-
-    fn reset_color() -> Result<(), std::io::Error> {
-        Self::queue_command(ResetColor)?;
-        Ok(())
+        let width = self.size()?.width;
+        queue_row(&mut stdout(), row, annotated_string, width, &self.theme)
     }
 
-    fn clear_line() -> Result<(), std::io::Error> {
-        Self::queue_command(Clear(ClearType::CurrentLine))?;
-        Ok(())
+    fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
     }
 
     /// Returns the current size of this Terminal.
     /// Edge Case for systems with `usize` < `u16`:
     /// * A `Size` representing the terminal size. Any coordinate `z` truncated to `usize` if `usize` < `z` < `u16`
-    pub fn size() -> Result<Size, std::io::Error> {
+    fn size(&self) -> Result<Size, std::io::Error> {
         let (width, height) = size()?;
 
         #[allow(clippy::as_conversions)]
@@ -166,13 +222,171 @@ impl Terminal {
         Ok(Size { height, width })
     }
 
-    pub fn execute() -> Result<(), std::io::Error> {
+    fn execute(&mut self) -> Result<(), std::io::Error> {
         stdout().flush()?;
         Ok(())
     }
+}
 
-    fn queue_command<T: Command>(command: T) -> Result<(), std::io::Error> {
-        queue!(stdout(), command)?;
-        Ok(())
+// Queue a row's text followed by clearing whatever a longer previous row left behind,
+// instead of clearing the row first. Text reaching the last column needs no clearing,
+// which would also erase the last character since the caret stays on it.
+#[allow(clippy::as_conversions, clippy::cast_possible_truncation)]
+fn queue_row<W: Write>(
+    out: &mut W,
+    row: usize,
+    annotated_string: &AnnotatedString,
+    terminal_width: usize,
+    theme: &Theme,
+) -> Result<(), std::io::Error> {
+    queue!(out, MoveTo(0, row as u16))?;
+    for part in annotated_string {
+        if let Some(typ) = part.typ {
+            let attribute = theme.attribute(typ);
+            if let Some(foreground_color) = attribute.foreground {
+                queue!(out, SetForegroundColor(foreground_color))?;
+            }
+            if let Some(background_color) = attribute.background {
+                queue!(out, SetBackgroundColor(background_color))?;
+            }
+            if let Some(underline_color) = attribute.underline {
+                queue!(
+                    out,
+                    SetUnderlineColor(underline_color),
+                    SetAttribute(Underlined)
+                )?;
+            }
+            if attribute.bold {
+                queue!(out, SetAttribute(Bold))?;
+            }
+            if attribute.italic {
+                queue!(out, SetAttribute(Italic))?;
+            }
+        }
+        queue!(out, Print(part.string), ResetColor)?;
+    }
+    if annotated_string.as_str().width() < terminal_width {
+        queue!(out, Clear(ClearType::UntilNewLine))?;
+    }
+    Ok(())
+}
+
+// the same as `queue_row` queues for `text` without annotations
+#[allow(clippy::as_conversions, clippy::cast_possible_truncation)]
+fn queue_plain_row<W: Write>(
+    out: &mut W,
+    row: usize,
+    text: &str,
+    terminal_width: usize,
+) -> Result<(), std::io::Error> {
+    queue!(out, MoveTo(0, row as u16))?;
+    if !text.is_empty() {
+        queue!(out, Print(text), ResetColor)?;
+    }
+    if text.width() < terminal_width {
+        queue!(out, Clear(ClearType::UntilNewLine))?;
+    }
+    Ok(())
+}
+
+// `text` cut or padded to `width` characters, like formatting it with `{:width$.width$}`
+#[allow(clippy::as_conversions, clippy::cast_possible_truncation)]
+fn queue_inverted_row<W: Write>(
+    out: &mut W,
+    row: usize,
+    text: &str,
+    width: usize,
+) -> Result<(), std::io::Error> {
+    const SPACES: &str = "                                ";
+    let end = text
+        .char_indices()
+        .nth(width)
+        .map_or(text.len(), |(byte_idx, _)| byte_idx);
+    let text = &text[..end];
+    let mut padding = width.saturating_sub(text.chars().count());
+    queue!(out, MoveTo(0, row as u16), Print(Reverse), Print(text))?;
+    while padding > 0 {
+        let spaces = padding.min(SPACES.len());
+        queue!(out, Print(&SPACES[..spaces]))?;
+        padding = padding.saturating_sub(spaces);
+    }
+    queue!(out, Print(Reset))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::editor::annotated_string::AnnotationType;
+
+    fn queued_row(text: &str, terminal_width: usize) -> String {
+        let mut annotated_string = AnnotatedString::from(text);
+        annotated_string.add_annotation(AnnotationType::Match, 0, 1);
+        let mut out = Vec::new();
+        queue_row(
+            &mut out,
+            2,
+            &annotated_string,
+            terminal_width,
+            &Theme::default(),
+        )
+        .unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn short_row_clears_until_end_of_line_once() {
+        let out = queued_row("abc", 10);
+        assert_eq!(out.matches("\x1b[K").count(), 1);
+        assert!(!out.contains("\x1b[2K"));
+        assert!(out.ends_with("c\x1b[0m\x1b[K"));
+    }
+
+    #[test]
+    fn rows_without_annotations_are_queued_alike() {
+        for (text, terminal_width) in [("", 5), ("abc", 5), ("abc", 3), ("~", 80)] {
+            let mut plain = Vec::new();
+            queue_plain_row(&mut plain, 1, text, terminal_width).unwrap();
+            let mut annotated = Vec::new();
+            let string = AnnotatedString::from(text);
+            queue_row(
+                &mut annotated,
+                1,
+                &string,
+                terminal_width,
+                &Theme::default(),
+            )
+            .unwrap();
+            assert_eq!(plain, annotated);
+        }
+        for (text, width) in [("", 3), ("abc", 2), ("Grüße", 7), ("x", 100)] {
+            let mut inverted = Vec::new();
+            queue_inverted_row(&mut inverted, 0, text, width).unwrap();
+            let mut formatted = Vec::new();
+            queue!(
+                formatted,
+                MoveTo(0, 0),
+                Print(format!("{Reverse}{text:width$.width$}{Reset}"))
+            )
+            .unwrap();
+            assert_eq!(inverted, formatted);
+        }
+    }
+
+    #[test]
+    fn spell_errors_are_underlined_until_reset() {
+        let mut annotated_string = AnnotatedString::from("teh");
+        annotated_string.add_annotation(AnnotationType::SpellError, 0, 3);
+        let mut out = Vec::new();
+        queue_row(&mut out, 0, &annotated_string, 3, &Theme::default()).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("\x1b[4m"));
+        assert!(out.ends_with("teh\x1b[0m"));
+    }
+
+    #[test]
+    fn full_row_is_not_cleared() {
+        let out = queued_row("abc", 3);
+        assert!(!out.contains("\x1b[K"));
     }
 }
@@ -0,0 +1,53 @@
+use super::{
+    super::{Position, Size},
+    Theme,
+};
+use crate::editor::annotated_string::AnnotatedString;
+
+/// Everything the editor needs from a terminal. `Terminal` talks to the real one,
+/// tests use an in-memory `MockTerminal` instead.
+pub trait TerminalIo {
+    fn initialize(&mut self) -> Result<(), std::io::Error>;
+
+    fn terminate(&mut self) -> Result<(), std::io::Error>;
+
+    fn clear_screen(&mut self) -> Result<(), std::io::Error>;
+
+    // everything queued between these two is shown at once by terminals supporting it
+    fn begin_synchronized_update(&mut self) -> Result<(), std::io::Error>;
+
+    fn end_synchronized_update(&mut self) -> Result<(), std::io::Error>;
+
+    fn move_caret_to(&mut self, p: &Position) -> Result<(), std::io::Error>;
+
+    fn hide_caret(&mut self) -> Result<(), std::io::Error>;
+
+    fn show_caret(&mut self) -> Result<(), std::io::Error>;
+
+    fn set_title(&mut self, title: &str) -> Result<(), std::io::Error>;
+
+    // report clicks to hecto instead of selecting text with them
+    fn set_mouse_capture(&mut self, enabled: bool) -> Result<(), std::io::Error>;
+
+    fn print(&mut self, s: &str) -> Result<(), std::io::Error>;
+
+    fn print_row(&mut self, row: usize, line_text: &str) -> Result<(), std::io::Error> {
+        self.print_annotated_row(row, &AnnotatedString::from(line_text))
+    }
+
+    fn print_inverted_row(&mut self, row: usize, line_text: &str) -> Result<(), std::io::Error>;
+
+    fn print_annotated_row(
+        &mut self,
+        row: usize,
+        annotated_string: &AnnotatedString,
+    ) -> Result<(), std::io::Error>;
+
+    // the colours of annotated text, terminals without colours ignore it
+    fn set_theme(&mut self, _theme: Theme) {}
+
+    fn size(&self) -> Result<Size, std::io::Error>;
+
+    // write out everything queued so far
+    fn execute(&mut self) -> Result<(), std::io::Error>;
+}
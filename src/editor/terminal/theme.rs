@@ -0,0 +1,203 @@
+use super::attribute::Attribute;
+use crate::editor::annotated_string::AnnotationType;
+use crossterm::style::Color;
+use serde::Deserialize;
+use std::{fs, path::Path};
+
+// colours of a theme file, e.g. `[match]` with `foreground = "black"`
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ThemeFile {
+    #[serde(rename = "match")]
+    search_match: Option<Colors>,
+    selected_match: Option<Colors>,
+    digit: Option<Colors>,
+    line_number: Option<Colors>,
+    word_match: Option<Colors>,
+    invisible: Option<Colors>,
+    warning: Option<Colors>,
+    error: Option<Colors>,
+    inserted: Option<Colors>,
+    deleted: Option<Colors>,
+    edited_line: Option<Colors>,
+    scroll_marker: Option<Colors>,
+    mark: Option<Colors>,
+    changed: Option<Colors>,
+    spell_error: Option<Colors>,
+    heading: Option<Colors>,
+    emphasis: Option<Colors>,
+    strong: Option<Colors>,
+    code: Option<Colors>,
+    markup: Option<Colors>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Colors {
+    foreground: Option<String>,
+    background: Option<String>,
+    underline: Option<String>,
+    bold: bool,
+    italic: bool,
+}
+
+// the colours of each annotation, falling back to the built-in ones
+#[derive(Default)]
+pub struct Theme {
+    search_match: Option<Attribute>,
+    selected_match: Option<Attribute>,
+    digit: Option<Attribute>,
+    line_number: Option<Attribute>,
+    word_match: Option<Attribute>,
+    invisible: Option<Attribute>,
+    warning: Option<Attribute>,
+    error: Option<Attribute>,
+    inserted: Option<Attribute>,
+    deleted: Option<Attribute>,
+    edited_line: Option<Attribute>,
+    scroll_marker: Option<Attribute>,
+    mark: Option<Attribute>,
+    changed: Option<Attribute>,
+    spell_error: Option<Attribute>,
+    heading: Option<Attribute>,
+    emphasis: Option<Attribute>,
+    strong: Option<Attribute>,
+    code: Option<Attribute>,
+    markup: Option<Attribute>,
+}
+
+impl Theme {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let toml = fs::read_to_string(path)
+            .map_err(|err| format!("Error reading theme '{}': {err}", path.display()))?;
+        Self::parse(&toml).map_err(|err| format!("Theme {}: {err}", path.display()))
+    }
+
+    fn parse(toml: &str) -> Result<Self, String> {
+        let file: ThemeFile =
+            toml::from_str(toml).map_err(|err| err.message().trim_end().replace('\n', " "))?;
+        Ok(Self {
+            search_match: file.search_match.map(Colors::parse).transpose()?,
+            selected_match: file.selected_match.map(Colors::parse).transpose()?,
+            digit: file.digit.map(Colors::parse).transpose()?,
+            line_number: file.line_number.map(Colors::parse).transpose()?,
+            word_match: file.word_match.map(Colors::parse).transpose()?,
+            invisible: file.invisible.map(Colors::parse).transpose()?,
+            warning: file.warning.map(Colors::parse).transpose()?,
+            error: file.error.map(Colors::parse).transpose()?,
+            inserted: file.inserted.map(Colors::parse).transpose()?,
+            deleted: file.deleted.map(Colors::parse).transpose()?,
+            edited_line: file.edited_line.map(Colors::parse).transpose()?,
+            scroll_marker: file.scroll_marker.map(Colors::parse).transpose()?,
+            mark: file.mark.map(Colors::parse).transpose()?,
+            changed: file.changed.map(Colors::parse).transpose()?,
+            spell_error: file.spell_error.map(Colors::parse).transpose()?,
+            heading: file.heading.map(Colors::parse).transpose()?,
+            emphasis: file.emphasis.map(Colors::parse).transpose()?,
+            strong: file.strong.map(Colors::parse).transpose()?,
+            code: file.code.map(Colors::parse).transpose()?,
+            markup: file.markup.map(Colors::parse).transpose()?,
+        })
+    }
+
+    pub fn attribute(&self, annotation_type: AnnotationType) -> Attribute {
+        let themed = match annotation_type {
+            AnnotationType::Match => self.search_match,
+            AnnotationType::SelectedMatch => self.selected_match,
+            AnnotationType::Digit => self.digit,
+            AnnotationType::LineNumber => self.line_number,
+            AnnotationType::WordMatch => self.word_match,
+            AnnotationType::Invisible => self.invisible,
+            AnnotationType::Warning => self.warning,
+            AnnotationType::Error => self.error,
+            AnnotationType::Inserted => self.inserted,
+            AnnotationType::Deleted => self.deleted,
+            AnnotationType::EditedLine => self.edited_line,
+            AnnotationType::ScrollMarker => self.scroll_marker,
+            AnnotationType::Mark => self.mark,
+            AnnotationType::Changed => self.changed,
+            AnnotationType::SpellError => self.spell_error,
+            AnnotationType::Heading => self.heading,
+            AnnotationType::Emphasis => self.emphasis,
+            AnnotationType::Strong => self.strong,
+            AnnotationType::Code => self.code,
+            AnnotationType::Markup => self.markup,
+        };
+        themed.unwrap_or_else(|| Attribute::from(annotation_type))
+    }
+}
+
+impl Colors {
+    fn parse(self) -> Result<Attribute, String> {
+        Ok(Attribute {
+            foreground: self.foreground.as_deref().map(parse_color).transpose()?,
+            background: self.background.as_deref().map(parse_color).transpose()?,
+            underline: self.underline.as_deref().map(parse_color).transpose()?,
+            bold: self.bold,
+            italic: self.italic,
+        })
+    }
+}
+
+// a colour name like `dark_red`, or `#rrggbb`
+fn parse_color(name: &str) -> Result<Color, String> {
+    let unknown = || format!("unknown colour '{name}'");
+    if let Some(hex) = name.strip_prefix('#') {
+        if hex.len() != 6 {
+            return Err(unknown());
+        }
+        let channel = |idx: usize| {
+            hex.get(idx..idx.saturating_add(2))
+                .and_then(|channel| u8::from_str_radix(channel, 16).ok())
+                .ok_or_else(unknown)
+        };
+        return Ok(Color::Rgb {
+            r: channel(0)?,
+            g: channel(2)?,
+            b: channel(4)?,
+        });
+    }
+    Color::try_from(name).map_err(|()| unknown())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn themed_colours_replace_the_built_in_ones() {
+        let theme =
+            Theme::parse("[match]\nforeground = \"white\"\nbackground = \"#0080ff\"\n").unwrap();
+        let attribute = theme.attribute(AnnotationType::Match);
+        assert_eq!(attribute.foreground, Some(Color::White));
+        assert_eq!(
+            attribute.background,
+            Some(Color::Rgb {
+                r: 0,
+                g: 0x80,
+                b: 0xff
+            })
+        );
+        assert_eq!(
+            theme.attribute(AnnotationType::Digit).foreground,
+            Some(Color::Red)
+        );
+    }
+
+    #[test]
+    fn themes_can_set_bold_and_italic() {
+        let theme = Theme::parse("[heading]\nforeground = \"blue\"\nitalic = true\n").unwrap();
+        let heading = theme.attribute(AnnotationType::Heading);
+        assert_eq!(heading.foreground, Some(Color::Blue));
+        assert!(heading.italic && !heading.bold);
+        assert!(theme.attribute(AnnotationType::Strong).bold);
+    }
+
+    #[test]
+    fn unknown_colours_are_errors() {
+        assert_eq!(
+            Theme::parse("[digit]\nforeground = \"redish\"\n").err(),
+            Some(String::from("unknown colour 'redish'"))
+        );
+    }
+}
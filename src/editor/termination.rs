@@ -0,0 +1,34 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicUsize, Ordering},
+};
+
+// SIGTERM or SIGHUP, as when the terminal window is closed. The handlers only note which
+// arrived, writing recovery files is left to the main loop as nothing else is safe to do in
+// a signal handler.
+#[derive(Clone, Default)]
+pub struct Termination {
+    // the number of the signal, 0 until one arrives
+    signal: Arc<AtomicUsize>,
+}
+
+impl Termination {
+    // from now on the signals are noted instead of killing hecto. Without them, as on
+    // Windows, nothing changes.
+    pub fn listen(&self) -> Result<(), std::io::Error> {
+        #[cfg(unix)]
+        for signal in [signal_hook::consts::SIGTERM, signal_hook::consts::SIGHUP] {
+            let number = usize::try_from(signal).unwrap_or_default();
+            signal_hook::flag::register_usize(signal, Arc::clone(&self.signal), number)?;
+        }
+        Ok(())
+    }
+
+    // the signal which arrived, if one did
+    pub fn signal(&self) -> Option<i32> {
+        match self.signal.load(Ordering::Relaxed) {
+            0 => None,
+            signal => i32::try_from(signal).ok(),
+        }
+    }
+}
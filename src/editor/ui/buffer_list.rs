@@ -0,0 +1,279 @@
+use super::super::{
+    Size,
+    annotated_string::{AnnotatedString, AnnotationType},
+    line::{Line, RenderOptions},
+    terminal::TerminalIo,
+};
+use super::UIComponent;
+
+// an open buffer as the list shows it
+pub struct BufferEntry {
+    pub idx: usize,
+    pub name: String,
+    pub is_modified: bool,
+    // None while the file isn't read yet
+    pub line_count: Option<usize>,
+}
+
+// The open buffers, listed below the panes to pick one to switch to or close. What was
+// typed narrows them down to the names containing its characters in order. The first row
+// says how many there are.
+pub struct BufferList {
+    entries: Vec<BufferEntry>,
+    filter: String,
+    // the entries whose names match the filter
+    shown: Vec<usize>,
+    // among the shown entries
+    selected: usize,
+    // the buffer with unsaved changes asked to be closed once already
+    close_asked: Option<usize>,
+    // the first entry shown
+    scroll_top: usize,
+    needs_redraw: bool,
+    size: Size,
+}
+
+impl BufferList {
+    // with the buffer at `focused` selected
+    pub fn new(entries: Vec<BufferEntry>, focused: usize) -> Self {
+        let mut list = Self {
+            entries: Vec::new(),
+            filter: String::new(),
+            shown: Vec::new(),
+            selected: 0,
+            close_asked: None,
+            scroll_top: 0,
+            needs_redraw: true,
+            size: Size::default(),
+        };
+        list.set_entries(entries);
+        list.selected = list
+            .shown
+            .iter()
+            .position(|entry| list.entries[*entry].idx == focused)
+            .unwrap_or(0);
+        list
+    }
+
+    // after a buffer was closed, keeping the filter and the selected row
+    pub fn set_entries(&mut self, entries: Vec<BufferEntry>) {
+        self.entries = entries;
+        let filter = std::mem::take(&mut self.filter);
+        let selected = self.selected;
+        self.filter(&filter);
+        self.selected = selected.min(self.shown.len().saturating_sub(1));
+    }
+
+    pub fn filter(&mut self, filter: &str) {
+        self.filter = filter.to_string();
+        self.shown = (0..self.entries.len())
+            .filter(|entry| is_fuzzy_match(&self.entries[*entry].name, filter))
+            .collect();
+        self.selected = 0;
+        self.close_asked = None;
+        self.set_needs_redraw(true);
+    }
+
+    // the index of the selected buffer
+    pub fn selected(&self) -> Option<usize> {
+        self.selected_entry().map(|entry| entry.idx)
+    }
+
+    fn selected_entry(&self) -> Option<&BufferEntry> {
+        self.shown
+            .get(self.selected)
+            .map(|entry| &self.entries[*entry])
+    }
+
+    pub fn select_next(&mut self) {
+        self.selected = self
+            .selected
+            .saturating_add(1)
+            .checked_rem(self.shown.len())
+            .unwrap_or(0);
+        self.close_asked = None;
+        self.set_needs_redraw(true);
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self
+            .selected
+            .checked_sub(1)
+            .unwrap_or_else(|| self.shown.len().saturating_sub(1));
+        self.close_asked = None;
+        self.set_needs_redraw(true);
+    }
+
+    // True if the selected buffer may be closed: it has no unsaved changes, or closing it
+    // was asked for before
+    pub fn may_close(&mut self) -> bool {
+        let Some(entry) = self.selected_entry() else {
+            return false;
+        };
+        if !entry.is_modified || self.close_asked == Some(entry.idx) {
+            return true;
+        }
+        self.close_asked = Some(entry.idx);
+        false
+    }
+
+    // e.g. "[2/5] main.rs"
+    pub fn hint(&self) -> String {
+        self.selected_entry().map_or_else(
+            || String::from("no match"),
+            |entry| {
+                format!(
+                    "[{}/{}] {}",
+                    self.selected.saturating_add(1),
+                    self.shown.len(),
+                    entry.name
+                )
+            },
+        )
+    }
+
+    // e.g. "5 buffers", or "2 of 5 buffers for 'rs'" while filtering
+    fn title(&self) -> String {
+        let count = self.entries.len();
+        let noun = if count == 1 { "buffer" } else { "buffers" };
+        if self.filter.is_empty() {
+            format!("{count} {noun}")
+        } else {
+            format!(
+                "{} of {count} {noun} for '{}'",
+                self.shown.len(),
+                self.filter
+            )
+        }
+    }
+
+    // ">2 main.rs [+]  120 lines", with the number as wide as the largest one
+    fn render_entry(&self, row: usize, number_width: usize) -> Option<AnnotatedString> {
+        let entry = &self.entries[*self.shown.get(row)?];
+        let marker = if row == self.selected { '>' } else { ' ' };
+        let prefix = format!("{marker}{:>number_width$} ", entry.idx.saturating_add(1));
+        let modified = if entry.is_modified { " [+]" } else { "" };
+        let lines = match entry.line_count {
+            Some(1) => String::from("1 line"),
+            Some(count) => format!("{count} lines"),
+            None => String::from("not read yet"),
+        };
+        let text = format!("{}{modified}  {lines}", entry.name);
+        let width = self.size.width.saturating_sub(prefix.len());
+        let mut text = Line::from(&text).get_annotated_visible_substr(
+            0..width,
+            None,
+            None,
+            RenderOptions::default(),
+        );
+        text.replace(0, 0, &prefix);
+        text.add_annotation(AnnotationType::LineNumber, 0, prefix.len());
+        Some(text)
+    }
+}
+
+// whether the characters of `filter` all appear in `name` in the same order, whatever
+// their case
+fn is_fuzzy_match(name: &str, filter: &str) -> bool {
+    let mut name = name.chars().flat_map(char::to_lowercase);
+    filter
+        .chars()
+        .flat_map(char::to_lowercase)
+        .all(|wanted| name.any(|ch| ch == wanted))
+}
+
+impl UIComponent for BufferList {
+    fn set_needs_redraw(&mut self, value: bool) {
+        self.needs_redraw = value;
+    }
+
+    fn get_needs_redraw(&self) -> bool {
+        self.needs_redraw
+    }
+
+    fn set_size(&mut self, size: Size) {
+        self.size = size;
+    }
+
+    fn draw(
+        &mut self,
+        terminal: &mut dyn TerminalIo,
+        origin_row: usize,
+    ) -> Result<(), std::io::Error> {
+        let Size { height, width } = self.size;
+        if height == 0 {
+            return Ok(());
+        }
+        let title = Line::from(&self.title()).get_visible_graphemes(0..width);
+        terminal.print_inverted_row(origin_row, &title)?;
+
+        // keep the selected buffer in view, and the rows filled after a buffer was closed
+        let rows = height.saturating_sub(1);
+        self.scroll_top = self.scroll_top.min(self.shown.len().saturating_sub(rows));
+        if self.selected < self.scroll_top {
+            self.scroll_top = self.selected;
+        } else if self.selected >= self.scroll_top.saturating_add(rows) {
+            self.scroll_top = self.selected.saturating_add(1).saturating_sub(rows);
+        }
+        let number_width = self.entries.len().to_string().len();
+        for row in 0..rows {
+            let current_row = origin_row.saturating_add(1).saturating_add(row);
+            match self.render_entry(self.scroll_top.saturating_add(row), number_width) {
+                Some(text) => terminal.print_annotated_row(current_row, &text)?,
+                None => terminal.print_row(current_row, "")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn entries(names: &[&str]) -> Vec<BufferEntry> {
+        names
+            .iter()
+            .enumerate()
+            .map(|(idx, name)| BufferEntry {
+                idx,
+                name: (*name).to_string(),
+                is_modified: idx == 1,
+                line_count: Some(idx),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn filters_by_characters_in_order() {
+        let mut list = BufferList::new(entries(&["main.rs", "README.md", "mod.rs"]), 2);
+        assert_eq!(list.selected(), Some(2));
+        assert_eq!(list.hint(), "[3/3] mod.rs");
+
+        list.filter("mrs");
+        assert_eq!(list.title(), "2 of 3 buffers for 'mrs'");
+        assert_eq!(list.selected(), Some(0));
+        list.select_previous();
+        assert_eq!(list.selected(), Some(2));
+        list.filter("RM");
+        assert_eq!(list.selected(), Some(1));
+        list.filter("rsm");
+        assert_eq!(list.selected(), None);
+        assert_eq!(list.hint(), "no match");
+    }
+
+    #[test]
+    fn asks_twice_to_close_a_modified_buffer() {
+        let mut list = BufferList::new(entries(&["a", "b", "c"]), 1);
+        assert!(!list.may_close());
+        assert!(list.may_close());
+        list.select_next();
+        list.select_previous();
+        assert!(!list.may_close());
+
+        list.select_previous();
+        assert!(list.may_close());
+        list.set_entries(entries(&["b", "c"]));
+        assert_eq!(list.selected(), Some(0));
+    }
+}
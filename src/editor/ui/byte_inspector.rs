@@ -0,0 +1,238 @@
+use super::super::{
+    Size,
+    annotated_string::{AnnotatedString, AnnotationType},
+    terminal::TerminalIo,
+};
+use super::UIComponent;
+use std::fmt::Write;
+
+const BYTES_PER_ROW: usize = 16;
+// "00000010  " before the hex column
+const OFFSET_WIDTH: usize = 10;
+// "41 42 … 4f  " before the printable column
+const HEX_WIDTH: usize = BYTES_PER_ROW * 3 + 1;
+
+// The bytes of the caret's line, listed below the panes sixteen to a row as offset, hex and
+// the printable ones, to see which control bytes or marks a line really holds. Read-only, the
+// selected byte is where the caret goes back to.
+pub struct ByteInspector {
+    bytes: Vec<u8>,
+    line_idx: usize,
+    selected: usize,
+    // the first row shown
+    scroll_top: usize,
+    needs_redraw: bool,
+    size: Size,
+}
+
+impl ByteInspector {
+    // `byte_idx` is selected, or the last byte if it's past it
+    pub fn new(line_idx: usize, text: &str, byte_idx: usize) -> Self {
+        Self {
+            bytes: text.as_bytes().to_vec(),
+            line_idx,
+            selected: byte_idx.min(text.len().saturating_sub(1)),
+            scroll_top: 0,
+            needs_redraw: true,
+            size: Size::default(),
+        }
+    }
+
+    pub const fn line_idx(&self) -> usize {
+        self.line_idx
+    }
+
+    pub const fn byte_offset(&self) -> usize {
+        self.selected
+    }
+
+    // by `delta` bytes, stopping at the first and the last
+    pub fn select(&mut self, delta: isize) {
+        let last = self.bytes.len().saturating_sub(1);
+        self.selected = self.selected.saturating_add_signed(delta).min(last);
+        self.set_needs_redraw(true);
+    }
+
+    pub fn select_first(&mut self) {
+        self.selected = 0;
+        self.set_needs_redraw(true);
+    }
+
+    pub fn select_last(&mut self) {
+        self.selected = self.bytes.len().saturating_sub(1);
+        self.set_needs_redraw(true);
+    }
+
+    // e.g. "0x41 65 'A'", or "empty line"
+    pub fn hint(&self) -> String {
+        self.bytes.get(self.selected).map_or_else(
+            || String::from("empty line"),
+            |byte| {
+                if byte.is_ascii_graphic() || *byte == b' ' {
+                    format!("0x{byte:02x} {byte} '{}'", char::from(*byte))
+                } else {
+                    format!("0x{byte:02x} {byte}")
+                }
+            },
+        )
+    }
+
+    // e.g. "line 3, 12 bytes"
+    fn title(&self) -> String {
+        let noun = if self.bytes.len() == 1 {
+            "byte"
+        } else {
+            "bytes"
+        };
+        format!(
+            "line {}, {} {noun}",
+            self.line_idx.saturating_add(1),
+            self.bytes.len()
+        )
+    }
+
+    // "00000010  41 42 0d  AB.", with the selected byte marked in both columns and the
+    // bytes that aren't printable dimmed
+    fn render_row(&self, row: usize) -> Option<AnnotatedString> {
+        let start = row.saturating_mul(BYTES_PER_ROW);
+        let bytes = self
+            .bytes
+            .get(start..self.bytes.len().min(start.saturating_add(BYTES_PER_ROW)))
+            .filter(|bytes| !bytes.is_empty())?;
+        let hex = bytes.iter().fold(String::new(), |mut hex, byte| {
+            let _ = write!(hex, "{byte:02x} ");
+            hex
+        });
+        let printable: String = bytes
+            .iter()
+            .map(|byte| {
+                if byte.is_ascii_graphic() || *byte == b' ' {
+                    char::from(*byte)
+                } else {
+                    '.'
+                }
+            })
+            .collect();
+        let mut text = format!("{start:08x}  {hex:<HEX_WIDTH$}{printable}");
+        text.truncate(self.size.width);
+        let mut row = AnnotatedString::from(&text);
+        let printable_start = OFFSET_WIDTH.saturating_add(HEX_WIDTH);
+        for (idx, byte) in bytes.iter().enumerate() {
+            let annotation_type = if start.saturating_add(idx) == self.selected {
+                AnnotationType::SelectedMatch
+            } else if byte.is_ascii_graphic() || *byte == b' ' {
+                continue;
+            } else {
+                AnnotationType::Invisible
+            };
+            let hex_start = OFFSET_WIDTH.saturating_add(idx.saturating_mul(3));
+            row.add_annotation(annotation_type, hex_start, hex_start.saturating_add(2));
+            let printable_idx = printable_start.saturating_add(idx);
+            row.add_annotation(
+                annotation_type,
+                printable_idx,
+                printable_idx.saturating_add(1),
+            );
+        }
+        Some(row)
+    }
+}
+
+impl UIComponent for ByteInspector {
+    fn set_needs_redraw(&mut self, value: bool) {
+        self.needs_redraw = value;
+    }
+
+    fn get_needs_redraw(&self) -> bool {
+        self.needs_redraw
+    }
+
+    fn set_size(&mut self, size: Size) {
+        self.size = size;
+    }
+
+    fn draw(
+        &mut self,
+        terminal: &mut dyn TerminalIo,
+        origin_row: usize,
+    ) -> Result<(), std::io::Error> {
+        let Size { height, width } = self.size;
+        if height == 0 {
+            return Ok(());
+        }
+        let mut title = self.title();
+        title.truncate(width);
+        terminal.print_inverted_row(origin_row, &title)?;
+
+        // keep the row of the selected byte in view
+        let rows = height.saturating_sub(1);
+        let selected_row = self.selected.checked_div(BYTES_PER_ROW).unwrap_or(0);
+        if selected_row < self.scroll_top {
+            self.scroll_top = selected_row;
+        } else if selected_row >= self.scroll_top.saturating_add(rows) {
+            self.scroll_top = selected_row.saturating_add(1).saturating_sub(rows);
+        }
+        for row in 0..rows {
+            let current_row = origin_row.saturating_add(1).saturating_add(row);
+            match self.render_row(self.scroll_top.saturating_add(row)) {
+                Some(text) => terminal.print_annotated_row(current_row, &text)?,
+                None => terminal.print_row(current_row, "")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn lists_offset_hex_and_printable_bytes() {
+        let mut inspector = ByteInspector::new(2, "\u{feff}tab\there, 0123456789", 4);
+        inspector.set_size(Size {
+            height: 4,
+            width: 80,
+        });
+        assert_eq!(inspector.title(), "line 3, 23 bytes");
+        assert_eq!(
+            inspector.render_row(0).unwrap().to_string(),
+            "00000000  ef bb bf 74 61 62 09 68 65 72 65 2c 20 30 31 32  ...tab.here, 012"
+        );
+        assert_eq!(
+            inspector.render_row(1).unwrap().to_string(),
+            format!("00000010  {:<49}3456789", "33 34 35 36 37 38 39")
+        );
+        assert!(inspector.render_row(2).is_none());
+        assert_eq!(inspector.hint(), "0x61 97 'a'");
+        let parts: Vec<_> = inspector
+            .render_row(0)
+            .unwrap()
+            .iter()
+            .filter_map(|part| part.typ.map(|typ| (part.string.to_string(), typ)))
+            .collect();
+        assert!(parts.contains(&(String::from("61"), AnnotationType::SelectedMatch)));
+        assert!(parts.contains(&(String::from("09"), AnnotationType::Invisible)));
+    }
+
+    #[test]
+    fn moves_by_byte_and_stops_at_the_ends() {
+        let mut inspector = ByteInspector::new(0, "ab\u{1}", 10);
+        assert_eq!(inspector.byte_offset(), 2);
+        assert_eq!(inspector.hint(), "0x01 1");
+        inspector.select(-1);
+        assert_eq!(inspector.byte_offset(), 1);
+        inspector.select(16);
+        assert_eq!(inspector.byte_offset(), 2);
+        inspector.select(-16);
+        assert_eq!(inspector.byte_offset(), 0);
+        inspector.select_last();
+        assert_eq!(inspector.byte_offset(), 2);
+        inspector.select_first();
+        assert_eq!(inspector.byte_offset(), 0);
+
+        let empty = ByteInspector::new(0, "", 0);
+        assert_eq!(empty.hint(), "empty line");
+        assert_eq!(empty.title(), "line 1, 0 bytes");
+    }
+}
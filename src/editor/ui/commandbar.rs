@@ -1,34 +1,48 @@
-use super::super::{
-    Size,
-    command::{Edit, Move},
-    line::Line,
-    terminal::Terminal,
-};
+use super::super::{Size, command::Edit, line::Line, terminal::TerminalIo};
 use super::UIComponent;
 use std::cmp::min;
 
+// how a prompt takes its answer
+#[derive(Default, Clone, Copy, PartialEq)]
+pub enum PromptMode {
+    // text of any length, until Enter
+    #[default]
+    Text,
+    // one of these lowercase characters, taken as soon as it is typed
+    SingleChar(&'static [char]),
+}
+
 #[derive(Default)]
 pub struct CommandBar {
     prompt: String,
+    mode: PromptMode,
     value: Line,
+    // shown after the value until the next edit
+    hint: String,
     needs_redraw: bool,
     size: Size,
 }
 impl CommandBar {
-    pub fn handle_edit_command(&mut self, edit_command: &Edit) {
+    pub fn handle_edit_command(&mut self, edit_command: Edit) {
+        // a single character answers the prompt, there is no value to edit
+        if self.mode != PromptMode::Text {
+            return;
+        }
         match edit_command {
-            Edit::InsertNewline | Edit::Delete => {}
-            Edit::Insert(ch) => self.value.append_char(*ch),
+            Edit::InsertNewline
+            | Edit::InsertLineBelow
+            | Edit::InsertLineAbove
+            | Edit::Delete
+            | Edit::TransposeGraphemes
+            | Edit::TransposeLines => {}
+            Edit::Insert(ch) => self.value.append_char(ch),
             Edit::InsertTab => self.value.append_char('\t'),
             Edit::DeleteBackward => self.value.delete_last(),
         }
+        self.hint.clear();
         self.set_needs_redraw(true);
     }
 
-    pub fn handle_move_command(&self, _move_command: &Move) {
-        todo!("ignore caret movement at this time");
-    }
-
     pub fn value(&self) -> String {
         self.value.to_string()
     }
@@ -44,11 +58,45 @@ impl CommandBar {
 
     pub fn set_prompt(&mut self, prompt: &str) {
         self.prompt = prompt.to_string();
+        self.mode = PromptMode::Text;
         self.set_needs_redraw(true);
     }
 
+    // ask `question`, answered by typing one of `choices`, which are shown behind it
+    pub fn set_choice_prompt(&mut self, question: &str, choices: &'static [char]) {
+        let shown: Vec<String> = choices.iter().map(char::to_string).collect();
+        self.prompt = format!("{question} ({})", shown.join("/"));
+        self.mode = PromptMode::SingleChar(choices);
+        self.set_needs_redraw(true);
+    }
+
+    // The choice `edit` typed, in either case. None while asking for text, or for anything
+    // but one of the choices.
+    pub fn answer(&self, edit: Edit) -> Option<char> {
+        let PromptMode::SingleChar(choices) = self.mode else {
+            return None;
+        };
+        match edit {
+            Edit::Insert(ch) => {
+                let ch = ch.to_ascii_lowercase();
+                choices.contains(&ch).then_some(ch)
+            }
+            _ => None,
+        }
+    }
+
     pub fn clear_value(&mut self) {
-        self.value = Line::default();
+        self.set_value("");
+    }
+
+    pub fn set_value(&mut self, value: &str) {
+        self.value = Line::from(value);
+        self.hint.clear();
+        self.set_needs_redraw(true);
+    }
+
+    pub fn set_hint(&mut self, hint: &str) {
+        self.hint = hint.to_string();
         self.set_needs_redraw(true);
     }
 }
@@ -66,17 +114,70 @@ impl UIComponent for CommandBar {
         self.size = size;
     }
 
-    fn draw(&mut self, origin_row: usize) -> Result<(), std::io::Error> {
-        let area_for_value = self.size.width.saturating_sub(self.prompt.len());
+    fn draw(
+        &mut self,
+        terminal: &mut dyn TerminalIo,
+        origin_row: usize,
+    ) -> Result<(), std::io::Error> {
+        // long prompts (like confirmation questions) are cut off at the right
+        let prompt = Line::from(&self.prompt);
+        let prompt_visible = prompt.get_visible_graphemes(0..self.size.width);
+        let area_for_value = self.size.width.saturating_sub(prompt.width());
         let value_end = self.value.width();
         let value_start = value_end.saturating_sub(area_for_value);
         let value_visible = self.value.get_visible_graphemes(value_start..value_end);
 
-        let message = &format!("{}{}", self.prompt, value_visible);
+        let area_for_hint = area_for_value.saturating_sub(self.value.width().saturating_add(1));
+        let hint_visible = Line::from(&self.hint).get_visible_graphemes(0..area_for_hint);
 
-        // FIXME: wish the editor is not too narrow 🙏
-        assert!(message.len() < self.size.width);
-        Terminal::print_row(origin_row, message)?;
+        let message = if hint_visible.is_empty() {
+            format!("{prompt_visible}{value_visible}")
+        } else {
+            format!("{prompt_visible}{value_visible} {hint_visible}")
+        };
+
+        // each part fits on its own, this only guards against them adding up in a narrow bar
+        let message = Line::from(&message).get_visible_graphemes(0..self.size.width);
+        terminal.print_row(origin_row, &message)?;
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::editor::terminal::MockTerminal;
+
+    #[test]
+    fn choice_prompts_take_a_single_character() {
+        let mut bar = CommandBar::default();
+        bar.set_choice_prompt("Overwrite?", &['y', 'n']);
+        assert_eq!(bar.prompt, "Overwrite? (y/n)");
+        assert_eq!(bar.answer(Edit::Insert('y')), Some('y'));
+        assert_eq!(bar.answer(Edit::Insert('N')), Some('n'));
+        assert_eq!(bar.answer(Edit::Insert('x')), None);
+        assert_eq!(bar.answer(Edit::InsertNewline), None);
+        // nothing else typed is kept
+        bar.handle_edit_command(Edit::Insert('x'));
+        assert_eq!(bar.value(), "");
+
+        bar.set_prompt("Save as: ");
+        assert_eq!(bar.answer(Edit::Insert('y')), None);
+        bar.handle_edit_command(Edit::Insert('y'));
+        assert_eq!(bar.value(), "y");
+    }
+
+    #[test]
+    fn narrow_bars_cut_the_prompt_off() {
+        let mut bar = CommandBar::default();
+        bar.set_choice_prompt("宽 Overwrite?", &['y', 'n']);
+        // a wide character cut in half shows as a placeholder
+        for (width, shown) in [(0, ""), (1, "⋯"), (3, "宽")] {
+            let size = Size { height: 1, width };
+            let terminal = MockTerminal::new(size);
+            bar.resize(size);
+            bar.render(&mut terminal.clone(), 0);
+            assert_eq!(terminal.row(0), shown);
+        }
+    }
+}
@@ -0,0 +1,219 @@
+use super::super::{
+    Size,
+    annotated_string::{AnnotatedString, AnnotationType},
+    diff::{Change, Hunk},
+    line::{Line, RenderOptions},
+    terminal::TerminalIo,
+};
+use super::UIComponent;
+
+// a row of the diff, and the line of the buffer it leads to
+struct DiffRow {
+    // None for the header of a hunk
+    change: Option<Change>,
+    text: String,
+    line_idx: usize,
+    hunk_idx: usize,
+}
+
+// The hunks between the saved file and the buffer, listed below the panes to look through
+// and jump to. The first row sums them up.
+pub struct DiffList {
+    rows: Vec<DiffRow>,
+    hunk_count: usize,
+    inserted: usize,
+    deleted: usize,
+    selected: usize,
+    // the first row shown
+    scroll_top: usize,
+    needs_redraw: bool,
+    size: Size,
+}
+
+impl DiffList {
+    // None without hunks, otherwise the first changed line is selected
+    pub fn new(hunks: &[Hunk], saved: &[String], lines: &[String]) -> Option<Self> {
+        if hunks.is_empty() {
+            return None;
+        }
+        let mut rows = Vec::new();
+        for (hunk_idx, hunk) in hunks.iter().enumerate() {
+            rows.push(DiffRow {
+                change: None,
+                text: hunk.header(),
+                line_idx: hunk.lines.first().map_or(0, |line| line.new_idx),
+                hunk_idx,
+            });
+            rows.extend(hunk.lines.iter().map(|line| {
+                let (sign, text) = match line.change {
+                    Change::Kept => (' ', lines.get(line.new_idx)),
+                    Change::Deleted => ('-', saved.get(line.old_idx)),
+                    Change::Inserted => ('+', lines.get(line.new_idx)),
+                };
+                DiffRow {
+                    change: Some(line.change),
+                    text: format!("{sign}{}", text.map_or("", String::as_str)),
+                    line_idx: line.new_idx,
+                    hunk_idx,
+                }
+            }));
+        }
+        let selected = rows
+            .iter()
+            .position(|row| row.change.is_some_and(|change| change != Change::Kept))
+            .unwrap_or(0);
+        Some(Self {
+            rows,
+            hunk_count: hunks.len(),
+            inserted: hunks.iter().map(|hunk| hunk.count(Change::Inserted)).sum(),
+            deleted: hunks.iter().map(|hunk| hunk.count(Change::Deleted)).sum(),
+            selected,
+            scroll_top: 0,
+            needs_redraw: true,
+            size: Size::default(),
+        })
+    }
+
+    // the line of the buffer the selected row leads to
+    pub fn selected_line(&self) -> Option<usize> {
+        self.rows.get(self.selected).map(|row| row.line_idx)
+    }
+
+    pub fn select_next(&mut self) {
+        self.selected = self
+            .selected
+            .saturating_add(1)
+            .min(self.rows.len().saturating_sub(1));
+        self.set_needs_redraw(true);
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+        self.set_needs_redraw(true);
+    }
+
+    // e.g. "[hunk 1/3] line 12"
+    pub fn hint(&self) -> String {
+        self.rows
+            .get(self.selected)
+            .map_or_else(String::new, |row| {
+                format!(
+                    "[hunk {}/{}] line {}",
+                    row.hunk_idx.saturating_add(1),
+                    self.hunk_count,
+                    row.line_idx.saturating_add(1)
+                )
+            })
+    }
+
+    // e.g. "2 hunks, +3 -1 lines since saved"
+    fn title(&self) -> String {
+        let noun = if self.hunk_count == 1 {
+            "hunk"
+        } else {
+            "hunks"
+        };
+        format!(
+            "{} {noun}, +{} -{} lines since saved",
+            self.hunk_count, self.inserted, self.deleted
+        )
+    }
+
+    // ">+the line", coloured by whether it was inserted or deleted
+    fn render_row(&self, idx: usize) -> Option<AnnotatedString> {
+        let row = self.rows.get(idx)?;
+        let width = self.size.width.saturating_sub(1);
+        let mut text = Line::from(&row.text).get_annotated_visible_substr(
+            0..width,
+            None,
+            None,
+            RenderOptions::default(),
+        );
+        let annotation_type = match row.change {
+            None => Some(AnnotationType::LineNumber),
+            Some(Change::Inserted) => Some(AnnotationType::Inserted),
+            Some(Change::Deleted) => Some(AnnotationType::Deleted),
+            Some(Change::Kept) => None,
+        };
+        if let Some(annotation_type) = annotation_type {
+            text.add_annotation(annotation_type, 0, text.to_string().len());
+        }
+        let marker = if idx == self.selected { ">" } else { " " };
+        text.replace(0, 0, marker);
+        Some(text)
+    }
+}
+
+impl UIComponent for DiffList {
+    fn set_needs_redraw(&mut self, value: bool) {
+        self.needs_redraw = value;
+    }
+
+    fn get_needs_redraw(&self) -> bool {
+        self.needs_redraw
+    }
+
+    fn set_size(&mut self, size: Size) {
+        self.size = size;
+    }
+
+    fn draw(
+        &mut self,
+        terminal: &mut dyn TerminalIo,
+        origin_row: usize,
+    ) -> Result<(), std::io::Error> {
+        let Size { height, width } = self.size;
+        if height == 0 {
+            return Ok(());
+        }
+        let title = Line::from(&self.title()).get_visible_graphemes(0..width);
+        terminal.print_inverted_row(origin_row, &title)?;
+
+        // keep the selected row in view
+        let rows = height.saturating_sub(1);
+        if self.selected < self.scroll_top {
+            self.scroll_top = self.selected;
+        } else if self.selected >= self.scroll_top.saturating_add(rows) {
+            self.scroll_top = self.selected.saturating_add(1).saturating_sub(rows);
+        }
+        for row in 0..rows {
+            let current_row = origin_row.saturating_add(1).saturating_add(row);
+            match self.render_row(self.scroll_top.saturating_add(row)) {
+                Some(text) => terminal.print_annotated_row(current_row, &text)?,
+                None => terminal.print_row(current_row, "")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::super::diff;
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn selects_the_first_change_and_stops_at_the_ends() {
+        let saved = lines("a\nb\nc\n");
+        let current = lines("a\nB\nc\nd\n");
+        let hunks = diff::diff(&saved, &current);
+        let mut list = DiffList::new(&hunks, &saved, &current).unwrap();
+        assert_eq!(list.title(), "1 hunk, +2 -1 lines since saved");
+        assert_eq!(list.selected_line(), Some(1));
+        assert_eq!(list.hint(), "[hunk 1/1] line 2");
+        for _ in 0..10 {
+            list.select_next();
+        }
+        assert_eq!(list.selected_line(), Some(3));
+        for _ in 0..10 {
+            list.select_previous();
+        }
+        assert_eq!(list.selected_line(), Some(0));
+
+        assert!(DiffList::new(&[], &saved, &saved).is_none());
+    }
+}
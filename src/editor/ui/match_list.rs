@@ -0,0 +1,349 @@
+use super::super::{
+    Size,
+    annotated_string::{AnnotatedString, AnnotationType},
+    line::{Line, RenderOptions},
+    terminal::TerminalIo,
+};
+use super::{Location, UIComponent};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+// Every match of a search, listed below the panes to pick one to jump to. The first row
+// says how many there are.
+pub struct MatchList {
+    query: String,
+    // with the file each is in, which is always the first for the matches of a buffer
+    locations: Vec<(usize, Location)>,
+    // the text of each line with a match in it, by its file and line
+    lines: HashMap<(usize, usize), Line>,
+    // the revision of the buffer the matches were found in
+    revision: usize,
+    // the files below `root` matches were found in, while searching files
+    files: Option<Files>,
+    selected: usize,
+    // the first match shown
+    scroll_top: usize,
+    needs_redraw: bool,
+    size: Size,
+}
+
+struct Files {
+    root: PathBuf,
+    paths: Vec<PathBuf>,
+    // until every file was searched
+    is_searching: bool,
+    // stopped after too many matches
+    is_capped: bool,
+}
+
+impl MatchList {
+    // None without matches, otherwise the first one at or behind the caret is selected
+    pub fn new(
+        query: &str,
+        locations: Vec<Location>,
+        revision: usize,
+        caret: Location,
+        line_text: impl Fn(usize) -> String,
+    ) -> Option<Self> {
+        if locations.is_empty() {
+            return None;
+        }
+        let lines = locations
+            .iter()
+            .map(|location| {
+                let line_idx = location.line_idx;
+                ((0, line_idx), Line::from(&line_text(line_idx)))
+            })
+            .collect();
+        let key = |location: &Location| (location.line_idx, location.grapheme_idx);
+        let selected = locations
+            .iter()
+            .position(|location| key(location) >= key(&caret))
+            .unwrap_or(0);
+        Some(Self {
+            query: query.to_string(),
+            locations: locations
+                .into_iter()
+                .map(|location| (0, location))
+                .collect(),
+            lines,
+            revision,
+            files: None,
+            selected,
+            scroll_top: 0,
+            needs_redraw: true,
+            size: Size::default(),
+        })
+    }
+
+    // empty until the matches in the files below `root` come in
+    pub fn in_files(query: &str, root: PathBuf) -> Self {
+        Self {
+            query: query.to_string(),
+            locations: Vec::new(),
+            lines: HashMap::new(),
+            revision: 0,
+            files: Some(Files {
+                root,
+                paths: Vec::new(),
+                is_searching: true,
+                is_capped: false,
+            }),
+            selected: 0,
+            scroll_top: 0,
+            needs_redraw: true,
+            size: Size::default(),
+        }
+    }
+
+    pub const fn is_in_files(&self) -> bool {
+        self.files.is_some()
+    }
+
+    // The next match in the files, at the byte `byte_idx` of `text`. They come file by file,
+    // so a path is only new if it isn't the last one.
+    pub fn push_file_match(&mut self, path: &Path, line_idx: usize, byte_idx: usize, text: &str) {
+        let Some(files) = &mut self.files else {
+            return;
+        };
+        if files.paths.last().is_none_or(|last| last != path) {
+            files.paths.push(path.to_path_buf());
+        }
+        let file_idx = files.paths.len().saturating_sub(1);
+        let line = self
+            .lines
+            .entry((file_idx, line_idx))
+            .or_insert_with(|| Line::from(text));
+        let grapheme_idx = line.byte_idx_to_grapheme_idx(byte_idx).unwrap_or(0);
+        self.locations.push((
+            file_idx,
+            Location {
+                grapheme_idx,
+                line_idx,
+            },
+        ));
+        self.set_needs_redraw(true);
+    }
+
+    // every file was searched, or the search stopped after too many matches if `capped`
+    pub fn finish_files(&mut self, capped: bool) {
+        if let Some(files) = &mut self.files {
+            files.is_searching = false;
+            files.is_capped = capped;
+        }
+        self.set_needs_redraw(true);
+    }
+
+    // the file the selected match is in, while searching files
+    pub fn selected_file(&self) -> Option<&Path> {
+        let (file_idx, _) = self.locations.get(self.selected)?;
+        Some(self.files.as_ref()?.paths.get(*file_idx)?)
+    }
+
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    pub const fn revision(&self) -> usize {
+        self.revision
+    }
+
+    pub fn selected(&self) -> Option<Location> {
+        self.locations
+            .get(self.selected)
+            .map(|(_, location)| *location)
+    }
+
+    pub fn select_next(&mut self) {
+        self.selected = self
+            .selected
+            .saturating_add(1)
+            .checked_rem(self.locations.len())
+            .unwrap_or(0);
+        self.set_needs_redraw(true);
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self
+            .selected
+            .checked_sub(1)
+            .unwrap_or_else(|| self.locations.len().saturating_sub(1));
+        self.set_needs_redraw(true);
+    }
+
+    // e.g. "[2/5] 12:7", or "[2/5] src/main.rs:12:7" in files
+    pub fn hint(&self) -> String {
+        if self.locations.is_empty() {
+            return String::new();
+        }
+        format!(
+            "[{}/{}] {}",
+            self.selected.saturating_add(1),
+            self.locations.len(),
+            self.describe(self.selected)
+        )
+    }
+
+    // e.g. "5 matches for 'foo'", or "5 matches for 'foo' in 2 files" once they're searched
+    fn title(&self) -> String {
+        let count = self.locations.len();
+        let noun = if count == 1 { "match" } else { "matches" };
+        let title = format!("{count} {noun} for '{}'", self.query);
+        match &self.files {
+            None => title,
+            Some(files) if files.is_searching => format!("{title} so far…"),
+            Some(files) if files.is_capped => format!("{title}, stopped there"),
+            Some(files) => {
+                let count = files.paths.len();
+                let noun = if count == 1 { "file" } else { "files" };
+                format!("{title} in {count} {noun}")
+            }
+        }
+    }
+
+    // the line and column of the match at `idx` counted from 1, after its file in files
+    fn describe(&self, idx: usize) -> String {
+        let Some((file_idx, location)) = self.locations.get(idx) else {
+            return String::new();
+        };
+        let path = self.files.as_ref().and_then(|files| {
+            let path = files.paths.get(*file_idx)?;
+            Some(path.strip_prefix(&files.root).unwrap_or(path))
+        });
+        let position = format!(
+            "{}:{}",
+            location.line_idx.saturating_add(1),
+            location.grapheme_idx.saturating_add(1)
+        );
+        match path {
+            Some(path) => format!("{}:{position}", path.display()),
+            None => position,
+        }
+    }
+
+    // ">12:7  the line", the match scrolled into view if the line is too long
+    fn render_entry(&self, idx: usize, number_width: usize) -> Option<AnnotatedString> {
+        let (file_idx, location) = self.locations.get(idx)?;
+        let line = self.lines.get(&(*file_idx, location.line_idx))?;
+        let marker = if idx == self.selected { '>' } else { ' ' };
+        let prefix = format!("{marker}{:<number_width$} ", self.describe(idx));
+        let width = self.size.width.saturating_sub(prefix.len());
+        let match_end = line
+            .width_until(location.grapheme_idx)
+            .saturating_add(Line::from(&self.query).width());
+        let left = if match_end > width {
+            match_end.saturating_sub(width.saturating_mul(2).div_ceil(3))
+        } else {
+            0
+        };
+        let mut text = line.get_annotated_visible_substr(
+            left..left.saturating_add(width),
+            Some(&self.query),
+            Some(location.grapheme_idx),
+            RenderOptions::default(),
+        );
+        text.replace(0, 0, &prefix);
+        text.add_annotation(AnnotationType::LineNumber, 0, prefix.len());
+        Some(text)
+    }
+}
+
+impl UIComponent for MatchList {
+    fn set_needs_redraw(&mut self, value: bool) {
+        self.needs_redraw = value;
+    }
+
+    fn get_needs_redraw(&self) -> bool {
+        self.needs_redraw
+    }
+
+    fn set_size(&mut self, size: Size) {
+        self.size = size;
+    }
+
+    fn draw(
+        &mut self,
+        terminal: &mut dyn TerminalIo,
+        origin_row: usize,
+    ) -> Result<(), std::io::Error> {
+        let Size { height, width } = self.size;
+        if height == 0 {
+            return Ok(());
+        }
+        let title = Line::from(&self.title()).get_visible_graphemes(0..width);
+        terminal.print_inverted_row(origin_row, &title)?;
+
+        // keep the selected match in view
+        let rows = height.saturating_sub(1);
+        if self.selected < self.scroll_top {
+            self.scroll_top = self.selected;
+        } else if self.selected >= self.scroll_top.saturating_add(rows) {
+            self.scroll_top = self.selected.saturating_add(1).saturating_sub(rows);
+        }
+        let number_width = (0..self.locations.len())
+            .map(|idx| self.describe(idx).len())
+            .max()
+            .unwrap_or(0);
+        for row in 0..rows {
+            let current_row = origin_row.saturating_add(1).saturating_add(row);
+            match self.render_entry(self.scroll_top.saturating_add(row), number_width) {
+                Some(text) => terminal.print_annotated_row(current_row, &text)?,
+                None => terminal.print_row(current_row, "")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn at(line_idx: usize, grapheme_idx: usize) -> Location {
+        Location {
+            grapheme_idx,
+            line_idx,
+        }
+    }
+
+    #[test]
+    fn selects_the_match_at_the_caret_and_cycles() {
+        let locations = vec![at(0, 4), at(2, 0), at(2, 8)];
+        let mut list =
+            MatchList::new("foo", locations, 0, at(1, 3), |_| String::from("foo")).unwrap();
+        assert_eq!(list.selected(), Some(at(2, 0)));
+        assert_eq!(list.hint(), "[2/3] 3:1");
+        list.select_next();
+        list.select_next();
+        assert_eq!(list.selected(), Some(at(0, 4)));
+        list.select_previous();
+        assert_eq!(list.selected(), Some(at(2, 8)));
+        assert_eq!(list.title(), "3 matches for 'foo'");
+
+        assert!(MatchList::new("foo", Vec::new(), 0, at(0, 0), |_| String::new()).is_none());
+    }
+
+    #[test]
+    fn lists_matches_in_files_as_they_come() {
+        let root = PathBuf::from("/project");
+        let mut list = MatchList::in_files("foo", root.clone());
+        assert_eq!(list.hint(), "");
+        assert_eq!(list.title(), "0 matches for 'foo' so far…");
+
+        list.push_file_match(&root.join("a.rs"), 4, 3, "é foo");
+        list.push_file_match(&root.join("b.rs"), 0, 0, "foo");
+        list.push_file_match(&root.join("b.rs"), 1, 0, "foo");
+        assert_eq!(list.hint(), "[1/3] a.rs:5:3");
+        assert_eq!(list.selected(), Some(at(4, 2)));
+        list.select_previous();
+        assert_eq!(list.selected_file(), Some(root.join("b.rs").as_path()));
+        assert_eq!(list.hint(), "[3/3] b.rs:2:1");
+
+        list.finish_files(false);
+        assert_eq!(list.title(), "3 matches for 'foo' in 2 files");
+        list.finish_files(true);
+        assert_eq!(list.title(), "3 matches for 'foo', stopped there");
+    }
+}
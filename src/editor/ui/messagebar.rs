@@ -1,27 +1,45 @@
-use super::super::Size;
+use super::super::{
+    Size,
+    annotated_string::{AnnotatedString, AnnotationType},
+    line::Line,
+};
 use super::UIComponent;
-use crate::editor::terminal::Terminal;
+use crate::editor::terminal::TerminalIo;
 use std::time::{Duration, Instant};
 
 const DEFAULT_DURATION: Duration = Duration::new(5, 0);
 
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Severity {
+    #[default]
+    Info,
+    Warning,
+    // stays until a key is pressed, however long that takes
+    Error,
+}
+
 struct Message {
     text: String,
+    severity: Severity,
     time: Instant,
+    // an error nobody pressed a key after yet
+    is_unseen: bool,
 }
 
 impl Default for Message {
     fn default() -> Self {
         Self {
             text: String::new(),
+            severity: Severity::default(),
             time: Instant::now(),
+            is_unseen: false,
         }
     }
 }
 
 impl Message {
     fn is_expired(&self) -> bool {
-        Instant::now().duration_since(self.time) > DEFAULT_DURATION
+        !self.is_unseen && Instant::now().duration_since(self.time) > DEFAULT_DURATION
     }
 }
 
@@ -30,17 +48,30 @@ pub struct MessageBar {
     current_message: Message,
     needs_redraw: bool,
     cleared_after_expiry: bool,
+    size: Size,
 }
 
 impl MessageBar {
-    pub fn update_message(&mut self, new_message: &str) {
+    pub fn update_message(&mut self, new_message: &str, severity: Severity) {
         self.current_message = Message {
             text: new_message.to_string(),
+            severity,
             time: Instant::now(),
+            is_unseen: severity == Severity::Error,
         };
         self.cleared_after_expiry = false;
         self.set_needs_redraw(true);
     }
+
+    // an error expires like any other message from now on
+    pub fn key_pressed(&mut self) {
+        self.current_message.is_unseen = false;
+    }
+
+    // whether a message is still shown
+    pub fn is_showing(&self) -> bool {
+        !self.current_message.text.is_empty() && !self.current_message.is_expired()
+    }
 }
 
 impl UIComponent for MessageBar {
@@ -52,20 +83,53 @@ impl UIComponent for MessageBar {
         (!self.cleared_after_expiry && self.current_message.is_expired()) || self.needs_redraw
     }
 
-    fn set_size(&mut self, _size: Size) {}
+    fn set_size(&mut self, size: Size) {
+        self.size = size;
+    }
 
-    fn draw(&mut self, origin_row: usize) -> Result<(), std::io::Error> {
+    fn draw(
+        &mut self,
+        terminal: &mut dyn TerminalIo,
+        origin_row: usize,
+    ) -> Result<(), std::io::Error> {
         if self.current_message.is_expired() {
             self.cleared_after_expiry = true;
         }
 
         let message = if self.current_message.is_expired() {
-            ""
+            String::new()
         } else {
-            &self.current_message.text
+            // cut off long messages like io errors at the edge of the terminal
+            Line::from(&self.current_message.text).get_visible_graphemes(0..self.size.width)
         };
 
-        Terminal::print_row(origin_row, message)?;
-        Ok(())
+        let annotation_type = match self.current_message.severity {
+            Severity::Info => return terminal.print_row(origin_row, &message),
+            Severity::Warning => AnnotationType::Warning,
+            Severity::Error => AnnotationType::Error,
+        };
+        let mut annotated = AnnotatedString::from(&message);
+        annotated.add_annotation(annotation_type, 0, message.len());
+        terminal.print_annotated_row(origin_row, &annotated)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn errors_wait_for_a_key_before_expiring() {
+        let mut message_bar = MessageBar::default();
+        let long_ago = Instant::now().checked_sub(DEFAULT_DURATION.saturating_mul(2));
+        message_bar.update_message("Error writing", Severity::Error);
+        message_bar.current_message.time = long_ago.unwrap();
+        assert!(message_bar.is_showing());
+        message_bar.key_pressed();
+        assert!(!message_bar.is_showing());
+
+        message_bar.update_message("Saved", Severity::Info);
+        message_bar.current_message.time = long_ago.unwrap();
+        assert!(!message_bar.is_showing());
     }
 }
@@ -1,11 +1,19 @@
+mod buffer_list;
+mod byte_inspector;
 mod commandbar;
+mod diff_list;
+mod match_list;
 mod messagebar;
 mod statusbar;
 mod uicomponent;
 mod view;
 
+pub use buffer_list::{BufferEntry, BufferList};
+pub use byte_inspector::ByteInspector;
 pub use commandbar::CommandBar;
-pub use messagebar::MessageBar;
-pub use statusbar::StatusBar;
+pub use diff_list::DiffList;
+pub use match_list::MatchList;
+pub use messagebar::{MessageBar, Severity};
+pub use statusbar::{Segment, StatusBar, StatusFormat};
 pub use uicomponent::UIComponent;
-pub use view::View;
+pub use view::{Buffer, Encoding, LineEnding, Location, LocationError, View, known_file_types};
@@ -1,9 +1,268 @@
-use super::super::{Size, documentstatus::DocumentStatus, terminal::Terminal};
+use super::super::{Size, documentstatus::DocumentStatus, terminal::TerminalIo};
 use super::UIComponent;
+use std::{iter::Peekable, mem, ops::Range, str::Chars};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+// `%(...%)` groups are left out when every field in them is empty, and a digit as in `%1(`
+// lets them go when the bar is too narrow, the lowest digit first
+pub const DEFAULT_FORMAT: &str = "%f%( %b%)%( %m%)%( %r%)%( %n%)%4( - %L lines%)%=%5(%M | %)%3(%y | %)%1(%e | %)%2(%E | %)%1(%h | %)Ln %l, Col %c";
+
+// filename, path, buffer number, modified flag, read-only flag, new file flag, mode, file type, encoding, line
+// endings, columns scrolled past, line, total lines, column and percent through the file
+const FIELDS: &str = "fFbmrnMyeEhlLcp";
+
+// what clicking a part of the bar does
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Segment {
+    // the line and column, going to another line
+    Position,
+    // choosing another file type
+    FileType,
+    // the modified flag, saving
+    Modified,
+}
+
+impl Segment {
+    fn of_field(field: char) -> Option<Self> {
+        match field {
+            'l' | 'L' | 'c' | 'p' => Some(Self::Position),
+            'y' => Some(Self::FileType),
+            'm' => Some(Self::Modified),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Item {
+    Text(String),
+    Field(char),
+    // where the left-aligned part ends and the right-aligned part starts
+    Split,
+    Group {
+        priority: Option<u8>,
+        items: Vec<Item>,
+    },
+}
+
+// a status line format from the config, parsed once
+#[derive(Debug, PartialEq, Eq)]
+pub struct StatusFormat {
+    items: Vec<Item>,
+}
+
+impl Default for StatusFormat {
+    fn default() -> Self {
+        Self::parse(DEFAULT_FORMAT).unwrap_or(Self { items: Vec::new() })
+    }
+}
+
+impl StatusFormat {
+    // specifiers which mean nothing are kept as they are, the error is a one-line message
+    pub fn parse(format: &str) -> Result<Self, String> {
+        let items = parse_items(&mut format.chars().peekable(), false)?;
+        if items.iter().filter(|item| **item == Item::Split).count() > 1 {
+            return Err(String::from("more than one %="));
+        }
+        Ok(Self { items })
+    }
+
+    // the pieces before and after `%=`, leaving out groups of priority `dropped` and below
+    fn render(&self, status: &DocumentStatus, dropped: u8) -> Vec<Vec<Piece>> {
+        let mut sides = vec![Vec::new()];
+        render_items(&self.items, status, dropped, &mut sides);
+        sides
+    }
+}
+
+// the text of a field, or of what is around it
+#[derive(Debug)]
+struct Piece {
+    text: String,
+    field: Option<char>,
+}
+
+fn width_of(pieces: &[Piece]) -> usize {
+    pieces.iter().map(|piece| piece.text.width()).sum()
+}
+
+// what the bar shows, and in which columns its segments are
+#[derive(Debug, Default)]
+struct Layout {
+    text: String,
+    segments: Vec<(Segment, Range<usize>)>,
+}
+
+impl Layout {
+    // cut to `width` columns. A segment reaches over the text between its fields, like
+    // ", Col " in "Ln 12, Col 3".
+    fn new<'a>(pieces: impl IntoIterator<Item = &'a Piece>, width: usize) -> Self {
+        let mut layout = Self::default();
+        let mut col = 0_usize;
+        let mut last_field = None;
+        for piece in pieces {
+            let start = col;
+            col = col.saturating_add(piece.text.width());
+            layout.text.push_str(&piece.text);
+            let Some(field) = piece.field.filter(|_| !piece.text.is_empty()) else {
+                continue;
+            };
+            let segment = Segment::of_field(field);
+            match (segment, layout.segments.last_mut()) {
+                (Some(segment), Some((last, range)))
+                    if *last == segment && last_field == Some(segment) =>
+                {
+                    range.end = col;
+                }
+                (Some(segment), _) => layout.segments.push((segment, start..col)),
+                (None, _) => {}
+            }
+            last_field = segment;
+        }
+        if layout.text.width() > width {
+            layout.text = truncate(&layout.text, width);
+            for (_, range) in &mut layout.segments {
+                range.end = range.end.min(width);
+            }
+            layout.segments.retain(|(_, range)| !range.is_empty());
+        }
+        layout
+    }
+}
+
+fn parse_items(chars: &mut Peekable<Chars>, in_group: bool) -> Result<Vec<Item>, String> {
+    let mut items = Vec::new();
+    let mut text = String::new();
+    while let Some(ch) = chars.next() {
+        if ch != '%' {
+            text.push(ch);
+            continue;
+        }
+        let Some(spec) = chars.next() else {
+            return Err(String::from("the format ends in a lone %"));
+        };
+        let item = match spec {
+            '%' => {
+                text.push('%');
+                continue;
+            }
+            '=' if in_group => return Err(String::from("%= inside a group")),
+            '=' => Item::Split,
+            ')' if in_group => {
+                if !text.is_empty() {
+                    items.push(Item::Text(text));
+                }
+                return Ok(items);
+            }
+            ')' => return Err(String::from("%) without a %(")),
+            '(' => Item::Group {
+                priority: None,
+                items: parse_items(chars, true)?,
+            },
+            '1'..='9' if chars.peek() == Some(&'(') => {
+                chars.next();
+                Item::Group {
+                    priority: Some((spec as u8).saturating_sub(b'0')),
+                    items: parse_items(chars, true)?,
+                }
+            }
+            field if FIELDS.contains(field) => Item::Field(field),
+            other => {
+                text.push('%');
+                text.push(other);
+                continue;
+            }
+        };
+        if !text.is_empty() {
+            items.push(Item::Text(mem::take(&mut text)));
+        }
+        items.push(item);
+    }
+    if in_group {
+        return Err(String::from("a %( is never closed"));
+    }
+    if !text.is_empty() {
+        items.push(Item::Text(text));
+    }
+    Ok(items)
+}
+
+// appends to the last of `sides`, returning whether any field had a value, or `None` without
+// any fields
+fn render_items(
+    items: &[Item],
+    status: &DocumentStatus,
+    dropped: u8,
+    sides: &mut Vec<Vec<Piece>>,
+) -> Option<bool> {
+    let mut filled = None;
+    for item in items {
+        let pieces = match item {
+            Item::Text(text) => vec![Piece {
+                text: text.clone(),
+                field: None,
+            }],
+            Item::Field(field) => {
+                let value = field_value(*field, status);
+                filled = Some(filled == Some(true) || !value.is_empty());
+                vec![Piece {
+                    text: value,
+                    field: Some(*field),
+                }]
+            }
+            Item::Split => {
+                sides.push(Vec::new());
+                continue;
+            }
+            Item::Group { priority, items } => {
+                if priority.is_some_and(|priority| priority <= dropped) {
+                    continue;
+                }
+                let mut group = vec![Vec::new()];
+                let group_filled = render_items(items, status, dropped, &mut group);
+                if let Some(group_filled) = group_filled {
+                    filled = Some(filled == Some(true) || group_filled);
+                }
+                if group_filled == Some(false) {
+                    continue;
+                }
+                group.into_iter().flatten().collect()
+            }
+        };
+        if let Some(side) = sides.last_mut() {
+            side.extend(pieces);
+        }
+    }
+    filled
+}
+
+fn field_value(field: char, status: &DocumentStatus) -> String {
+    match field {
+        'f' => status.filename.clone(),
+        'F' => status.path.clone(),
+        'b' => status.buffer_indicator_to_string(),
+        'm' => status.modified_indicator_to_string(),
+        'r' => status.read_only_indicator_to_string(),
+        'n' => status.new_file_indicator_to_string(),
+        'M' => status.mode_indicator_to_string(),
+        'y' => status.file_type.clone(),
+        'e' => status.encoding.to_string(),
+        'E' => status.line_ending.to_string(),
+        'h' => status.scroll_hint_to_string(),
+        'l' => status.current_line_idx.saturating_add(1).to_string(),
+        'L' => status.total_lines.to_string(),
+        'c' => status.column_to_string(),
+        'p' => status.percent().to_string(),
+        _ => String::new(),
+    }
+}
 
 #[derive(Default)]
 pub struct StatusBar {
     current_status: DocumentStatus,
+    format: StatusFormat,
+    // where the segments were drawn last
+    segments: Vec<(Segment, Range<usize>)>,
     needs_redraw: bool,
     size: Size,
 }
@@ -15,6 +274,57 @@ impl StatusBar {
             self.set_needs_redraw(true);
         }
     }
+
+    pub fn set_format(&mut self, format: StatusFormat) {
+        if self.format != format {
+            self.format = format;
+            self.set_needs_redraw(true);
+        }
+    }
+
+    // the segment drawn in column `col`, None for the padding between them
+    pub fn segment_at(&self, col: usize) -> Option<Segment> {
+        self.segments
+            .iter()
+            .find(|(_, range)| range.contains(&col))
+            .map(|(segment, _)| *segment)
+    }
+
+    // e.g. "a.rs [+] - 40 lines      rust | utf-8 | unix | Ln 12, Col 3", leaving out
+    // groups until it fits into `width`
+    fn layout(&self, width: usize) -> Layout {
+        for dropped in 0..=9 {
+            let sides = self.format.render(&self.current_status, dropped);
+            match sides.as_slice() {
+                [line] if width_of(line) <= width => return Layout::new(line, width),
+                [left, right] => {
+                    // at least one space between both sides
+                    let used = width_of(left).saturating_add(width_of(right));
+                    if used < width {
+                        let gap = Piece {
+                            text: " ".repeat(width.saturating_sub(used)),
+                            field: None,
+                        };
+                        return Layout::new(left.iter().chain([&gap]).chain(right), width);
+                    }
+                }
+                _ => {}
+            }
+        }
+        let sides = self.format.render(&self.current_status, 9);
+        let space = Piece {
+            text: String::from(" "),
+            field: None,
+        };
+        let mut pieces = Vec::new();
+        for (idx, side) in sides.iter().enumerate() {
+            if idx > 0 {
+                pieces.push(&space);
+            }
+            pieces.extend(side);
+        }
+        Layout::new(pieces, width)
+    }
 }
 
 impl UIComponent for StatusBar {
@@ -30,34 +340,15 @@ impl UIComponent for StatusBar {
         self.size = size;
     }
 
-    fn draw(&mut self, origin_row: usize) -> Result<(), std::io::Error> {
-        if let Ok(size) = Terminal::size() {
-            // left
-            let filename = &self.current_status.filename;
-            let line_count = &self.current_status.line_count_to_string();
-            let modified_indicator = &self.current_status.modified_indicator_to_string();
-            let beginning = if modified_indicator.is_empty() {
-                format!("{filename} - {line_count}")
-            } else {
-                format!("{filename} {modified_indicator} - {line_count}")
-            };
-
-            // right
-            let position_indicator = &self.current_status.position_indicator_to_string();
-
-            // cat
-            let remainder_width = size.width.saturating_sub(beginning.len());
-            let status = format!("{beginning}{position_indicator:>remainder_width$}");
-
-            // Only print out the status if it fits.
-            // Otherwise write out an empty string to ensure the row is cleared.
-            let to_print = if status.len() <= size.width {
-                status
-            } else {
-                String::new()
-            };
-
-            let result = Terminal::print_inverted_row(origin_row, &to_print);
+    fn draw(
+        &mut self,
+        terminal: &mut dyn TerminalIo,
+        origin_row: usize,
+    ) -> Result<(), std::io::Error> {
+        if let Ok(size) = terminal.size() {
+            let layout = self.layout(size.width);
+            self.segments = layout.segments;
+            let result = terminal.print_inverted_row(origin_row, &layout.text);
             // will ignore this in release build
             debug_assert!(result.is_ok(), "Failed to render line");
 
@@ -67,3 +358,117 @@ impl UIComponent for StatusBar {
         Ok(())
     }
 }
+
+// cut `text` to at most `width` columns
+fn truncate(text: &str, width: usize) -> String {
+    let mut used = 0_usize;
+    text.chars()
+        .take_while(|ch| {
+            used = used.saturating_add(ch.width().unwrap_or(0));
+            used <= width
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn status_bar() -> StatusBar {
+        let mut status_bar = StatusBar::default();
+        status_bar.update_status(DocumentStatus {
+            total_lines: 40,
+            current_line_idx: 11,
+            grapheme_col: 2,
+            visual_col: 2,
+            filename: String::from("main.rs"),
+            file_type: String::from("rust"),
+            encoding: "utf-8",
+            line_ending: "unix",
+            ..DocumentStatus::default()
+        });
+        status_bar
+    }
+
+    #[test]
+    fn narrow_bars_drop_segments() {
+        let status_bar = status_bar();
+        assert_eq!(
+            status_bar.layout(55).text,
+            "main.rs - 40 lines   rust | utf-8 | unix | Ln 12, Col 3"
+        );
+        assert_eq!(
+            status_bar.layout(40).text,
+            "main.rs - 40 lines   rust | Ln 12, Col 3"
+        );
+        assert_eq!(status_bar.layout(24).text, "main.rs     Ln 12, Col 3");
+        assert_eq!(status_bar.layout(10).text, "main.rs Ln");
+    }
+
+    #[test]
+    fn formats_fill_in_fields() {
+        let mut status_bar = status_bar();
+        status_bar.set_format(StatusFormat::parse("%f %m| %y | %l/%L:%c %p%% %x").unwrap());
+        assert_eq!(
+            status_bar.layout(40).text,
+            "main.rs | rust | 12/40:3 30% %x"
+        );
+
+        status_bar.set_format(StatusFormat::parse("%f%( [%m]%)%=%3(%y %)%p%%").unwrap());
+        assert_eq!(status_bar.layout(20).text, "main.rs     rust 30%");
+        assert_eq!(status_bar.layout(15).text, "main.rs     30%");
+
+        status_bar.set_format(StatusFormat::parse("%(%h | %)Col %c").unwrap());
+        assert_eq!(status_bar.layout(40).text, "Col 3");
+        status_bar.update_status(DocumentStatus {
+            cols_before: 12,
+            cols_after: 38,
+            ..DocumentStatus::default()
+        });
+        assert_eq!(status_bar.layout(40).text, "← 12 cols → +38 cols | Col 1");
+    }
+
+    #[test]
+    fn segments_follow_the_layout() {
+        let mut status_bar = status_bar();
+        assert_eq!(
+            status_bar.layout(55).segments,
+            [
+                (Segment::Position, 10..12),
+                (Segment::FileType, 21..25),
+                (Segment::Position, 46..55),
+            ]
+        );
+        // narrower, the file type is left out and the position moves
+        assert_eq!(
+            status_bar.layout(24).segments,
+            [(Segment::Position, 15..24)]
+        );
+        // cut off before the numbers, the label alone stands for nothing
+        assert_eq!(status_bar.layout(10).segments, []);
+
+        status_bar.current_status.is_modified = true;
+        status_bar.set_format(StatusFormat::parse("%f %m%=%y | %l:%c").unwrap());
+        let layout = status_bar.layout(30);
+        assert_eq!(layout.text, "main.rs [+]        rust | 12:3");
+        assert_eq!(
+            layout.segments,
+            [
+                (Segment::Modified, 8..11),
+                (Segment::FileType, 19..23),
+                (Segment::Position, 26..30),
+            ]
+        );
+    }
+
+    #[test]
+    fn malformed_formats_fail() {
+        assert_eq!(
+            StatusFormat::parse(DEFAULT_FORMAT),
+            Ok(StatusFormat::default())
+        );
+        for format in ["%f %", "%(%f", "%f%)", "%=%=", "%(%=%)"] {
+            assert!(StatusFormat::parse(format).is_err(), "{format}");
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use super::super::Size;
+use super::super::{Size, terminal::TerminalIo};
 
 pub trait UIComponent {
     // marks this UI component as in need of redrawing or not
@@ -17,12 +17,12 @@ pub trait UIComponent {
     fn set_size(&mut self, size: Size);
 
     // draw this component if it's visible and in need of redrawing
-    fn render(&mut self, origin_row: usize) {
+    fn render(&mut self, terminal: &mut dyn TerminalIo, origin_row: usize) {
         if !self.get_needs_redraw() {
             return;
         }
 
-        match self.draw(origin_row) {
+        match self.draw(terminal, origin_row) {
             Ok(()) => self.set_needs_redraw(false),
             Err(err) => {
                 #[cfg(debug_assertions)]
@@ -31,12 +31,16 @@ pub trait UIComponent {
                 }
                 #[cfg(not(debug_assertions))]
                 {
-                    let _ = err;
+                    log::error!("Could not render component: {err:?}");
                 }
             }
         }
     }
 
     // method to actually draw the component
-    fn draw(&mut self, origin_row: usize) -> Result<(), std::io::Error>;
+    fn draw(
+        &mut self,
+        terminal: &mut dyn TerminalIo,
+        origin_row: usize,
+    ) -> Result<(), std::io::Error>;
 }
@@ -1,175 +1,1227 @@
-use super::Location;
-use super::fileinfo::FileInfo;
-use crate::editor::line::Line;
+use super::edit_op::{EditKind, EditOp};
+use super::encoding::Encoding;
+use super::fileinfo::{FileInfo, LineEnding};
+use super::line_storage::{LineStorage, Storage};
+use super::loader::{Loaded, Loader};
+use super::statistics::{Statistics, TextSize};
+use super::{Location, LocationError};
+use crate::editor::{
+    command::Edit,
+    diff::{self, LineChange},
+    git::{Head, HeadRequest},
+    line::{CellWidths, Line},
+};
+use log::warn;
+use std::cell::{Cell, RefCell};
+use std::cmp::Ordering;
+use std::collections::HashSet;
 use std::fs::File;
-use std::fs::read_to_string;
-use std::io::Write;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::{BufWriter, ErrorKind, Write};
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Arc;
+use unicode_segmentation::UnicodeSegmentation;
 
+/// The lines of a text, with what is needed to edit and save them.
+///
+/// ```
+/// use hecto::{Buffer, Edit, Location};
+///
+/// let mut buffer = Buffer::from_text("hello\n");
+/// let end = Location { grapheme_idx: 5, line_idx: 0 };
+/// let caret = buffer.insert_text(", world\nbye", &end)?;
+/// assert_eq!(caret, Location { grapheme_idx: 3, line_idx: 1 });
+/// buffer.apply(Edit::DeleteBackward, &caret)?;
+/// assert_eq!(buffer.line(0).unwrap().to_string(), "hello, world");
+/// assert_eq!(buffer.line(1).unwrap().to_string(), "by");
+/// assert!(buffer.is_modified());
+/// # Ok::<(), hecto::LocationError>(())
+/// ```
 #[derive(Default)]
 pub struct Buffer {
-    pub file_info: FileInfo,
-    pub lines: Vec<Line>,
-    pub dirty: bool,
+    pub(crate) file_info: FileInfo,
+    pub(crate) lines: Box<dyn LineStorage>,
+    // how `lines` are kept once the text is read
+    storage: Storage,
+    // counts the edits, the contents are modified unless they are the same as when last saved
+    revision: usize,
+    saved_revision: usize,
+    saved_hash: Option<u64>,
+    // the revision whether the contents are modified was last worked out for
+    modified_at: Cell<Option<(usize, bool)>>,
+    // the words for completion, collected again once the revision changes
+    words: RefCell<Option<(usize, HashSet<String>)>>,
+    // a copy of the text for searching it on another thread, made again once the revision
+    // changes
+    snapshot: RefCell<Option<(usize, Arc<[String]>)>>,
+    // None unless the file is still being read in the background
+    loader: Option<Loader>,
+    // of the text read last, which edits don't change
+    text_size: TextSize,
+    // the file doesn't exist yet, saving creates it
+    pub(crate) is_new: bool,
+    // only named so far, the file is read once the buffer is first shown
+    pub(crate) is_unread: bool,
+    // saving over the file is refused, saving it under another name isn't
+    pub(crate) is_read_only: bool,
+    // whether words are spell checked, None for the default of the file type
+    pub(crate) spell_check: Option<bool>,
+    widths: CellWidths,
+    // what the edits since they were last taken did
+    edits: Vec<EditOp>,
+    // None unless git is reading the file as checked in, to compare the lines with
+    head: Option<HeadRequest>,
+    // how each line differs from the file as checked in, as of when git last read it. Empty
+    // outside a work tree or without differences.
+    git_changes: Vec<Option<LineChange>>,
 }
 
 impl Buffer {
-    pub fn load(filename: &str) -> Self {
-        if let Ok(string) = read_to_string(filename) {
-            let lines = string.lines().map(Line::from).collect();
-            Self {
-                file_info: FileInfo::from(filename),
-                lines,
-                dirty: false,
+    /// Reads the file at `filename`, or opens an empty buffer to create it if it doesn't exist.
+    ///
+    /// # Errors
+    ///
+    /// If the file exists but can't be read, e.g. for lack of permission.
+    pub fn load(filename: &str) -> Result<Self, std::io::Error> {
+        Self::load_as(filename, Encoding::default())
+    }
+
+    // like `load`, for a file in another encoding than UTF-8
+    pub(crate) fn load_as(filename: &str, encoding: Encoding) -> Result<Self, std::io::Error> {
+        match encoding.read(Path::new(filename)) {
+            Ok(string) => {
+                let mut buffer = Self {
+                    file_info: FileInfo::from(filename),
+                    ..Self::default()
+                };
+                buffer.read_text(&string, encoding);
+                buffer.mark_saved();
+                Ok(buffer)
             }
-        } else {
             // open as an empty file if file doesn't exist
-            Self {
-                file_info: FileInfo::from(filename),
-                lines: vec![Line::default()],
-                dirty: true,
+            Err(err) if err.kind() == ErrorKind::NotFound => {
+                let mut buffer = Self {
+                    file_info: FileInfo::from(filename),
+                    lines: Box::new(vec![Line::default()]),
+                    is_new: true,
+                    ..Self::default()
+                };
+                buffer.mark_saved();
+                Ok(buffer)
             }
+            Err(err) => Err(err),
+        }
+    }
+
+    // a buffer for `filename` which doesn't read it yet
+    pub(crate) fn unread(filename: &str) -> Self {
+        let mut buffer = Self {
+            file_info: FileInfo::from(filename),
+            lines: Box::new(vec![Line::default()]),
+            is_unread: true,
+            ..Self::default()
+        };
+        buffer.mark_saved();
+        buffer
+    }
+
+    /// Text which didn't come from a file, like what was piped into hecto. Both `\n` and
+    /// `\r\n` end a line.
+    ///
+    /// ```
+    /// let buffer = hecto::Buffer::from_text("one\r\ntwo");
+    /// assert_eq!(buffer.get_height(), 2);
+    /// assert_eq!(buffer.line(1).unwrap().to_string(), "two");
+    /// ```
+    #[must_use]
+    pub fn from_text(text: &str) -> Self {
+        let mut buffer = Self::default();
+        buffer.read_text(text, Encoding::Utf8);
+        buffer.mark_saved();
+        buffer
+    }
+
+    // like `load`, but the file is read in the background, the lines show up as they arrive
+    pub(crate) fn start_loading(
+        filename: &str,
+        encoding: Encoding,
+    ) -> Result<Self, std::io::Error> {
+        let loader = Loader::start(Path::new(filename), encoding)?;
+        let storage = Storage::default();
+        let mut file_info = FileInfo::from(filename);
+        file_info.encoding = encoding;
+        let mut buffer = Self {
+            file_info,
+            lines: storage.store(Vec::new(), loader.total_bytes()),
+            storage,
+            loader: Some(loader),
+            ..Self::default()
+        };
+        buffer.mark_saved();
+        Ok(buffer)
+    }
+
+    pub(crate) const fn is_loading(&self) -> bool {
+        self.loader.is_some()
+    }
+
+    // how much of the file has arrived, None once it has been loaded
+    pub(crate) fn load_percent(&self) -> Option<u64> {
+        self.loader.as_ref().map(Loader::percent)
+    }
+
+    // Take the lines which arrived meanwhile, or wait for the rest of the file if `wait` is
+    // set. Returns whether there were new lines. After an error the buffer keeps the lines
+    // before it, and loading is over.
+    pub(crate) fn receive_loaded(&mut self, wait: bool) -> Result<bool, std::io::Error> {
+        let mut received = false;
+        let mut is_done = false;
+        let mut result = Ok(());
+        while let Some(loaded) = self.loader.as_mut().and_then(|loader| loader.next(wait)) {
+            match loaded {
+                Loaded::Format(encoding, line_ending) => {
+                    self.file_info.encoding = encoding;
+                    self.file_info.line_ending = line_ending;
+                }
+                Loaded::Lines(lines, _) => {
+                    let widths = self.widths;
+                    for line in &lines {
+                        self.text_size.add_line(line);
+                    }
+                    self.lines.extend(
+                        lines
+                            .iter()
+                            .map(|line| Line::with_widths(line, widths))
+                            .collect(),
+                    );
+                    received = true;
+                }
+                Loaded::Done => {
+                    self.loader = None;
+                    is_done = true;
+                }
+                Loaded::Failed(err) => {
+                    self.loader = None;
+                    result = Err(err);
+                }
+            }
+        }
+        if received {
+            // the lines are as saved, not edits
+            self.mark_changed();
+            self.saved_revision = self.revision;
+        }
+        if is_done {
+            self.mark_saved();
+        }
+        result.map(|()| received)
+    }
+
+    // stop reading the file, keeping the lines which arrived so far
+    pub(crate) fn cancel_loading(&mut self) {
+        self.loader = None;
+    }
+
+    // re-read the backing file, keeping the current contents if it can't be read
+    pub(crate) fn reload(&mut self) -> Result<(), std::io::Error> {
+        if let Some(path) = self.file_info.get_path() {
+            let encoding = self.file_info.encoding;
+            let string = encoding.read(path)?;
+            self.read_text(&string, encoding);
+            // lists of matches found before go stale
+            self.mark_changed();
+            self.mark_saved();
         }
+        Ok(())
     }
 
-    pub const fn is_file_loaded(&self) -> bool {
+    // the lines of the backing file as they are on disk, None without a file
+    pub(crate) fn saved_lines(&self) -> Option<Result<Vec<String>, std::io::Error>> {
+        let path = self.file_info.get_path()?;
+        let encoding = self.file_info.encoding;
+        Some(encoding.read(path).map(|string| {
+            let (text, _) = encoding.decode(&string);
+            text.lines().map(ToString::to_string).collect()
+        }))
+    }
+
+    // take the lines of `text` as read from a file in `encoding`, and how the file was written
+    fn read_text(&mut self, text: &str, encoding: Encoding) {
+        let (text, encoding) = encoding.decode(text);
+        self.file_info.encoding = encoding;
+        self.file_info.line_ending = LineEnding::detect(text);
+        self.text_size = TextSize::default();
+        let lines = text
+            .lines()
+            .map(|line| {
+                self.text_size.add_line(line);
+                Line::with_widths(line, self.widths)
+            })
+            .collect();
+        self.lines = self.storage.store(lines, text.len() as u64);
+        self.words.take();
+        self.snapshot.take();
+        self.git_changes.clear();
+    }
+
+    // convert the line endings, which only changes the file once it is saved
+    pub(crate) fn set_line_ending(&mut self, line_ending: LineEnding) {
+        if self.file_info.line_ending != line_ending {
+            self.file_info.line_ending = line_ending;
+            self.mark_changed();
+        }
+    }
+
+    // add or drop the byte order mark, which also only changes the file once it is saved
+    pub(crate) fn set_encoding(&mut self, encoding: Encoding) {
+        if self.file_info.encoding != encoding {
+            self.file_info.encoding = encoding;
+            self.mark_changed();
+        }
+    }
+
+    pub(crate) fn set_widths(&mut self, widths: CellWidths) {
+        self.widths = widths;
+        for line in self.lines.iter_mut() {
+            line.set_widths(widths);
+        }
+    }
+
+    // the size of the text as it was loaded, counted while reading it
+    pub(crate) const fn text_size(&self) -> TextSize {
+        self.text_size
+    }
+
+    pub(crate) const fn is_file_loaded(&self) -> bool {
         self.file_info.has_path()
     }
 
+    // region: modified
+    /// Whether the text differs from when it was last loaded or saved. Edits taken back by
+    /// hand don't count.
+    pub fn is_modified(&self) -> bool {
+        if self.revision == self.saved_revision {
+            return false;
+        }
+        if let Some((revision, modified)) = self.modified_at.get()
+            && revision == self.revision
+        {
+            return modified;
+        }
+        // edits may have been taken back by hand
+        let modified = self.saved_hash != Some(self.content_hash());
+        self.modified_at.set(Some((self.revision, modified)));
+        modified
+    }
+
+    // changes with every edit
+    pub(crate) const fn revision(&self) -> usize {
+        self.revision
+    }
+
+    fn mark_changed(&mut self) {
+        self.revision = self.revision.wrapping_add(1);
+    }
+
+    // every edit ends up here, saying what it did
+    fn record(&mut self, op: EditOp) {
+        self.mark_changed();
+        self.follow_with_git_changes(&op);
+        self.edits.push(op);
+    }
+
+    // what the edits since the last call did, in the order they were made
+    pub(crate) fn take_edits(&mut self) -> Vec<EditOp> {
+        std::mem::take(&mut self.edits)
+    }
+
+    fn mark_saved(&mut self) {
+        self.saved_revision = self.revision;
+        self.saved_hash = Some(self.content_hash());
+        for line in self.lines.iter_mut() {
+            line.set_edited(false);
+        }
+    }
+
+    // everything that ends up in the file
+    fn content_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.file_info.encoding.hash(&mut hasher);
+        self.file_info.line_ending.hash(&mut hasher);
+        for line in self.lines.iter() {
+            (**line).hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+    // endregion
+
+    // counts of the whole buffer and of what is before `caret`, whose line counts as well
+    pub(crate) fn statistics(&self, caret: &Location) -> (Statistics, Statistics) {
+        let line_ending = self.file_info.line_ending.as_str();
+        let mut total = Statistics::default();
+        let mut before = Statistics::default();
+        total.bytes = self.file_info.encoding.prefix().len();
+        for (line_idx, line) in self.lines.iter().enumerate() {
+            if line_idx == caret.line_idx {
+                before = total;
+                before.add_text(line.text_before(caret.grapheme_idx));
+                before.lines = before.lines.saturating_add(1);
+            }
+            total.add_text(line);
+            total.add_line_ending(line_ending);
+        }
+        // past the last line
+        if caret.line_idx >= self.lines.len() {
+            before = total;
+            before.lines = before.lines.saturating_add(1);
+        }
+        (before, total)
+    }
+
+    // the words of the buffer which are longer than `prefix` and start with it, sorted
+    pub(crate) fn words_starting_with(&self, prefix: &str) -> Vec<String> {
+        let mut words = self.words.borrow_mut();
+        if words
+            .as_ref()
+            .is_none_or(|(revision, _)| *revision != self.revision)
+        {
+            let found = self.lines.iter().flat_map(Line::words);
+            *words = Some((self.revision, found.map(str::to_string).collect()));
+        }
+        let mut candidates: Vec<String> = words
+            .iter()
+            .flat_map(|(_, words)| words)
+            .filter(|word| word.len() > prefix.len() && word.starts_with(prefix))
+            .cloned()
+            .collect();
+        candidates.sort();
+        candidates
+    }
+
+    pub(crate) fn snapshot(&self) -> Arc<[String]> {
+        let mut snapshot = self.snapshot.borrow_mut();
+        if let Some((revision, lines)) = snapshot.as_ref()
+            && *revision == self.revision
+        {
+            return Arc::clone(lines);
+        }
+        let lines: Arc<[String]> = self.lines.iter().map(ToString::to_string).collect();
+        *snapshot = Some((self.revision, Arc::clone(&lines)));
+        lines
+    }
+
+    /// Whether there are no lines at all, like in an empty file. A single empty line is one.
     pub fn is_empty(&self) -> bool {
         self.lines.is_empty()
     }
 
+    /// The number of lines.
     pub fn get_height(&self) -> usize {
         self.lines.len()
     }
 
+    /// The line at `line_idx`, counted from 0.
+    pub fn line(&self, line_idx: usize) -> Option<&Line> {
+        self.lines.get(line_idx)
+    }
+
     // region: save
+    /// Writes the text to `filename`, which becomes the buffer's file.
+    ///
+    /// # Errors
+    ///
+    /// If the file can't be written, or is the one the buffer was opened from read-only.
     pub fn save_as(&mut self, filename: &str) -> Result<(), std::io::Error> {
-        let file_info = FileInfo::from(filename);
+        if self.is_read_only && self.file_info.is_same_file(filename) {
+            return Err(Self::read_only_error());
+        }
+        let file_info = self.file_info.with_path(filename);
         self.save_to_file(&file_info)?;
         self.file_info = file_info;
-        self.dirty = false;
+        self.mark_saved();
+        self.is_new = false;
+        self.is_read_only = false;
         Ok(())
     }
 
+    /// Writes the text to the buffer's file, with the line endings and byte order mark it was
+    /// read with. Without a file, like for a buffer made by [`Buffer::from_text`], nothing is
+    /// written.
+    ///
+    /// # Errors
+    ///
+    /// If the file can't be written, or the buffer was opened read-only.
     pub fn save(&mut self) -> Result<(), std::io::Error> {
+        if self.is_read_only {
+            return Err(Self::read_only_error());
+        }
         self.save_to_file(&self.file_info)?;
-        self.dirty = false;
+        self.mark_saved();
+        self.is_new = false;
         Ok(())
     }
 
+    fn read_only_error() -> std::io::Error {
+        std::io::Error::new(ErrorKind::PermissionDenied, "opened read-only")
+    }
+
     fn save_to_file(&self, file_info: &FileInfo) -> Result<(), std::io::Error> {
         if let Some(path) = file_info.get_path() {
-            let mut file = File::create(path)?;
-            for line in &self.lines {
-                writeln!(file, "{line}")?;
+            self.write_encoded(path, file_info.encoding)?;
+        }
+
+        Ok(())
+    }
+
+    // Write the contents to `path` without making it the buffer's file, e.g. for crash
+    // recovery. It's UTF-8 whatever the file is, so any character can be written.
+    pub(crate) fn write_to(&self, path: &Path) -> Result<(), std::io::Error> {
+        self.write_encoded(path, Encoding::Utf8)
+    }
+
+    // Characters the encoding has no bytes for fail before anything is written, so the file
+    // isn't left cut short.
+    fn write_encoded(&self, path: &Path, encoding: Encoding) -> Result<(), std::io::Error> {
+        let encoded = self
+            .lines
+            .iter()
+            .enumerate()
+            .map(|(line_idx, line)| {
+                encoding.encode(line).map_err(|ch| {
+                    std::io::Error::new(
+                        ErrorKind::InvalidData,
+                        format!(
+                            "'{ch}' on line {} can't be written as {}",
+                            line_idx.saturating_add(1),
+                            encoding.name()
+                        ),
+                    )
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut file = BufWriter::new(File::create(path)?);
+        file.write_all(encoding.prefix().as_bytes())?;
+        let line_ending = self.file_info.line_ending.as_str().as_bytes();
+        for line in encoded {
+            file.write_all(&line)?;
+            file.write_all(line_ending)?;
+        }
+        file.flush()
+    }
+
+    // strip whitespace at the end of every line, returning whether anything changed
+    pub(crate) fn trim_trailing_whitespace(&mut self) -> bool {
+        let mut ops = Vec::new();
+        for (line_idx, line) in self.lines.iter_mut().enumerate() {
+            if line.trim_end().len() < line.len() {
+                let trimmed = Line::with_widths(line.trim_end(), self.widths);
+                ops.push(EditOp {
+                    kind: EditKind::DeleteRange,
+                    at: Location {
+                        grapheme_idx: trimmed.grapheme_count(),
+                        line_idx,
+                    },
+                    text: line[trimmed.len()..].to_string(),
+                });
+                *line = trimmed;
+                line.set_edited(true);
             }
         }
+        let trimmed = !ops.is_empty();
+        for op in ops {
+            self.record(op);
+        }
+        trimmed
+    }
 
+    // replace the contents with those written by `write_to`, keeping the buffer's own file
+    // and its encoding
+    pub(crate) fn restore_from(&mut self, path: &Path) -> Result<(), std::io::Error> {
+        let string = Encoding::Utf8.read(path)?;
+        let encoding = self.file_info.encoding;
+        let before = self.line_strings(0, usize::MAX);
+        self.read_text(&string, Encoding::Utf8);
+        self.file_info.encoding = encoding;
+        let after = self.line_strings(0, usize::MAX);
+        self.record(EditOp::replace_lines(0, &before, &after));
         Ok(())
     }
     // endregion
 
     // region: edit
-    pub fn insert_char(&mut self, ch: char, at: &Location) {
-        if let Some(line) = self.lines.get_mut(at.line_idx) {
-            line.insert_char(ch, at.grapheme_idx);
+    /// Applies `edit` at `at`, returning where the caret goes then: behind what was inserted,
+    /// at the start of a new line, or where the text was deleted.
+    ///
+    /// ```
+    /// use hecto::{Buffer, Edit, Location};
+    ///
+    /// let mut buffer = Buffer::from_text("ab\ncd\n");
+    /// let at = Location { grapheme_idx: 0, line_idx: 1 };
+    /// assert_eq!(buffer.apply(Edit::DeleteBackward, &at)?, Location { grapheme_idx: 2, line_idx: 0 });
+    /// assert_eq!(buffer.line(0).unwrap().to_string(), "abcd");
+    /// assert!(buffer.apply(Edit::Delete, &Location { grapheme_idx: 0, line_idx: 5 }).is_err());
+    /// # Ok::<(), hecto::LocationError>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// If `at` is outside the text, which is left as it is then. The start of an empty buffer
+    /// is inside, it is where its first line goes.
+    pub fn apply(&mut self, edit: Edit, at: &Location) -> Result<Location, LocationError> {
+        self.check(at)?;
+        let location = match edit {
+            Edit::Insert(ch) => self.insert_char(ch, at).unwrap_or(*at),
+            Edit::InsertTab => self.insert_char('\t', at).unwrap_or(*at),
+            Edit::InsertNewline => self.insert_newline(at),
+            Edit::InsertLineBelow | Edit::InsertLineAbove => {
+                let mut line_idx = at.line_idx;
+                if matches!(edit, Edit::InsertLineBelow) && !self.is_empty() {
+                    line_idx = line_idx.saturating_add(1);
+                }
+                self.insert_line(line_idx, Line::default());
+                Location {
+                    grapheme_idx: 0,
+                    line_idx,
+                }
+            }
+            Edit::Delete => {
+                self.delete(at);
+                self.clamped(*at)
+            }
+            Edit::DeleteBackward => {
+                let before = if at.grapheme_idx > 0 {
+                    Location {
+                        grapheme_idx: at.grapheme_idx.saturating_sub(1),
+                        line_idx: at.line_idx,
+                    }
+                } else if let Some(line_idx) = at.line_idx.checked_sub(1) {
+                    Location {
+                        grapheme_idx: self.lines.get(line_idx).map_or(0, Line::grapheme_count),
+                        line_idx,
+                    }
+                } else {
+                    return Ok(*at);
+                };
+                self.delete(&before);
+                self.clamped(before)
+            }
+            Edit::TransposeGraphemes => self.transpose_graphemes(at).unwrap_or(*at),
+            Edit::TransposeLines => {
+                self.swap_lines(at.line_idx);
+                *at
+            }
+        };
+        Ok(location)
+    }
+
+    /// Inserts `text` of any number of lines at `at`, returning the location behind it.
+    ///
+    /// # Errors
+    ///
+    /// If `at` is outside the text, like for [`Buffer::apply`].
+    pub fn insert_text(&mut self, text: &str, at: &Location) -> Result<Location, LocationError> {
+        self.check(at)?;
+        Ok(self.insert_str(text, at))
+    }
+
+    // whether `at` is on a line and at most at its end, or the start of an empty buffer
+    fn check(&self, at: &Location) -> Result<(), LocationError> {
+        let inside = self.lines.get(at.line_idx).map_or_else(
+            || self.is_empty() && *at == Location::default(),
+            |line| at.grapheme_idx <= line.grapheme_count(),
+        );
+        if inside {
+            Ok(())
         } else {
-            self.lines.push(Line::from(&ch.to_string()));
+            Err(LocationError { location: *at })
         }
-        self.dirty = true;
     }
 
-    pub fn delete(&mut self, at: &Location) {
+    // the graphemes around a deleted one may have merged, leaving fewer on the line
+    fn clamped(&self, at: Location) -> Location {
+        Location {
+            grapheme_idx: self
+                .lines
+                .get(at.line_idx)
+                .map_or(0, |line| at.grapheme_idx.min(line.grapheme_count())),
+            line_idx: at.line_idx,
+        }
+    }
+
+    // Returns where the caret goes, behind the grapheme the character ended up in. Below the
+    // end of the buffer, empty lines are added up to `at`.
+    pub(crate) fn insert_char(&mut self, ch: char, at: &Location) -> Option<Location> {
+        self.pad_to(at.line_idx);
+        let line = &mut self.lines[at.line_idx];
+        let at = Location {
+            grapheme_idx: at.grapheme_idx.min(line.grapheme_count()),
+            line_idx: at.line_idx,
+        };
+        let grapheme_idx = match line.insert_char(ch, at.grapheme_idx) {
+            Ok(grapheme_idx) => grapheme_idx,
+            Err(err) => {
+                warn!("Not inserting {ch:?} at {at:?}: {err}");
+                return None;
+            }
+        };
+        self.record(EditOp {
+            kind: EditKind::InsertChar,
+            at,
+            text: ch.to_string(),
+        });
+        Some(Location {
+            line_idx: at.line_idx,
+            grapheme_idx,
+        })
+    }
+
+    // add empty lines until there is one at `line_idx`
+    fn pad_to(&mut self, line_idx: usize) {
+        let height = self.get_height();
+        let missing = line_idx.saturating_add(1).saturating_sub(height);
+        if missing == 0 {
+            return;
+        }
+        let padding = (0..missing)
+            .map(|_| {
+                let mut line = Line::with_widths("", self.widths);
+                line.set_edited(true);
+                line
+            })
+            .collect();
+        self.lines.extend(padding);
+        let empty = vec![String::new(); missing];
+        self.record(EditOp::replace_lines(height, &[], &empty));
+    }
+
+    pub(crate) fn delete(&mut self, at: &Location) {
         let height = self.get_height();
         if let Some(line) = self.lines.get(at.line_idx) {
-            if at.line_idx < height.saturating_sub(1) && at.grapheme_idx == line.grapheme_count() {
+            let line_len = line.grapheme_count();
+            if at.line_idx < height.saturating_sub(1) && at.grapheme_idx == line_len {
                 // join with the line below if at the end of line and there's line below
                 let next_line = self.lines.remove(at.line_idx.saturating_add(1));
                 self.lines[at.line_idx].append(&next_line);
-            } else if at.line_idx < height {
-                // not at the end of the buffer
-                self.lines[at.line_idx].delete(at.grapheme_idx);
+                self.record(EditOp {
+                    kind: EditKind::JoinLines,
+                    at: *at,
+                    text: String::from("\n"),
+                });
+            } else {
+                let deleted = line
+                    .text_of(at.grapheme_idx..at.grapheme_idx.saturating_add(1))
+                    .to_string();
+                match self.lines[at.line_idx].delete(at.grapheme_idx) {
+                    Ok(()) => self.record(EditOp {
+                        kind: EditKind::DeleteRange,
+                        at: *at,
+                        text: deleted,
+                    }),
+                    // at the end of the buffer there is nothing to delete
+                    Err(_) if at.grapheme_idx == line_len => {}
+                    Err(err) => warn!("Not deleting at {at:?}: {err}"),
+                }
             }
-            self.dirty = true;
         }
     }
 
-    pub fn insert_newline(&mut self, at: &Location) {
-        if let Some(line) = self.lines.get_mut(at.line_idx) {
-            let new_line = line.split(at.grapheme_idx);
-            self.lines.insert(at.line_idx.saturating_add(1), new_line);
+    // remove up to `count` lines from `line_idx` on, returning their text
+    pub(crate) fn remove_lines(&mut self, line_idx: usize, count: usize) -> Vec<String> {
+        let range = self.line_range(line_idx, count);
+        if range.is_empty() {
+            return Vec::new();
+        }
+        let removed: Vec<String> = self
+            .lines
+            .splice(range.clone(), Vec::new())
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        self.record(EditOp::replace_lines(range.start, &removed, &[]));
+        removed
+    }
+
+    pub(crate) fn insert_lines(&mut self, line_idx: usize, lines: &[String]) {
+        let at = line_idx.min(self.get_height());
+        let new_lines: Vec<Line> = lines
+            .iter()
+            .map(|line| {
+                let mut line = Line::with_widths(line, self.widths);
+                line.set_edited(true);
+                line
+            })
+            .collect();
+        self.lines.splice(at..at, new_lines);
+        if !lines.is_empty() {
+            self.record(EditOp::replace_lines(at, &[], lines));
+        }
+    }
+
+    // replace the lines in `range` by `lines`, returning whether that changed anything
+    pub(crate) fn replace_lines(&mut self, range: Range<usize>, lines: &[&str]) -> bool {
+        let range = self.line_range(range.start, range.len());
+        if self
+            .lines
+            .range(range.clone())
+            .map(|line| &**line)
+            .eq(lines.iter().copied())
+        {
+            return false;
+        }
+        let mut new_lines: Vec<Line> = lines
+            .iter()
+            .map(|line| Line::with_widths(line, self.widths))
+            .collect();
+        let before = self.line_strings(range.start, range.len());
+        let edited = self.edited_marks(range.clone());
+        Self::carry_edited_marks(&before, &edited, &mut new_lines);
+        let after: Vec<String> = lines.iter().map(ToString::to_string).collect();
+        self.lines.splice(range.clone(), new_lines);
+        self.record(EditOp::replace_lines(range.start, &before, &after));
+        true
+    }
+
+    fn edited_marks(&self, range: Range<usize>) -> Vec<bool> {
+        self.lines.range(range).map(Line::is_edited).collect()
+    }
+
+    // lines which replaced the `old` ones keep their marks where they are the same, the
+    // others count as edited
+    fn carry_edited_marks(old: &[String], old_edited: &[bool], new: &mut [Line]) {
+        let new_strings: Vec<String> = new.iter().map(ToString::to_string).collect();
+        let kept = diff::kept_lines(old, &new_strings);
+        for (line, kept) in new.iter_mut().zip(kept) {
+            let edited = kept.and_then(|old_idx| old_edited.get(old_idx).copied());
+            line.set_edited(edited.unwrap_or(true));
+        }
+    }
+
+    // replace the lines in `range` by what `transform` makes of them, returning whether that
+    // changed anything
+    pub(crate) fn transform_line_range(
+        &mut self,
+        range: Range<usize>,
+        transform: impl FnOnce(Vec<Line>) -> Vec<Line>,
+    ) -> bool {
+        let range = self.line_range(range.start, range.len());
+        let before = self.line_strings(range.start, range.len());
+        let edited = self.edited_marks(range.clone());
+        let mut lines = transform(self.lines.splice(range.clone(), Vec::new()));
+        for line in &mut lines {
+            line.set_widths(self.widths);
+        }
+        let changed = !lines
+            .iter()
+            .map(|line| &**line)
+            .eq(before.iter().map(String::as_str));
+        if !changed {
+            self.lines.splice(range.start..range.start, lines);
+            return false;
+        }
+        Self::carry_edited_marks(&before, &edited, &mut lines);
+        let after: Vec<String> = lines.iter().map(ToString::to_string).collect();
+        self.lines.splice(range.start..range.start, lines);
+        self.record(EditOp::replace_lines(range.start, &before, &after));
+        true
+    }
+
+    pub(crate) fn line_strings(&self, line_idx: usize, count: usize) -> Vec<String> {
+        self.lines
+            .range(self.line_range(line_idx, count))
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    fn line_range(&self, line_idx: usize, count: usize) -> Range<usize> {
+        let height = self.get_height();
+        let start = line_idx.min(height);
+        start..start.saturating_add(count).min(height)
+    }
+
+    // swap the grapheme before `at` with the one at it, or the last two at the end of a line,
+    // returning where the caret goes then
+    pub(crate) fn transpose_graphemes(&mut self, at: &Location) -> Option<Location> {
+        let line = self.lines.get_mut(at.line_idx)?;
+        let second = at.grapheme_idx.min(line.grapheme_count().saturating_sub(1));
+        let first = second.checked_sub(1)?;
+        let pair = first..second.saturating_add(1);
+        let removed = line.text_of(pair.clone()).to_string();
+        line.swap_graphemes(first).ok()?;
+        let text = line.text_of(pair).to_string();
+        let location = Location {
+            line_idx: at.line_idx,
+            grapheme_idx: second.saturating_add(1).min(line.grapheme_count()),
+        };
+        self.record(EditOp {
+            kind: EditKind::ReplaceRange { removed },
+            at: Location {
+                grapheme_idx: first,
+                line_idx: at.line_idx,
+            },
+            text,
+        });
+        Some(location)
+    }
+
+    // swap the line at `line_idx` with the one above, returning whether there were both
+    pub(crate) fn swap_lines(&mut self, line_idx: usize) -> bool {
+        let Some(above) = line_idx.checked_sub(1) else {
+            return false;
+        };
+        if line_idx >= self.lines.len() {
+            return false;
+        }
+        let before = self.line_strings(above, 2);
+        self.lines.swap_with_next(above);
+        self.lines[above].set_edited(true);
+        self.lines[line_idx].set_edited(true);
+        let after = self.line_strings(above, 2);
+        self.record(EditOp::replace_lines(above, &before, &after));
+        true
+    }
+
+    // Splits the line at `at`, returning the start of the new line below it. Below the end of
+    // the buffer, empty lines are added up to `at`.
+    pub(crate) fn insert_newline(&mut self, at: &Location) -> Location {
+        self.pad_to(at.line_idx);
+        let line = &mut self.lines[at.line_idx];
+        let at = Location {
+            grapheme_idx: at.grapheme_idx.min(line.grapheme_count()),
+            line_idx: at.line_idx,
+        };
+        let mut new_line = line.split(at.grapheme_idx);
+        new_line.set_widths(self.widths);
+        new_line.set_edited(true);
+        let line_idx = at.line_idx.saturating_add(1);
+        self.lines.insert(line_idx, new_line);
+        self.record(EditOp {
+            kind: EditKind::SplitLine,
+            at,
+            text: String::from("\n"),
+        });
+        Location {
+            line_idx,
+            grapheme_idx: 0,
+        }
+    }
+
+    // insert `line` before the line at `line_idx`, or behind the last one
+    pub(crate) fn insert_line(&mut self, line_idx: usize, mut line: Line) {
+        line.set_widths(self.widths);
+        line.set_edited(true);
+        let line_idx = line_idx.min(self.lines.len());
+        let inserted = [line.to_string()];
+        self.lines.insert(line_idx, line);
+        self.record(EditOp::replace_lines(line_idx, &[], &inserted));
+    }
+
+    // insert `text` of any number of lines at `at`, returning the location behind it
+    pub(crate) fn insert_str(&mut self, text: &str, at: &Location) -> Location {
+        if text.is_empty() {
+            return *at;
+        }
+        let line_idx = at.line_idx;
+        self.pad_to(line_idx);
+        let line = &mut self.lines[line_idx];
+        line.set_edited(true);
+        let at = Location {
+            grapheme_idx: at.grapheme_idx.min(line.grapheme_count()),
+            line_idx,
+        };
+        let tail = line.split(at.grapheme_idx);
+
+        let pieces: Vec<&str> = text
+            .split('\n')
+            .map(|piece| piece.strip_suffix('\r').unwrap_or(piece))
+            .collect();
+        let inserted = pieces.join("\n");
+        let mut pieces = pieces.into_iter();
+        if let Some(first) = pieces.next() {
+            self.lines[line_idx].append(&Line::from(first));
+        }
+        let mut last_idx = line_idx;
+        for piece in pieces {
+            last_idx = last_idx.saturating_add(1);
+            let mut line = Line::with_widths(piece, self.widths);
+            line.set_edited(true);
+            self.lines.insert(last_idx, line);
+        }
+        let last_line = &mut self.lines[last_idx];
+        let grapheme_idx = last_line.grapheme_count();
+        last_line.append(&tail);
+        self.record(EditOp {
+            kind: EditKind::ReplaceRange {
+                removed: String::new(),
+            },
+            at,
+            text: inserted,
+        });
+        Location {
+            grapheme_idx,
+            line_idx: last_idx,
+        }
+    }
+    // endregion
+
+    // The first of the next lines edited since saving behind `line_idx`, or of the previous
+    // ones before it, wrapping around at the ends of the buffer.
+    pub(crate) fn edited_line(&self, line_idx: usize, forward: bool) -> Option<usize> {
+        let is_edited = |idx| self.lines.get(idx).is_some_and(Line::is_edited);
+        Self::next_run(self.get_height(), is_edited, line_idx, forward)
+    }
+
+    // like `edited_line`, for the lines differing from the file as checked in
+    pub(crate) fn git_hunk(&self, line_idx: usize, forward: bool) -> Option<usize> {
+        let is_changed = |idx| self.git_change(idx).is_some();
+        Self::next_run(self.git_changes.len(), is_changed, line_idx, forward)
+    }
+
+    // The start of the next run of lines up to `height` which `is_in_run`, after `line_idx`
+    // or before it, wrapping around at the ends.
+    fn next_run(
+        height: usize,
+        is_in_run: impl Fn(usize) -> bool,
+        line_idx: usize,
+        forward: bool,
+    ) -> Option<usize> {
+        let starts: Vec<usize> = (0..height)
+            .filter(|idx| {
+                is_in_run(*idx) && idx.checked_sub(1).is_none_or(|above| !is_in_run(above))
+            })
+            .collect();
+        if forward {
+            starts
+                .iter()
+                .find(|start| **start > line_idx)
+                .or_else(|| starts.first())
+                .copied()
         } else {
-            // add a new line if at the bottom of the document
-            self.lines.push(Line::default());
+            starts
+                .iter()
+                .rev()
+                .find(|start| **start < line_idx)
+                .or_else(|| starts.last())
+                .copied()
+        }
+    }
+
+    // region: git
+    // Have git read the file as checked in, for the lines to be compared with it once it
+    // has. Outside a work tree there is nothing to compare with.
+    pub(crate) fn compare_with_head(&mut self) {
+        self.head = self.file_info.get_path().map(HeadRequest::start);
+    }
+
+    pub(crate) const fn is_comparing_with_head(&self) -> bool {
+        self.head.is_some()
+    }
+
+    // Compare the lines with the file as checked in once git has read it, or wait for git if
+    // `wait` is set. Returns whether the comparison changed.
+    pub(crate) fn receive_head(&mut self, wait: bool) -> bool {
+        let Some(head) = self.head.as_ref().and_then(|head| head.next(wait)) else {
+            return false;
+        };
+        self.head = None;
+        let before = std::mem::take(&mut self.git_changes);
+        let encoding = self.file_info.encoding;
+        if let Head::Bytes(bytes) = head
+            && let Ok(text) = encoding.decode_bytes(bytes)
+        {
+            let (text, _) = encoding.decode(&text);
+            let head: Vec<String> = text.lines().map(ToString::to_string).collect();
+            let changes = diff::line_changes(&head, &self.line_strings(0, usize::MAX));
+            if changes.iter().any(Option::is_some) {
+                self.git_changes = changes;
+            }
+        }
+        self.git_changes != before
+    }
+
+    pub(crate) fn git_change(&self, line_idx: usize) -> Option<LineChange> {
+        self.git_changes.get(line_idx).copied().flatten()
+    }
+
+    pub(crate) fn has_git_changes(&self) -> bool {
+        !self.git_changes.is_empty()
+    }
+
+    // Lines keep what git told about them while they move along with edits, those the edit
+    // adds have nothing to tell until git is asked again.
+    fn follow_with_git_changes(&mut self, op: &EditOp) {
+        if self.git_changes.is_empty() {
+            return;
         }
-        self.dirty = true;
+        let (line_idx, removed, inserted) = op.lines_replaced();
+        let start = line_idx.min(self.git_changes.len());
+        let end = start.saturating_add(removed).min(self.git_changes.len());
+        let kept = removed.min(inserted);
+        let replacement: Vec<Option<LineChange>> = self.git_changes[start..end]
+            .iter()
+            .copied()
+            .take(kept)
+            .chain(std::iter::repeat_n(None, inserted.saturating_sub(kept)))
+            .collect();
+        self.git_changes.splice(start..end, replacement);
     }
     // endregion
 
     // region: search
-    pub fn search_forward(&self, query: &str, from: &Location) -> Option<Location> {
+    /// Every match of `query`, from the start of the buffer on.
+    ///
+    /// ```
+    /// use hecto::{Buffer, Location};
+    ///
+    /// let buffer = Buffer::from_text("a老虎\n老虎\n");
+    /// assert_eq!(
+    ///     buffer.find_all_locations("虎"),
+    ///     [Location { grapheme_idx: 2, line_idx: 0 }, Location { grapheme_idx: 1, line_idx: 1 }]
+    /// );
+    /// ```
+    pub fn find_all_locations(&self, query: &str) -> Vec<Location> {
         if query.is_empty() {
+            return Vec::new();
+        }
+        self.lines
+            .iter()
+            .enumerate()
+            .flat_map(|(line_idx, line)| {
+                line.find_all_graphemes(query)
+                    .into_iter()
+                    .map(move |grapheme_idx| Location {
+                        grapheme_idx,
+                        line_idx,
+                    })
+            })
+            .collect()
+    }
+
+    /// The first match at or behind `from`, wrapping around at the end of the buffer or of
+    /// `bounds`, which matches must lie within.
+    pub fn search_forward(
+        &self,
+        query: &str,
+        from: &Location,
+        bounds: Option<&Range<Location>>,
+    ) -> Option<Location> {
+        let lines = self.bounded_lines(bounds);
+        if query.is_empty() || lines.is_empty() {
             return None;
         }
+        let len = query.graphemes(true).count();
+        let from = if lines.contains(&from.line_idx) {
+            *from
+        } else {
+            Location {
+                grapheme_idx: 0,
+                line_idx: lines.start,
+            }
+        };
 
         let mut is_first = true;
-        let len = self.lines.len();
-        for (line_idx, line) in self
-            .lines
-            .iter()
-            .enumerate()
+        for line_idx in lines
+            .clone()
             .cycle() // makes the iterator endless
-            .skip(from.line_idx) // taking one more, to search the current line twice
-            .take(len.saturating_add(1))
+            .skip(from.line_idx.saturating_sub(lines.start)) // taking one more, to search the current line twice
+            .take(lines.len().saturating_add(1))
         {
-            let from_grapheme_idx = if is_first {
+            let line = self.lines.get(line_idx)?;
+            let mut from_grapheme_idx = if is_first {
                 is_first = false;
                 from.grapheme_idx
             } else {
                 0
             };
-            if let Some(grapheme_idx) = line.search_forward(query, from_grapheme_idx) {
-                return Some(Location {
+            while let Some(grapheme_idx) = line.search_forward(query, from_grapheme_idx) {
+                let at = Location {
                     grapheme_idx,
                     line_idx,
-                });
+                };
+                match Self::compare_to_bounds(bounds, at, len) {
+                    Ordering::Equal => return Some(at),
+                    Ordering::Less => from_grapheme_idx = grapheme_idx.saturating_add(1),
+                    Ordering::Greater => break,
+                }
             }
         }
         None
     }
 
-    pub fn search_backward(&self, query: &str, from: &Location) -> Option<Location> {
-        if query.is_empty() {
+    /// Like [`Buffer::search_forward`], the last match before `from`.
+    pub fn search_backward(
+        &self,
+        query: &str,
+        from: &Location,
+        bounds: Option<&Range<Location>>,
+    ) -> Option<Location> {
+        let lines = self.bounded_lines(bounds);
+        if query.is_empty() || lines.is_empty() {
             return None;
         }
+        let len = query.graphemes(true).count();
+        let last_line = lines.end.saturating_sub(1);
+        let from = if lines.contains(&from.line_idx) {
+            *from
+        } else {
+            Location {
+                grapheme_idx: usize::MAX,
+                line_idx: last_line,
+            }
+        };
 
         let mut is_first = true;
-        let len = self.lines.len();
-        for (line_idx, line) in self
-            .lines
-            .iter()
-            .enumerate()
+        for line_idx in lines
+            .clone()
             .rev()
             .cycle()
-            .skip(len.saturating_sub(from.line_idx).saturating_sub(1))
-            .take(len.saturating_add(1))
+            .skip(last_line.saturating_sub(from.line_idx))
+            .take(lines.len().saturating_add(1))
         {
-            let from_grapheme_idx = if is_first {
+            let line = self.lines.get(line_idx)?;
+            let mut from_grapheme_idx = if is_first {
                 is_first = false;
                 from.grapheme_idx
             } else {
                 line.grapheme_count()
             };
-            if let Some(grapheme_idx) = line.search_backward(query, from_grapheme_idx) {
-                return Some(Location {
+            while let Some(grapheme_idx) = line.search_backward(query, from_grapheme_idx) {
+                let at = Location {
                     grapheme_idx,
                     line_idx,
-                });
+                };
+                match Self::compare_to_bounds(bounds, at, len) {
+                    Ordering::Equal => return Some(at),
+                    Ordering::Greater => from_grapheme_idx = grapheme_idx,
+                    Ordering::Less => break,
+                }
             }
         }
         None
     }
+
+    // the lines which may hold matches within `bounds`
+    fn bounded_lines(&self, bounds: Option<&Range<Location>>) -> Range<usize> {
+        let height = self.get_height();
+        bounds.map_or(0..height, |bounds| {
+            bounds.start.line_idx.min(height)..bounds.end.line_idx.saturating_add(1).min(height)
+        })
+    }
+
+    // whether a match of `len` graphemes at `at` is before `bounds`, within or behind them
+    fn compare_to_bounds(bounds: Option<&Range<Location>>, at: Location, len: usize) -> Ordering {
+        let Some(bounds) = bounds else {
+            return Ordering::Equal;
+        };
+        let key = |location: &Location| (location.line_idx, location.grapheme_idx);
+        if key(&at) < key(&bounds.start) {
+            Ordering::Less
+        } else if (at.line_idx, at.grapheme_idx.saturating_add(len)) > key(&bounds.end) {
+            Ordering::Greater
+        } else {
+            Ordering::Equal
+        }
+    }
     // endregion
 }
 
@@ -177,8 +1229,19 @@ impl Buffer {
 mod test {
     use super::*;
 
-    fn init() -> Buffer {
-        let mut buffer = Buffer::default();
+    // every test of editing and searching runs with each way of keeping the lines
+    const STORAGES: [Storage; 2] = [Storage::Flat, Storage::Chunked(2)];
+
+    fn buffer_with(storage: Storage, text: &str) -> Buffer {
+        let mut buffer = Buffer {
+            storage,
+            ..Buffer::default()
+        };
+        buffer.read_text(text, Encoding::Utf8);
+        buffer
+    }
+
+    fn init(storage: Storage) -> Buffer {
         let string = concat!(
             "0_234567890\n",
             "foo345foo90\n",
@@ -191,80 +1254,716 @@ mod test {
             "8_234567890\n",
             "9_234567890\n",
         );
-        buffer.lines = string.lines().map(Line::from).collect();
-        buffer
+        buffer_with(storage, string)
+    }
+
+    #[test]
+    fn inserts_several_lines() {
+        for storage in STORAGES {
+            let mut buffer = buffer_with(storage, "ab\ncd\n");
+            let at = Location {
+                grapheme_idx: 1,
+                line_idx: 0,
+            };
+            let end = buffer.insert_str("1\r\n2\n3", &at);
+            assert_eq!(buffer.line_strings(0, 5), ["a1", "2", "3b", "cd"]);
+            assert_eq!(
+                end,
+                Location {
+                    grapheme_idx: 1,
+                    line_idx: 2,
+                }
+            );
+            let end = buffer.insert_str("x\n", &end);
+            assert_eq!(buffer.line_strings(2, 3), ["3x", "b", "cd"]);
+            assert_eq!((end.line_idx, end.grapheme_idx), (3, 0));
+        }
+    }
+
+    #[test]
+    fn replacing_lines_by_the_same_changes_nothing() {
+        for storage in STORAGES {
+            let mut buffer = buffer_with(storage, "b\na\nc\n");
+            assert!(!buffer.replace_lines(0..2, &["b", "a"]));
+            assert!(!buffer.is_modified());
+            assert!(buffer.replace_lines(0..2, &["a", "b", "b"]));
+            assert_eq!(buffer.line_strings(0, 5), ["a", "b", "b", "c"]);
+            assert!(buffer.replace_lines(1..9, &[]));
+            assert_eq!(buffer.line_strings(0, 5), ["a"]);
+        }
+    }
+
+    #[test]
+    fn completes_from_the_current_words() {
+        for storage in STORAGES {
+            let mut buffer = buffer_with(storage, "fo foo food\nfoo_bar\n");
+            assert_eq!(buffer.words_starting_with("fo"), ["foo", "foo_bar", "food"]);
+            let at = Location {
+                grapheme_idx: 0,
+                line_idx: 1,
+            };
+            buffer.insert_str("fox ", &at);
+            assert_eq!(
+                buffer.words_starting_with("fo"),
+                ["foo", "foo_bar", "food", "fox"]
+            );
+            assert!(buffer.words_starting_with("food").is_empty());
+        }
+    }
+
+    #[test]
+    fn transforms_a_range_of_lines() {
+        for storage in STORAGES {
+            let mut buffer = buffer_with(storage, "x\nb\nB\na\na\ny\n");
+            assert!(buffer.transform_line_range(1..5, |mut lines| {
+                lines.sort_by(|a, b| (**a).cmp(b));
+                lines
+            }));
+            assert_eq!(buffer.line_strings(0, 9), ["x", "B", "a", "a", "b", "y"]);
+            assert!(buffer.transform_line_range(2..4, |mut lines| {
+                lines.dedup_by(|a, b| **a == **b);
+                lines
+            }));
+            assert_eq!(buffer.line_strings(0, 9), ["x", "B", "a", "b", "y"]);
+
+            buffer.mark_saved();
+            assert!(!buffer.transform_line_range(0..9, |lines| lines));
+            assert!(!buffer.is_modified());
+        }
+    }
+
+    #[test]
+    fn transposes_nothing_at_the_edges() {
+        for storage in STORAGES {
+            let mut buffer = buffer_with(storage, "abc\nx\n");
+            let at = |line_idx, grapheme_idx| Location {
+                grapheme_idx,
+                line_idx,
+            };
+            assert_eq!(buffer.transpose_graphemes(&at(0, 1)), Some(at(0, 2)));
+            assert_eq!(buffer.line_strings(0, 1), ["bac"]);
+            // at the end of the line the last two swap
+            assert_eq!(buffer.transpose_graphemes(&at(0, 3)), Some(at(0, 3)));
+            assert_eq!(buffer.line_strings(0, 1), ["bca"]);
+            assert_eq!(buffer.transpose_graphemes(&at(0, 0)), None);
+            assert_eq!(buffer.transpose_graphemes(&at(1, 1)), None);
+            assert_eq!(buffer.transpose_graphemes(&at(2, 0)), None);
+
+            assert!(!buffer.swap_lines(0));
+            assert!(!buffer.swap_lines(2));
+            assert!(buffer.swap_lines(1));
+            assert_eq!(buffer.line_strings(0, 2), ["x", "bca"]);
+        }
+    }
+
+    #[test]
+    fn counts_up_to_the_caret() {
+        for storage in STORAGES {
+            let buffer = buffer_with(storage, "Grüße, Welt!\r\nzwei Wörter\r\n");
+            let caret = Location {
+                grapheme_idx: 6,
+                line_idx: 1,
+            };
+            let (before, total) = buffer.statistics(&caret);
+            assert_eq!(
+                total,
+                Statistics {
+                    lines: 2,
+                    words: 4,
+                    graphemes: 25,
+                    bytes: 30,
+                }
+            );
+            assert_eq!(
+                before,
+                Statistics {
+                    lines: 2,
+                    words: 4,
+                    graphemes: 19,
+                    bytes: 22,
+                }
+            );
+        }
     }
 
     #[test]
     fn search_from_beginning() {
-        let buffer = init();
-        let from = Location {
-            line_idx: 0,
-            grapheme_idx: 0,
-        };
-        let found = Location {
-            line_idx: 1,
-            grapheme_idx: 0,
-        };
-        assert_eq!(buffer.search_forward("foo", &from), Some(found));
+        for storage in STORAGES {
+            let buffer = init(storage);
+            let from = Location {
+                line_idx: 0,
+                grapheme_idx: 0,
+            };
+            let found = Location {
+                line_idx: 1,
+                grapheme_idx: 0,
+            };
+            assert_eq!(buffer.search_forward("foo", &from, None), Some(found));
+        }
     }
 
     #[test]
     fn search_for_next() {
-        let buffer = init();
-        let step_right = 1;
-        let from = Location {
-            line_idx: 1,
-            #[allow(clippy::identity_op)]
-            grapheme_idx: 0 + step_right,
-        };
-        let found = Location {
-            line_idx: 1,
-            grapheme_idx: 6,
-        };
-        assert_eq!(buffer.search_forward("foo", &from), Some(found));
+        for storage in STORAGES {
+            let buffer = init(storage);
+            let step_right = 1;
+            let from = Location {
+                line_idx: 1,
+                #[allow(clippy::identity_op)]
+                grapheme_idx: 0 + step_right,
+            };
+            let found = Location {
+                line_idx: 1,
+                grapheme_idx: 6,
+            };
+            assert_eq!(buffer.search_forward("foo", &from, None), Some(found));
+        }
     }
 
     #[test]
     fn search_for_next_at_end() {
-        let buffer = init();
-        let step_right = 3;
-        let from = Location {
-            line_idx: 6,
-            grapheme_idx: 8 + step_right,
-        };
-        let found = Location {
-            line_idx: 7,
-            grapheme_idx: 8,
-        };
-        assert_eq!(buffer.search_forward("foo", &from), Some(found));
+        for storage in STORAGES {
+            let buffer = init(storage);
+            let step_right = 3;
+            let from = Location {
+                line_idx: 6,
+                grapheme_idx: 8 + step_right,
+            };
+            let found = Location {
+                line_idx: 7,
+                grapheme_idx: 8,
+            };
+            assert_eq!(buffer.search_forward("foo", &from, None), Some(found));
+        }
     }
 
     #[test]
     fn search_from_middle() {
-        let buffer = init();
-        let from = Location {
-            line_idx: 3,
-            grapheme_idx: 9,
-        };
-        let found = Location {
-            line_idx: 4,
-            grapheme_idx: 3,
-        };
-        assert_eq!(buffer.search_forward("foo", &from), Some(found));
+        for storage in STORAGES {
+            let buffer = init(storage);
+            let from = Location {
+                line_idx: 3,
+                grapheme_idx: 9,
+            };
+            let found = Location {
+                line_idx: 4,
+                grapheme_idx: 3,
+            };
+            assert_eq!(buffer.search_forward("foo", &from, None), Some(found));
+        }
     }
 
     #[test]
     fn search_previous() {
-        let buffer = init();
-        let from = Location {
-            line_idx: 9,
-            grapheme_idx: 10,
+        for storage in STORAGES {
+            let buffer = init(storage);
+            let from = Location {
+                line_idx: 9,
+                grapheme_idx: 10,
+            };
+            // the nearest match above is "7_234barfoo", the one on line 4 only comes after it
+            let found = Location {
+                line_idx: 7,
+                grapheme_idx: 8,
+            };
+            assert_eq!(buffer.search_backward("foo", &from, None), Some(found));
+            let found_next = Location {
+                line_idx: 6,
+                grapheme_idx: 8,
+            };
+            assert_eq!(
+                buffer.search_backward("foo", &found, None),
+                Some(found_next)
+            );
+            let on_line_4 = Location {
+                line_idx: 4,
+                grapheme_idx: 3,
+            };
+            assert_eq!(
+                buffer.search_backward("foo", &found_next, None),
+                Some(on_line_4)
+            );
+        }
+    }
+
+    #[test]
+    fn search_within_bounds() {
+        for storage in STORAGES {
+            let buffer = init(storage);
+            let at = |line_idx, grapheme_idx| Location {
+                grapheme_idx,
+                line_idx,
+            };
+            let bounds = at(1, 0)..at(2, 0);
+            // from outside the bounds the search starts at their edge
+            assert_eq!(
+                buffer.search_forward("foo", &at(0, 0), Some(&bounds)),
+                Some(at(1, 0))
+            );
+            assert_eq!(
+                buffer.search_backward("foo", &at(0, 0), Some(&bounds)),
+                Some(at(1, 6))
+            );
+            // and wraps around within them
+            assert_eq!(
+                buffer.search_forward("foo", &at(1, 7), Some(&bounds)),
+                Some(at(1, 0))
+            );
+            // a match must end within the bounds
+            let bounds = at(1, 0)..at(1, 5);
+            assert_eq!(
+                buffer.search_forward("foo", &at(1, 1), Some(&bounds)),
+                Some(at(1, 0))
+            );
+            let bounds = at(2, 0)..at(5, 0);
+            assert_eq!(
+                buffer.search_backward("foo", &at(6, 0), Some(&bounds)),
+                Some(at(4, 3))
+            );
+            assert_eq!(buffer.search_forward("bar", &at(0, 0), Some(&bounds)), None);
+        }
+    }
+
+    #[test]
+    fn edited_lines_are_marked_until_saved() {
+        for storage in STORAGES {
+            let mut buffer = buffer_with(storage, "a\nb\nc\nd\n");
+            let at = |line_idx, grapheme_idx| Location {
+                grapheme_idx,
+                line_idx,
+            };
+            let edited = |buffer: &Buffer| -> Vec<bool> {
+                buffer.lines.iter().map(Line::is_edited).collect()
+            };
+            buffer.insert_char('x', &at(2, 0));
+            buffer.insert_newline(&at(0, 1));
+            assert_eq!(edited(&buffer), [false, true, false, true, false]);
+            // joining marks the line joined to
+            buffer.delete(&at(2, 1));
+            assert_eq!(edited(&buffer), [false, true, true, false]);
+            assert_eq!(buffer.edited_line(0, true), Some(1));
+            assert_eq!(buffer.edited_line(1, true), Some(1));
+            assert_eq!(buffer.edited_line(3, false), Some(1));
+            // the marks move along with the lines
+            buffer.remove_lines(0, 1);
+            assert_eq!(edited(&buffer), [true, true, false]);
+
+            buffer.mark_saved();
+            assert_eq!(edited(&buffer), [false, false, false]);
+            assert_eq!(buffer.edited_line(0, true), None);
+            // replacing lines only marks those which are new
+            assert!(buffer.replace_lines(0..3, &["", "bxc", "d", "e"]));
+            assert_eq!(edited(&buffer), [false, false, false, true]);
+        }
+    }
+
+    #[test]
+    fn inserting_below_the_end_pads_with_empty_lines() {
+        for storage in STORAGES {
+            let at = |line_idx, grapheme_idx| Location {
+                grapheme_idx,
+                line_idx,
+            };
+            let lines = |buffer: &Buffer| -> Vec<String> {
+                buffer.lines.iter().map(ToString::to_string).collect()
+            };
+            let mut buffer = buffer_with(storage, "ab\n");
+            assert_eq!(buffer.insert_char('x', &at(3, 5)), Some(at(3, 1)));
+            assert_eq!(lines(&buffer), ["ab", "", "", "x"]);
+            assert!(buffer.lines.iter().skip(1).all(Line::is_edited));
+            // right behind the last line, and past the end of a line
+            assert_eq!(buffer.insert_char('y', &at(0, 9)), Some(at(0, 3)));
+            assert_eq!(buffer.insert_char('z', &at(4, 0)), Some(at(4, 1)));
+            assert_eq!(lines(&buffer), ["aby", "", "", "x", "z"]);
+
+            let mut buffer = buffer_with(storage, "ab\n");
+            assert_eq!(buffer.insert_newline(&at(2, 3)), at(3, 0));
+            assert_eq!(lines(&buffer), ["ab", "", "", ""]);
+            assert_eq!(buffer.insert_newline(&at(0, 9)), at(1, 0));
+            assert_eq!(lines(&buffer), ["ab", "", "", "", ""]);
+
+            let mut buffer = buffer_with(storage, "ab\n");
+            assert_eq!(buffer.insert_str("c\nd", &at(2, 4)), at(3, 1));
+            assert_eq!(lines(&buffer), ["ab", "", "c", "d"]);
+        }
+    }
+
+    #[test]
+    fn edits_apply_where_the_text_is() {
+        for storage in STORAGES {
+            let at = |line_idx, grapheme_idx| Location {
+                grapheme_idx,
+                line_idx,
+            };
+            let lines = |buffer: &Buffer| -> Vec<String> {
+                buffer.lines.iter().map(ToString::to_string).collect()
+            };
+            let mut buffer = buffer_with(storage, "ab\ncd\n");
+            let cases = [
+                (
+                    Edit::Insert('x'),
+                    at(0, 1),
+                    at(0, 2),
+                    ["axb", "cd"].as_slice(),
+                ),
+                (Edit::InsertTab, at(1, 2), at(1, 3), &["axb", "cd\t"]),
+                (Edit::Delete, at(1, 2), at(1, 2), &["axb", "cd"]),
+                (Edit::DeleteBackward, at(1, 0), at(0, 3), &["axbcd"]),
+                (Edit::DeleteBackward, at(0, 0), at(0, 0), &["axbcd"]),
+                (Edit::InsertNewline, at(0, 3), at(1, 0), &["axb", "cd"]),
+                (
+                    Edit::InsertLineAbove,
+                    at(1, 1),
+                    at(1, 0),
+                    &["axb", "", "cd"],
+                ),
+                (
+                    Edit::InsertLineBelow,
+                    at(2, 1),
+                    at(3, 0),
+                    &["axb", "", "cd", ""],
+                ),
+                (
+                    Edit::TransposeLines,
+                    at(2, 0),
+                    at(2, 0),
+                    &["axb", "cd", "", ""],
+                ),
+                (
+                    Edit::TransposeGraphemes,
+                    at(0, 1),
+                    at(0, 2),
+                    &["xab", "cd", "", ""],
+                ),
+            ];
+            for (edit, location, caret, expected) in cases {
+                assert_eq!(buffer.apply(edit, &location), Ok(caret), "{location:?}");
+                assert_eq!(lines(&buffer), expected, "{location:?}");
+            }
+
+            // nothing is padded below the end or behind a line
+            for location in [at(4, 0), at(9, 9), at(0, 4)] {
+                assert_eq!(
+                    buffer.apply(Edit::Insert('y'), &location),
+                    Err(LocationError { location })
+                );
+                assert_eq!(
+                    buffer.insert_text("y", &location),
+                    Err(LocationError { location })
+                );
+            }
+            assert_eq!(buffer.get_height(), 4);
+
+            // the first line of an empty buffer is where typing goes
+            let mut buffer = buffer_with(storage, "");
+            assert!(buffer.apply(Edit::Insert('y'), &at(0, 1)).is_err());
+            assert_eq!(buffer.apply(Edit::Insert('y'), &at(0, 0)), Ok(at(0, 1)));
+            assert_eq!(lines(&buffer), ["y"]);
+        }
+    }
+
+    #[test]
+    fn edits_say_what_they_did() {
+        for storage in STORAGES {
+            let at = |line_idx, grapheme_idx| Location {
+                grapheme_idx,
+                line_idx,
+            };
+            let op = |kind, at, text: &str| EditOp {
+                kind,
+                at,
+                text: text.to_string(),
+            };
+            let replace = |removed: &str| EditKind::ReplaceRange {
+                removed: removed.to_string(),
+            };
+            let mut buffer = buffer_with(storage, "ab\ncd\n");
+            buffer.insert_char('x', &at(0, 9));
+            buffer.insert_newline(&at(0, 1));
+            buffer.delete(&at(0, 1));
+            buffer.delete(&at(1, 0));
+            assert_eq!(
+                buffer.take_edits(),
+                [
+                    op(EditKind::InsertChar, at(0, 2), "x"),
+                    op(EditKind::SplitLine, at(0, 1), "\n"),
+                    op(EditKind::JoinLines, at(0, 1), "\n"),
+                    op(EditKind::DeleteRange, at(1, 0), "c"),
+                ]
+            );
+            assert!(buffer.take_edits().is_empty());
+
+            // "abx", "d"
+            buffer.transpose_graphemes(&at(0, 1));
+            buffer.swap_lines(1);
+            buffer.insert_str("1\r\n2", &at(1, 1));
+            buffer.insert_lines(4, &[String::from("e")]);
+            assert_eq!(
+                buffer.take_edits(),
+                [
+                    op(replace("ab"), at(0, 0), "ba"),
+                    op(replace("bax\nd\n"), at(0, 0), "d\nbax\n"),
+                    op(replace(""), at(1, 1), "1\n2"),
+                    op(replace(""), at(3, 0), "e\n"),
+                ]
+            );
+
+            // nothing changes, so nothing is said
+            buffer.delete(&at(3, 1));
+            assert!(!buffer.replace_lines(0..1, &["d"]));
+            assert!(buffer.take_edits().is_empty());
+
+            buffer.remove_lines(3, 9);
+            buffer.insert_char('y', &at(4, 0));
+            assert_eq!(
+                buffer.take_edits(),
+                [
+                    op(replace("e\n"), at(3, 0), ""),
+                    op(replace(""), at(3, 0), "\n\n"),
+                    op(EditKind::InsertChar, at(4, 0), "y"),
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn counts_the_text_as_loaded() {
+        for storage in STORAGES {
+            let mut buffer = buffer_with(storage, "ab\nlöwe\n\nc");
+            assert_eq!(
+                buffer.text_size(),
+                TextSize {
+                    bytes: 8,
+                    chars: 7,
+                    longest_line: Some((1, 5)),
+                    lines: 4,
+                }
+            );
+            // edits don't change it
+            buffer.insert_newline(&Location::default());
+            assert_eq!(buffer.text_size().longest_line, Some((1, 5)));
+        }
+        assert_eq!(Buffer::default().text_size().longest_line, None);
+    }
+
+    #[test]
+    fn finds_all_locations_in_order() {
+        for storage in STORAGES {
+            let buffer = init(storage);
+            let at = |line_idx, grapheme_idx| Location {
+                grapheme_idx,
+                line_idx,
+            };
+            assert_eq!(
+                buffer.find_all_locations("foo"),
+                [at(1, 0), at(1, 6), at(4, 3), at(6, 8), at(7, 8)]
+            );
+            assert!(buffer.find_all_locations("").is_empty());
+        }
+    }
+
+    #[test]
+    fn editing_the_top_of_a_big_buffer_is_cheap() {
+        let text = "line\n".repeat(1_000_000);
+        let mut buffer = buffer_with(Storage::default(), &text);
+        let start = std::time::Instant::now();
+        for _ in 0..1_000 {
+            buffer.insert_newline(&Location::default());
+            buffer.delete(&Location::default());
+        }
+        assert!(start.elapsed() < std::time::Duration::from_secs(2));
+        assert_eq!(buffer.get_height(), 1_000_000);
+    }
+
+    #[test]
+    fn reload_reads_file_again() {
+        let path = std::env::temp_dir().join("hecto-reload-test.txt");
+        std::fs::write(&path, "one\ntwo\n").unwrap();
+
+        let mut buffer = Buffer::load(path.to_str().unwrap()).unwrap();
+        buffer.insert_char('x', &Location::default());
+        std::fs::write(&path, "three\n").unwrap();
+
+        assert!(buffer.reload().is_ok());
+        assert!(!buffer.is_modified());
+        assert_eq!(buffer.get_height(), 1);
+        assert_eq!(buffer.lines[0].to_string(), "three");
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn reload_keeps_contents_if_file_is_gone() {
+        let path = std::env::temp_dir().join("hecto-reload-gone-test.txt");
+        let mut buffer = Buffer::load(path.to_str().unwrap()).unwrap();
+        buffer.insert_char('x', &Location::default());
+
+        assert!(buffer.reload().is_err());
+        assert!(buffer.is_modified());
+        assert_eq!(buffer.lines[0].to_string(), "x");
+    }
+
+    #[test]
+    fn missing_file_starts_clean() {
+        let path = std::env::temp_dir().join("hecto-missing-file-test.txt");
+        let mut buffer = Buffer::load(path.to_str().unwrap()).unwrap();
+        assert!(!buffer.is_modified());
+        assert!(buffer.is_new);
+
+        buffer.delete(&Location::default());
+        assert!(!buffer.is_modified());
+        buffer.insert_char('x', &Location::default());
+        assert!(buffer.is_modified());
+    }
+
+    #[test]
+    fn taking_edits_back_clears_the_modified_flag() {
+        for storage in STORAGES {
+            let mut buffer = init(storage);
+            buffer.mark_saved();
+            let at = Location {
+                line_idx: 1,
+                grapheme_idx: 3,
+            };
+            buffer.insert_char('x', &at);
+            assert!(buffer.is_modified());
+            buffer.delete(&at);
+            assert!(!buffer.is_modified());
+
+            buffer.set_line_ending(LineEnding::Dos);
+            assert!(buffer.is_modified());
+            buffer.set_line_ending(LineEnding::Unix);
+            assert!(!buffer.is_modified());
+        }
+    }
+
+    #[test]
+    fn save_into_missing_directory_fails() {
+        let path = std::env::temp_dir().join("hecto-missing-dir/file.txt");
+        let mut buffer = Buffer::load(path.to_str().unwrap()).unwrap();
+        buffer.insert_char('x', &Location::default());
+        let err = buffer.save().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+        assert!(buffer.is_modified());
+    }
+
+    #[test]
+    fn load_directory_fails() {
+        let dir = std::env::temp_dir();
+        assert!(Buffer::load(dir.to_str().unwrap()).is_err());
+    }
+
+    #[test]
+    fn recovery_copy_round_trips_multibyte_content() {
+        let path = std::env::temp_dir().join("hecto-recovery-copy.txt.hecto-recover");
+        let buffer = Buffer {
+            lines: Box::new(vec![
+                Line::from("Löwe 老虎"),
+                Line::from("e\u{301}👨\u{200D}👩"),
+            ]),
+            ..Buffer::default()
         };
-        let found = Location {
-            line_idx: 4,
-            grapheme_idx: 3,
+        buffer.write_to(&path).unwrap();
+        assert!(!buffer.is_file_loaded());
+
+        let mut restored = Buffer::load("hecto-recovery-original.txt").unwrap();
+        restored.restore_from(&path).unwrap();
+        let lines: Vec<String> = restored.lines.iter().map(ToString::to_string).collect();
+        assert_eq!(lines, vec!["Löwe 老虎", "e\u{301}👨\u{200D}👩"]);
+        assert!(restored.is_modified());
+        assert!(
+            restored
+                .file_info
+                .is_same_file("hecto-recovery-original.txt")
+        );
+    }
+
+    #[test]
+    fn save_keeps_line_endings_and_byte_order_mark() {
+        let path = std::env::temp_dir().join("hecto-line-endings-test.txt");
+        std::fs::write(&path, "\u{feff}one\r\ntwo\r\n").unwrap();
+
+        let mut buffer = Buffer::load(path.to_str().unwrap()).unwrap();
+        assert_eq!(buffer.lines[0].to_string(), "one");
+        assert_eq!(buffer.file_info.line_ending, LineEnding::Dos);
+        assert_eq!(buffer.file_info.encoding, Encoding::Utf8Bom);
+        buffer.save().unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "\u{feff}one\r\ntwo\r\n"
+        );
+
+        buffer.set_line_ending(LineEnding::Unix);
+        assert!(buffer.is_modified());
+        buffer.save().unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "\u{feff}one\ntwo\n"
+        );
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn byte_order_mark_survives_an_edit_and_can_be_dropped() {
+        let path = std::env::temp_dir().join("hecto-bom-round-trip-test.txt");
+        let original = b"\xef\xbb\xbfone\r\n\ttwo\r\nthree\r\nfour\r\n";
+        std::fs::write(&path, original).unwrap();
+
+        let mut buffer = Buffer::load(path.to_str().unwrap()).unwrap();
+        // nothing of the mark is left in the first line to find or count
+        assert_eq!(buffer.lines[0].grapheme_count(), 3);
+        assert_eq!(
+            buffer.search_forward("one", &Location::default(), None),
+            Some(Location::default())
+        );
+        let at = Location {
+            line_idx: 2,
+            grapheme_idx: 5,
         };
-        assert_eq!(buffer.search_backward("foo", &from), Some(found));
+        buffer.insert_text("!", &at).unwrap();
+        buffer.save().unwrap();
+        assert_eq!(
+            std::fs::read(&path).unwrap(),
+            b"\xef\xbb\xbfone\r\n\ttwo\r\nthree!\r\nfour\r\n"
+        );
+
+        buffer.set_encoding(Encoding::Utf8);
+        assert!(buffer.is_modified());
+        buffer.save().unwrap();
+        assert_eq!(
+            std::fs::read(&path).unwrap(),
+            b"one\r\n\ttwo\r\nthree!\r\nfour\r\n"
+        );
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn windows_1252_files_keep_their_bytes() {
+        let path = std::env::temp_dir().join("hecto-windows-1252-test.txt");
+        let legacy = include_bytes!("../../../../test-windows-1252.txt");
+        std::fs::write(&path, legacy).unwrap();
+        assert!(Buffer::load(path.to_str().unwrap()).is_err());
+
+        let mut buffer = Buffer::load_as(path.to_str().unwrap(), Encoding::Windows1252).unwrap();
+        assert_eq!(
+            buffer.lines[1].to_string(),
+            "Café crème, naïve façade, smørrebrød"
+        );
+        buffer.set_encoding(Encoding::Utf8);
+        buffer.set_encoding(Encoding::Windows1252);
+        buffer.save().unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), legacy);
+
+        let at = Location {
+            line_idx: 2,
+            grapheme_idx: 0,
+        };
+        buffer.insert_text("✓", &at).unwrap();
+        let err = buffer.save().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "'✓' on line 3 can't be written as windows-1252"
+        );
+        assert_eq!(std::fs::read(&path).unwrap(), legacy);
+        let _ = std::fs::remove_file(&path);
     }
 }
@@ -0,0 +1,47 @@
+use super::location::Location;
+use crate::editor::line::{Line, Revision};
+use std::ops::Range;
+
+// shorter words aren't worth highlighting
+const MIN_GRAPHEMES: usize = 2;
+
+// The word under the caret, which is only worked out again once the caret leaves the
+// graphemes it was found at or its line changes.
+#[derive(Default)]
+pub struct CaretWord {
+    line_idx: usize,
+    revision: Option<Revision>,
+    // the caret positions giving the same word
+    graphemes: Range<usize>,
+    word: Option<String>,
+}
+
+impl CaretWord {
+    pub fn update(&mut self, line: Option<&Line>, at: Location) {
+        let Some(line) = line else {
+            *self = Self::default();
+            return;
+        };
+        if self.revision == Some(line.revision())
+            && self.line_idx == at.line_idx
+            && (self.graphemes.start..=self.graphemes.end).contains(&at.grapheme_idx)
+        {
+            return;
+        }
+        let graphemes = line
+            .word_at(at.grapheme_idx)
+            .filter(|graphemes| graphemes.len() >= MIN_GRAPHEMES);
+        *self = Self {
+            line_idx: at.line_idx,
+            revision: Some(line.revision()),
+            word: graphemes
+                .clone()
+                .map(|graphemes| line.text_of(graphemes).to_string()),
+            graphemes: graphemes.unwrap_or(at.grapheme_idx..at.grapheme_idx),
+        };
+    }
+
+    pub fn word(&self) -> Option<&str> {
+        self.word.as_deref()
+    }
+}
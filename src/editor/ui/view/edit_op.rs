@@ -0,0 +1,112 @@
+use super::location::Location;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum EditKind {
+    // `text` went in at `at`, within its line
+    InsertChar,
+    // `text` was taken out at `at`, within its line
+    DeleteRange,
+    // the line was broken in two at `at`
+    SplitLine,
+    // the line below was joined onto the line at `at`, which is its end
+    JoinLines,
+    // `removed` at `at` was replaced by `text`, either of any number of lines. Whole lines
+    // start at the start of one and each end with a line break.
+    ReplaceRange { removed: String },
+}
+
+// What an edit did to the buffer, for whatever follows its changes. Every edit of the buffer
+// is told as one of these, and only by them.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EditOp {
+    pub kind: EditKind,
+    pub at: Location,
+    pub text: String,
+}
+
+impl EditOp {
+    // `removed` lines from `line_idx` on were replaced by `inserted` ones
+    pub fn replace_lines(line_idx: usize, removed: &[String], inserted: &[String]) -> Self {
+        let whole_lines = |lines: &[String]| {
+            lines.iter().fold(String::new(), |mut text, line| {
+                text.push_str(line);
+                text.push('\n');
+                text
+            })
+        };
+        Self {
+            kind: EditKind::ReplaceRange {
+                removed: whole_lines(removed),
+            },
+            at: Location {
+                grapheme_idx: 0,
+                line_idx,
+            },
+            text: whole_lines(inserted),
+        }
+    }
+
+    // The lines which changed, as the line the edit starts on and how many lines from there
+    // were replaced by how many others. Lines only differing within themselves count as
+    // replaced by one.
+    pub fn lines_replaced(&self) -> (usize, usize, usize) {
+        let (removed, inserted) = match &self.kind {
+            EditKind::InsertChar | EditKind::DeleteRange => (0, 0),
+            EditKind::SplitLine => (0, 1),
+            EditKind::JoinLines => (1, 0),
+            EditKind::ReplaceRange { removed } => {
+                let breaks = |text: &str| text.matches('\n').count();
+                let whole_lines = self.at.grapheme_idx == 0
+                    && [removed, &self.text]
+                        .iter()
+                        .all(|text| text.is_empty() || text.ends_with('\n'));
+                if whole_lines {
+                    return (self.at.line_idx, breaks(removed), breaks(&self.text));
+                }
+                (breaks(removed), breaks(&self.text))
+            }
+        };
+        (
+            self.at.line_idx,
+            removed.saturating_add(1),
+            inserted.saturating_add(1),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn op(kind: EditKind, grapheme_idx: usize, text: &str) -> EditOp {
+        EditOp {
+            kind,
+            at: Location {
+                grapheme_idx,
+                line_idx: 3,
+            },
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn tells_which_lines_were_replaced() {
+        assert_eq!(op(EditKind::InsertChar, 2, "x").lines_replaced(), (3, 1, 1));
+        assert_eq!(op(EditKind::SplitLine, 2, "\n").lines_replaced(), (3, 1, 2));
+        assert_eq!(op(EditKind::JoinLines, 2, "\n").lines_replaced(), (3, 2, 1));
+
+        let removed = String::from("a\nb");
+        let replace = EditKind::ReplaceRange { removed };
+        assert_eq!(op(replace.clone(), 2, "c").lines_replaced(), (3, 2, 1));
+        // only whole lines, the line at `at` doesn't change
+        let removed = String::from("a\nb\n");
+        let replace = EditKind::ReplaceRange { removed };
+        assert_eq!(op(replace.clone(), 0, "").lines_replaced(), (3, 2, 0));
+        assert_eq!(op(replace, 1, "").lines_replaced(), (3, 3, 1));
+        let lines = [String::from("x")];
+        assert_eq!(
+            EditOp::replace_lines(3, &[], &lines).lines_replaced(),
+            (3, 0, 1)
+        );
+    }
+}
@@ -0,0 +1,140 @@
+use std::{
+    borrow::Cow,
+    fs,
+    io::{self, ErrorKind},
+    path::Path,
+};
+
+const BOM: char = '\u{feff}';
+
+// what the bytes 0x80 to 0x9f stand for, the five unassigned ones as the control characters
+// of the same code, like browsers read them
+const WINDOWS_1252_HIGH: [char; 32] = [
+    '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8d}', 'Ž', '\u{8f}',
+    '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9d}', 'ž', 'Ÿ',
+];
+
+// How the text of a file is stored on disk. The buffer always holds UTF-8, only reading and
+// writing files go through here: a byte order mark is kept out of the text and written back
+// on save, and files which aren't UTF-8 can be read and written as Windows-1252, a superset
+// of Latin-1.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    Utf8Bom,
+    Windows1252,
+}
+
+impl Encoding {
+    // the contents of the file at `path`, a byte order mark included
+    pub fn read(self, path: &Path) -> Result<String, io::Error> {
+        self.decode_bytes(fs::read(path)?)
+    }
+
+    // UTF-8 fails on bytes which aren't, Windows-1252 takes any
+    pub fn decode_bytes(self, bytes: Vec<u8>) -> Result<String, io::Error> {
+        match self {
+            Self::Utf8 | Self::Utf8Bom => String::from_utf8(bytes).map_err(|_| {
+                io::Error::new(ErrorKind::InvalidData, "stream did not contain valid UTF-8")
+            }),
+            Self::Windows1252 => Ok(bytes.into_iter().map(windows_1252_char).collect()),
+        }
+    }
+
+    // the text without a byte order mark, and the encoding it turns out to have
+    pub fn decode(self, text: &str) -> (&str, Self) {
+        match self {
+            Self::Utf8 | Self::Utf8Bom => text
+                .strip_prefix(BOM)
+                .map_or((text, Self::Utf8), |text| (text, Self::Utf8Bom)),
+            Self::Windows1252 => (text, self),
+        }
+    }
+
+    // the bytes `text` is written as, or the first character which can't be
+    pub fn encode(self, text: &str) -> Result<Cow<'_, [u8]>, char> {
+        match self {
+            Self::Utf8 | Self::Utf8Bom => Ok(Cow::Borrowed(text.as_bytes())),
+            Self::Windows1252 => text.chars().map(windows_1252_byte).collect(),
+        }
+    }
+
+    pub const fn prefix(self) -> &'static str {
+        match self {
+            Self::Utf8 | Self::Windows1252 => "",
+            Self::Utf8Bom => "\u{feff}",
+        }
+    }
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Utf8 => "utf-8",
+            Self::Utf8Bom => "utf-8 BOM",
+            Self::Windows1252 => "windows-1252",
+        }
+    }
+}
+
+fn windows_1252_char(byte: u8) -> char {
+    match byte {
+        0x80..=0x9f => WINDOWS_1252_HIGH[usize::from(byte & 0x1f)],
+        _ => char::from(byte),
+    }
+}
+
+fn windows_1252_byte(ch: char) -> Result<u8, char> {
+    let byte = u8::try_from(ch)
+        .ok()
+        .filter(|byte| !(0x80..=0x9f).contains(byte));
+    byte.or_else(|| {
+        let idx = WINDOWS_1252_HIGH.iter().position(|high| *high == ch)?;
+        u8::try_from(idx).ok().map(|idx| idx | 0x80)
+    })
+    .ok_or(ch)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // the same text as it was saved by a legacy editor, and as UTF-8
+    const LEGACY: &[u8] = include_bytes!("../../../../test-windows-1252.txt");
+    const UTF_8: &str = include_str!("../../../../test-windows-1252.utf-8.txt");
+
+    #[test]
+    fn windows_1252_round_trips_through_utf_8() {
+        assert!(Encoding::Utf8.decode_bytes(LEGACY.to_vec()).is_err());
+        let text = Encoding::Windows1252.decode_bytes(LEGACY.to_vec()).unwrap();
+        assert_eq!(text, UTF_8);
+        assert_eq!(
+            Encoding::Windows1252.decode(&text),
+            (UTF_8, Encoding::Windows1252)
+        );
+        assert_eq!(Encoding::Windows1252.encode(UTF_8).unwrap(), LEGACY);
+
+        // every byte stands for a character of its own
+        let bytes: Vec<u8> = (0..=u8::MAX).collect();
+        let text = Encoding::Windows1252.decode_bytes(bytes.clone()).unwrap();
+        assert_eq!(text.chars().count(), 256);
+        assert_eq!(Encoding::Windows1252.encode(&text).unwrap(), bytes);
+    }
+
+    #[test]
+    fn characters_beyond_windows_1252_are_not_written() {
+        assert_eq!(Encoding::Windows1252.encode("a ✓ b"), Err('✓'));
+        // a C1 control which Windows-1252 has a quote for instead
+        assert_eq!(Encoding::Windows1252.encode("\u{93}"), Err('\u{93}'));
+        assert!(Encoding::Utf8.encode("a ✓ b").is_ok());
+    }
+
+    #[test]
+    fn only_utf_8_has_a_byte_order_mark() {
+        assert_eq!(Encoding::Utf8.decode("\u{feff}a"), ("a", Encoding::Utf8Bom));
+        assert_eq!(Encoding::Utf8Bom.decode("a"), ("a", Encoding::Utf8));
+        assert_eq!(
+            Encoding::Windows1252.decode("\u{feff}a"),
+            ("\u{feff}a", Encoding::Windows1252)
+        );
+    }
+}
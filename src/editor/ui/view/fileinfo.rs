@@ -1,20 +1,128 @@
+use super::encoding::Encoding;
 use std::{
+    env,
     fmt::Display,
-    path::{Path, PathBuf},
+    path::{self, Path, PathBuf},
 };
 
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum LineEnding {
+    #[default]
+    Unix,
+    Dos,
+}
+
+impl LineEnding {
+    // whatever the first line ends with
+    pub fn detect(text: &str) -> Self {
+        match text.find('\n') {
+            Some(idx) if text[..idx].ends_with('\r') => Self::Dos,
+            _ => Self::Unix,
+        }
+    }
+
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Unix => "\n",
+            Self::Dos => "\r\n",
+        }
+    }
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Unix => "unix",
+            Self::Dos => "dos",
+        }
+    }
+}
+
+// the file types known by name, and the extensions which tell them
+const FILE_TYPES: [(&str, &[&str]); 11] = [
+    ("rust", &["rs"]),
+    ("markdown", &["md", "markdown"]),
+    ("python", &["py"]),
+    ("javascript", &["js", "mjs"]),
+    ("typescript", &["ts"]),
+    ("c", &["c", "h"]),
+    ("c++", &["cc", "cpp", "cxx", "hpp"]),
+    ("shell", &["sh", "bash"]),
+    ("yaml", &["yml", "yaml"]),
+    ("html", &["htm", "html"]),
+    ("text", &["txt"]),
+];
+
+// the names of the file types known, to pick one of them
+pub fn known_file_types() -> impl Iterator<Item = &'static str> {
+    FILE_TYPES.iter().map(|(name, _)| *name)
+}
+
 #[derive(Default)]
 pub struct FileInfo {
     path: Option<PathBuf>,
+    pub line_ending: LineEnding,
+    pub encoding: Encoding,
+    // picked by the user, instead of going by the extension
+    pub file_type: Option<String>,
 }
 
 impl FileInfo {
     pub fn from(filename: &str) -> Self {
         Self {
-            path: Some(PathBuf::from(filename)),
+            path: Some(resolve(filename)),
+            ..Self::default()
+        }
+    }
+
+    // the same format under another name, for saving as
+    pub fn with_path(&self, filename: &str) -> Self {
+        Self {
+            path: Some(resolve(filename)),
+            line_ending: self.line_ending,
+            encoding: self.encoding,
+            file_type: self.file_type.clone(),
         }
     }
 
+    // a name for the kind of file, going by the extension unless one was picked
+    pub fn file_type(&self) -> String {
+        if let Some(file_type) = &self.file_type {
+            return file_type.clone();
+        }
+        let Some(extension) = self
+            .get_path()
+            .and_then(Path::extension)
+            .map(|extension| extension.to_string_lossy().to_lowercase())
+        else {
+            return String::from("text");
+        };
+        FILE_TYPES
+            .iter()
+            .find(|(_, extensions)| extensions.contains(&extension.as_str()))
+            .map_or(extension.clone(), |(name, _)| (*name).to_string())
+    }
+
+    // whether `file_type` is `name`, which doesn't take making a string of it each time
+    pub fn is_file_type(&self, name: &str) -> bool {
+        if let Some(file_type) = &self.file_type {
+            return file_type == name;
+        }
+        let Some(extension) = self.get_path().and_then(Path::extension) else {
+            return name == "text";
+        };
+        let extension = extension.to_string_lossy();
+        FILE_TYPES
+            .iter()
+            .find(|(_, extensions)| {
+                extensions
+                    .iter()
+                    .any(|known| known.eq_ignore_ascii_case(&extension))
+            })
+            .map_or_else(
+                || extension.eq_ignore_ascii_case(name),
+                |(known, _)| *known == name,
+            )
+    }
+
     pub const fn has_path(&self) -> bool {
         self.path.is_some()
     }
@@ -22,6 +130,45 @@ impl FileInfo {
     pub fn get_path(&self) -> Option<&Path> {
         self.path.as_deref()
     }
+
+    pub fn is_same_file(&self, filename: &str) -> bool {
+        self.get_path()
+            .is_some_and(|path| path == resolve(filename))
+    }
+
+    // the whole path with the home directory as "~", for the title
+    pub fn display_name(&self) -> String {
+        let home = env::var_os("HOME").map(PathBuf::from);
+        self.get_path().map_or_else(
+            || String::from("[No Name]"),
+            |path| abbreviate_home(path, home.as_deref()),
+        )
+    }
+}
+
+// Where `filename` is, independent of the working directory. Symlinks and ".." are resolved
+// as far as the file or at least its directory exists.
+fn resolve(filename: &str) -> PathBuf {
+    let path = Path::new(filename);
+    if let Ok(path) = path.canonicalize() {
+        return path;
+    }
+    let in_parent = path.file_name().and_then(|name| {
+        let parent = path.parent().filter(|dir| !dir.as_os_str().is_empty());
+        let dir = parent.unwrap_or(Path::new(".")).canonicalize().ok()?;
+        Some(dir.join(name))
+    });
+    in_parent
+        .or_else(|| path::absolute(path).ok())
+        .unwrap_or_else(|| path.to_path_buf())
+}
+
+fn abbreviate_home(path: &Path, home: Option<&Path>) -> String {
+    match home.and_then(|home| path.strip_prefix(home).ok()) {
+        Some(rest) if rest.as_os_str().is_empty() => String::from("~"),
+        Some(rest) => format!("~/{}", rest.display()),
+        None => path.display().to_string(),
+    }
 }
 
 impl Display for FileInfo {
@@ -35,3 +182,38 @@ impl Display for FileInfo {
         write!(f, "{name}")
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn paths_are_resolved_and_abbreviated() {
+        let dir = env::temp_dir().canonicalize().unwrap();
+        let relative = env::current_dir().unwrap().join("hecto-not-there.txt");
+        assert_eq!(
+            FileInfo::from("hecto-not-there.txt").get_path(),
+            Some(relative.as_path())
+        );
+        let path = dir.join("hecto-resolved.txt");
+        let roundabout = format!("{}/../{}/hecto-resolved.txt", dir.display(), {
+            dir.file_name().unwrap().to_string_lossy()
+        });
+        let file_info = FileInfo::from(&roundabout);
+        assert_eq!(file_info.get_path(), Some(path.as_path()));
+        assert!(file_info.is_same_file(&path.to_string_lossy()));
+        assert_eq!(file_info.to_string(), "hecto-resolved.txt");
+
+        let home = Path::new("/home/me");
+        assert_eq!(
+            abbreviate_home(Path::new("/home/me/notes/a.md"), Some(home)),
+            "~/notes/a.md"
+        );
+        assert_eq!(
+            abbreviate_home(Path::new("/home/meg/a.md"), Some(home)),
+            "/home/meg/a.md"
+        );
+        assert_eq!(abbreviate_home(Path::new("/etc/hosts"), None), "/etc/hosts");
+        assert_eq!(FileInfo::default().display_name(), "[No Name]");
+    }
+}
@@ -0,0 +1,88 @@
+use super::line_storage::LineStorage;
+use crate::editor::{
+    line::{Line, Revision},
+    syntax::{Highlight, State, Syntax},
+};
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+// lines whose highlights are remembered before the cache starts over
+const MAX_CACHED_LINES: usize = 4_096;
+
+// the highlights of lines by revision and the state they start in
+type Highlights = HashMap<(Revision, State), Rc<[Highlight]>>;
+
+// The highlights a view draws lines with, for the syntax of its file type.
+#[derive(Clone, Default)]
+pub struct Highlighter {
+    syntax: Option<Syntax>,
+    // The revision of each line from the top, as far as it was needed, and the state the
+    // line below starts in. Only these states are kept for the lines above the view.
+    states: RefCell<Vec<(Revision, State)>>,
+    highlights: RefCell<Highlights>,
+}
+
+impl Highlighter {
+    pub const fn syntax(&self) -> Option<Syntax> {
+        self.syntax
+    }
+
+    pub fn set_syntax(&mut self, syntax: Option<Syntax>) {
+        if self.syntax != syntax {
+            *self = Self {
+                syntax,
+                ..Self::default()
+            };
+        }
+    }
+
+    // Forget the states from the first line above `end` which was edited since on, as the
+    // lines below may start in another one now.
+    pub fn forget_edited(&mut self, lines: &dyn LineStorage, end: usize) {
+        let states = self.states.get_mut();
+        let end = end.min(states.len());
+        let edited = (0..end).find(|line_idx| {
+            lines
+                .get(*line_idx)
+                .is_none_or(|line| line.revision() != states[*line_idx].0)
+        });
+        if let Some(edited) = edited {
+            states.truncate(edited);
+        }
+    }
+
+    // The state the line at `line_idx` starts in, worked out from the top as far as needed.
+    // Lines edited since must have been forgotten.
+    pub fn state_at(&self, lines: &dyn LineStorage, line_idx: usize) -> State {
+        let Some(syntax) = self.syntax else {
+            return State::default();
+        };
+        let mut states = self.states.borrow_mut();
+        while states.len() < line_idx {
+            let Some(line) = lines.get(states.len()) else {
+                break;
+            };
+            let state = states
+                .last()
+                .map_or_else(State::default, |(_, state)| *state);
+            states.push((line.revision(), syntax.next_state(line, state)));
+        }
+        line_idx
+            .checked_sub(1)
+            .and_then(|above| states.get(above))
+            .map_or_else(State::default, |(_, state)| *state)
+    }
+
+    // None without a syntax
+    pub fn highlights(&self, line: &Line, state: State) -> Option<Rc<[Highlight]>> {
+        let syntax = self.syntax?;
+        let mut highlights = self.highlights.borrow_mut();
+        if highlights.len() >= MAX_CACHED_LINES {
+            highlights.clear();
+        }
+        Some(Rc::clone(
+            highlights
+                .entry((line.revision(), state))
+                .or_insert_with(|| Rc::from(syntax.highlight(line, state))),
+        ))
+    }
+}
@@ -0,0 +1,96 @@
+use super::location::Location;
+
+// older jumps are forgotten beyond this
+const MAX_JUMPS: usize = 100;
+
+// Where the caret was before far moves like searches and go-to-line, to walk back and
+// forward through like a browser history.
+#[derive(Clone, Default)]
+pub struct JumpList {
+    locations: Vec<Location>,
+    // `locations.len()` unless walking through the list
+    current: usize,
+}
+
+impl JumpList {
+    // record `from` as left by a jump, dropping what was ahead of the current entry
+    pub fn push(&mut self, from: Location) {
+        self.locations.truncate(self.current);
+        if self.locations.last() != Some(&from) {
+            self.locations.push(from);
+        }
+        if self.locations.len() > MAX_JUMPS {
+            self.locations.remove(0);
+        }
+        self.current = self.locations.len();
+    }
+
+    // `at` is where the caret is, kept to come forward to again
+    pub fn back(&mut self, at: Location) -> Option<Location> {
+        if self.current == 0 {
+            return None;
+        }
+        if self.current == self.locations.len() {
+            self.push(at);
+            self.current = self.locations.len().saturating_sub(1);
+        }
+        self.current = self.current.checked_sub(1)?;
+        self.locations.get(self.current).copied()
+    }
+
+    pub fn forward(&mut self) -> Option<Location> {
+        let next = self.current.saturating_add(1);
+        let location = self.locations.get(next).copied()?;
+        self.current = next;
+        Some(location)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn at(line_idx: usize) -> Location {
+        Location {
+            grapheme_idx: 0,
+            line_idx,
+        }
+    }
+
+    #[test]
+    fn walks_back_and_forward() {
+        let mut jumps = JumpList::default();
+        assert_eq!(jumps.back(at(0)), None);
+        jumps.push(at(1));
+        jumps.push(at(1));
+        jumps.push(at(5));
+        assert_eq!(jumps.back(at(9)), Some(at(5)));
+        assert_eq!(jumps.back(at(5)), Some(at(1)));
+        assert_eq!(jumps.back(at(1)), None);
+        assert_eq!(jumps.forward(), Some(at(5)));
+        assert_eq!(jumps.forward(), Some(at(9)));
+        assert_eq!(jumps.forward(), None);
+
+        // a jump from the middle forgets what was ahead
+        jumps.back(at(9));
+        jumps.back(at(5));
+        jumps.push(at(1));
+        assert_eq!(jumps.forward(), None);
+        assert_eq!(jumps.back(at(7)), Some(at(1)));
+        assert_eq!(jumps.back(at(1)), None);
+    }
+
+    #[test]
+    fn keeps_the_latest_jumps() {
+        let mut jumps = JumpList::default();
+        for line_idx in 0..150 {
+            jumps.push(at(line_idx));
+        }
+        let mut oldest = None;
+        while let Some(location) = jumps.back(at(150)) {
+            oldest = Some(location);
+        }
+        // the caret's location took the place of the oldest one
+        assert_eq!(oldest, Some(at(51)));
+    }
+}
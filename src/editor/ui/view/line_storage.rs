@@ -0,0 +1,420 @@
+use crate::editor::line::Line;
+use std::{
+    iter,
+    ops::{Index, IndexMut, Range},
+};
+
+// how many lines a chunk holds at most, unless told otherwise
+const CHUNK_LINES: usize = 1_024;
+
+// texts of this many bytes or more are kept in chunks
+const CHUNKED_FROM_BYTES: u64 = 1 << 20;
+
+// the lines of a range, from either end
+pub trait LineIter<'a>: DoubleEndedIterator<Item = &'a Line> + ExactSizeIterator {}
+
+impl<'a, I: DoubleEndedIterator<Item = &'a Line> + ExactSizeIterator> LineIter<'a> for I {}
+
+// Where a buffer keeps its lines. Everything else only goes through this, so any way of
+// keeping them behaves the same.
+pub trait LineStorage {
+    fn len(&self) -> usize;
+
+    fn get(&self, line_idx: usize) -> Option<&Line>;
+
+    fn get_mut(&mut self, line_idx: usize) -> Option<&mut Line>;
+
+    // the lines in `range`, which ends at `len()` at most
+    fn range(&self, range: Range<usize>) -> Box<dyn LineIter<'_> + '_>;
+
+    fn iter_mut(&mut self) -> Box<dyn Iterator<Item = &mut Line> + '_>;
+
+    // replace the lines in `range` by `lines`, returning the ones replaced
+    fn splice(&mut self, range: Range<usize>, lines: Vec<Line>) -> Vec<Line>;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn iter(&self) -> Box<dyn LineIter<'_> + '_> {
+        self.range(0..self.len())
+    }
+
+    fn last(&self) -> Option<&Line> {
+        self.get(self.len().checked_sub(1)?)
+    }
+
+    fn insert(&mut self, line_idx: usize, line: Line) {
+        self.splice(line_idx..line_idx, vec![line]);
+    }
+
+    fn push(&mut self, line: Line) {
+        self.insert(self.len(), line);
+    }
+
+    fn extend(&mut self, lines: Vec<Line>) {
+        let len = self.len();
+        self.splice(len..len, lines);
+    }
+
+    fn remove(&mut self, line_idx: usize) -> Line {
+        let removed = self.splice(line_idx..line_idx.saturating_add(1), Vec::new());
+        removed
+            .into_iter()
+            .next()
+            .unwrap_or_else(|| panic!("no line {line_idx} to remove"))
+    }
+
+    // swap the line at `line_idx` with the one below
+    fn swap_with_next(&mut self, line_idx: usize) {
+        let below = self.remove(line_idx.saturating_add(1));
+        self.insert(line_idx, below);
+    }
+}
+
+impl Index<usize> for dyn LineStorage {
+    type Output = Line;
+
+    fn index(&self, line_idx: usize) -> &Line {
+        let len = self.len();
+        self.get(line_idx)
+            .unwrap_or_else(|| panic!("line {line_idx} is out of {len} lines"))
+    }
+}
+
+impl IndexMut<usize> for dyn LineStorage {
+    fn index_mut(&mut self, line_idx: usize) -> &mut Line {
+        let len = self.len();
+        self.get_mut(line_idx)
+            .unwrap_or_else(|| panic!("line {line_idx} is out of {len} lines"))
+    }
+}
+
+impl Default for Box<dyn LineStorage> {
+    fn default() -> Self {
+        Box::new(Vec::new())
+    }
+}
+
+// how a buffer keeps its lines
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Storage {
+    // in chunks for big texts, else all in one place
+    #[default]
+    BySize,
+    Flat,
+    // in chunks of at most this many lines
+    Chunked(usize),
+}
+
+impl Storage {
+    // keep `lines`, which came from a text of `bytes`
+    pub fn store(self, lines: Vec<Line>, bytes: u64) -> Box<dyn LineStorage> {
+        match self {
+            Self::BySize if bytes >= CHUNKED_FROM_BYTES => {
+                Self::Chunked(CHUNK_LINES).store(lines, bytes)
+            }
+            Self::BySize => Self::Flat.store(lines, bytes),
+            Self::Flat => Box::new(lines),
+            Self::Chunked(chunk_lines) => Box::new(ChunkedLines::new(lines, chunk_lines)),
+        }
+    }
+}
+
+impl LineStorage for Vec<Line> {
+    fn len(&self) -> usize {
+        self.as_slice().len()
+    }
+
+    fn get(&self, line_idx: usize) -> Option<&Line> {
+        self.as_slice().get(line_idx)
+    }
+
+    fn get_mut(&mut self, line_idx: usize) -> Option<&mut Line> {
+        self.as_mut_slice().get_mut(line_idx)
+    }
+
+    fn range(&self, range: Range<usize>) -> Box<dyn LineIter<'_> + '_> {
+        Box::new(self[range].iter())
+    }
+
+    fn iter_mut(&mut self) -> Box<dyn Iterator<Item = &mut Line> + '_> {
+        Box::new(self.as_mut_slice().iter_mut())
+    }
+
+    fn splice(&mut self, range: Range<usize>, lines: Vec<Line>) -> Vec<Line> {
+        Self::splice(self, range, lines).collect()
+    }
+
+    fn swap_with_next(&mut self, line_idx: usize) {
+        self.swap(line_idx, line_idx.saturating_add(1));
+    }
+}
+
+// Lines kept in chunks of a bounded size, so adding or removing lines only moves those of
+// one chunk, however many there are.
+pub struct ChunkedLines {
+    // never empty ones
+    chunks: Vec<Vec<Line>>,
+    // the index of the first line of each chunk
+    starts: Vec<usize>,
+    len: usize,
+    chunk_lines: usize,
+}
+
+impl ChunkedLines {
+    pub fn new(lines: Vec<Line>, chunk_lines: usize) -> Self {
+        let mut chunked = Self {
+            chunks: Vec::new(),
+            starts: Vec::new(),
+            len: 0,
+            chunk_lines: chunk_lines.max(1),
+        };
+        chunked.extend(lines);
+        chunked
+    }
+
+    // the chunk holding the line at `line_idx`, and where in it the line is
+    fn locate(&self, line_idx: usize) -> Option<(usize, usize)> {
+        if line_idx >= self.len {
+            return None;
+        }
+        let chunk_idx = self
+            .starts
+            .partition_point(|start| *start <= line_idx)
+            .saturating_sub(1);
+        Some((chunk_idx, line_idx.saturating_sub(self.starts[chunk_idx])))
+    }
+
+    // split `lines` into chunks of about the same size, none with more than allowed
+    fn split_evenly(&self, lines: Vec<Line>) -> Vec<Vec<Line>> {
+        let count = lines.len().div_ceil(self.chunk_lines);
+        let mut left = lines.len();
+        let mut lines = lines.into_iter();
+        (1..=count)
+            .rev()
+            .map(|chunks_left| {
+                let size = left.div_ceil(chunks_left);
+                left = left.saturating_sub(size);
+                lines.by_ref().take(size).collect()
+            })
+            .collect()
+    }
+
+    fn update_starts(&mut self, from_chunk: usize) {
+        self.starts.truncate(from_chunk);
+        let mut start = from_chunk.checked_sub(1).map_or(0, |previous| {
+            self.starts[previous].saturating_add(self.chunks[previous].len())
+        });
+        for chunk in &self.chunks[from_chunk..] {
+            self.starts.push(start);
+            start = start.saturating_add(chunk.len());
+        }
+        self.len = start;
+    }
+}
+
+impl LineStorage for ChunkedLines {
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn get(&self, line_idx: usize) -> Option<&Line> {
+        let (chunk_idx, offset) = self.locate(line_idx)?;
+        self.chunks[chunk_idx].get(offset)
+    }
+
+    fn get_mut(&mut self, line_idx: usize) -> Option<&mut Line> {
+        let (chunk_idx, offset) = self.locate(line_idx)?;
+        self.chunks[chunk_idx].get_mut(offset)
+    }
+
+    fn range(&self, range: Range<usize>) -> Box<dyn LineIter<'_> + '_> {
+        assert!(
+            range.start <= range.end && range.end <= self.len,
+            "lines {range:?} are out of {} lines",
+            self.len
+        );
+        let Some((first, first_offset)) = self.locate(range.start).filter(|_| !range.is_empty())
+        else {
+            return Box::new(iter::empty());
+        };
+        let (last, last_offset) = self
+            .locate(range.end.saturating_sub(1))
+            .unwrap_or((first, first_offset));
+        let pieces: Vec<&[Line]> = (first..=last)
+            .map(|chunk_idx| {
+                let chunk = &self.chunks[chunk_idx];
+                let start = if chunk_idx == first { first_offset } else { 0 };
+                let end = if chunk_idx == last {
+                    last_offset.saturating_add(1)
+                } else {
+                    chunk.len()
+                };
+                &chunk[start..end]
+            })
+            .collect();
+        Box::new(Counted {
+            lines: pieces.into_iter().flatten(),
+            len: range.len(),
+        })
+    }
+
+    fn iter_mut(&mut self) -> Box<dyn Iterator<Item = &mut Line> + '_> {
+        Box::new(self.chunks.iter_mut().flatten())
+    }
+
+    fn splice(&mut self, range: Range<usize>, lines: Vec<Line>) -> Vec<Line> {
+        assert!(
+            range.start <= range.end && range.end <= self.len,
+            "lines {range:?} are out of {} lines",
+            self.len
+        );
+        if range.is_empty() && lines.is_empty() {
+            return Vec::new();
+        }
+        // the chunks the range touches, or the last one when it is at the very end
+        let (first, offset) = self
+            .locate(range.start)
+            .or_else(|| {
+                let last = self.chunks.len().checked_sub(1)?;
+                Some((last, self.chunks[last].len()))
+            })
+            .unwrap_or((0, 0));
+        let last = self
+            .locate(range.end.saturating_sub(1))
+            .filter(|_| !range.is_empty())
+            .map_or(first, |(last, _)| last);
+        let mut end = last.saturating_add(1).min(self.chunks.len());
+        // a chunk which became small takes in the next one, so chunks don't dwindle
+        let kept: usize = self.chunks[first..end].iter().map(Vec::len).sum();
+        let new_len = kept.saturating_sub(range.len()).saturating_add(lines.len());
+        if new_len.saturating_mul(2) < self.chunk_lines && end < self.chunks.len() {
+            end = end.saturating_add(1);
+        }
+        let mut merged: Vec<Line> = self.chunks.drain(first..end).flatten().collect();
+        let removed = merged
+            .splice(offset..offset.saturating_add(range.len()), lines)
+            .collect();
+        let pieces = self.split_evenly(merged);
+        self.chunks.splice(first..first, pieces);
+        self.update_starts(first);
+        removed
+    }
+}
+
+// the lines of several chunks, knowing how many there are
+struct Counted<I> {
+    lines: I,
+    len: usize,
+}
+
+impl<'a, I: DoubleEndedIterator<Item = &'a Line>> Iterator for Counted<I> {
+    type Item = &'a Line;
+
+    fn next(&mut self) -> Option<&'a Line> {
+        let line = self.lines.next()?;
+        self.len = self.len.saturating_sub(1);
+        Some(line)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, I: DoubleEndedIterator<Item = &'a Line>> DoubleEndedIterator for Counted<I> {
+    fn next_back(&mut self) -> Option<&'a Line> {
+        let line = self.lines.next_back()?;
+        self.len = self.len.saturating_sub(1);
+        Some(line)
+    }
+}
+
+impl<'a, I: DoubleEndedIterator<Item = &'a Line>> ExactSizeIterator for Counted<I> {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn strings(lines: &dyn LineStorage) -> Vec<String> {
+        lines.iter().map(ToString::to_string).collect()
+    }
+
+    fn lines(count: usize) -> Vec<Line> {
+        (0..count).map(|idx| Line::from(&idx.to_string())).collect()
+    }
+
+    fn to_strings(lines: &[Line]) -> Vec<String> {
+        lines.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn chunks_behave_like_a_vector() {
+        let mut flat: Box<dyn LineStorage> = Box::new(lines(10));
+        let mut chunked: Box<dyn LineStorage> = Box::new(ChunkedLines::new(lines(10), 3));
+        let edits: [(Range<usize>, usize); 9] = [
+            (0..0, 5),
+            (3..9, 0),
+            (0..8, 1),
+            (2..2, 20),
+            (15..22, 0),
+            (15..15, 2),
+            (1..4, 4),
+            (0..17, 0),
+            (0..0, 3),
+        ];
+        for (step, (range, count)) in edits.into_iter().enumerate() {
+            let new = || -> Vec<Line> {
+                (0..count)
+                    .map(|idx| Line::from(&format!("{step}.{idx}")))
+                    .collect()
+            };
+            let removed = flat.splice(range.clone(), new());
+            let removed_chunked = chunked.splice(range.clone(), new());
+            assert_eq!(to_strings(&removed), to_strings(&removed_chunked));
+            assert_eq!(
+                strings(&*flat),
+                strings(&*chunked),
+                "after replacing {range:?}"
+            );
+            assert_eq!(chunked.len(), flat.len());
+            for line_idx in 0..flat.len() {
+                let rest = line_idx..flat.len();
+                assert_eq!(chunked.range(rest.clone()).len(), rest.len());
+                let back = |lines: &dyn LineStorage| -> Vec<String> {
+                    let before = lines.range(0..line_idx).rev();
+                    before.map(ToString::to_string).collect()
+                };
+                assert_eq!(back(&*chunked), back(&*flat));
+            }
+        }
+        chunked.swap_with_next(1);
+        flat.swap_with_next(1);
+        assert_eq!(strings(&*flat), strings(&*chunked));
+        assert_eq!(
+            chunked.last().map(ToString::to_string),
+            flat.last().map(ToString::to_string)
+        );
+    }
+
+    #[test]
+    fn chunks_stay_within_bounds() {
+        let mut chunked = ChunkedLines::new(lines(100), 8);
+        for _ in 0..50 {
+            chunked.insert(3, Line::from("x"));
+        }
+        for _ in 0..140 {
+            chunked.remove(1);
+        }
+        assert_eq!(chunked.len(), 10);
+        assert!(
+            chunked
+                .chunks
+                .iter()
+                .all(|chunk| !chunk.is_empty() && chunk.len() <= 8)
+        );
+        // small chunks were merged as lines went away
+        assert!(chunked.chunks.len() <= 3);
+    }
+}
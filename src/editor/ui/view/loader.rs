@@ -0,0 +1,188 @@
+use super::{encoding::Encoding, fileinfo::LineEnding};
+use std::{
+    fs::File,
+    io::{self, ErrorKind, Read},
+    mem,
+    path::Path,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender, TryRecvError},
+    },
+    thread,
+};
+
+// how much is read at once, and so how many lines arrive together
+const CHUNK_BYTES: usize = 1 << 20;
+
+// what the thread reading a file sends, in this order
+pub enum Loaded {
+    // detected from the start of the file
+    Format(Encoding, LineEnding),
+    // the next lines, and how many bytes of the file have been read with them
+    Lines(Vec<String>, u64),
+    Done,
+    Failed(io::Error),
+}
+
+// Reads a file on a thread of its own and splits it into lines, so the buffer can show them
+// as they arrive. Dropping it stops the reading.
+pub struct Loader {
+    results: Receiver<Loaded>,
+    cancelled: Arc<AtomicBool>,
+    total_bytes: u64,
+    read_bytes: u64,
+}
+
+impl Loader {
+    // Opening the file happens right away, so a file which can't be read fails like any
+    // other.
+    pub fn start(path: &Path, encoding: Encoding) -> Result<Self, io::Error> {
+        let file = File::open(path)?;
+        let total_bytes = file.metadata()?.len();
+        let (sender, results) = mpsc::channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let stop = Arc::clone(&cancelled);
+        thread::spawn(move || {
+            if let Err(err) = read(file, encoding, &sender, &stop) {
+                let _ = sender.send(Loaded::Failed(err));
+            }
+        });
+        Ok(Self {
+            results,
+            cancelled,
+            total_bytes,
+            read_bytes: 0,
+        })
+    }
+
+    pub const fn total_bytes(&self) -> u64 {
+        self.total_bytes
+    }
+
+    // how much of the file has arrived, from 0 to 100
+    pub fn percent(&self) -> u64 {
+        if self.total_bytes == 0 {
+            return 100;
+        }
+        self.read_bytes
+            .saturating_mul(100)
+            .checked_div(self.total_bytes)
+            .unwrap_or(100)
+            .min(100)
+    }
+
+    // The next part of the file, waiting for it if `wait` is set. None if nothing new has
+    // arrived.
+    pub fn next(&mut self, wait: bool) -> Option<Loaded> {
+        let loaded = if wait {
+            self.results.recv().ok()
+        } else {
+            match self.results.try_recv() {
+                Ok(loaded) => Some(loaded),
+                Err(TryRecvError::Empty) => return None,
+                Err(TryRecvError::Disconnected) => None,
+            }
+        };
+        // the thread always says how it ended, unless it panicked
+        let loaded = loaded
+            .unwrap_or_else(|| Loaded::Failed(io::Error::other("reading stopped unexpectedly")));
+        if let Loaded::Lines(_, read_bytes) = &loaded {
+            self.read_bytes = *read_bytes;
+        }
+        Some(loaded)
+    }
+}
+
+impl Drop for Loader {
+    fn drop(&mut self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+// sends the lines of `file` a chunk at a time, split like `str::lines` splits them
+fn read(
+    mut file: File,
+    encoding: Encoding,
+    sender: &Sender<Loaded>,
+    cancelled: &AtomicBool,
+) -> io::Result<()> {
+    let mut chunk = vec![0; CHUNK_BYTES];
+    // read, but not sent yet since the line isn't complete
+    let mut pending = Vec::new();
+    let mut read_bytes: u64 = 0;
+    let mut is_first = true;
+    loop {
+        if cancelled.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+        let len = match file.read(&mut chunk) {
+            Ok(len) => len,
+            Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+        read_bytes = read_bytes.saturating_add(len as u64);
+        let is_end = len == 0;
+        pending.extend_from_slice(&chunk[..len]);
+        let complete = if is_end {
+            pending.len()
+        } else if let Some(newline) = pending.iter().rposition(|byte| *byte == b'\n') {
+            newline.saturating_add(1)
+        } else {
+            continue;
+        };
+        let rest = pending.split_off(complete);
+        let text = encoding.decode_bytes(mem::replace(&mut pending, rest))?;
+        let mut text = text.as_str();
+        if is_first && !text.is_empty() {
+            is_first = false;
+            let (decoded, encoding) = encoding.decode(text);
+            text = decoded;
+            let _ = sender.send(Loaded::Format(encoding, LineEnding::detect(text)));
+        }
+        let lines = text.lines().map(str::to_string).collect();
+        if sender.send(Loaded::Lines(lines, read_bytes)).is_err() {
+            return Ok(());
+        }
+        if is_end {
+            let _ = sender.send(Loaded::Done);
+            return Ok(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::{env, fs};
+
+    #[test]
+    fn splits_lines_across_chunks() {
+        let long_line = "ü".repeat(CHUNK_BYTES);
+        let text = format!(
+            "\u{feff}short\r\n{long_line}\r\n\r\n{}last",
+            "line\r\n".repeat(50_000)
+        );
+        let path = env::temp_dir().join("hecto-loader-chunks.txt");
+        fs::write(&path, &text).unwrap();
+
+        let mut loader = Loader::start(&path, Encoding::Utf8).unwrap();
+        let mut lines = Vec::new();
+        let mut format = None;
+        loop {
+            match loader.next(true) {
+                Some(Loaded::Format(encoding, line_ending)) => {
+                    format = Some((encoding, line_ending));
+                }
+                Some(Loaded::Lines(more, _)) => lines.extend(more),
+                Some(Loaded::Done) => break,
+                Some(Loaded::Failed(err)) => panic!("{err}"),
+                None => panic!("loading ended without a result"),
+            }
+        }
+        assert_eq!(format, Some((Encoding::Utf8Bom, LineEnding::Dos)));
+        assert_eq!(loader.percent(), 100);
+        let expected: Vec<&str> = text.trim_start_matches('\u{feff}').lines().collect();
+        assert_eq!(lines, expected);
+    }
+}
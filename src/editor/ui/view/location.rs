@@ -1,5 +1,28 @@
-#[derive(Default, Clone, Copy, Debug, PartialEq)]
+use std::fmt;
+
+/// A place in a [`Buffer`](crate::Buffer), before the grapheme at `grapheme_idx` of the
+/// line at `line_idx`, both counted from 0.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Location {
     pub grapheme_idx: usize,
     pub line_idx: usize,
 }
+
+/// A [`Location`] outside the text of a buffer: below its last line, or behind the end of a
+/// line.
+#[derive(Debug, PartialEq, Eq)]
+pub struct LocationError {
+    pub location: Location,
+}
+
+impl fmt::Display for LocationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "grapheme {} of line {} is outside the buffer",
+            self.location.grapheme_idx, self.location.line_idx
+        )
+    }
+}
+
+impl std::error::Error for LocationError {}
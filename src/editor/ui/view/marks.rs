@@ -0,0 +1,181 @@
+use super::location::Location;
+use std::collections::BTreeMap;
+
+// the mark kept at the location of the last edit
+pub const LAST_EDIT: char = '.';
+
+// Locations named by a letter or digit, which follow the lines they are on as lines are
+// inserted and deleted above them.
+#[derive(Clone, Default)]
+pub struct Marks {
+    marks: BTreeMap<char, Location>,
+}
+
+impl Marks {
+    pub fn is_valid_name(name: char) -> bool {
+        name.is_ascii_alphanumeric() || name == LAST_EDIT
+    }
+
+    pub fn set(&mut self, name: char, location: Location) {
+        self.marks.insert(name, location);
+    }
+
+    pub fn get(&self, name: char) -> Option<Location> {
+        self.marks.get(&name).copied()
+    }
+
+    // returns false if there was no such mark
+    pub fn remove(&mut self, name: char) -> bool {
+        self.marks.remove(&name).is_some()
+    }
+
+    // the marks set by name, leaving out the one of the last edit
+    fn bookmarks(&self) -> impl Iterator<Item = (char, Location)> + '_ {
+        self.iter().filter(|(name, _)| *name != LAST_EDIT)
+    }
+
+    pub fn has_bookmarks(&self) -> bool {
+        self.bookmarks().next().is_some()
+    }
+
+    // the first by name of the bookmarks on the line, shown in the gutter
+    pub fn on_line(&self, line_idx: usize) -> Option<char> {
+        self.bookmarks()
+            .find(|(_, location)| location.line_idx == line_idx)
+            .map(|(name, _)| name)
+    }
+
+    // The first bookmark on the next line after `line_idx` which has any, whatever its name,
+    // or the previous line's going backward. Past the last one it starts over at the first,
+    // which the second value tells.
+    pub fn next_bookmark(&self, line_idx: usize, forward: bool) -> Option<(Location, bool)> {
+        let mut locations: Vec<Location> = self.bookmarks().map(|(_, location)| location).collect();
+        locations.sort_unstable_by_key(|location| (location.line_idx, location.grapheme_idx));
+        if forward {
+            let next = locations
+                .iter()
+                .find(|location| location.line_idx > line_idx);
+            next.map(|location| (*location, false))
+                .or_else(|| locations.first().map(|location| (*location, true)))
+        } else {
+            let previous = locations
+                .iter()
+                .rev()
+                .find(|location| location.line_idx < line_idx)
+                .map(|location| location.line_idx);
+            // the first bookmark on that line, not the last one
+            let first_on = |line_idx| {
+                locations
+                    .iter()
+                    .find(|location| location.line_idx == line_idx)
+                    .copied()
+            };
+            previous
+                .and_then(first_on)
+                .map(|location| (location, false))
+                .or_else(|| {
+                    let last = locations.last()?.line_idx;
+                    first_on(last).map(|location| (location, true))
+                })
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (char, Location)> + '_ {
+        self.marks.iter().map(|(name, location)| (*name, *location))
+    }
+
+    // the lines at `a` and `b` traded places
+    pub fn swap_lines(&mut self, a: usize, b: usize) {
+        for location in self.marks.values_mut() {
+            if location.line_idx == a {
+                location.line_idx = b;
+            } else if location.line_idx == b {
+                location.line_idx = a;
+            }
+        }
+    }
+
+    // `removed` lines from `line_idx` on were replaced by `inserted` lines. Marks on lines
+    // which are gone move to the last of the inserted lines, or the line after them.
+    pub fn adjust(&mut self, line_idx: usize, removed: usize, inserted: usize) {
+        let removed_end = line_idx.saturating_add(removed);
+        let inserted_end = line_idx.saturating_add(inserted);
+        for location in self.marks.values_mut() {
+            if location.line_idx >= removed_end {
+                location.line_idx = location
+                    .line_idx
+                    .saturating_sub(removed)
+                    .saturating_add(inserted);
+            } else if location.line_idx >= inserted_end {
+                location.line_idx = if inserted > 0 {
+                    inserted_end.saturating_sub(1)
+                } else {
+                    line_idx
+                };
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn at(line_idx: usize) -> Location {
+        Location {
+            grapheme_idx: 0,
+            line_idx,
+        }
+    }
+
+    #[test]
+    fn marks_follow_their_lines() {
+        let mut marks = Marks::default();
+        marks.set('a', at(2));
+        marks.set('b', at(5));
+        marks.set('c', at(7));
+
+        // a line inserted after line 3
+        marks.adjust(4, 0, 1);
+        assert_eq!(marks.get('a'), Some(at(2)));
+        assert_eq!(marks.get('b'), Some(at(6)));
+
+        // lines 5 to 7 deleted
+        marks.adjust(5, 3, 0);
+        assert_eq!(marks.get('b'), Some(at(5)));
+        assert_eq!(marks.get('c'), Some(at(5)));
+
+        // line 2 joined into line 1
+        marks.adjust(1, 2, 1);
+        assert_eq!(marks.get('a'), Some(at(1)));
+        assert_eq!(marks.get('b'), Some(at(4)));
+        assert_eq!(marks.get('z'), None);
+
+        marks.swap_lines(3, 4);
+        assert_eq!(marks.get('b'), Some(at(3)));
+        assert_eq!(marks.get('a'), Some(at(1)));
+    }
+
+    #[test]
+    fn bookmarks_are_visited_by_line_whatever_their_names() {
+        let mut marks = Marks::default();
+        assert_eq!(marks.next_bookmark(0, true), None);
+        marks.set(LAST_EDIT, at(1));
+        assert!(!marks.has_bookmarks());
+        marks.set('z', at(2));
+        marks.set('a', at(6));
+        marks.set('b', at(2));
+        assert_eq!(marks.on_line(2), Some('b'));
+        assert_eq!(marks.on_line(1), None);
+
+        assert_eq!(marks.next_bookmark(0, true), Some((at(2), false)));
+        assert_eq!(marks.next_bookmark(2, true), Some((at(6), false)));
+        assert_eq!(marks.next_bookmark(6, true), Some((at(2), true)));
+        assert_eq!(marks.next_bookmark(6, false), Some((at(2), false)));
+        assert_eq!(marks.next_bookmark(2, false), Some((at(6), true)));
+
+        assert!(marks.remove('a'));
+        assert!(!marks.remove('a'));
+        assert_eq!(marks.next_bookmark(2, true), Some((at(2), true)));
+    }
+}
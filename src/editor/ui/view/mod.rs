@@ -1,59 +1,428 @@
 use super::super::{
     NAME, Position, Size, VERSION,
+    annotated_string::{AnnotatedString, AnnotationType},
     command::{Edit, Move},
+    config::Config,
+    diff::LineChange,
     documentstatus::DocumentStatus,
-    line::Line,
+    line::{Line, RenderOptions, Revision, TextFragment},
     position::{Col, Row},
-    terminal::Terminal,
+    search_worker::Request as SearchRequest,
+    spell::{self, Dictionary},
+    syntax::{Highlight, State, Syntax},
+    terminal::TerminalIo,
 };
 use super::UIComponent;
-use buffer::Buffer;
-use location::Location;
+pub use buffer::Buffer;
+use caret_word::CaretWord;
+pub use encoding::Encoding;
+pub use fileinfo::{LineEnding, known_file_types};
+use highlighter::Highlighter;
+use jump_list::JumpList;
+pub use location::{Location, LocationError};
+use marks::{LAST_EDIT, Marks};
+use rendered_row::RenderedRow;
 use search_direction::SearchDirection;
 use searchinfo::SearchInfo;
-use std::cmp::{max, min};
+use spelling::Spelling;
+use statistics::TextSize;
+use std::{
+    cell::{OnceCell, RefCell},
+    cmp::{max, min},
+    collections::HashMap,
+    fmt::Write,
+    ops::Range,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+use visual_row::VisualRow;
 
 mod buffer;
+mod caret_word;
+mod edit_op;
+mod encoding;
 mod fileinfo;
+mod highlighter;
+mod jump_list;
+mod line_storage;
+mod loader;
 mod location;
+mod marks;
+mod rendered_row;
 mod search_direction;
 mod searchinfo;
+mod spelling;
+mod statistics;
+mod visual_row;
+
+// drawn in the gutter next to lines edited since saving
+const EDITED_MARKER: char = '▎';
+
+// drawn in the gutter next to lines differing from the file as checked in with git
+const GIT_ADDED: char = '+';
+const GIT_MODIFIED: char = '~';
+const GIT_REMOVED: char = '-';
+
+// the right edge of the view, with the part showing which lines are in view filled
+const SCROLLBAR_TRACK: &str = "│";
+const SCROLLBAR_THUMB: &str = "█";
+
+// drawn at the edges of the view where a line goes on beyond them
+const MORE_BEFORE: &str = "<";
+const MORE_AFTER: &str = ">";
+
+// wrapped lines remembered before the cache starts over
+const MAX_CACHED_WRAPS: usize = 4_096;
+
+// where the rows of wrapped lines start, by line revision and width
+type Wraps = HashMap<(Revision, Col), Rc<[Col]>>;
+
+// the misspelled words of a line and its syntax highlights
+type LineStyles = (Option<Rc<[Range<usize>]>>, Option<Rc<[Highlight]>>);
 
 #[derive(Default)]
 pub struct View {
-    buffer: Buffer,
+    // shared with other views showing the same buffer
+    buffer: Rc<RefCell<Buffer>>,
     needs_redraw: bool,
     size: Size,
     text_location: Location,
     scroll_offset: Position,
+    // when lines wrap, the first of the top line's rows which is visible
+    scroll_row_in_line: usize,
+    wraps: RefCell<Wraps>,
+    // the row starts of every line while lines don't wrap
+    unwrapped: OnceCell<Rc<[Col]>>,
     search_info: Option<SearchInfo>,
+    spelling: Spelling,
+    highlighter: Highlighter,
+    jumps: JumpList,
+    marks: Marks,
+    caret_word: CaretWord,
+    // what is currently on screen, so unchanged rows can be skipped
+    rendered_rows: Vec<Option<RenderedRow>>,
+    rendered_col: Col,
+    rendered_query: Option<String>,
+    rendered_bounds: Option<Range<Location>>,
+    rendered_word: Option<String>,
+    rendered_gutter_width: Col,
+    rendered_thumb: Option<Range<Row>>,
+    rendered_spelling: bool,
+    // reused for every row drawn, so drawing doesn't allocate
+    row_text: AnnotatedString,
+    row_number: String,
+    config: Config,
 }
 
 impl View {
-    pub fn load(&mut self, filename: &str) {
-        self.buffer = Buffer::load(filename);
+    pub fn load(&mut self, filename: &str, encoding: Encoding) -> Result<(), std::io::Error> {
+        let mut buffer = Buffer::load_as(filename, encoding)?;
+        buffer.set_widths(self.config.cell_widths());
+        buffer.compare_with_head();
+        self.buffer = Rc::new(RefCell::new(buffer));
+        Ok(())
+    }
+
+    // name the file to show without reading it yet, see `is_unread`
+    pub fn defer_load(&mut self, filename: &str) {
+        self.buffer = Rc::new(RefCell::new(Buffer::unread(filename)));
+    }
+
+    pub fn show_text(&mut self, text: &str) {
+        let mut buffer = Buffer::from_text(text);
+        buffer.set_widths(self.config.cell_widths());
+        self.buffer = Rc::new(RefCell::new(buffer));
+    }
+
+    // like `load`, the lines of the file show up as they are read in the background
+    pub fn start_loading(
+        &mut self,
+        filename: &str,
+        encoding: Encoding,
+    ) -> Result<(), std::io::Error> {
+        let mut buffer = Buffer::start_loading(filename, encoding)?;
+        buffer.set_widths(self.config.cell_widths());
+        self.buffer = Rc::new(RefCell::new(buffer));
+        Ok(())
+    }
+
+    pub fn text_size(&self) -> TextSize {
+        self.buffer.borrow().text_size()
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.buffer.borrow().is_read_only
+    }
+
+    pub fn set_read_only(&mut self) {
+        self.buffer.borrow_mut().is_read_only = true;
+    }
+
+    // whether the file is still to be read before the buffer is shown
+    pub fn is_unread(&self) -> bool {
+        self.buffer.borrow().is_unread
+    }
+
+    pub fn is_loading(&self) -> bool {
+        self.buffer.borrow().is_loading()
+    }
+
+    pub fn load_percent(&self) -> Option<u64> {
+        self.buffer.borrow().load_percent()
+    }
+
+    // show the lines read meanwhile, or wait for all of them if `wait` is set
+    pub fn receive_loaded(&mut self, wait: bool) -> Result<(), std::io::Error> {
+        let received = self.buffer.borrow_mut().receive_loaded(wait);
+        if !matches!(received, Ok(false)) {
+            self.set_needs_redraw(true);
+        }
+        if matches!(received, Ok(true)) && !self.is_loading() {
+            self.buffer.borrow_mut().compare_with_head();
+        }
+        received.map(|_| ())
+    }
+
+    pub fn cancel_loading(&mut self) {
+        self.buffer.borrow_mut().cancel_loading();
+    }
+
+    pub fn set_config(&mut self, config: &Config) {
+        self.buffer.borrow_mut().set_widths(config.cell_widths());
+        self.config = config.clone();
+        // the gutter or colours may have changed
+        self.rendered_rows.clear();
+        self.snap_to_valid_grapheme();
+        self.scroll_text_location_into_view();
+        self.set_needs_redraw(true);
+    }
+
+    // a new view onto the same buffer, starting at the same location
+    pub fn share_buffer(&self) -> Self {
+        Self {
+            buffer: Rc::clone(&self.buffer),
+            needs_redraw: true,
+            size: self.size,
+            text_location: self.text_location,
+            scroll_offset: self.scroll_offset,
+            scroll_row_in_line: self.scroll_row_in_line,
+            wraps: RefCell::default(),
+            unwrapped: OnceCell::new(),
+            search_info: None,
+            spelling: self.spelling.clone(),
+            highlighter: self.highlighter.clone(),
+            jumps: self.jumps.clone(),
+            marks: self.marks.clone(),
+            caret_word: CaretWord::default(),
+            rendered_rows: Vec::new(),
+            rendered_col: 0,
+            rendered_query: None,
+            rendered_bounds: None,
+            rendered_word: None,
+            rendered_gutter_width: 0,
+            rendered_thumb: None,
+            rendered_spelling: false,
+            row_text: AnnotatedString::default(),
+            row_number: String::new(),
+            config: self.config.clone(),
+        }
+    }
+
+    pub fn shares_buffer_with(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.buffer, &other.buffer)
     }
 
     pub fn is_file_loaded(&self) -> bool {
-        self.buffer.is_file_loaded()
+        self.buffer.borrow().is_file_loaded()
+    }
+
+    pub fn is_same_file(&self, filename: &str) -> bool {
+        self.buffer.borrow().file_info.is_same_file(filename)
+    }
+
+    // reload the buffer from disk, keeping the scroll offset if the caret is still visible
+    pub fn reload(&mut self) -> Result<(), std::io::Error> {
+        self.buffer.borrow_mut().reload()?;
+        self.buffer.borrow_mut().compare_with_head();
+        self.snap_to_valid_line();
+        self.snap_to_valid_grapheme();
+        self.scroll_text_location_into_view();
+        self.set_needs_redraw(true);
+        Ok(())
+    }
+
+    pub fn file_path(&self) -> Option<PathBuf> {
+        self.buffer
+            .borrow()
+            .file_info
+            .get_path()
+            .map(Path::to_path_buf)
+    }
+
+    pub fn scroll_offset(&self) -> Position {
+        self.scroll_offset
+    }
+
+    // put the caret and the scroll offset back where a session left them, clamped to what
+    // the file holds now
+    pub fn restore_position(&mut self, location: Location, scroll_offset: Position) {
+        self.restore_jump(location);
+        let last_line = self.buffer.borrow().get_height().saturating_sub(1);
+        self.scroll_offset = Position {
+            row: scroll_offset.row.min(last_line),
+            col: scroll_offset.col,
+        };
+        self.scroll_row_in_line = 0;
+        self.scroll_text_location_into_view();
+        self.set_needs_redraw(true);
+    }
+
+    pub fn saved_lines(&self) -> Option<Result<Vec<String>, std::io::Error>> {
+        self.buffer.borrow().saved_lines()
+    }
+
+    // write the buffer to `path` without making it the buffer's file
+    pub fn write_copy(&self, path: &Path) -> Result<(), std::io::Error> {
+        self.buffer.borrow().write_to(path)
+    }
+
+    // replace the buffer's contents with those recovered from `path`
+    pub fn restore_from(&mut self, path: &Path) -> Result<(), std::io::Error> {
+        self.buffer.borrow_mut().restore_from(path)?;
+        self.follow_edits();
+        self.snap_to_valid_line();
+        self.snap_to_valid_grapheme();
+        self.scroll_text_location_into_view();
+        self.set_needs_redraw(true);
+        Ok(())
     }
 
     // region: save
     pub fn save(&mut self) -> Result<(), std::io::Error> {
-        self.buffer.save()
+        self.before_save();
+        self.buffer.borrow_mut().save()?;
+        self.buffer.borrow_mut().compare_with_head();
+        // the gutter no longer marks edited lines
+        self.set_needs_redraw(true);
+        Ok(())
     }
 
     pub fn save_as(&mut self, filename: &str) -> Result<(), std::io::Error> {
-        self.buffer.save_as(filename)
+        self.before_save();
+        self.buffer.borrow_mut().save_as(filename)?;
+        self.buffer.borrow_mut().compare_with_head();
+        self.set_needs_redraw(true);
+        Ok(())
+    }
+
+    fn before_save(&mut self) {
+        if self.config.trim_trailing_whitespace_on_save
+            && self.buffer.borrow_mut().trim_trailing_whitespace()
+        {
+            self.follow_edits();
+            self.snap_to_valid_grapheme();
+            self.scroll_text_location_into_view();
+            self.set_needs_redraw(true);
+        }
+    }
+    // endregion
+
+    // region: git
+    // compare the lines with the file as checked in again, which git reads meanwhile
+    pub fn compare_with_head(&mut self) {
+        self.buffer.borrow_mut().compare_with_head();
+    }
+
+    pub fn is_comparing_with_head(&self) -> bool {
+        self.buffer.borrow().is_comparing_with_head()
+    }
+
+    // Mark the lines differing from the file as checked in once git has read it, or wait for
+    // git if `wait` is set. Returns whether the gutter changed.
+    pub fn receive_head(&mut self, wait: bool) -> bool {
+        let received = self.buffer.borrow_mut().receive_head(wait);
+        if received {
+            self.scroll_text_location_into_view();
+            self.set_needs_redraw(true);
+        }
+        received
+    }
+
+    // returns false if no lines differ from the file as checked in
+    pub fn jump_to_git_hunk(&mut self, forward: bool) -> bool {
+        let line_idx = self
+            .buffer
+            .borrow()
+            .git_hunk(self.text_location.line_idx, forward);
+        let Some(line_idx) = line_idx else {
+            return false;
+        };
+        self.jump_to(Location {
+            line_idx,
+            grapheme_idx: 0,
+        });
+        true
+    }
+    // endregion
+
+    // region: spelling
+    pub fn set_dictionary(&mut self, dictionary: &Rc<RefCell<Dictionary>>) {
+        if self
+            .spelling
+            .dictionary()
+            .is_none_or(|current| !Rc::ptr_eq(current, dictionary))
+        {
+            self.spelling.set_dictionary(dictionary);
+            self.rendered_rows.clear();
+            self.set_needs_redraw(true);
+        }
+    }
+
+    // Prose is checked unless turned off for the buffer, code only once turned on for it.
+    pub fn checks_spelling(&self) -> bool {
+        let buffer = self.buffer.borrow();
+        buffer.spell_check.unwrap_or_else(|| {
+            self.config.spell_check
+                && (buffer.file_info.is_file_type("markdown")
+                    || buffer.file_info.is_file_type("text"))
+        })
+    }
+
+    // the syntax highlighted, by the file type
+    fn syntax(&self) -> Option<Syntax> {
+        let buffer = self.buffer.borrow();
+        Syntax::ALL
+            .into_iter()
+            .find(|syntax| buffer.file_info.is_file_type(syntax.file_type()))
+    }
+
+    // returns whether words are checked now
+    pub fn toggle_spell_check(&mut self) -> bool {
+        let checks = !self.checks_spelling();
+        self.buffer.borrow_mut().spell_check = Some(checks);
+        self.forget_spelling();
+        checks
+    }
+
+    // check the words again, e.g. after the dictionary learned some
+    pub fn forget_spelling(&mut self) {
+        self.spelling.forget();
+        self.rendered_rows.clear();
+        self.set_needs_redraw(true);
+    }
+
+    // the word the caret is on or right behind
+    pub fn word_at_caret(&self) -> Option<String> {
+        let buffer = self.buffer.borrow();
+        let line = buffer.lines.get(self.text_location.line_idx)?;
+        let byte_idx = line.text_of(0..self.text_location.grapheme_idx).len();
+        spell::word_at(line, byte_idx).map(ToString::to_string)
     }
     // endregion
 
     // region: search
     pub fn enter_search(&mut self) {
-        self.search_info = Some(SearchInfo {
-            previous_location: self.text_location,
-            query: None,
-        });
+        self.search_info = Some(SearchInfo::new(self.text_location));
     }
 
     pub fn dismiss_search(&mut self) {
@@ -65,21 +434,94 @@ impl View {
         }
     }
 
-    pub fn search(&mut self, query: &str) {
+    // keep the match, going back to where the search started is a jump back
+    pub fn accept_search(&mut self) {
+        if let Some(search_info) = &self.search_info
+            && search_info.previous_location != self.text_location
+        {
+            self.jumps.push(search_info.previous_location);
+        }
+    }
+
+    // The search functions return false if there is a query but no match for it. Matches
+    // are restricted to `lines` if given.
+    pub fn search(&mut self, query: &str, lines: Option<Range<usize>>) -> bool {
+        self.set_search(query, lines);
+        self.search_in_direction(self.text_location, SearchDirection::default())
+    }
+
+    // like `search`, leaving finding the match to `search_request`
+    pub fn set_search(&mut self, query: &str, lines: Option<Range<usize>>) {
+        self.set_needs_redraw(true);
         if let Some(search_info) = &mut self.search_info {
-            search_info.query = Some(Line::from(query));
+            search_info.set_query(query);
+            search_info.bounds = lines.map(|lines| {
+                Location {
+                    grapheme_idx: 0,
+                    line_idx: lines.start,
+                }..Location {
+                    grapheme_idx: 0,
+                    line_idx: lines.end,
+                }
+            });
+        }
+    }
+
+    // what a search in the background looks for to find the next match from the caret on,
+    // None without a query
+    pub fn search_request(&self) -> Option<SearchRequest> {
+        let search_info = self.search_info.as_ref()?;
+        let query = search_info.query().filter(|query| !query.is_empty())?;
+        let buffer = self.buffer.borrow();
+        let range = search_info
+            .bounds
+            .as_ref()
+            .map_or(0..buffer.get_height(), |bounds| {
+                bounds.start.line_idx..bounds.end.line_idx
+            });
+        Some(SearchRequest {
+            lines: buffer.snapshot(),
+            query: query.to_string(),
+            from: (self.text_location.line_idx, self.caret_byte_idx()),
+            range,
+        })
+    }
+
+    // the byte of its line the caret is at
+    pub fn caret_byte_idx(&self) -> usize {
+        self.buffer
+            .borrow()
+            .lines
+            .get(self.text_location.line_idx)
+            .map_or(0, |line| {
+                line.text_before(self.text_location.grapheme_idx).len()
+            })
+    }
+
+    // move to the grapheme holding a byte, given by line and byte index, e.g. of a match
+    // found in the background
+    pub fn show_search_hit(&mut self, (line_idx, byte_idx): (usize, usize)) {
+        let grapheme_idx = self
+            .buffer
+            .borrow()
+            .lines
+            .get(line_idx)
+            .and_then(|line| line.byte_idx_to_grapheme_idx(byte_idx));
+        if let Some(grapheme_idx) = grapheme_idx {
+            self.text_location = Location {
+                grapheme_idx,
+                line_idx,
+            };
+            self.scroll_text_location_into_view();
+            self.set_needs_redraw(true);
         }
-        self.search_in_direction(self.text_location, SearchDirection::default());
     }
 
     // Attempts to get the current search query - for scenarios where the search query absolutely must be there.
     // Panics if not present in debug, or if search info is not present in debug
     // Returns None on release.
     fn get_search_query(&self) -> Option<&Line> {
-        let query = self
-            .search_info
-            .as_ref()
-            .and_then(|search_info| search_info.query.as_ref());
+        let query = self.search_info.as_ref().and_then(SearchInfo::query);
         debug_assert!(
             query.is_some(),
             "Attempting to search with malformed searchinfo present"
@@ -87,25 +529,65 @@ impl View {
         query
     }
 
-    fn search_in_direction(&mut self, from: Location, direction: SearchDirection) {
-        if let Some(location) = self.get_search_query().and_then(|query| {
-            if query.is_empty() {
-                None
-            } else if direction == SearchDirection::Forward {
-                self.buffer.search_forward(query, &from)
-            } else if direction == SearchDirection::Backwoard {
-                self.buffer.search_backward(query, &from)
-            } else {
-                unreachable!()
-            }
-        }) {
+    pub const fn caret_location(&self) -> Location {
+        self.text_location
+    }
+
+    // the query of the current or the last search
+    pub fn search_query(&self) -> Option<String> {
+        self.search_info
+            .as_ref()
+            .and_then(SearchInfo::query)
+            .filter(|query| !query.is_empty())
+            .map(ToString::to_string)
+    }
+
+    pub fn find_all_matches(&self, query: &str) -> Vec<Location> {
+        self.buffer.borrow().find_all_locations(query)
+    }
+
+    pub fn line_text(&self, line_idx: usize) -> String {
+        self.buffer
+            .borrow()
+            .lines
+            .get(line_idx)
+            .map(ToString::to_string)
+            .unwrap_or_default()
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.buffer.borrow().get_height()
+    }
+
+    pub fn revision(&self) -> usize {
+        self.buffer.borrow().revision()
+    }
+
+    fn search_in_direction(&mut self, from: Location, direction: SearchDirection) -> bool {
+        self.set_needs_redraw(true);
+        let Some(query) = self.get_search_query().filter(|query| !query.is_empty()) else {
+            return true;
+        };
+        let bounds = self
+            .search_info
+            .as_ref()
+            .and_then(|search_info| search_info.bounds.as_ref());
+
+        let location = if direction == SearchDirection::Forward {
+            self.buffer.borrow().search_forward(query, &from, bounds)
+        } else if direction == SearchDirection::Backwoard {
+            self.buffer.borrow().search_backward(query, &from, bounds)
+        } else {
+            unreachable!()
+        };
+        if let Some(location) = location {
             self.text_location = location;
             self.scroll_text_location_into_view();
         }
-        self.set_needs_redraw(true);
+        location.is_some()
     }
 
-    pub fn search_next(&mut self) {
+    pub fn search_next(&mut self) -> bool {
         let step_right = self
             .get_search_query()
             .map_or(1, |query| max(query.grapheme_count(), 1));
@@ -113,32 +595,190 @@ impl View {
             line_idx: self.text_location.line_idx,
             grapheme_idx: self.text_location.grapheme_idx.saturating_add(step_right),
         };
-        self.search_in_direction(location, SearchDirection::Forward);
+        let from = self.text_location;
+        let found = self.search_in_direction(location, SearchDirection::Forward);
+        self.record_jump(from);
+        found
     }
 
-    pub fn search_backward(&mut self) {
-        self.search_in_direction(self.text_location, SearchDirection::Backwoard);
+    pub fn search_backward(&mut self) -> bool {
+        let from = self.text_location;
+        let found = self.search_in_direction(self.text_location, SearchDirection::Backwoard);
+        self.record_jump(from);
+        found
     }
     // endregion
 
+    pub fn set_line_ending(&mut self, line_ending: LineEnding) {
+        self.buffer.borrow_mut().set_line_ending(line_ending);
+    }
+
+    pub fn set_encoding(&mut self, encoding: Encoding) {
+        self.buffer.borrow_mut().set_encoding(encoding);
+    }
+
+    // None goes by the extension again
+    pub fn set_file_type(&mut self, file_type: Option<String>) {
+        self.buffer.borrow_mut().file_info.file_type = file_type;
+    }
+
     pub fn get_status(&self) -> DocumentStatus {
+        let buffer = self.buffer.borrow();
+        let line = buffer.lines.get(self.text_location.line_idx);
+        let (cols_before, cols_after) = if self.config.wrap {
+            (0, 0)
+        } else {
+            // what the markers take is hidden as well
+            let left = self.scroll_offset.col;
+            let cols = left..left.saturating_add(self.text_width());
+            line.map_or((left, 0), |line| {
+                let (cols, _) = Self::cols_within_markers(line, cols);
+                (cols.start, line.width().saturating_sub(cols.end))
+            })
+        };
         DocumentStatus {
-            total_lines: self.buffer.get_height(),
+            total_lines: buffer.get_height(),
             current_line_idx: self.text_location.line_idx,
-            is_modified: self.buffer.dirty,
-            filename: format!("{}", self.buffer.file_info),
+            grapheme_col: self.text_location.grapheme_idx,
+            visual_col: line.map_or(0, |line| line.width_until(self.text_location.grapheme_idx)),
+            cols_before,
+            cols_after,
+            is_modified: buffer.is_modified(),
+            is_new_file: buffer.is_new,
+            is_read_only: buffer.is_read_only,
+            filename: format!("{}", buffer.file_info),
+            path: buffer.file_info.display_name(),
+            file_type: buffer.file_info.file_type(),
+            encoding: buffer.file_info.encoding.name(),
+            line_ending: buffer.file_info.line_ending.name(),
+            ..DocumentStatus::default()
         }
     }
 
     pub fn caret_position(&self) -> Position {
-        self.text_location_to_position()
-            .saturating_sub(&self.scroll_offset)
+        if self.config.wrap {
+            let (caret, col) = self.caret_row();
+            return Position {
+                row: self.rows_between(self.scroll_top(), caret, self.size.height),
+                col: col.saturating_add(self.gutter_width()),
+            };
+        }
+        let Position { row, col } = self
+            .text_location_to_position()
+            .saturating_sub(&self.scroll_offset);
+        Position {
+            row,
+            col: col.saturating_add(self.gutter_width()),
+        }
+    }
+
+    // the columns left of the text, for the names of marks, how lines differ from the file
+    // as checked in and line numbers
+    fn gutter_width(&self) -> Col {
+        self.mark_width()
+            .saturating_add(self.git_width())
+            .saturating_add(self.number_width())
+    }
+
+    // the name of a mark and a space, while any are set
+    fn mark_width(&self) -> Col {
+        if self.marks.has_bookmarks() { 2 } else { 0 }
+    }
+
+    // a sign and a space, while any line differs from the file as checked in
+    fn git_width(&self) -> Col {
+        if self.buffer.borrow().has_git_changes() {
+            2
+        } else {
+            0
+        }
+    }
+
+    // the columns taken by line numbers, wide enough for the last line plus a space
+    fn number_width(&self) -> Col {
+        if !self.config.show_line_numbers {
+            return 0;
+        }
+        let last_line = self.buffer.borrow().get_height().max(1);
+        let digits = last_line.checked_ilog10().unwrap_or(0).saturating_add(1);
+        usize::try_from(digits).map_or(0, |digits| digits.saturating_add(1))
+    }
+
+    // the columns left for text between the line numbers and the scrollbar
+    fn text_width(&self) -> Col {
+        self.size
+            .width
+            .saturating_sub(self.gutter_width())
+            .saturating_sub(self.scrollbar_width())
+    }
+
+    // The scrollbar only takes a column while there are more lines than rows and a column of
+    // text is left next to it. Lines are counted rather than rows, as how lines wrap depends
+    // on the width the scrollbar leaves.
+    fn scrollbar_width(&self) -> Col {
+        let shown = self.config.scrollbar
+            && self.buffer.borrow().get_height() > self.size.height
+            && self.size.width > self.gutter_width().saturating_add(1);
+        usize::from(shown)
+    }
+
+    // the rows of the scrollbar filled, as big a part of it as the part of the lines in view
+    // and reaching the bottom once the last line is
+    fn scrollbar_thumb(&self) -> Option<Range<Row>> {
+        if self.scrollbar_width() == 0 {
+            return None;
+        }
+        let height = self.size.height;
+        let total = self.buffer.borrow().get_height();
+        let size = height
+            .saturating_mul(height)
+            .checked_div(total)
+            .unwrap_or(height)
+            .clamp(1, height);
+        let top = self.scroll_top().line_idx;
+        let start = if top.saturating_add(height) >= total {
+            height.saturating_sub(size)
+        } else {
+            top.saturating_mul(height)
+                .checked_div(total)
+                .unwrap_or(0)
+                .min(height.saturating_sub(size))
+        };
+        Some(start..start.saturating_add(size))
+    }
+
+    // Jump to the line as far through the buffer as `row` is down the scrollbar, the first
+    // and the last line at its ends. It's centered like `go_to_percentage` unless that leaves
+    // rows below the last line. Not recorded as a jump, as dragging the scrollbar goes through
+    // many lines. False without a scrollbar.
+    pub fn scroll_to_scrollbar_row(&mut self, row: Row) -> bool {
+        if self.scrollbar_width() == 0 {
+            return false;
+        }
+        let last_line = self.buffer.borrow().get_height().saturating_sub(1);
+        let last_row = self.size.height.saturating_sub(1);
+        let line_idx = row
+            .min(last_row)
+            .saturating_mul(last_line)
+            .checked_div(last_row)
+            .unwrap_or(0);
+        self.text_location = Location {
+            line_idx,
+            grapheme_idx: 0,
+        };
+        self.scroll_text_location_into_view();
+        let lines_below = last_line.saturating_sub(line_idx);
+        let middle = last_row.div_euclid(2);
+        self.place_caret_row(middle.max(last_row.saturating_sub(lines_below)));
+        self.set_needs_redraw(true);
+        true
     }
 
     fn text_location_to_position(&self) -> Position {
         let row = self.text_location.line_idx;
         let col = self
             .buffer
+            .borrow()
             .lines
             .get(row)
             .map_or(0, |line| line.width_until(self.text_location.grapheme_idx));
@@ -147,80 +787,568 @@ impl View {
     }
 
     // region: edit
-    pub fn handle_edit_command(&mut self, command: &Edit) {
+    pub fn handle_edit_command(&mut self, command: Edit) {
         match command {
-            Edit::Insert(ch) => self.insert_char(*ch),
+            Edit::Insert(ch) => self.insert_char(ch),
             Edit::InsertTab => self.insert_tab(),
             Edit::InsertNewline => self.insert_newline(),
+            Edit::InsertLineBelow => self.open_line(true),
+            Edit::InsertLineAbove => self.open_line(false),
             Edit::Delete => self.delete(),
             Edit::DeleteBackward => self.delete_backward(),
+            Edit::TransposeGraphemes => self.transpose_graphemes(),
+            Edit::TransposeLines => self.transpose_lines(),
+        }
+        self.follow_edits();
+        self.marks.set(LAST_EDIT, self.text_location);
+    }
+
+    pub fn handle_repeated_edit_command(&mut self, command: Edit, count: usize) {
+        let count = match command {
+            Edit::Delete | Edit::DeleteBackward | Edit::TransposeGraphemes => {
+                count.min(self.max_steps())
+            }
+            Edit::Insert(_)
+            | Edit::InsertTab
+            | Edit::InsertNewline
+            | Edit::InsertLineBelow
+            | Edit::InsertLineAbove
+            | Edit::TransposeLines => count,
+        };
+        for _ in 0..count {
+            self.handle_edit_command(command);
         }
     }
 
+    // move the marks along with what the edits to the buffer did to their lines
+    fn follow_edits(&mut self) {
+        let edits = self.buffer.borrow_mut().take_edits();
+        for op in edits {
+            let (line_idx, removed, inserted) = op.lines_replaced();
+            self.marks.adjust(line_idx, removed, inserted);
+        }
+    }
+
+    // the caret lands behind the grapheme the character ended up in, which is the one before
+    // the caret if a combining mark merged with it
     fn insert_char(&mut self, ch: char) {
-        let old_len = self
+        let location = self
             .buffer
-            .lines
-            .get(self.text_location.line_idx)
-            .map_or(0, Line::grapheme_count);
+            .borrow_mut()
+            .insert_char(ch, &self.text_location);
+        if let Some(location) = location {
+            self.text_location = location;
+            self.scroll_text_location_into_view();
+        }
+        self.set_needs_redraw(true);
+    }
+
+    fn insert_tab(&mut self) {
+        if self.config.expand_tab {
+            for _ in 0..self.config.tab_width.max(1) {
+                self.insert_char(' ');
+            }
+        } else {
+            self.insert_char('\t');
+        }
+    }
+
+    fn insert_newline(&mut self) {
+        let location = self.buffer.borrow_mut().insert_newline(&self.text_location);
+        self.text_location = location;
+        self.scroll_text_location_into_view();
+        self.set_needs_redraw(true);
+    }
+
+    // insert an empty line below or above the caret's line and move onto it, like vim's `o`
+    // and `O`
+    fn open_line(&mut self, below: bool) {
+        let height = self.buffer.borrow().get_height();
+        let mut line_idx = self.text_location.line_idx.min(height);
+        if below {
+            // below the first line of an empty buffer, which has to exist first
+            if line_idx == height {
+                self.buffer
+                    .borrow_mut()
+                    .insert_line(line_idx, Line::default());
+            }
+            line_idx = line_idx.saturating_add(1);
+        }
+        self.buffer
+            .borrow_mut()
+            .insert_line(line_idx, Line::default());
+        self.text_location = Location {
+            line_idx,
+            grapheme_idx: 0,
+        };
+        self.scroll_text_location_into_view();
+        self.set_needs_redraw(true);
+    }
+
+    fn delete(&mut self) {
+        // there is nothing behind the last line to delete
+        if self.snap_caret() {
+            return;
+        }
+        self.buffer.borrow_mut().delete(&self.text_location);
+        // the graphemes around the deleted one may have merged, leaving fewer on the line
+        self.snap_to_valid_grapheme();
+        self.set_needs_redraw(true);
+    }
+
+    fn transpose_graphemes(&mut self) {
+        let location = self
+            .buffer
+            .borrow_mut()
+            .transpose_graphemes(&self.text_location);
+        if let Some(location) = location {
+            self.text_location = location;
+            self.scroll_text_location_into_view();
+            self.set_needs_redraw(true);
+        }
+    }
+
+    // the caret stays on the lower of the two lines
+    fn transpose_lines(&mut self) {
+        let line_idx = self.text_location.line_idx;
+        if self.buffer.borrow_mut().swap_lines(line_idx) {
+            self.marks.swap_lines(line_idx.saturating_sub(1), line_idx);
+            self.snap_to_valid_grapheme();
+            self.scroll_text_location_into_view();
+            self.set_needs_redraw(true);
+        }
+    }
 
-        self.buffer.insert_char(ch, &self.text_location);
+    fn delete_backward(&mut self) {
+        // behind the last line there is only an empty line to join onto it, which changes
+        // nothing but where the caret is
+        if self.snap_caret() {
+            return;
+        }
+        // do nothing if at top-left corner
+        if self.text_location.line_idx == 0 && self.text_location.grapheme_idx == 0 {
+            return;
+        }
+        self.handle_move_command(Move::Left);
+        self.delete();
+    }
+    // endregion
 
-        let new_len = self
+    // insert `text` of any number of lines at the caret and move behind it
+    pub fn insert_text(&mut self, text: &str) {
+        self.text_location = self
             .buffer
+            .borrow_mut()
+            .insert_str(text, &self.text_location);
+        self.follow_edits();
+        self.marks.set(LAST_EDIT, self.text_location);
+        self.scroll_text_location_into_view();
+        self.set_needs_redraw(true);
+    }
+
+    // the part of the word left of the caret which is before it, if any
+    pub fn word_before_caret(&self) -> Option<String> {
+        let buffer = self.buffer.borrow();
+        let line = buffer.lines.get(self.text_location.line_idx)?;
+        let caret = self.text_location.grapheme_idx;
+        let word = line.word_at(caret.checked_sub(1)?)?;
+        (word.start < caret).then(|| line.text_of(word.start..caret).to_string())
+    }
+
+    // the text around the caret up to whitespace on either side
+    pub fn token_under_caret(&self) -> Option<String> {
+        let buffer = self.buffer.borrow();
+        let line = buffer.lines.get(self.text_location.line_idx)?;
+        let token = line.token_at(self.text_location.grapheme_idx)?;
+        Some(line.text_of(token).to_string())
+    }
+
+    pub fn words_starting_with(&self, prefix: &str) -> Vec<String> {
+        self.buffer.borrow().words_starting_with(prefix)
+    }
+
+    // what the grapheme under the caret is made of
+    pub fn describe_grapheme(&self) -> String {
+        let buffer = self.buffer.borrow();
+        if buffer.is_empty() {
+            return String::from("Empty buffer");
+        }
+        let Some(line) = buffer.lines.get(self.text_location.line_idx) else {
+            return String::from("End of buffer");
+        };
+        line.grapheme_at(self.text_location.grapheme_idx)
+            .map_or_else(|| String::from("End of line"), TextFragment::describe)
+    }
+
+    // every line, each ended by a line break, e.g. to pipe through a filter
+    pub fn text(&self) -> String {
+        self.buffer
+            .borrow()
             .lines
-            .get(self.text_location.line_idx)
-            .map_or(0, Line::grapheme_count);
+            .iter()
+            .fold(String::new(), |mut text, line| {
+                text.push_str(line);
+                text.push('\n');
+                text
+            })
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        self.buffer.borrow().line_strings(0, usize::MAX)
+    }
+
+    // replace every line by those of `text` and move to the start, returning whether
+    // anything changed
+    pub fn replace_text(&mut self, text: &str) -> bool {
+        if !self.replace_lines(text) {
+            return false;
+        }
+        self.text_location = Location::default();
+        self.marks.set(LAST_EDIT, self.text_location);
+        self.scroll_text_location_into_view();
+        true
+    }
+
+    // like `replace_text`, but the caret stays on its line, e.g. after formatting
+    pub fn reformat_text(&mut self, text: &str) -> bool {
+        if !self.replace_lines(text) {
+            return false;
+        }
+        self.keep_caret_on_its_line();
+        true
+    }
+
+    // replace every line by what `transform` makes of them, the caret staying on its line
+    pub fn transform_lines(&mut self, transform: impl FnOnce(Vec<Line>) -> Vec<Line>) -> bool {
+        let height = self.buffer.borrow().get_height();
+        if !self
+            .buffer
+            .borrow_mut()
+            .transform_line_range(0..height, transform)
+        {
+            return false;
+        }
+        self.follow_edits();
+        self.keep_caret_on_its_line();
+        self.set_needs_redraw(true);
+        true
+    }
+
+    fn keep_caret_on_its_line(&mut self) {
+        let height = self.buffer.borrow().get_height();
+        self.text_location.line_idx = self.text_location.line_idx.min(height.saturating_sub(1));
+        self.snap_to_valid_grapheme();
+        self.scroll_text_location_into_view();
+    }
+
+    fn replace_lines(&mut self, text: &str) -> bool {
+        let lines: Vec<&str> = text.lines().collect();
+        let height = self.buffer.borrow().get_height();
+        if !self.buffer.borrow_mut().replace_lines(0..height, &lines) {
+            return false;
+        }
+        self.follow_edits();
+        self.set_needs_redraw(true);
+        true
+    }
+
+    // region: lines
+    // remove `count` lines from the caret's on, returning their text
+    pub fn delete_lines(&mut self, count: usize) -> Vec<String> {
+        let line_idx = self.text_location.line_idx;
+        let removed = self.buffer.borrow_mut().remove_lines(line_idx, count);
+        self.follow_edits();
+        let height = self.buffer.borrow().get_height();
+        self.text_location = Location {
+            line_idx: line_idx.min(height.saturating_sub(1)),
+            grapheme_idx: 0,
+        };
+        self.marks.set(LAST_EDIT, self.text_location);
+        self.scroll_text_location_into_view();
+        self.set_needs_redraw(true);
+        removed
+    }
+
+    pub fn yank_lines(&self, count: usize) -> Vec<String> {
+        self.buffer
+            .borrow()
+            .line_strings(self.text_location.line_idx, count)
+    }
+
+    // insert `lines` below the caret's line and move to the first of them
+    pub fn paste_lines(&mut self, lines: &[String]) {
+        let height = self.buffer.borrow().get_height();
+        let line_idx = self.text_location.line_idx.saturating_add(1).min(height);
+        self.buffer.borrow_mut().insert_lines(line_idx, lines);
+        self.follow_edits();
+        self.text_location = Location {
+            line_idx,
+            grapheme_idx: 0,
+        };
+        self.marks.set(LAST_EDIT, self.text_location);
+        self.scroll_text_location_into_view();
+        self.set_needs_redraw(true);
+    }
+    // endregion
+
+    // e.g. "Line 3 of 10, word 12 of 54, ..." for the caret's location in the buffer
+    pub fn describe_statistics(&self) -> String {
+        let (before, total) = self.buffer.borrow().statistics(&self.text_location);
+        before.describe(&total)
+    }
+
+    // region: marks
+    // returns false for names which can't be set, like the mark of the last edit
+    pub fn set_mark(&mut self, name: char) -> bool {
+        if !Marks::is_valid_name(name) || name == LAST_EDIT {
+            return false;
+        }
+        self.marks.set(name, self.text_location);
+        // the first mark takes a column from the text
+        self.scroll_text_location_into_view();
+        self.set_needs_redraw(true);
+        true
+    }
+
+    // returns false if there is no such mark
+    pub fn delete_mark(&mut self, name: char) -> bool {
+        let deleted = name != LAST_EDIT && self.marks.remove(name);
+        if deleted {
+            self.scroll_text_location_into_view();
+            self.set_needs_redraw(true);
+        }
+        deleted
+    }
 
-        if new_len.saturating_sub(old_len) > 0 {
-            self.handle_move_command(&Move::Right);
-        }
-        self.set_needs_redraw(true);
+    // the next line with a mark other than the last edit's, or the previous one, None if
+    // there is none and whether it started over at the other end of the buffer otherwise
+    pub fn jump_to_next_mark(&mut self, forward: bool) -> Option<bool> {
+        let (location, wrapped) = self
+            .marks
+            .next_bookmark(self.text_location.line_idx, forward)?;
+        self.jump_to(location);
+        Some(wrapped)
     }
 
-    fn insert_tab(&mut self) {
-        self.insert_char('\t');
+    // returns false if there is no such mark
+    pub fn jump_to_mark(&mut self, name: char) -> bool {
+        let Some(location) = self.marks.get(name) else {
+            return false;
+        };
+        self.jump_to(location);
+        true
     }
 
-    fn insert_newline(&mut self) {
-        self.buffer.insert_newline(&self.text_location);
-        self.handle_move_command(&Move::Right);
-        self.set_needs_redraw(true);
+    // a far move, which can be walked back with `JumpBack`
+    pub fn jump_to(&mut self, location: Location) {
+        let from = self.text_location;
+        self.restore_jump(location);
+        self.record_jump(from);
+        self.scroll_text_location_into_view();
     }
 
-    fn delete(&mut self) {
-        self.buffer.delete(&self.text_location);
-        self.set_needs_redraw(true);
+    // returns false if no lines were edited since saving
+    pub fn jump_to_edited_line(&mut self, forward: bool) -> bool {
+        let line_idx = self
+            .buffer
+            .borrow()
+            .edited_line(self.text_location.line_idx, forward);
+        let Some(line_idx) = line_idx else {
+            return false;
+        };
+        self.jump_to(Location {
+            line_idx,
+            grapheme_idx: 0,
+        });
+        true
     }
 
-    fn delete_backward(&mut self) {
-        // do nothing if at top-left corner
-        if self.text_location.line_idx == 0 && self.text_location.grapheme_idx == 0 {
-            return;
-        }
-        self.handle_move_command(&Move::Left);
-        self.delete();
+    // e.g. "a 3:1", with line and column counted from 1
+    pub fn describe_marks(&self) -> Vec<String> {
+        self.marks
+            .iter()
+            .map(|(name, location)| {
+                format!(
+                    "{name} {}:{}",
+                    location.line_idx.saturating_add(1),
+                    location.grapheme_idx.saturating_add(1)
+                )
+            })
+            .collect()
     }
     // endregion
 
     // region: move
-    pub fn handle_move_command(&mut self, command: &Move) {
+    pub fn handle_move_command(&mut self, command: Move) {
+        self.handle_repeated_move_command(command, 1);
+    }
+
+    // move `count` times, scrolling only once at the end
+    pub fn handle_repeated_move_command(&mut self, command: Move, count: usize) {
         let Size { height, .. } = self.size;
+        let page = height.saturating_sub(1);
+        let from = self.text_location;
 
         // This match moves the position, but does not check for all boundaries.
         // The final boundary checking happens after the match statement.
         match command {
-            Move::Up => self.move_up(1),
-            Move::Down => self.move_down(1),
-            Move::Left => self.move_left(1),
-            Move::Right => self.move_right(1),
-            Move::PageUp => self.move_up(height.saturating_sub(1)),
-            Move::PageDown => self.move_down(height.saturating_sub(1)),
+            Move::Up => self.move_rows_up(count),
+            Move::Down => self.move_rows_down(count),
+            Move::LineUp => self.move_up(count),
+            Move::LineDown => self.move_down(count),
+            Move::Left => {
+                for _ in 0..count.min(self.max_steps()) {
+                    self.move_left(1);
+                }
+            }
+            Move::Right => {
+                for _ in 0..count.min(self.max_steps()) {
+                    self.move_right(1);
+                }
+            }
+            Move::PageUp => self.move_rows_up(page.saturating_mul(count)),
+            Move::PageDown => self.move_rows_down(page.saturating_mul(count)),
             Move::StartOfLine => self.move_to_start_of_line(),
             Move::EndOfLine => self.move_to_end_of_line(),
+            Move::StartOfFile => self.text_location = Location::default(),
+            Move::EndOfFile => {
+                let line_idx = self.buffer.borrow().get_height().saturating_sub(1);
+                self.text_location.line_idx = line_idx;
+                self.move_to_end_of_line();
+            }
+            Move::NextWord => {
+                for _ in 0..count.min(self.max_steps()) {
+                    self.move_to_next_word();
+                }
+            }
+            Move::PreviousWord => {
+                for _ in 0..count.min(self.max_steps()) {
+                    self.move_to_previous_word();
+                }
+            }
+            Move::JumpBack => {
+                for _ in 0..count {
+                    let Some(location) = self.jumps.back(self.text_location) else {
+                        break;
+                    };
+                    self.restore_jump(location);
+                }
+            }
+            Move::JumpForward => {
+                for _ in 0..count {
+                    let Some(location) = self.jumps.forward() else {
+                        break;
+                    };
+                    self.restore_jump(location);
+                }
+            }
+        }
+        if matches!(
+            command,
+            Move::PageUp | Move::PageDown | Move::StartOfFile | Move::EndOfFile
+        ) {
+            self.record_jump(from);
         }
 
         self.scroll_text_location_into_view();
+        // the word under the caret may be another one now
+        if self.config.highlight_word && from != self.text_location {
+            self.set_needs_redraw(true);
+        }
+    }
+
+    // A different horizontal scroll or search changes every row, as does the gutter growing
+    // with the line count or the scrollbar's thumb moving. Another highlighted word only
+    // changes the rows showing it.
+    fn forget_outdated_rows(
+        &mut self,
+        left: usize,
+        gutter_width: usize,
+        thumb: Option<&Range<Row>>,
+    ) {
+        let search_info = self.search_info.as_ref();
+        let query = search_info
+            .and_then(SearchInfo::query)
+            .map(|query| &**query);
+        let bounds = search_info.and_then(|search_info| search_info.bounds.clone());
+        let word = query.is_none().then(|| self.caret_word.word()).flatten();
+        let checks_spelling = self.checks_spelling();
+        let syntax = self.syntax();
+        if self.rendered_col != left
+            || self.rendered_query.as_deref() != query
+            || self.rendered_bounds != bounds
+            || self.rendered_gutter_width != gutter_width
+            || self.rendered_thumb.as_ref() != thumb
+            || self.rendered_spelling != checks_spelling
+            || self.highlighter.syntax() != syntax
+        {
+            self.rendered_rows.clear();
+            self.rendered_col = left;
+            self.rendered_query = query.map(str::to_string);
+            self.rendered_bounds = bounds;
+            self.rendered_word = word.map(str::to_string);
+            self.rendered_gutter_width = gutter_width;
+            self.rendered_thumb = thumb.cloned();
+            self.rendered_spelling = checks_spelling;
+            self.highlighter.set_syntax(syntax);
+        } else if self.rendered_word.as_deref() != word {
+            let buffer = self.buffer.borrow();
+            let words = [self.rendered_word.as_deref(), word];
+            let long_line_bytes = self.config.long_line_bytes;
+            // long lines never show the word
+            let shows_word = |line: &Line| {
+                line.len() <= long_line_bytes
+                    && words.iter().flatten().any(|word| line.contains(word))
+            };
+            for row in &mut self.rendered_rows {
+                if let Some(RenderedRow::Line { line_idx, .. }) = row
+                    && buffer.lines.get(*line_idx).is_none_or(shows_word)
+                {
+                    *row = None;
+                }
+            }
+            self.rendered_word = word.map(str::to_string);
+        }
+        // the states of lines edited since, with at most a line drawn on each row
+        let end = self.scroll_top().line_idx.saturating_add(self.size.height);
+        self.highlighter
+            .forget_edited(&*self.buffer.borrow().lines, end);
+    }
+
+    fn update_caret_word(&mut self) {
+        if self.config.highlight_word {
+            let buffer = self.buffer.borrow();
+            self.caret_word.update(
+                buffer.lines.get(self.text_location.line_idx),
+                self.text_location,
+            );
+        } else {
+            self.caret_word = CaretWord::default();
+        }
+    }
+
+    // remember `from` if the caret was moved away from it
+    fn record_jump(&mut self, from: Location) {
+        if from != self.text_location {
+            self.jumps.push(from);
+        }
+    }
+
+    // the buffer may have shrunk since the location was recorded
+    fn restore_jump(&mut self, location: Location) {
+        self.text_location = location;
+        self.snap_to_valid_line();
+        self.snap_to_valid_grapheme();
+    }
+
+    // the most single steps which can still move the caret or delete something, so a
+    // huge count stops there instead of spinning
+    fn max_steps(&self) -> usize {
+        self.buffer.borrow().lines.iter().fold(0, |steps, line| {
+            steps
+                .saturating_add(line.grapheme_count())
+                .saturating_add(1)
+        })
     }
 
     fn move_up(&mut self, step: usize) {
@@ -232,8 +1360,48 @@ impl View {
     fn move_down(&mut self, step: usize) {
         let line_idx = &mut self.text_location.line_idx;
         *line_idx = line_idx.saturating_add(step);
-        self.snap_to_valid_grapheme();
         self.snap_to_valid_line();
+        self.snap_to_valid_grapheme();
+    }
+
+    // by screen rows when lines wrap, by lines otherwise
+    fn move_rows_up(&mut self, step: usize) {
+        if !self.config.wrap {
+            self.move_up(step);
+            return;
+        }
+        let (caret, col) = self.caret_row();
+        self.move_to_row(self.rows_up(caret, step), col);
+    }
+
+    fn move_rows_down(&mut self, step: usize) {
+        if !self.config.wrap {
+            self.move_down(step);
+            return;
+        }
+        let (caret, col) = self.caret_row();
+        let (to, _) = self.rows_down(caret, step);
+        self.move_to_row(to, col);
+    }
+
+    // to `col` columns into the row, or the last grapheme on it
+    fn move_to_row(&mut self, to: VisualRow, col: Col) {
+        let buffer = self.buffer.borrow();
+        let grapheme_idx = buffer.lines.get(to.line_idx).map_or(0, |line| {
+            let starts = self.row_starts(line);
+            let start = starts.get(to.row).copied().unwrap_or(0);
+            let grapheme_idx = line.col_to_grapheme_idx(start.saturating_add(col));
+            starts
+                .get(to.row.saturating_add(1))
+                .map_or(grapheme_idx, |next| {
+                    grapheme_idx.min(line.col_to_grapheme_idx(*next).saturating_sub(1))
+                })
+        });
+        drop(buffer);
+        self.text_location = Location {
+            line_idx: to.line_idx,
+            grapheme_idx,
+        };
     }
 
     fn move_left(&mut self, step: usize) {
@@ -252,6 +1420,7 @@ impl View {
         let grapheme_idx = &mut self.text_location.grapheme_idx;
         let length = self
             .buffer
+            .borrow()
             .lines
             .get(self.text_location.line_idx)
             .map_or(0, Line::grapheme_count);
@@ -265,6 +1434,113 @@ impl View {
         }
     }
 
+    // to the next word, possibly on a later line, or the end of the buffer
+    fn move_to_next_word(&mut self) {
+        let Location {
+            line_idx,
+            grapheme_idx,
+        } = self.text_location;
+        let buffer = self.buffer.borrow();
+        let location = buffer
+            .lines
+            .get(line_idx)
+            .and_then(|line| line.next_word_start(grapheme_idx))
+            .map(|grapheme_idx| Location {
+                grapheme_idx,
+                line_idx,
+            })
+            .or_else(|| {
+                let below = line_idx.saturating_add(1).min(buffer.get_height());
+                buffer
+                    .lines
+                    .range(below..buffer.get_height())
+                    .zip(below..)
+                    .find_map(|(line, line_idx)| {
+                        line.first_word_start().map(|grapheme_idx| Location {
+                            grapheme_idx,
+                            line_idx,
+                        })
+                    })
+            })
+            .or_else(|| {
+                buffer.lines.last().map(|line| Location {
+                    line_idx: buffer.get_height().saturating_sub(1),
+                    grapheme_idx: line.grapheme_count(),
+                })
+            });
+        drop(buffer);
+        if let Some(location) = location {
+            self.text_location = location;
+        }
+    }
+
+    // to the previous word, possibly on an earlier line, or the start of the buffer
+    fn move_to_previous_word(&mut self) {
+        let Location {
+            line_idx,
+            grapheme_idx,
+        } = self.text_location;
+        let buffer = self.buffer.borrow();
+        let location = buffer
+            .lines
+            .get(line_idx)
+            .and_then(|line| line.previous_word_start(grapheme_idx))
+            .map(|grapheme_idx| Location {
+                grapheme_idx,
+                line_idx,
+            })
+            .or_else(|| {
+                buffer
+                    .lines
+                    .range(0..line_idx.min(buffer.get_height()))
+                    .enumerate()
+                    .rev()
+                    .find_map(|(line_idx, line)| {
+                        line.last_word_start().map(|grapheme_idx| Location {
+                            grapheme_idx,
+                            line_idx,
+                        })
+                    })
+            })
+            .unwrap_or_default();
+        drop(buffer);
+        self.text_location = location;
+    }
+
+    pub fn is_at_end_of_line(&self) -> bool {
+        self.buffer
+            .borrow()
+            .lines
+            .get(self.text_location.line_idx)
+            .is_none_or(|line| self.text_location.grapheme_idx >= line.grapheme_count())
+    }
+
+    // jump to the start of the line at `line_idx`, or the last line
+    pub fn go_to_line(&mut self, line_idx: usize) {
+        self.go_to_line_and_grapheme(line_idx, 0);
+    }
+
+    // like `go_to_line`, to the grapheme at `grapheme_idx` or the end of the line
+    pub fn go_to_line_and_grapheme(&mut self, line_idx: usize, grapheme_idx: usize) {
+        let last_line = self.buffer.borrow().get_height().saturating_sub(1);
+        let from = self.text_location;
+        self.text_location = Location {
+            line_idx: line_idx.min(last_line),
+            grapheme_idx,
+        };
+        self.snap_to_valid_grapheme();
+        self.record_jump(from);
+        self.scroll_text_location_into_view();
+    }
+
+    // the line `percent` through the buffer, in the middle of the view, like vim's `N%`
+    pub fn go_to_percentage(&mut self, percent: usize) {
+        let height = self.buffer.borrow().get_height();
+        let line = percent.saturating_mul(height).div_ceil(100);
+        self.go_to_line(line.saturating_sub(1));
+        self.center_caret();
+    }
+
     fn move_to_start_of_line(&mut self) {
         self.text_location.grapheme_idx = 0;
     }
@@ -272,6 +1548,7 @@ impl View {
     fn move_to_end_of_line(&mut self) {
         self.text_location.grapheme_idx = self
             .buffer
+            .borrow()
             .lines
             .get(self.text_location.line_idx)
             .map_or(0, Line::grapheme_count);
@@ -283,6 +1560,7 @@ impl View {
     fn snap_to_valid_grapheme(&mut self) {
         self.text_location.grapheme_idx = self
             .buffer
+            .borrow()
             .lines
             .get(self.text_location.line_idx)
             .map_or(0, |line| {
@@ -290,29 +1568,140 @@ impl View {
             });
     }
 
-    // ensure self.location.grapheme_idx points to a valid grapheme idx by snapping it
-    // to the left most grapheme if appropriate
+    // Move a caret left behind the last line, e.g. by edits in another view of the buffer,
+    // to the end of the last line. Returns whether it had to move.
+    pub fn snap_caret(&mut self) -> bool {
+        let height = self.buffer.borrow().get_height();
+        if self.text_location.line_idx < height.max(1) {
+            return false;
+        }
+        self.text_location.line_idx = height.saturating_sub(1);
+        self.move_to_end_of_line();
+        self.scroll_text_location_into_view();
+        self.set_needs_redraw(true);
+        true
+    }
+
+    // ensure self.location.line_idx points to a line by snapping it to the last one
     // do not trigger scolling
-    // line_idx can be exactly self.buffer.height() since sometimes we want to modify below buffer
+    // only in an empty buffer the caret is on a line which doesn't exist yet
     fn snap_to_valid_line(&mut self) {
-        self.text_location.line_idx = min(self.text_location.line_idx, self.buffer.get_height());
+        self.text_location.line_idx = min(
+            self.text_location.line_idx,
+            self.buffer.borrow().get_height().saturating_sub(1),
+        );
     }
 
     fn scroll_text_location_into_view(&mut self) {
+        if self.config.wrap {
+            self.scroll_horizontally(0, 0);
+            self.scroll_to_caret_row();
+            return;
+        }
+        self.scroll_row_in_line = 0;
         let Position { row, col } = self.text_location_to_position();
+        let line_width = self.buffer.borrow().lines.get(row).map_or(0, Line::width);
         self.scroll_vertically(row);
-        self.scroll_horizontally(col);
+        self.scroll_horizontally(col, line_width);
+    }
+
+    // scroll so the caret's row is in the middle of the view, like vim's `zz`
+    pub fn center_caret(&mut self) {
+        self.place_caret_row(self.size.height.saturating_sub(1).div_euclid(2));
+    }
+
+    // like vim's `zt`
+    pub fn scroll_caret_to_top(&mut self) {
+        self.place_caret_row(0);
+    }
+
+    // like vim's `zb`
+    pub fn scroll_caret_to_bottom(&mut self) {
+        self.place_caret_row(self.size.height.saturating_sub(1));
+    }
+
+    // scroll so there are `rows_above` rows above the caret's, as far as the start of the
+    // buffer and `scroll_off` allow, without moving the caret
+    fn place_caret_row(&mut self, rows_above: usize) {
+        let Size { height, .. } = self.size;
+        let margin = self
+            .config
+            .scroll_off
+            .min(height.saturating_sub(1).div_euclid(2));
+        let rows_above = rows_above.clamp(margin, height.saturating_sub(1).saturating_sub(margin));
+        let (caret, _) = self.caret_row();
+        self.set_scroll_top(self.rows_up(caret, rows_above));
+    }
+
+    // scroll to `top` itself instead of following the caret, though not past the last line
+    fn set_scroll_top(&mut self, top: VisualRow) {
+        let last_line = self.buffer.borrow().get_height().saturating_sub(1);
+        let top = VisualRow {
+            line_idx: top.line_idx.min(last_line),
+            row: if self.config.wrap { top.row } else { 0 },
+        };
+        if top != self.scroll_top() {
+            self.scroll_offset.row = top.line_idx;
+            self.scroll_row_in_line = top.row;
+            self.set_needs_redraw(true);
+        }
+    }
+
+    // like `scroll_vertically`, counting screen rows instead of lines
+    fn scroll_to_caret_row(&mut self) {
+        let Size { height, .. } = self.size;
+        let margin = self
+            .config
+            .scroll_off
+            .min(height.saturating_sub(1).div_euclid(2));
+        let (caret, _) = self.caret_row();
+        let bottom_margin = self.rows_down(caret, margin).1;
+        let top = self.scroll_top();
+
+        let rows_above = self.rows_between(top, caret, height);
+        let new_top = if caret < top || rows_above < margin {
+            self.rows_up(caret, margin)
+        } else if rows_above.saturating_add(bottom_margin) >= height {
+            self.rows_up(
+                caret,
+                height.saturating_sub(1).saturating_sub(bottom_margin),
+            )
+        } else {
+            top
+        };
+
+        let offset_changed = new_top != top;
+        self.scroll_offset.row = new_top.line_idx;
+        self.scroll_row_in_line = new_top.row;
+        self.set_needs_redraw(offset_changed || self.get_needs_redraw());
     }
 
     fn scroll_vertically(&mut self, to: Row) {
-        let Position { row, .. } = &mut self.scroll_offset;
         let Size { height, .. } = self.size;
+        // keep `scroll_off` lines around the caret, unless the view is too small for that,
+        // without scrolling past the last line
+        let margin = self
+            .config
+            .scroll_off
+            .min(height.saturating_sub(1).div_euclid(2));
+        let lines_below = self
+            .buffer
+            .borrow()
+            .get_height()
+            .saturating_sub(to.saturating_add(1));
+        let bottom_margin = margin.min(lines_below);
+        let Position { row, .. } = &mut self.scroll_offset;
 
-        let offset_changed = if to < *row {
-            *row = to;
-            true
-        } else if to >= row.saturating_add(height) {
-            *row = to.saturating_sub(height).saturating_add(1);
+        let offset_changed = if to < row.saturating_add(margin) {
+            let new_row = to.saturating_sub(margin);
+            let changed = new_row != *row;
+            *row = new_row;
+            changed
+        } else if to.saturating_add(bottom_margin) >= row.saturating_add(height) {
+            *row = to
+                .saturating_add(bottom_margin)
+                .saturating_sub(height)
+                .saturating_add(1);
             true
         } else {
             false
@@ -321,15 +1710,22 @@ impl View {
         self.set_needs_redraw(offset_changed || self.get_needs_redraw());
     }
 
-    fn scroll_horizontally(&mut self, to: Col) {
+    // the caret stays off the columns at the edges where the markers of more of its line
+    // beyond them go, unless the view is too narrow for them
+    fn scroll_horizontally(&mut self, to: Col, line_width: Col) {
+        let width = self.text_width();
+        let margin = usize::from(width >= 3);
+        // whether the marker of more after the view goes in its last column, scrolled to `col`
+        let right_margin =
+            |col: Col| margin.min(usize::from(line_width > col.saturating_add(width)));
         let Position { col, .. } = &mut self.scroll_offset;
-        let Size { width, .. } = self.size;
 
-        let offset_changed = if to < *col {
-            *col = to;
+        let offset_changed = if to < col.saturating_add(margin) && *col > 0 {
+            *col = to.saturating_sub(margin);
             true
-        } else if to >= col.saturating_add(width) {
-            *col = to.saturating_sub(width).saturating_add(1);
+        } else if to >= col.saturating_add(width).saturating_sub(right_margin(*col)) {
+            let fits = to.saturating_add(1).saturating_sub(width);
+            *col = fits.saturating_add(right_margin(fits));
             true
         } else {
             false
@@ -339,25 +1735,367 @@ impl View {
     }
     // endregion
 
-    fn render_line(at: usize, line_text: &str) -> Result<(), std::io::Error> {
-        Terminal::print_row(at, line_text)?;
+    // region: wrap
+    // the columns of a line the row at `row_in_line` of it shows, given where its rows start
+    fn wrapped_row_cols(&self, starts: &[Col], row_in_line: usize) -> Range<Col> {
+        let start = starts.get(row_in_line).copied().unwrap_or(0);
+        let end = starts
+            .get(row_in_line.saturating_add(1))
+            .copied()
+            .unwrap_or_else(|| start.saturating_add(self.text_width()));
+        start..end
+    }
+
+    // where each row of `line` starts, a single row unless lines wrap
+    fn row_starts(&self, line: &Line) -> Rc<[Col]> {
+        if !self.config.wrap {
+            return Rc::clone(self.unwrapped.get_or_init(|| Rc::from([0].as_slice())));
+        }
+        let width = self.text_width();
+        let mut wraps = self.wraps.borrow_mut();
+        if wraps.len() >= MAX_CACHED_WRAPS {
+            wraps.clear();
+        }
+        Rc::clone(
+            wraps
+                .entry((line.revision(), width))
+                .or_insert_with(|| Rc::from(line.wrap(width))),
+        )
+    }
+
+    // lines past the end of the buffer take a row as well
+    fn row_count(&self, line_idx: usize) -> usize {
+        self.buffer
+            .borrow()
+            .lines
+            .get(line_idx)
+            .map_or(1, |line| self.row_starts(line).len())
+    }
+
+    fn scroll_top(&self) -> VisualRow {
+        let line_idx = self.scroll_offset.row;
+        VisualRow {
+            line_idx,
+            row: self
+                .scroll_row_in_line
+                .min(self.row_count(line_idx).saturating_sub(1)),
+        }
+    }
+
+    // the row the caret is on, and its column within that row
+    fn caret_row(&self) -> (VisualRow, Col) {
+        let Location {
+            line_idx,
+            grapheme_idx,
+        } = self.text_location;
+        let buffer = self.buffer.borrow();
+        let Some(line) = buffer.lines.get(line_idx) else {
+            return (VisualRow { line_idx, row: 0 }, 0);
+        };
+        let col = line.width_until(grapheme_idx);
+        let starts = self.row_starts(line);
+        let row = starts
+            .partition_point(|start| *start <= col)
+            .saturating_sub(1);
+        let start = starts.get(row).copied().unwrap_or(0);
+        (VisualRow { line_idx, row }, col.saturating_sub(start))
+    }
+
+    // `count` rows above `from`, or the first row
+    fn rows_up(&self, from: VisualRow, count: usize) -> VisualRow {
+        let mut at = from;
+        let mut remaining = count;
+        while remaining > at.row && at.line_idx > 0 {
+            remaining = remaining.saturating_sub(at.row.saturating_add(1));
+            at.line_idx = at.line_idx.saturating_sub(1);
+            at.row = self.row_count(at.line_idx).saturating_sub(1);
+        }
+        at.row = at.row.saturating_sub(remaining);
+        at
+    }
+
+    // `count` rows below `from`, or the last row of the buffer, and how many rows that is
+    fn rows_down(&self, from: VisualRow, count: usize) -> (VisualRow, usize) {
+        let last_line = self.buffer.borrow().get_height().saturating_sub(1);
+        let mut at = from;
+        let mut moved = 0_usize;
+        loop {
+            let below = self
+                .row_count(at.line_idx)
+                .saturating_sub(at.row.saturating_add(1));
+            let remaining = count.saturating_sub(moved);
+            if below >= remaining || at.line_idx >= last_line {
+                let step = below.min(remaining);
+                at.row = at.row.saturating_add(step);
+                return (at, moved.saturating_add(step));
+            }
+            moved = moved.saturating_add(below).saturating_add(1);
+            at = VisualRow {
+                line_idx: at.line_idx.saturating_add(1),
+                row: 0,
+            };
+        }
+    }
+
+    // how many rows `to` is below `from`, counting no further than `limit`
+    fn rows_between(&self, from: VisualRow, to: VisualRow, limit: usize) -> usize {
+        if to <= from {
+            return 0;
+        }
+        if to.line_idx == from.line_idx {
+            return to.row.saturating_sub(from.row);
+        }
+        let mut rows = self
+            .row_count(from.line_idx)
+            .saturating_sub(from.row)
+            .saturating_add(to.row);
+        for line_idx in from.line_idx.saturating_add(1)..to.line_idx {
+            if rows >= limit {
+                break;
+            }
+            rows = rows.saturating_add(self.row_count(line_idx));
+        }
+        rows
+    }
+    // endregion
+
+    fn render_line(
+        terminal: &mut dyn TerminalIo,
+        at: usize,
+        line_text: &str,
+    ) -> Result<(), std::io::Error> {
+        terminal.print_row(at, line_text)?;
         Ok(())
     }
 
-    fn build_welcome_message(width: usize) -> String {
+    // the name of the mark and how the line differs from the file as checked in, the columns
+    // of the gutter left of the line number
+    fn gutter_signs(&self, line_idx: usize, row_in_line: usize) -> (Option<char>, Option<char>) {
+        (
+            self.gutter_mark(line_idx, row_in_line),
+            self.gutter_git_sign(line_idx, row_in_line),
+        )
+    }
+
+    // The name of the mark on the first row of a line, a space on other rows, or None while
+    // no mark takes a column
+    fn gutter_mark(&self, line_idx: usize, row_in_line: usize) -> Option<char> {
+        (self.mark_width() > 0).then(|| {
+            self.marks
+                .on_line(line_idx)
+                .filter(|_| row_in_line == 0)
+                .unwrap_or(' ')
+        })
+    }
+
+    // How the line differs from the file as checked in, on its first row. Like
+    // `gutter_mark` a space on other rows and lines, and None while no line differs.
+    fn gutter_git_sign(&self, line_idx: usize, row_in_line: usize) -> Option<char> {
+        (self.git_width() > 0).then(|| {
+            let change = self
+                .buffer
+                .borrow()
+                .git_change(line_idx)
+                .filter(|_| row_in_line == 0);
+            match change {
+                Some(LineChange::Added) => GIT_ADDED,
+                Some(LineChange::Modified) => GIT_MODIFIED,
+                Some(LineChange::Removed) => GIT_REMOVED,
+                None => ' ',
+            }
+        })
+    }
+
+    // Rows continuing a wrapped line get no number. Lines edited since saving get a bar in
+    // place of the space behind the number. The sign of how the line differs from the file
+    // as checked in goes left of the number and the name of a mark left of that, either is a
+    // space on lines without one and None while it takes no column.
+    fn add_gutter(
+        text: &mut AnnotatedString,
+        number: &mut String,
+        line_idx: Option<usize>,
+        number_width: Col,
+        edited: bool,
+        mark: Option<char>,
+        git: Option<char>,
+    ) {
+        if number_width > 0 {
+            let digits = number_width.saturating_sub(1);
+            let separator = if edited { EDITED_MARKER } else { ' ' };
+            number.clear();
+            // writing to a string can't fail
+            let _ = match line_idx {
+                Some(line_idx) => {
+                    write!(number, "{:>digits$}{separator}", line_idx.saturating_add(1))
+                }
+                None => write!(number, "{:>digits$}{separator}", ""),
+            };
+            text.replace(0, 0, number);
+            text.add_annotation(AnnotationType::LineNumber, 0, digits);
+            if edited {
+                text.add_annotation(AnnotationType::EditedLine, digits, number.len());
+            }
+        }
+        if let Some(sign) = git {
+            let mut sign_text = [0; 4];
+            let sign_text = sign.encode_utf8(&mut sign_text);
+            text.replace(0, 0, " ");
+            text.replace(0, 0, sign_text);
+            let annotation_type = match sign {
+                GIT_ADDED => Some(AnnotationType::Inserted),
+                GIT_MODIFIED => Some(AnnotationType::Changed),
+                GIT_REMOVED => Some(AnnotationType::Deleted),
+                _ => None,
+            };
+            if let Some(annotation_type) = annotation_type {
+                text.add_annotation(annotation_type, 0, sign_text.len());
+            }
+        }
+        if let Some(mark) = mark {
+            let mut name = [0; 4];
+            let name = mark.encode_utf8(&mut name);
+            text.replace(0, 0, " ");
+            text.replace(0, 0, name);
+            text.add_annotation(AnnotationType::Mark, 0, name.len());
+        }
+    }
+
+    // The columns of `line` left for text between markers of more of it beyond either edge
+    // of `cols`, and whether there is more before and after. Rows too narrow for a marker
+    // and a column of text get none.
+    fn cols_within_markers(line: &Line, cols: Range<Col>) -> (Range<Col>, (bool, bool)) {
+        if cols.len() < 3 {
+            return (cols, (false, false));
+        }
+        let before = cols.start > 0 && line.width() > 0;
+        let after = line.width() > cols.end;
+        let start = cols.start.saturating_add(usize::from(before));
+        let end = cols.end.saturating_sub(usize::from(after));
+        (start..end, (before, after))
+    }
+
+    // the columns of a line a row shows, within the markers unless lines wrap
+    fn row_cols(
+        &self,
+        line: Option<&Line>,
+        starts: Option<&[Col]>,
+        row_in_line: usize,
+        unwrapped: Range<Col>,
+    ) -> (Range<Col>, (bool, bool)) {
+        match (starts, line) {
+            (Some(starts), _) if self.config.wrap => {
+                (self.wrapped_row_cols(starts, row_in_line), (false, false))
+            }
+            (_, Some(line)) => Self::cols_within_markers(line, unwrapped),
+            _ => (unwrapped, (false, false)),
+        }
+    }
+
+    // the number of markers added, each takes a column
+    fn add_scroll_markers(text: &mut AnnotatedString, (before, after): (bool, bool)) -> Col {
+        if before {
+            text.replace(0, 0, MORE_BEFORE);
+            text.add_annotation(AnnotationType::ScrollMarker, 0, MORE_BEFORE.len());
+        }
+        if after {
+            let start = text.as_str().len();
+            text.push_str(MORE_AFTER);
+            text.add_annotation(AnnotationType::ScrollMarker, start, text.as_str().len());
+        }
+        usize::from(before).saturating_add(usize::from(after))
+    }
+
+    // pad `text` up to the scrollbar at `col`, filled on the rows of the thumb
+    fn add_scrollbar(text: &mut AnnotatedString, filled: Col, col: Col, thumb: bool) {
+        for _ in filled..col {
+            text.push_str(" ");
+        }
+        let start = text.as_str().len();
+        text.push_str(if thumb {
+            SCROLLBAR_THUMB
+        } else {
+            SCROLLBAR_TRACK
+        });
+        text.add_annotation(AnnotationType::LineNumber, start, text.as_str().len());
+    }
+
+    // the name and version, with a hint of the most important keys one row below
+    fn build_welcome_message(line: usize, width: usize) -> String {
+        let text = if line == 0 {
+            format!("{NAME} editor -- version {VERSION}")
+        } else {
+            String::from("Press Ctrl-F to search, Ctrl-S to save, Ctrl-T to quit")
+        };
         if width == 0 {
             return String::new();
         }
 
-        let welcome_message = format!("{NAME} editor -- version {VERSION}");
-
-        let len = welcome_message.len();
+        // centered after the tilde, cut short with an ellipsis if it doesn't fit
         let remaining_width = width.saturating_sub(1);
-        if remaining_width <= len {
-            return "~".to_string();
+        let text = Line::from(&text);
+        let text = if text.width() <= remaining_width {
+            text.to_string()
+        } else if remaining_width == 0 {
+            String::new()
+        } else {
+            let mut text = text.get_visible_graphemes(0..remaining_width.saturating_sub(1));
+            text.push('…');
+            text
+        };
+        let padding = remaining_width
+            .saturating_sub(Line::from(&text).width())
+            .div_euclid(2);
+        format!("~{}{text}", " ".repeat(padding))
+    }
+
+    // the misspelled words of `line` and its syntax highlights, which long lines go without
+    fn line_styles(&self, line: &Line, state: State, is_long: bool) -> LineStyles {
+        if is_long {
+            return (None, None);
+        }
+        let misspelled = self
+            .rendered_spelling
+            .then(|| self.spelling.misspelled(line))
+            .flatten();
+        (misspelled, self.highlighter.highlights(line, state))
+    }
+
+    // the welcome message in an empty buffer, starting a third down the view, or a tilde
+    fn filler_row(buffer: &Buffer, current_row: usize, top_third: usize) -> RenderedRow {
+        if buffer.is_empty()
+            && !buffer.is_loading()
+            && let Some(welcome_line) = current_row
+                .checked_sub(top_third)
+                .filter(|welcome_line| *welcome_line < 2)
+        {
+            RenderedRow::Welcome(welcome_line)
+        } else {
+            RenderedRow::Empty
         }
+    }
 
-        format!("{:<1}{:^remaining_width$}", "~", welcome_message)
+    // the welcome message or a tilde past the end of the buffer, next to the scrollbar at the
+    // column given, which is filled on the rows of its thumb
+    fn draw_filler_row(
+        terminal: &mut dyn TerminalIo,
+        current_row: usize,
+        row: &RenderedRow,
+        width: Col,
+        text: &mut AnnotatedString,
+        scrollbar: Option<(Col, bool)>,
+    ) -> Result<(), std::io::Error> {
+        if let RenderedRow::Welcome(welcome_line) = row {
+            // render welcome message if no file is opened
+            let message = Self::build_welcome_message(*welcome_line, width);
+            Self::render_line(terminal, current_row, &message)
+        } else if let Some((col, thumb)) = scrollbar {
+            text.reset("~");
+            Self::add_scrollbar(text, 1, col, thumb);
+            terminal.print_annotated_row(current_row, text)
+        } else {
+            // else render tilde at empty lines
+            Self::render_line(terminal, current_row, "~")
+        }
     }
 }
 
@@ -372,45 +2110,412 @@ impl UIComponent for View {
 
     fn set_size(&mut self, size: Size) {
         self.size = size;
+        // whatever was drawn before is no longer known to be on screen
+        self.rendered_rows.clear();
         self.scroll_text_location_into_view();
     }
 
-    fn draw(&mut self, origin_row: usize) -> Result<(), std::io::Error> {
+    fn draw(
+        &mut self,
+        terminal: &mut dyn TerminalIo,
+        origin_row: usize,
+    ) -> Result<(), std::io::Error> {
         let Size { height, width } = self.size;
         let end_y = origin_row.saturating_add(height);
+        let gutter_width = self.gutter_width();
+        let number_width = self.number_width();
 
         let top_third = height.div_ceil(3); // a good position to put our welcome message
-        let scroll_top = self.scroll_offset.row;
+        let mut at = self.scroll_top();
+        let left = self.scroll_offset.col;
+        let right = left.saturating_add(self.text_width());
+        let thumb = self.scrollbar_thumb();
+        self.update_caret_word();
+        self.forget_outdated_rows(left, gutter_width, thumb.as_ref());
+        self.rendered_rows.resize(height, None);
+        let buffer = self.buffer.borrow();
+        let search_info = self.search_info.as_ref();
+        let query = search_info
+            .and_then(SearchInfo::query)
+            .map(|query| &**query);
+        // the search highlight wins over the word under the caret
+        let word = query.is_none().then(|| self.caret_word.word()).flatten();
+
+        // the line typing into an empty buffer creates, drawn like the empty line it will be
+        let first_line = Line::default();
+        let first_line = (buffer.is_empty() && !buffer.is_loading()).then_some(&first_line);
 
         for current_row in origin_row..end_y {
-            // to get the correct line idx, we have to take current_row (the absolute row on
-            // screen), subtract origin_row to get the current row relative to the view (ranging from
-            // 0 to self.size.height) and add the scroll offset
-            let line_idx = current_row
-                .saturating_sub(origin_row)
-                .saturating_add(scroll_top);
-            if let Some(line) = self.buffer.lines.get(line_idx) {
-                let left = self.scroll_offset.col;
-                let right = self.scroll_offset.col.saturating_add(width);
-                let query = self
-                    .search_info
-                    .as_ref()
-                    .and_then(|search_info| search_info.query.as_deref());
-                let selected_match = (self.text_location.line_idx == line_idx && query.is_some())
-                    .then_some(self.text_location.grapheme_idx);
-                Terminal::print_annotated_row(
-                    current_row,
-                    &line.get_annotated_visible_substr(left..right, query, selected_match),
-                )?;
-            } else if (current_row == top_third) && self.buffer.is_empty() {
-                // render welcome message if no file is opened
-                Self::render_line(current_row, &Self::build_welcome_message(width))?;
+            // each row shows the next row of a wrapped line, or the next line
+            let VisualRow {
+                line_idx,
+                row: row_in_line,
+            } = at;
+            let line = buffer
+                .lines
+                .get(line_idx)
+                .or(first_line.filter(|_| line_idx == 0));
+            // highlighting long lines would take longer than drawing them
+            let is_long = line.is_some_and(|line| line.len() > self.config.long_line_bytes);
+            let query = query.filter(|_| {
+                !is_long && search_info.is_some_and(|search_info| search_info.covers_line(line_idx))
+            });
+            let word = word.filter(|_| !is_long);
+            let selected_match = (self.text_location.line_idx == line_idx && query.is_some())
+                .then_some(self.text_location.grapheme_idx);
+            let starts = line.map(|line| self.row_starts(line));
+            at = at.next(starts.as_ref().map_or(0, |starts| starts.len()));
+            let (cols, markers) = self.row_cols(line, starts.as_deref(), row_in_line, left..right);
+            let (mark, git) = self.gutter_signs(line_idx, row_in_line);
+            let state = self.highlighter.state_at(&*buffer.lines, line_idx);
+            let row = if let Some(line) = line {
+                RenderedRow::Line {
+                    line_idx,
+                    row_in_line,
+                    revision: line.revision(),
+                    state,
+                    selected_match,
+                    edited: line.is_edited(),
+                    mark,
+                    git,
+                }
             } else {
-                // else render tilde at empty lines
-                Self::render_line(current_row, "~")?;
+                Self::filler_row(&buffer, current_row, top_third)
+            };
+
+            let row_idx = current_row.saturating_sub(origin_row);
+            if self.rendered_rows.get(row_idx) == Some(&Some(row)) {
+                continue;
+            }
+
+            // the column of the scrollbar, and whether it's filled on this row
+            let scrollbar = thumb
+                .as_ref()
+                .map(|thumb| (width.saturating_sub(1), thumb.contains(&row_idx)));
+            if let Some(line) = line {
+                let matches = search_info
+                    .filter(|_| query.is_some())
+                    .map(|search_info| search_info.matches(line));
+                let (misspelled, highlights) = self.line_styles(line, state, is_long);
+                let options = RenderOptions {
+                    invisibles: self.config.invisibles,
+                    word,
+                    matches: matches.as_deref(),
+                    misspelled: misspelled.as_deref(),
+                    highlights: highlights.as_deref(),
+                    scroll_markers: markers,
+                };
+                let text = &mut self.row_text;
+                let filled = line.visible_width(cols.clone(), self.config.invisibles);
+                line.annotate_visible_substr(text, cols, query, selected_match, options);
+                let filled = filled.saturating_add(Self::add_scroll_markers(text, markers));
+                let number = (row_in_line == 0).then_some(line_idx);
+                let (row_number, edited) = (&mut self.row_number, line.is_edited());
+                Self::add_gutter(text, row_number, number, number_width, edited, mark, git);
+                if let Some((col, thumb)) = scrollbar {
+                    let filled = gutter_width.saturating_add(filled);
+                    Self::add_scrollbar(text, filled, col, thumb);
+                }
+                terminal.print_annotated_row(current_row, text)?;
+            } else {
+                let text = &mut self.row_text;
+                Self::draw_filler_row(terminal, current_row, &row, width, text, scrollbar)?;
+            }
+            if let Some(rendered_row) = self.rendered_rows.get_mut(row_idx) {
+                *rendered_row = Some(row);
             }
         }
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::counting_alloc::allocations;
+
+    // a terminal which forgets everything, so only what drawing itself allocates is counted
+    struct Discard;
+
+    impl TerminalIo for Discard {
+        fn initialize(&mut self) -> Result<(), std::io::Error> {
+            Ok(())
+        }
+
+        fn terminate(&mut self) -> Result<(), std::io::Error> {
+            Ok(())
+        }
+
+        fn clear_screen(&mut self) -> Result<(), std::io::Error> {
+            Ok(())
+        }
+
+        fn begin_synchronized_update(&mut self) -> Result<(), std::io::Error> {
+            Ok(())
+        }
+
+        fn end_synchronized_update(&mut self) -> Result<(), std::io::Error> {
+            Ok(())
+        }
+
+        fn move_caret_to(&mut self, _p: &Position) -> Result<(), std::io::Error> {
+            Ok(())
+        }
+
+        fn hide_caret(&mut self) -> Result<(), std::io::Error> {
+            Ok(())
+        }
+
+        fn show_caret(&mut self) -> Result<(), std::io::Error> {
+            Ok(())
+        }
+
+        fn set_title(&mut self, _title: &str) -> Result<(), std::io::Error> {
+            Ok(())
+        }
+
+        fn set_mouse_capture(&mut self, _enabled: bool) -> Result<(), std::io::Error> {
+            Ok(())
+        }
+
+        fn print(&mut self, _s: &str) -> Result<(), std::io::Error> {
+            Ok(())
+        }
+
+        fn print_row(&mut self, _row: usize, _line_text: &str) -> Result<(), std::io::Error> {
+            Ok(())
+        }
+
+        fn print_inverted_row(
+            &mut self,
+            _row: usize,
+            _line_text: &str,
+        ) -> Result<(), std::io::Error> {
+            Ok(())
+        }
+
+        fn print_annotated_row(
+            &mut self,
+            _row: usize,
+            _annotated_string: &AnnotatedString,
+        ) -> Result<(), std::io::Error> {
+            Ok(())
+        }
+
+        fn size(&self) -> Result<Size, std::io::Error> {
+            Ok(Size::default())
+        }
+
+        fn execute(&mut self) -> Result<(), std::io::Error> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn scrolling_back_draws_without_allocating() {
+        let text = (0..100).fold(String::new(), |mut text, idx| {
+            let _ = writeln!(text, "line {idx}\twith 12 digits: 123456789");
+            text
+        });
+        for (wrap, show_line_numbers, scrollbar) in [
+            (false, false, false),
+            (false, true, false),
+            (true, true, false),
+            (false, true, true),
+            (true, false, true),
+        ] {
+            let mut view = View::default();
+            view.config.wrap = wrap;
+            view.config.show_line_numbers = show_line_numbers;
+            view.config.scrollbar = scrollbar;
+            view.replace_text(&text);
+            view.set_size(Size {
+                height: 10,
+                width: 20,
+            });
+            let mut terminal = Discard;
+            let mut scroll_to = |view: &mut View, row| {
+                view.scroll_offset.row = row;
+                view.draw(&mut terminal, 0).unwrap();
+            };
+            // the first time a line is shown, what is kept of it is built
+            for row in 0..=100 {
+                scroll_to(&mut view, row);
+            }
+            let count = allocations(|| {
+                for row in (0..=100).rev().chain(0..=100) {
+                    scroll_to(&mut view, row);
+                }
+            });
+            assert_eq!(
+                count, 0,
+                "wrap: {wrap}, line numbers: {show_line_numbers}, scrollbar: {scrollbar}"
+            );
+        }
+    }
+
+    #[test]
+    fn loading_looks_only_at_the_visible_lines() {
+        let path = std::env::temp_dir().join("hecto-lazy-lines.txt");
+        let text = "Löwe 老虎\tline\n".repeat(500_000);
+        std::fs::write(&path, &text).unwrap();
+
+        let start = std::time::Instant::now();
+        let mut view = View::default();
+        view.load(&path.to_string_lossy(), Encoding::Utf8).unwrap();
+        view.set_size(Size {
+            height: 10,
+            width: 40,
+        });
+        view.draw(&mut Discard, 0).unwrap();
+        let status = view.get_status();
+        view.caret_position();
+        assert!(start.elapsed() < std::time::Duration::from_secs(2));
+        assert_eq!(status.total_lines, 500_000);
+        let buffer = view.buffer.borrow();
+        let looked_at = buffer.lines.iter().filter(|line| line.has_fragments());
+        assert!(looked_at.count() <= 10);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn deleting_at_the_ends_of_the_buffer() {
+        type Before = (&'static str, (usize, usize), Edit);
+        type After = (&'static [&'static str], (usize, usize), bool);
+        // the lines after `edit` at `caret`, where the caret ends up, and whether the
+        // buffer changed
+        let edited = |text: &str, caret: (usize, usize), edit: Edit| {
+            let mut view = View::default();
+            view.replace_text(text);
+            view.text_location = Location {
+                line_idx: caret.0,
+                grapheme_idx: caret.1,
+            };
+            let revision = view.buffer.borrow().revision();
+            view.handle_edit_command(edit);
+            let Location {
+                line_idx,
+                grapheme_idx,
+            } = view.caret_location();
+            let changed = view.buffer.borrow().revision() != revision;
+            (view.lines(), (line_idx, grapheme_idx), changed)
+        };
+        // the text, the caret and the edit there, then the lines, the caret and whether
+        // anything changed after it
+        let cases: [(Before, After); 16] = [
+            // an empty buffer
+            (("", (0, 0), Edit::Delete), (&[], (0, 0), false)),
+            (("", (0, 0), Edit::DeleteBackward), (&[], (0, 0), false)),
+            // a single empty line
+            (("\n", (0, 0), Edit::Delete), (&[""], (0, 0), false)),
+            (("\n", (0, 0), Edit::DeleteBackward), (&[""], (0, 0), false)),
+            // the start of the buffer
+            (
+                ("ab\ncd", (0, 0), Edit::Delete),
+                (&["b", "cd"], (0, 0), true),
+            ),
+            (
+                ("ab\ncd", (0, 0), Edit::DeleteBackward),
+                (&["ab", "cd"], (0, 0), false),
+            ),
+            // the end of a line joins the next one
+            (("ab\ncd", (0, 2), Edit::Delete), (&["abcd"], (0, 2), true)),
+            (
+                ("ab\ncd", (1, 0), Edit::DeleteBackward),
+                (&["abcd"], (0, 2), true),
+            ),
+            // the last character of the last line
+            (
+                ("ab\ncd", (1, 1), Edit::Delete),
+                (&["ab", "c"], (1, 1), true),
+            ),
+            (
+                ("ab\ncd", (1, 1), Edit::DeleteBackward),
+                (&["ab", "d"], (1, 0), true),
+            ),
+            // the end of the last line
+            (
+                ("ab\ncd", (1, 2), Edit::Delete),
+                (&["ab", "cd"], (1, 2), false),
+            ),
+            (
+                ("ab\ncd", (1, 2), Edit::DeleteBackward),
+                (&["ab", "c"], (1, 1), true),
+            ),
+            // behind the last line, e.g. after another view removed lines
+            (
+                ("ab\ncd", (2, 0), Edit::Delete),
+                (&["ab", "cd"], (1, 2), false),
+            ),
+            (
+                ("ab\ncd", (2, 0), Edit::DeleteBackward),
+                (&["ab", "cd"], (1, 2), false),
+            ),
+            (
+                ("ab\ncd", (5, 3), Edit::Delete),
+                (&["ab", "cd"], (1, 2), false),
+            ),
+            (
+                ("ab\n\n", (2, 0), Edit::DeleteBackward),
+                (&["ab", ""], (1, 0), false),
+            ),
+        ];
+        for (case, ((text, caret, edit), (lines, expected_caret, changed))) in
+            cases.into_iter().enumerate()
+        {
+            assert_eq!(
+                edited(text, caret, edit),
+                (
+                    lines.iter().map(ToString::to_string).collect(),
+                    expected_caret,
+                    changed
+                ),
+                "case {case}"
+            );
+        }
+    }
+
+    #[test]
+    fn the_caret_stays_within_the_buffer() {
+        // moving by rows when lines wrap agrees with moving by lines
+        for wrap in [false, true] {
+            let mut view = View::default();
+            view.config.wrap = wrap;
+            view.replace_text("ab\ncd\n");
+            for command in [Move::Down, Move::Down, Move::Right, Move::PageDown] {
+                view.handle_move_command(command);
+            }
+            assert_eq!(
+                view.caret_location(),
+                Location {
+                    line_idx: 1,
+                    grapheme_idx: 1
+                },
+                "wrap: {wrap}"
+            );
+            view.handle_move_command(Move::EndOfLine);
+            view.handle_move_command(Move::Right);
+            assert_eq!(view.caret_location().line_idx, 1);
+        }
+    }
+
+    #[test]
+    fn welcome_message_fits_any_width() {
+        let message = format!("{NAME} editor -- version {VERSION}");
+        let width = message.chars().count().saturating_add(1);
+        assert_eq!(View::build_welcome_message(0, 0), "");
+        assert_eq!(View::build_welcome_message(0, 1), "~");
+        assert_eq!(View::build_welcome_message(0, 2), "~…");
+        assert_eq!(View::build_welcome_message(0, width), format!("~{message}"));
+        assert_eq!(
+            View::build_welcome_message(0, width.saturating_add(4)),
+            format!("~  {message}")
+        );
+
+        let cut = View::build_welcome_message(0, width.saturating_sub(1));
+        assert!(cut.ends_with('…'));
+        assert_eq!(cut.chars().count(), width.saturating_sub(1));
+        assert!(View::build_welcome_message(1, 80).contains("Ctrl-F to search"));
+    }
+}
@@ -0,0 +1,23 @@
+use crate::editor::{line::Revision, syntax::State};
+
+// what a screen row showed when it was last drawn
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum RenderedRow {
+    Line {
+        line_idx: usize,
+        // which of the rows of a wrapped line
+        row_in_line: usize,
+        revision: Revision,
+        // what the lines above tell about its syntax
+        state: State,
+        selected_match: Option<usize>,
+        // the gutter marks lines edited since saving, the name of a mark on the line and how
+        // it differs from the file as checked in
+        edited: bool,
+        mark: Option<char>,
+        git: Option<char>,
+    },
+    // the line of the welcome message
+    Welcome(usize),
+    Empty,
+}
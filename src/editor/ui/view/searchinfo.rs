@@ -1,7 +1,63 @@
 use super::location::Location;
-use crate::editor::line::Line;
+use crate::editor::line::{Line, Revision};
+use std::{cell::RefCell, collections::HashMap, ops::Range, rc::Rc};
+
+// lines whose matches are remembered before the cache starts over
+const MAX_CACHED_MATCHES: usize = 4_096;
 
 pub struct SearchInfo {
     pub previous_location: Location,
-    pub query: Option<Line>,
+    query: Option<Line>,
+    // matches must lie within these if set
+    pub bounds: Option<Range<Location>>,
+    // Where the matches of `query` start, by line revision. Every edit gives a line a new
+    // revision, so the matches of a line as it was are never looked up again.
+    matches: RefCell<HashMap<Revision, Rc<[usize]>>>,
+}
+
+impl SearchInfo {
+    pub fn new(previous_location: Location) -> Self {
+        Self {
+            previous_location,
+            query: None,
+            bounds: None,
+            matches: RefCell::default(),
+        }
+    }
+
+    pub const fn query(&self) -> Option<&Line> {
+        self.query.as_ref()
+    }
+
+    pub fn set_query(&mut self, query: &str) {
+        if self.query.as_deref() != Some(query) {
+            self.query = Some(Line::from(query));
+            self.matches.get_mut().clear();
+        }
+    }
+
+    // whether matches on the line are within the bounds, at least partly
+    pub fn covers_line(&self, line_idx: usize) -> bool {
+        self.bounds.as_ref().is_none_or(|bounds| {
+            bounds.start.line_idx <= line_idx
+                && (line_idx < bounds.end.line_idx
+                    || line_idx == bounds.end.line_idx && bounds.end.grapheme_idx > 0)
+        })
+    }
+
+    // the grapheme indices where matches of the query start on `line`
+    pub fn matches(&self, line: &Line) -> Rc<[usize]> {
+        let Some(query) = self.query.as_ref().filter(|query| !query.is_empty()) else {
+            return Rc::from([].as_slice());
+        };
+        let mut matches = self.matches.borrow_mut();
+        if matches.len() >= MAX_CACHED_MATCHES {
+            matches.clear();
+        }
+        Rc::clone(
+            matches
+                .entry(line.revision())
+                .or_insert_with(|| Rc::from(line.find_all_graphemes(query))),
+        )
+    }
 }
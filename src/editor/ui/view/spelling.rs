@@ -0,0 +1,45 @@
+use crate::editor::{
+    line::{Line, Revision},
+    spell::Dictionary,
+};
+use std::{cell::RefCell, collections::HashMap, ops::Range, rc::Rc};
+
+// lines whose misspelled words are remembered before the cache starts over
+const MAX_CACHED_LINES: usize = 4_096;
+
+// The words a view underlines, found with the dictionary shared by all views.
+#[derive(Clone, Default)]
+pub struct Spelling {
+    dictionary: Option<Rc<RefCell<Dictionary>>>,
+    // The byte ranges of the misspelled words, by line revision. Like the matches of a search,
+    // lines are only checked once they are drawn.
+    misspelled: RefCell<HashMap<Revision, Rc<[Range<usize>]>>>,
+}
+
+impl Spelling {
+    pub fn set_dictionary(&mut self, dictionary: &Rc<RefCell<Dictionary>>) {
+        self.dictionary = Some(Rc::clone(dictionary));
+        self.forget();
+    }
+
+    pub fn dictionary(&self) -> Option<&Rc<RefCell<Dictionary>>> {
+        self.dictionary.as_ref()
+    }
+
+    // after the dictionary learned words, which may have been misspelled until now
+    pub fn forget(&mut self) {
+        self.misspelled.get_mut().clear();
+    }
+
+    // None without a dictionary
+    pub fn misspelled(&self, line: &Line) -> Option<Rc<[Range<usize>]>> {
+        let dictionary = self.dictionary.as_ref()?;
+        let mut misspelled = self.misspelled.borrow_mut();
+        if misspelled.len() >= MAX_CACHED_LINES {
+            misspelled.clear();
+        }
+        Some(Rc::clone(misspelled.entry(line.revision()).or_insert_with(
+            || Rc::from(dictionary.borrow().misspelled(line)),
+        )))
+    }
+}
@@ -0,0 +1,67 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+// counts of the text of a buffer, or of the part of it before the caret
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Statistics {
+    pub lines: usize,
+    pub words: usize,
+    pub graphemes: usize,
+    pub bytes: usize,
+}
+
+// the size of a buffer's text as it was read, worked out while loading it
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TextSize {
+    // without line endings
+    pub bytes: usize,
+    pub chars: usize,
+    // the index and the length in bytes of the longest line, None without lines
+    pub longest_line: Option<(usize, usize)>,
+    pub lines: usize,
+}
+
+impl TextSize {
+    // count `text`, the line after the ones added before
+    pub fn add_line(&mut self, text: &str) {
+        self.bytes = self.bytes.saturating_add(text.len());
+        self.chars = self.chars.saturating_add(text.chars().count());
+        if self
+            .longest_line
+            .is_none_or(|(_, longest)| text.len() > longest)
+        {
+            self.longest_line = Some((self.lines, text.len()));
+        }
+        self.lines = self.lines.saturating_add(1);
+    }
+}
+
+impl Statistics {
+    // count `text` of a line, without its line ending
+    pub fn add_text(&mut self, text: &str) {
+        self.words = self.words.saturating_add(text.unicode_words().count());
+        self.graphemes = self.graphemes.saturating_add(text.graphemes(true).count());
+        self.bytes = self.bytes.saturating_add(text.len());
+    }
+
+    // a line ending counts as a grapheme like it does in the file
+    pub fn add_line_ending(&mut self, line_ending: &str) {
+        self.lines = self.lines.saturating_add(1);
+        self.graphemes = self.graphemes.saturating_add(1);
+        self.bytes = self.bytes.saturating_add(line_ending.len());
+    }
+
+    // e.g. "Line 3 of 10, word 12 of 54, ..." for the counts up to the caret
+    pub fn describe(&self, total: &Self) -> String {
+        format!(
+            "Line {} of {}, word {} of {}, grapheme {} of {}, byte {} of {}",
+            self.lines,
+            total.lines,
+            self.words,
+            total.words,
+            self.graphemes,
+            total.graphemes,
+            self.bytes,
+            total.bytes
+        )
+    }
+}
@@ -0,0 +1,23 @@
+// a row on screen, the `row`-th of the rows the line at `line_idx` wraps into
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct VisualRow {
+    pub line_idx: usize,
+    pub row: usize,
+}
+
+impl VisualRow {
+    // the row below, on the next line once the line's `row_count` rows are used up
+    pub const fn next(self, row_count: usize) -> Self {
+        if self.row.saturating_add(1) < row_count {
+            Self {
+                line_idx: self.line_idx,
+                row: self.row.saturating_add(1),
+            }
+        } else {
+            Self {
+                line_idx: self.line_idx.saturating_add(1),
+                row: 0,
+            }
+        }
+    }
+}
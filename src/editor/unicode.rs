@@ -0,0 +1,80 @@
+// characters which can be inserted by name instead of by code point
+const NAMES: [(&str, char); 16] = [
+    ("emdash", '\u{2014}'),
+    ("endash", '\u{2013}'),
+    ("ellipsis", '\u{2026}'),
+    ("bullet", '\u{2022}'),
+    ("nbsp", '\u{a0}'),
+    ("shy", '\u{ad}'),
+    ("zwsp", '\u{200b}'),
+    ("zwnj", '\u{200c}'),
+    ("zwj", '\u{200d}'),
+    ("bom", '\u{feff}'),
+    ("copy", '\u{a9}'),
+    ("reg", '\u{ae}'),
+    ("trade", '\u{2122}'),
+    ("deg", '\u{b0}'),
+    ("euro", '\u{20ac}'),
+    ("section", '\u{a7}'),
+];
+
+// the character typed as a hex code point like "U+2014" or "2014", or by one of `NAMES`.
+// The error is a message to show in the message bar.
+pub fn parse_char(input: &str) -> Result<char, String> {
+    let input = input.trim();
+    if let Some((_, ch)) = NAMES
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(input))
+    {
+        return Ok(*ch);
+    }
+    let hex = input
+        .strip_prefix("U+")
+        .or_else(|| input.strip_prefix("u+"))
+        .unwrap_or(input);
+    if hex.is_empty() || !hex.chars().all(|ch| ch.is_ascii_hexdigit()) {
+        return Err(format!("Unknown character: {input}"));
+    }
+    let code = u32::from_str_radix(hex, 16)
+        .ok()
+        .filter(|code| *code <= u32::from(char::MAX))
+        .ok_or_else(|| format!("Out of the Unicode range: U+{hex}"))?;
+    char::from_u32(code).ok_or_else(|| format!("A surrogate is no character: U+{code:04X}"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_code_points_and_names() {
+        assert_eq!(parse_char("U+2014"), Ok('—'));
+        assert_eq!(parse_char("2014"), Ok('—'));
+        assert_eq!(parse_char(" u+1f600 "), Ok('😀'));
+        assert_eq!(parse_char("301"), Ok('\u{301}'));
+        assert_eq!(parse_char("EmDash"), Ok('—'));
+        assert_eq!(parse_char("zwj"), Ok('\u{200d}'));
+    }
+
+    #[test]
+    fn rejects_what_is_no_character() {
+        assert_eq!(
+            parse_char("D800"),
+            Err(String::from("A surrogate is no character: U+D800"))
+        );
+        assert_eq!(
+            parse_char("110000"),
+            Err(String::from("Out of the Unicode range: U+110000"))
+        );
+        assert_eq!(
+            parse_char("ffffffffff"),
+            Err(String::from("Out of the Unicode range: U+ffffffffff"))
+        );
+        assert_eq!(
+            parse_char("dash"),
+            Err(String::from("Unknown character: dash"))
+        );
+        assert!(parse_char("U+").is_err());
+        assert!(parse_char("+41").is_err());
+    }
+}
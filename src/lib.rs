@@ -0,0 +1,43 @@
+//! The text handling of the hecto editor, usable without a terminal.
+//!
+//! A [`Buffer`] holds the lines of a text, edited by [`Edit`] commands at a [`Location`]. Each
+//! [`Line`] renders any window of its columns to an [`AnnotatedString`], whose parts carry the
+//! [`AnnotationType`] to colour them with:
+//!
+//! ```
+//! use hecto::{AnnotationType, Buffer, Edit, Location, RenderOptions};
+//!
+//! let mut buffer = Buffer::from_text("fn main() {}\n");
+//! let caret = buffer.apply(Edit::InsertNewline, &Location::default())?;
+//! let caret = buffer.apply(Edit::Insert('1'), &caret)?;
+//! assert_eq!(buffer.get_height(), 2);
+//!
+//! let line = buffer.line(caret.line_idx).unwrap();
+//! let row = line.get_annotated_visible_substr(0..80, None, None, RenderOptions::default());
+//! let parts: Vec<_> = row.into_iter().map(|part| (part.string, part.typ)).collect();
+//! assert_eq!(parts, [("1", Some(AnnotationType::Digit)), ("fn main() {}", None)]);
+//! # Ok::<(), hecto::LocationError>(())
+//! ```
+//!
+//! [`Editor`] is the whole editor on the terminal, as the `hecto` binary runs it.
+#![warn(
+    clippy::all,
+    clippy::pedantic,
+    clippy::print_stdout,
+    clippy::arithmetic_side_effects,
+    clippy::integer_division
+)]
+
+#[cfg(test)]
+mod counting_alloc;
+pub mod dirs;
+mod editor;
+
+pub use editor::annotated_string::{AnnotatedString, AnnotatedStringPart, AnnotationType};
+pub use editor::command::{Command, Edit, Move, System};
+pub use editor::line::{
+    AmbiguousWidth, CellWidths, IndexError, Invisibles, Line, LineBreakError, RenderOptions,
+};
+pub use editor::size::Size;
+pub use editor::ui::{Buffer, Location, LocationError};
+pub use editor::{DEFAULT_AUTOSAVE_DELAY, Editor, NAME, VERSION, parse_script, session_path};
@@ -0,0 +1,77 @@
+use hecto::dirs;
+use log::{LevelFilter, Log, Metadata, Record};
+use std::{
+    env,
+    fs::{self, File, OpenOptions},
+    io::Write,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+// Anything written to stderr would garble the alternate screen, so log messages go to
+// a file instead. Logging is off unless enabled with `HECTO_LOG=<level>` or `--log`.
+struct FileLogger {
+    file: Mutex<File>,
+}
+
+impl Log for FileLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let since_epoch = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        if let Ok(mut file) = self.file.lock() {
+            let _ = writeln!(
+                file,
+                "{}.{:03} {:<5} {}: {}",
+                since_epoch.as_secs(),
+                since_epoch.subsec_millis(),
+                record.level(),
+                record.target(),
+                record.args()
+            );
+        }
+    }
+
+    fn flush(&self) {
+        if let Ok(mut file) = self.file.lock() {
+            let _ = file.flush();
+        }
+    }
+}
+
+// the level set with `HECTO_LOG`, or debug if only `--log` was given
+fn level(log_flag: bool) -> LevelFilter {
+    env::var("HECTO_LOG")
+        .ok()
+        .and_then(|level| level.parse().ok())
+        .unwrap_or(if log_flag {
+            LevelFilter::Debug
+        } else {
+            LevelFilter::Off
+        })
+}
+
+pub fn init(log_flag: bool) -> Result<(), std::io::Error> {
+    let level = level(log_flag);
+    if level == LevelFilter::Off {
+        return Ok(());
+    }
+    let dir = dirs::cache_dir().ok_or_else(|| std::io::Error::other("no cache directory"))?;
+    fs::create_dir_all(&dir)?;
+    let path = dir.join("hecto.log");
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+
+    log::set_boxed_logger(Box::new(FileLogger {
+        file: Mutex::new(file),
+    }))
+    .map_err(std::io::Error::other)?;
+    log::set_max_level(level);
+    Ok(())
+}
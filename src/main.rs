@@ -6,9 +6,77 @@
     clippy::integer_division
 )]
 
-mod editor;
-use editor::Editor;
+mod cli;
+mod logger;
+use hecto::{Editor, NAME, VERSION, parse_script, session_path};
+use std::{
+    env, fs,
+    io::{self, Write},
+    process,
+};
 
 fn main() {
-    Editor::new().unwrap().run();
+    let args = cli::parse(env::args().skip(1)).unwrap_or_else(|err| {
+        eprintln!("hecto: {err}");
+        process::exit(2);
+    });
+    if args.help || args.version {
+        let text = if args.help {
+            cli::USAGE.to_string()
+        } else {
+            format!("{NAME} {VERSION}\n")
+        };
+        let _ = io::stdout().write_all(text.as_bytes());
+        return;
+    }
+    let session = args.session.as_ref().map(|session| {
+        session_path(session.name()).unwrap_or_else(|| {
+            eprintln!("hecto: --session needs $HOME or $XDG_STATE_HOME to be set");
+            process::exit(2);
+        })
+    });
+
+    if let Err(err) = logger::init(args.log) {
+        eprintln!("hecto: could not open the log file: {err}");
+    }
+
+    let events = args.script.as_ref().map_or_else(
+        || Ok(Vec::new()),
+        |path| {
+            fs::read_to_string(path)
+                .map_err(|err| err.to_string())
+                .and_then(|script| parse_script(&script))
+                .map_err(|err| format!("hecto: --script '{path}': {err}"))
+        },
+    );
+    let events = events.unwrap_or_else(|err| {
+        eprintln!("{err}");
+        process::exit(2);
+    });
+
+    // read before the terminal is set up, keys are read from the terminal itself then
+    let stdin = args.reads_stdin().then(|| {
+        io::read_to_string(io::stdin()).unwrap_or_else(|err| {
+            eprintln!("hecto: could not read stdin: {err}");
+            process::exit(1);
+        })
+    });
+
+    let mut editor = Editor::new(&args.files, stdin.as_deref(), args.config, session).unwrap();
+    if args.read_only {
+        editor.set_read_only();
+    }
+    if let Some(line) = args.line {
+        editor.start_at_line(line);
+    }
+    if let Some(delay) = args.autosave {
+        editor.enable_autosave(delay);
+    }
+    editor.run_with_events(events);
+    editor.run();
+    // like a shell reports a process killed by a signal
+    if let Some(signal) = editor.terminated_by() {
+        drop(editor);
+        process::exit(signal.saturating_add(128));
+    }
 }
@@ -0,0 +1,34 @@
+//! The hot paths of `benches/hot_paths.rs` on inputs a tenth of the size. They only fail if
+//! one got an order of magnitude slower, far above what any takes in a debug build.
+#![warn(
+    clippy::all,
+    clippy::pedantic,
+    clippy::arithmetic_side_effects,
+    clippy::integer_division
+)]
+
+#[path = "../benches/cases/mod.rs"]
+mod cases;
+
+use cases::{CASES, corpus};
+use std::time::Duration;
+
+const SMOKE_BOUND: Duration = Duration::from_mins(1);
+
+#[test]
+fn corpus_is_deterministic_and_mixed() {
+    let text = corpus(7, 1_000, Some(50));
+    assert_eq!(text, corpus(7, 1_000, Some(50)));
+    assert_ne!(text, corpus(8, 1_000, Some(50)));
+    assert!(text.contains('漢') && text.contains('👍') && text.contains("lorem"));
+    assert!(text.lines().all(|line| line.len() < 80));
+    assert!(!corpus(7, 1_000, None).contains('\n'));
+}
+
+#[test]
+fn hot_paths_are_not_blundered() {
+    for (name, case) in CASES {
+        let duration = case(1);
+        assert!(duration < SMOKE_BOUND, "{name} took {duration:?}");
+    }
+}
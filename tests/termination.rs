@@ -0,0 +1,126 @@
+//! A hecto process told to terminate by a signal, run on a pseudo terminal as it needs one.
+#![cfg(unix)]
+#![warn(
+    clippy::all,
+    clippy::pedantic,
+    clippy::arithmetic_side_effects,
+    clippy::integer_division
+)]
+
+use std::{
+    env,
+    fs::{self, File},
+    io::Read,
+    os::fd::{FromRawFd, OwnedFd},
+    process::{Child, Command, ExitStatus},
+    ptr,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+// far longer than hecto takes to start, even asking a terminal which never answers
+const TIMEOUT: Duration = Duration::from_secs(20);
+
+// the controlling and the terminal end of a new pseudo terminal
+fn open_pty() -> (File, OwnedFd) {
+    let (mut controller, mut terminal) = (0, 0);
+    let size = libc::winsize {
+        ws_row: 24,
+        ws_col: 80,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+    // SAFETY: the pointers are valid for the call, and the descriptors returned are owned
+    // by nothing else
+    unsafe {
+        let opened = libc::openpty(
+            &raw mut controller,
+            &raw mut terminal,
+            ptr::null_mut(),
+            ptr::null(),
+            &raw const size,
+        );
+        assert_eq!(opened, 0, "{}", std::io::Error::last_os_error());
+        (
+            File::from_raw_fd(controller),
+            OwnedFd::from_raw_fd(terminal),
+        )
+    }
+}
+
+// everything `child` writes to the terminal, read until it ends so it never blocks
+fn collect_output(mut controller: File) -> Arc<Mutex<String>> {
+    let output = Arc::new(Mutex::new(String::new()));
+    let collected = Arc::clone(&output);
+    thread::spawn(move || {
+        let mut buffer = [0; 4096];
+        while let Ok(read) = controller.read(&mut buffer) {
+            if read == 0 {
+                break;
+            }
+            let text = String::from_utf8_lossy(&buffer[..read]);
+            collected.lock().unwrap().push_str(&text);
+        }
+    });
+    output
+}
+
+fn wait_until(what: &str, mut done: impl FnMut() -> bool) {
+    let start = Instant::now();
+    while !done() {
+        assert!(start.elapsed() < TIMEOUT, "timed out waiting for {what}");
+        thread::sleep(Duration::from_millis(20));
+    }
+}
+
+fn wait_for_exit(child: &mut Child) -> ExitStatus {
+    let mut status = None;
+    wait_until("hecto to exit", || {
+        status = child.try_wait().unwrap();
+        status.is_some()
+    });
+    status.unwrap()
+}
+
+#[test]
+fn sighup_writes_recovery_files_and_exits_like_a_shell_reports_it() {
+    let dir = env::temp_dir().join("hecto-termination");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    // not a text file, so no misspelled words split the row drawn with underlines
+    let file = dir.join("terminated.log");
+    fs::write(&file, "hello 老虎\n").unwrap();
+    let script = dir.join("script");
+    fs::write(&script, "x").unwrap();
+
+    let (controller, terminal) = open_pty();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_hecto"))
+        .arg("--script")
+        .arg(&script)
+        .arg(&file)
+        // without the config, cache and sessions of whoever runs the tests
+        .env("XDG_CONFIG_HOME", &dir)
+        .env("XDG_CACHE_HOME", &dir)
+        .env("XDG_STATE_HOME", &dir)
+        .stdin(terminal.try_clone().unwrap())
+        .stdout(terminal.try_clone().unwrap())
+        .stderr(terminal)
+        .spawn()
+        .unwrap();
+    let output = collect_output(controller);
+    wait_until("the edited line to be drawn", || {
+        output.lock().unwrap().contains("xhello 老虎")
+    });
+
+    let pid = i32::try_from(child.id()).unwrap();
+    // SAFETY: signalling the child, which hasn't been waited for so the pid is still its
+    assert_eq!(unsafe { libc::kill(pid, libc::SIGHUP) }, 0);
+    let status = wait_for_exit(&mut child);
+    assert_eq!(status.code(), Some(libc::SIGHUP.saturating_add(128)));
+
+    let recovery = dir.join("terminated.log.hecto-recover");
+    assert_eq!(fs::read_to_string(&recovery).unwrap(), "xhello 老虎\n");
+    assert_eq!(fs::read_to_string(&file).unwrap(), "hello 老虎\n");
+    fs::remove_dir_all(&dir).unwrap();
+}